@@ -0,0 +1,404 @@
+use crate::parser::split_heredoc_bodies;
+use std::collections::BTreeMap;
+
+/// Reserved words after which the next word is still in command position.
+const COMMAND_PREFIX_WORDS: &[&str] = &[
+    "!", "{", "if", "then", "else", "elif", "while", "until", "do", "time",
+];
+
+/// Where alias expansion is in a `case` command.
+#[derive(PartialEq)]
+enum CasePart {
+    /// The word being matched, up to `in`
+    Subject,
+    /// Patterns, up to the `)` that ends them
+    Patterns,
+    /// The commands run for a pattern, up to `;;`, `;&` or `;;&`
+    Commands,
+}
+
+/// The shell's aliases, by name.
+#[derive(Debug, Clone, Default)]
+pub struct Aliases {
+    table: BTreeMap<String, String>,
+}
+
+impl Aliases {
+    pub fn new() -> Self {
+        Self {
+            table: BTreeMap::new(),
+        }
+    }
+
+    pub fn set(&mut self, name: String, value: String) {
+        self.table.insert(name, value);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&String> {
+        self.table.get(name)
+    }
+
+    /// Remove an alias, returning false if it was not defined.
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.table.remove(name).is_some()
+    }
+
+    pub fn clear(&mut self) {
+        self.table.clear();
+    }
+
+    /// Iterate over all aliases, sorted by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.table.iter()
+    }
+
+    /// Returns true if `name` may be used as an alias name.
+    pub fn is_valid_name(name: &str) -> bool {
+        !name.is_empty()
+            && !name
+                .chars()
+                .any(|c| is_metachar(c) || matches!(c, '/' | '$' | '`' | '=' | '\\' | '\'' | '"'))
+    }
+
+    /// Expand aliases in raw input before it is parsed.
+    ///
+    /// Follows bash semantics: only the first word of a simple command is
+    /// checked, the word after an alias whose value ends in a blank is checked
+    /// too, and an alias is never expanded again inside its own expansion.
+    /// Here-document bodies are left as they are.
+    pub fn expand(&self, input: &str) -> String {
+        if self.table.is_empty() {
+            return input.to_string();
+        }
+        split_heredoc_bodies(input)
+            .into_iter()
+            .map(|(run, body)| match body {
+                true => run.to_string(),
+                false => self.expand_guarded(run, &mut Vec::new()).0,
+            })
+            .collect()
+    }
+
+    /// Returns the expanded text and whether the word following it is in
+    /// command position.
+    fn expand_guarded(&self, input: &str, active: &mut Vec<String>) -> (String, bool) {
+        let chars: Vec<char> = input.chars().collect();
+        let mut out = String::with_capacity(input.len());
+        let mut command_position = true;
+        // The `case` commands being read, innermost last
+        let mut cases: Vec<CasePart> = Vec::new();
+        let mut i = 0;
+
+        while i < chars.len() {
+            let c = chars[i];
+            match c {
+                ';' if cases.last() == Some(&CasePart::Commands)
+                    && matches!(chars.get(i + 1), Some(';' | '&')) =>
+                {
+                    // `;;`, `;&` or `;;&`: patterns follow
+                    out.push(c);
+                    i += 1;
+                    while i < chars.len() && matches!(chars[i], ';' | '&') {
+                        out.push(chars[i]);
+                        i += 1;
+                    }
+                    cases.pop();
+                    cases.push(CasePart::Patterns);
+                    command_position = false;
+                }
+                '\n' | '|' | '(' if cases.last() == Some(&CasePart::Patterns) => {
+                    out.push(c);
+                    i += 1;
+                }
+                '\n' | ';' | '&' | '|' | '(' => {
+                    out.push(c);
+                    command_position = true;
+                    i += 1;
+                }
+                ')' => {
+                    // Ends a case pattern or the `()` of a function
+                    // definition, both followed by commands
+                    out.push(c);
+                    if cases.last() == Some(&CasePart::Patterns) {
+                        cases.pop();
+                        cases.push(CasePart::Commands);
+                    }
+                    command_position = true;
+                    i += 1;
+                }
+                '<' | '>' => {
+                    // A redirection and its target leave command position untouched
+                    while i < chars.len() && matches!(chars[i], '<' | '>' | '&' | '|' | '-') {
+                        out.push(chars[i]);
+                        i += 1;
+                    }
+                    while i < chars.len() && chars[i].is_whitespace() && chars[i] != '\n' {
+                        out.push(chars[i]);
+                        i += 1;
+                    }
+                    let end = scan_word(&chars, i).0;
+                    out.extend(&chars[i..end]);
+                    i = end;
+                }
+                '#' => {
+                    while i < chars.len() && chars[i] != '\n' {
+                        out.push(chars[i]);
+                        i += 1;
+                    }
+                }
+                c if c.is_whitespace() => {
+                    out.push(c);
+                    i += 1;
+                }
+                _ => {
+                    let (end, quoted) = scan_word(&chars, i);
+                    let word: String = chars[i..end].iter().collect();
+                    i = end;
+
+                    match (cases.last(), word.as_str()) {
+                        (Some(CasePart::Subject), "in") => {
+                            cases.pop();
+                            cases.push(CasePart::Patterns);
+                        }
+                        (Some(CasePart::Patterns), "esac") => {
+                            cases.pop();
+                        }
+                        (Some(CasePart::Commands), "esac") if command_position => {
+                            cases.pop();
+                            command_position = false;
+                        }
+                        (_, "case") if command_position && !quoted => {
+                            cases.push(CasePart::Subject);
+                            command_position = false;
+                        }
+                        _ => {}
+                    }
+
+                    if !command_position || cases.last() == Some(&CasePart::Patterns) {
+                        out.push_str(&word);
+                        continue;
+                    }
+
+                    let alias = if quoted || active.contains(&word) {
+                        None
+                    } else {
+                        self.table.get(&word)
+                    };
+
+                    if let Some(value) = alias {
+                        active.push(word);
+                        let (expanded, trailing) = self.expand_guarded(value, active);
+                        active.pop();
+                        out.push_str(&expanded);
+                        command_position = trailing || value.ends_with([' ', '\t']);
+                    } else {
+                        command_position =
+                            COMMAND_PREFIX_WORDS.contains(&word.as_str()) || is_assignment(&word);
+                        out.push_str(&word);
+                    }
+                }
+            }
+        }
+
+        (out, command_position)
+    }
+}
+
+fn is_metachar(c: char) -> bool {
+    c.is_whitespace() || matches!(c, ';' | '&' | '|' | '(' | ')' | '<' | '>')
+}
+
+fn is_assignment(word: &str) -> bool {
+    match word.find('=') {
+        Some(pos) if pos > 0 => word[..pos]
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_'),
+        _ => false,
+    }
+}
+
+/// Scan a shell word starting at `start`, honouring quotes, escapes and
+/// `$(...)`/backtick substitutions. Returns the end index and whether any
+/// part of the word was quoted or escaped.
+fn scan_word(chars: &[char], start: usize) -> (usize, bool) {
+    let mut i = start;
+    let mut quoted = false;
+
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => {
+                quoted = true;
+                i += 2;
+            }
+            '\'' => {
+                quoted = true;
+                i += 1;
+                while i < chars.len() && chars[i] != '\'' {
+                    i += 1;
+                }
+                i += 1;
+            }
+            '"' => {
+                quoted = true;
+                i += 1;
+                while i < chars.len() && chars[i] != '"' {
+                    if chars[i] == '\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                i += 1;
+            }
+            '`' => {
+                i += 1;
+                while i < chars.len() && chars[i] != '`' {
+                    i += 1;
+                }
+                i += 1;
+            }
+            '$' if chars.get(i + 1) == Some(&'(') => {
+                let mut depth = 0;
+                i += 1;
+                while i < chars.len() {
+                    match chars[i] {
+                        '(' => depth += 1,
+                        ')' => {
+                            depth -= 1;
+                            if depth == 0 {
+                                break;
+                            }
+                        }
+                        _ => {}
+                    }
+                    i += 1;
+                }
+                i += 1;
+            }
+            c if is_metachar(c) => break,
+            _ => i += 1,
+        }
+    }
+
+    (i.min(chars.len()), quoted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aliases(pairs: &[(&str, &str)]) -> Aliases {
+        let mut aliases = Aliases::new();
+        for (name, value) in pairs {
+            aliases.set(name.to_string(), value.to_string());
+        }
+        aliases
+    }
+
+    #[test]
+    fn test_expand_first_word_only() {
+        let a = aliases(&[("ll", "ls -l")]);
+        assert_eq!(a.expand("ll /tmp"), "ls -l /tmp");
+        assert_eq!(a.expand("echo ll"), "echo ll");
+    }
+
+    #[test]
+    fn test_expand_after_separators() {
+        let a = aliases(&[("ll", "ls -l")]);
+        assert_eq!(a.expand("cd /; ll"), "cd /; ls -l");
+        assert_eq!(a.expand("ll | wc -l && ll"), "ls -l | wc -l && ls -l");
+        assert_eq!(a.expand("(ll)"), "(ls -l)");
+        assert_eq!(a.expand("FOO=1 ll"), "FOO=1 ls -l");
+        assert_eq!(a.expand("if ll; then ll; fi"), "if ls -l; then ls -l; fi");
+    }
+
+    #[test]
+    fn test_expand_case_commands_not_patterns() {
+        let a = aliases(&[("ll", "ls -l")]);
+        assert_eq!(
+            a.expand("case ll in ll) ll;; ll|x) ll;& (ll) ll;;& *) ll\nesac; ll"),
+            "case ll in ll) ls -l;; ll|x) ls -l;& (ll) ls -l;;& *) ls -l\nesac; ls -l"
+        );
+        assert_eq!(
+            a.expand("case x in\n  ll)\n    ll\n    ;;\nesac"),
+            "case x in\n  ll)\n    ls -l\n    ;;\nesac"
+        );
+    }
+
+    #[test]
+    fn test_expand_function_body() {
+        let a = aliases(&[("ll", "ls -l")]);
+        assert_eq!(a.expand("f() { ll; }"), "f() { ls -l; }");
+    }
+
+    #[test]
+    fn test_expand_value_with_pipes_and_semicolons() {
+        let a = aliases(&[("count", "ls | wc -l; echo done")]);
+        assert_eq!(a.expand("count"), "ls | wc -l; echo done");
+    }
+
+    #[test]
+    fn test_trailing_space_expands_next_word() {
+        let a = aliases(&[("sudo", "sudo "), ("ll", "ls -l")]);
+        assert_eq!(a.expand("sudo ll"), "sudo  ls -l");
+
+        let b = aliases(&[("nosp", "sudo"), ("ll", "ls -l")]);
+        assert_eq!(b.expand("nosp ll"), "sudo ll");
+    }
+
+    #[test]
+    fn test_recursion_guard() {
+        let a = aliases(&[("ls", "ls --color")]);
+        assert_eq!(a.expand("ls"), "ls --color");
+
+        let b = aliases(&[("a", "b"), ("b", "a")]);
+        assert_eq!(b.expand("a"), "a");
+    }
+
+    #[test]
+    fn test_chained_aliases() {
+        let a = aliases(&[("l", "ll"), ("ll", "ls -l")]);
+        assert_eq!(a.expand("l -a"), "ls -l -a");
+    }
+
+    #[test]
+    fn test_quoted_words_not_expanded() {
+        let a = aliases(&[("ll", "ls -l")]);
+        assert_eq!(a.expand("'ll'"), "'ll'");
+        assert_eq!(a.expand("\\ll"), "\\ll");
+        assert_eq!(a.expand("echo 'x; ll'"), "echo 'x; ll'");
+    }
+
+    #[test]
+    fn test_redirection_targets_not_expanded() {
+        let a = aliases(&[("ll", "ls -l")]);
+        assert_eq!(a.expand(">ll ll"), ">ll ls -l");
+    }
+
+    #[test]
+    fn test_heredoc_bodies_not_expanded() {
+        let a = aliases(&[("ll", "ls -l")]);
+        assert_eq!(
+            a.expand("cat <<EOF\nll\nEOF\nll\n"),
+            "cat <<EOF\nll\nEOF\nls -l\n"
+        );
+    }
+
+    #[test]
+    fn test_valid_names() {
+        assert!(Aliases::is_valid_name("ll"));
+        assert!(Aliases::is_valid_name("git-st"));
+        assert!(!Aliases::is_valid_name(""));
+        assert!(!Aliases::is_valid_name("a/b"));
+        assert!(!Aliases::is_valid_name("a b"));
+        assert!(!Aliases::is_valid_name("$x"));
+    }
+
+    #[test]
+    fn test_remove_and_clear() {
+        let mut a = aliases(&[("x", "y"), ("z", "w")]);
+        assert!(a.remove("x"));
+        assert!(!a.remove("x"));
+        a.clear();
+        assert!(a.get("z").is_none());
+    }
+}
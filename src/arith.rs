@@ -0,0 +1,361 @@
+use crate::variables::Variables;
+
+/// How deeply variables may refer to other expressions, as in `a=b; b=a`.
+const MAX_DEPTH: usize = 32;
+
+/// Evaluate a shell arithmetic expression, as used for `declare -i`
+/// variables. Variables are referred to by name; their values are
+/// themselves evaluated, and unset or empty ones count as 0.
+pub fn eval(expr: &str, vars: &Variables) -> Result<i64, String> {
+    eval_at_depth(expr, vars, 0)
+}
+
+fn eval_at_depth(expr: &str, vars: &Variables, depth: usize) -> Result<i64, String> {
+    if depth > MAX_DEPTH {
+        return Err(format!("{}: expression recursion level exceeded", expr));
+    }
+    let tokens = tokenize(expr)?;
+    if tokens.is_empty() {
+        return Ok(0);
+    }
+    let mut parser = Parser {
+        tokens: &tokens,
+        pos: 0,
+        expr,
+    };
+    let ast = parser.ternary()?;
+    if let Some(token) = parser.tokens.get(parser.pos) {
+        return Err(parser.syntax_error(token));
+    }
+    ast.eval(expr, vars, depth)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(i64),
+    Name(String),
+    Op(&'static str),
+}
+
+/// Operators, longest first so that `**` is not read as two `*`.
+const OPERATORS: &[&str] = &[
+    "**", "<<", ">>", "<=", ">=", "==", "!=", "&&", "||", "+", "-", "*", "/", "%", "<", ">", "&",
+    "|", "^", "!", "~", "(", ")", "?", ":",
+];
+
+fn tokenize(expr: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = expr.trim_start();
+    while !rest.is_empty() {
+        let c = rest.chars().next().unwrap_or_default();
+        let len = if c.is_ascii_digit() {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '#' || c == '_'))
+                .unwrap_or(rest.len());
+            tokens.push(Token::Number(parse_number(&rest[..len])?));
+            len
+        } else if c.is_ascii_alphabetic() || c == '_' || c == '$' {
+            // `$name` means the same as `name`
+            let start = usize::from(c == '$');
+            let len = start
+                + rest[start..]
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(rest.len() - start);
+            tokens.push(Token::Name(rest[start..len].to_string()));
+            len
+        } else if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(*op)) {
+            tokens.push(Token::Op(op));
+            op.len()
+        } else {
+            return Err(format!(
+                "{}: syntax error: invalid arithmetic operator (error token is \"{}\")",
+                expr, rest
+            ));
+        };
+        rest = rest[len..].trim_start();
+    }
+    Ok(tokens)
+}
+
+/// Parse an integer constant: decimal, `0x` hexadecimal, `0` octal, or
+/// `base#digits` with a base from 2 to 64.
+fn parse_number(text: &str) -> Result<i64, String> {
+    let invalid = || format!("{}: value too great for base", text);
+    let (base, digits) = if let Some((base, digits)) = text.split_once('#') {
+        match base.parse::<u32>() {
+            Ok(base @ 2..=64) => (base, digits),
+            _ => return Err(format!("{}: invalid arithmetic base", text)),
+        }
+    } else if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        (16, hex)
+    } else if text.len() > 1 && text.starts_with('0') {
+        (8, &text[1..])
+    } else {
+        (10, text)
+    };
+    let mut value: i64 = 0;
+    for c in digits.chars() {
+        // Bases above 36 use both cases, then `@` and `_`, as bash does
+        let digit = match c {
+            '0'..='9' => c as u32 - '0' as u32,
+            'a'..='z' => c as u32 - 'a' as u32 + 10,
+            'A'..='Z' if base <= 36 => c as u32 - 'A' as u32 + 10,
+            'A'..='Z' => c as u32 - 'A' as u32 + 36,
+            '@' => 62,
+            '_' => 63,
+            _ => return Err(invalid()),
+        };
+        if digit >= base {
+            return Err(invalid());
+        }
+        value = value.wrapping_mul(base as i64).wrapping_add(digit as i64);
+    }
+    if digits.is_empty() {
+        return Err(invalid());
+    }
+    Ok(value)
+}
+
+#[derive(Debug)]
+enum Expr {
+    Number(i64),
+    Name(String),
+    Unary(&'static str, Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+    Ternary(Box<Expr>, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn eval(&self, expr: &str, vars: &Variables, depth: usize) -> Result<i64, String> {
+        let eval = |e: &Expr| e.eval(expr, vars, depth);
+        Ok(match self {
+            Expr::Number(n) => *n,
+            Expr::Name(name) => match vars.get(name) {
+                Some(value) if !value.trim().is_empty() => eval_at_depth(value, vars, depth + 1)?,
+                _ => 0,
+            },
+            Expr::Unary(op, operand) => {
+                let value = eval(operand)?;
+                match *op {
+                    "-" => value.wrapping_neg(),
+                    "!" => i64::from(value == 0),
+                    "~" => !value,
+                    _ => value,
+                }
+            }
+            // The logical operators and `?:` only evaluate what they need
+            Expr::Binary("&&", lhs, rhs) => i64::from(eval(lhs)? != 0 && eval(rhs)? != 0),
+            Expr::Binary("||", lhs, rhs) => i64::from(eval(lhs)? != 0 || eval(rhs)? != 0),
+            Expr::Ternary(cond, then, otherwise) => {
+                if eval(cond)? != 0 {
+                    eval(then)?
+                } else {
+                    eval(otherwise)?
+                }
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let (lhs, rhs) = (eval(lhs)?, eval(rhs)?);
+                match *op {
+                    "+" => lhs.wrapping_add(rhs),
+                    "-" => lhs.wrapping_sub(rhs),
+                    "*" => lhs.wrapping_mul(rhs),
+                    "/" | "%" if rhs == 0 => return Err(format!("{}: division by 0", expr.trim())),
+                    "/" => lhs.wrapping_div(rhs),
+                    "%" => lhs.wrapping_rem(rhs),
+                    "**" if rhs < 0 => {
+                        return Err(format!("{}: exponent less than 0", expr.trim()))
+                    }
+                    "**" => lhs.wrapping_pow(rhs.min(u32::MAX as i64) as u32),
+                    "<<" => lhs.wrapping_shl(rhs as u32),
+                    ">>" => lhs.wrapping_shr(rhs as u32),
+                    "<" => i64::from(lhs < rhs),
+                    "<=" => i64::from(lhs <= rhs),
+                    ">" => i64::from(lhs > rhs),
+                    ">=" => i64::from(lhs >= rhs),
+                    "==" => i64::from(lhs == rhs),
+                    "!=" => i64::from(lhs != rhs),
+                    "&" => lhs & rhs,
+                    "|" => lhs | rhs,
+                    _ => lhs ^ rhs,
+                }
+            }
+        })
+    }
+}
+
+/// Binary operators from the loosest binding to the tightest, below `?:`.
+const LEVELS: &[&[&str]] = &[
+    &["||"],
+    &["&&"],
+    &["|"],
+    &["^"],
+    &["&"],
+    &["==", "!="],
+    &["<", "<=", ">", ">="],
+    &["<<", ">>"],
+    &["+", "-"],
+    &["*", "/", "%"],
+];
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    expr: &'a str,
+}
+
+impl Parser<'_> {
+    fn peek_op(&self) -> Option<&'static str> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Op(op)) => Some(op),
+            _ => None,
+        }
+    }
+
+    fn expect(&mut self, op: &str) -> Result<(), String> {
+        if self.peek_op() == Some(op) {
+            self.pos += 1;
+            return Ok(());
+        }
+        Err(match self.tokens.get(self.pos) {
+            Some(token) => self.syntax_error(token),
+            None => self.missing_operand(),
+        })
+    }
+
+    fn syntax_error(&self, token: &Token) -> String {
+        let text = match token {
+            Token::Number(n) => n.to_string(),
+            Token::Name(name) => name.clone(),
+            Token::Op(op) => op.to_string(),
+        };
+        format!(
+            "{}: syntax error in expression (error token is \"{}\")",
+            self.expr.trim(),
+            text
+        )
+    }
+
+    fn missing_operand(&self) -> String {
+        format!("{}: syntax error: operand expected", self.expr.trim())
+    }
+
+    fn ternary(&mut self) -> Result<Expr, String> {
+        let cond = self.binary(0)?;
+        if self.peek_op() != Some("?") {
+            return Ok(cond);
+        }
+        self.pos += 1;
+        let then = self.ternary()?;
+        self.expect(":")?;
+        let otherwise = self.ternary()?;
+        Ok(Expr::Ternary(
+            Box::new(cond),
+            Box::new(then),
+            Box::new(otherwise),
+        ))
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Expr, String> {
+        let Some(ops) = LEVELS.get(level) else {
+            return self.power();
+        };
+        let mut lhs = self.binary(level + 1)?;
+        while let Some(op) = self.peek_op().filter(|op| ops.contains(op)) {
+            self.pos += 1;
+            let rhs = self.binary(level + 1)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    /// `**` is right-associative and binds more loosely than unary minus.
+    fn power(&mut self) -> Result<Expr, String> {
+        let base = self.unary()?;
+        if self.peek_op() != Some("**") {
+            return Ok(base);
+        }
+        self.pos += 1;
+        let exponent = self.power()?;
+        Ok(Expr::Binary("**", Box::new(base), Box::new(exponent)))
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if let Some(op @ ("-" | "+" | "!" | "~")) = self.peek_op() {
+            self.pos += 1;
+            return Ok(Expr::Unary(op, Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .ok_or_else(|| self.missing_operand())?;
+        self.pos += 1;
+        match token {
+            Token::Number(n) => Ok(Expr::Number(*n)),
+            Token::Name(name) => Ok(Expr::Name(name.clone())),
+            Token::Op("(") => {
+                let inner = self.ternary()?;
+                self.expect(")")?;
+                Ok(inner)
+            }
+            token => Err(self.syntax_error(token)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval_str(expr: &str) -> Result<i64, String> {
+        eval(expr, &Variables::new())
+    }
+
+    #[test]
+    fn test_precedence_and_operators() {
+        assert_eq!(eval_str("1 + 2 * 3"), Ok(7));
+        assert_eq!(eval_str("(1 + 2) * 3"), Ok(9));
+        assert_eq!(eval_str("2 ** 3 ** 2"), Ok(512));
+        assert_eq!(eval_str("-2 ** 2"), Ok(4));
+        assert_eq!(eval_str("7 / 2 + 7 % 2"), Ok(4));
+        assert_eq!(eval_str("1 << 4 | 1"), Ok(17));
+        assert_eq!(eval_str("3 > 2 && 2 >= 2 || 0"), Ok(1));
+        assert_eq!(eval_str("!0 + ~0"), Ok(0));
+        assert_eq!(eval_str("1 ? 2 : 3"), Ok(2));
+        assert_eq!(eval_str("0 ? 2 : 0 ? 3 : 4"), Ok(4));
+        assert_eq!(eval_str(""), Ok(0));
+    }
+
+    #[test]
+    fn test_number_bases() {
+        assert_eq!(eval_str("0x1f"), Ok(31));
+        assert_eq!(eval_str("017"), Ok(15));
+        assert_eq!(eval_str("2#101"), Ok(5));
+        assert_eq!(eval_str("36#z"), Ok(35));
+        assert!(eval_str("08").is_err());
+        assert!(eval_str("65#1").is_err());
+    }
+
+    #[test]
+    fn test_variables() {
+        let mut vars = Variables::new();
+        vars.set("a".to_string(), "4".to_string());
+        vars.set("b".to_string(), "a * 2".to_string());
+        vars.set("loop".to_string(), "loop".to_string());
+        assert_eq!(eval("b + $a + unset_name", &vars), Ok(12));
+        assert!(eval("loop", &vars).unwrap_err().contains("recursion"));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(eval_str("1 / 0").unwrap_err(), "1 / 0: division by 0");
+        assert_eq!(eval_str("0 && 1 / 0"), Ok(0));
+        assert!(eval_str("1 +").unwrap_err().contains("operand expected"));
+        assert!(eval_str("(1").is_err());
+        assert!(eval_str("1 2").is_err());
+        assert!(eval_str("1 @ 2").is_err());
+    }
+}
@@ -0,0 +1,270 @@
+use std::collections::BTreeMap;
+
+/// Starts an assignment value that holds an array literal. The parser turns
+/// `name=(a "b c")` into `name=` followed by this character and the
+/// elements, separated by `ELEMENT_SEP`, since conch-parser has no arrays.
+pub const LITERAL_START: char = '\u{1e}';
+/// Separates the elements of an array literal.
+pub const ELEMENT_SEP: char = '\u{1f}';
+/// Put by the parser before an unquoted `${name[@]}` or `${name[*]}`,
+/// whose elements are then split into fields. Quotes do not survive
+/// parsing, so without it the two could not be told apart.
+pub const SPLIT_MARK: char = '\u{1d}';
+
+/// The value of an array variable.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Array {
+    /// `declare -a`, or any `name=(...)`: sparse, indexed from 0.
+    Indexed(BTreeMap<usize, String>),
+    /// `declare -A`: keyed by string.
+    Assoc(BTreeMap<String, String>),
+}
+
+impl Array {
+    pub fn indexed() -> Self {
+        Array::Indexed(BTreeMap::new())
+    }
+
+    pub fn assoc() -> Self {
+        Array::Assoc(BTreeMap::new())
+    }
+
+    pub fn is_assoc(&self) -> bool {
+        matches!(self, Array::Assoc(_))
+    }
+
+    /// The element values, in index or key order.
+    pub fn values(&self) -> Vec<String> {
+        match self {
+            Array::Indexed(map) => map.values().cloned().collect(),
+            Array::Assoc(map) => map.values().cloned().collect(),
+        }
+    }
+
+    /// The indices or keys that are set, as `${!name[@]}` lists them.
+    pub fn keys(&self) -> Vec<String> {
+        match self {
+            Array::Indexed(map) => map.keys().map(|k| k.to_string()).collect(),
+            Array::Assoc(map) => map.keys().cloned().collect(),
+        }
+    }
+
+    /// The index one past the highest one set, where `a+=(x)` appends.
+    pub fn next_index(&self) -> usize {
+        match self {
+            Array::Indexed(map) => map.keys().next_back().map_or(0, |k| k + 1),
+            Array::Assoc(_) => 0,
+        }
+    }
+
+    /// Resolve a subscript that has already been expanded to the key it
+    /// names. Indexed arrays take an arithmetic expression, which `eval`
+    /// evaluates; negative indices count back from the end.
+    pub fn key(
+        &self,
+        subscript: &str,
+        eval: impl Fn(&str) -> Result<i64, String>,
+    ) -> Result<Key, String> {
+        if self.is_assoc() {
+            return Ok(Key::Assoc(subscript.to_string()));
+        }
+        let index = eval(subscript)?;
+        if index >= 0 {
+            return Ok(Key::Index(index as usize));
+        }
+        let from_end = self.next_index() as i64 + index;
+        if from_end < 0 {
+            return Err(format!("{}: bad array subscript", index));
+        }
+        Ok(Key::Index(from_end as usize))
+    }
+
+    pub fn get(&self, key: &Key) -> Option<&String> {
+        match (self, key) {
+            (Array::Indexed(map), Key::Index(i)) => map.get(i),
+            (Array::Assoc(map), Key::Assoc(k)) => map.get(k),
+            _ => None,
+        }
+    }
+
+    pub fn set(&mut self, key: Key, value: String) {
+        match (self, key) {
+            (Array::Indexed(map), Key::Index(i)) => {
+                map.insert(i, value);
+            }
+            (Array::Assoc(map), Key::Assoc(k)) => {
+                map.insert(k, value);
+            }
+            (Array::Indexed(map), Key::Assoc(k)) => {
+                map.insert(k.parse().unwrap_or(0), value);
+            }
+            (Array::Assoc(map), Key::Index(i)) => {
+                map.insert(i.to_string(), value);
+            }
+        }
+    }
+
+    /// The array as a literal that assigns it back, as `declare -p`
+    /// prints it: `([0]="a" [1]="b c")`.
+    pub fn to_literal(&self) -> String {
+        let elements: Vec<String> = self
+            .keys()
+            .into_iter()
+            .zip(self.values())
+            .map(|(key, value)| format!("[{}]={}", key, double_quote(&value)))
+            .collect();
+        format!("({})", elements.join(" "))
+    }
+
+    pub fn remove(&mut self, key: &Key) {
+        match (self, key) {
+            (Array::Indexed(map), Key::Index(i)) => {
+                map.remove(i);
+            }
+            (Array::Assoc(map), Key::Assoc(k)) => {
+                map.remove(k);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// An element of an array, see `Array::key`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Key {
+    Index(usize),
+    Assoc(String),
+}
+
+/// The left-hand side of an assignment: `name`, `name[sub]`, with `+=`
+/// for appending.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Target<'a> {
+    pub name: &'a str,
+    /// The subscript, unexpanded.
+    pub subscript: Option<&'a str>,
+    pub append: bool,
+}
+
+impl<'a> Target<'a> {
+    /// Parse the part of an assignment word before `=`.
+    pub fn parse(lhs: &'a str) -> Option<Target<'a>> {
+        let (lhs, append) = match lhs.strip_suffix('+') {
+            Some(lhs) => (lhs, true),
+            None => (lhs, false),
+        };
+        let (name, subscript) = match lhs.split_once('[') {
+            Some((name, rest)) => (name, Some(rest.strip_suffix(']')?)),
+            None => (lhs, None),
+        };
+        is_name(name).then_some(Target {
+            name,
+            subscript,
+            append,
+        })
+    }
+}
+
+/// Split a command word such as `a[1]=x` or `a+=x` into its target and
+/// value, if it is an assignment.
+pub fn split_assignment(word: &str) -> Option<(&str, &str)> {
+    // The subscript may itself contain `=`, so look for it after the `]`
+    let search_from = match word.split_once('[') {
+        Some((name, _)) if is_name(name) => word.find("]")?,
+        _ => 0,
+    };
+    let eq = search_from + word[search_from..].find('=')?;
+    let (lhs, value) = (&word[..eq], &word[eq + 1..]);
+    Target::parse(lhs).map(|_| (lhs, value))
+}
+
+/// Returns the elements of an array literal value, or `None` if `value`
+/// is a plain string.
+pub fn literal_elements(value: &str) -> Option<Vec<&str>> {
+    let body = value.strip_prefix(LITERAL_START)?;
+    if body.is_empty() {
+        return Some(Vec::new());
+    }
+    Some(body.split(ELEMENT_SEP).collect())
+}
+
+/// Quote `s` in double quotes, escaping the characters special in them.
+pub fn double_quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        if matches!(c, '"' | '\\' | '$' | '`') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Returns true if `s` is a valid variable name.
+pub fn is_name(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_parse() {
+        assert_eq!(
+            Target::parse("a[$i]+"),
+            Some(Target {
+                name: "a",
+                subscript: Some("$i"),
+                append: true
+            })
+        );
+        assert_eq!(Target::parse("x").unwrap().subscript, None);
+        assert!(Target::parse("1x").is_none());
+        assert!(Target::parse("a[1").is_none());
+
+        assert_eq!(split_assignment("a[k=v]=w"), Some(("a[k=v]", "w")));
+        assert_eq!(split_assignment("a+=x"), Some(("a+", "x")));
+        assert_eq!(split_assignment("--opt=x"), None);
+        assert_eq!(split_assignment("echo"), None);
+    }
+
+    #[test]
+    fn test_indexed_keys() {
+        let mut array = Array::indexed();
+        array.set(Key::Index(0), "a".to_string());
+        array.set(Key::Index(5), "b".to_string());
+        assert_eq!(array.keys(), vec!["0", "5"]);
+        assert_eq!(array.next_index(), 6);
+
+        let eval = |s: &str| match s {
+            "i" => Ok(5),
+            s => s.parse().map_err(|_| format!("{}: syntax error", s)),
+        };
+        assert_eq!(array.key("i", eval), Ok(Key::Index(5)));
+        assert_eq!(array.key("-1", eval), Ok(Key::Index(5)));
+        assert!(array.key("-7", eval).is_err());
+        assert!(array.key("1+", eval).is_err());
+    }
+
+    #[test]
+    fn test_to_literal() {
+        let mut array = Array::assoc();
+        array.set(Key::Assoc("k".to_string()), "say \"$x\"".to_string());
+        assert_eq!(array.to_literal(), r#"([k]="say \"\$x\"")"#);
+        assert_eq!(Array::indexed().to_literal(), "()");
+    }
+
+    #[test]
+    fn test_literal_elements() {
+        assert_eq!(literal_elements("plain"), None);
+        assert_eq!(literal_elements("\u{1e}"), Some(vec![]));
+        assert_eq!(literal_elements("\u{1e}a\u{1f}b c"), Some(vec!["a", "b c"]));
+    }
+}
@@ -0,0 +1,153 @@
+use crate::aliases::Aliases;
+use crate::builtins::common::shell_quote;
+use crate::parser::SimpleCommand;
+
+use super::{Builtin, BuiltinResult, ShellContext};
+
+pub struct Alias;
+
+impl Builtin for Alias {
+    fn name(&self) -> &'static str {
+        "alias"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "alias [-p] [name[=value] ...]"
+    }
+
+    fn about(&self) -> &'static str {
+        "Define or display aliases."
+    }
+
+    fn run(&self, ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+        execute(cmd, ctx.aliases)
+    }
+}
+
+fn format_alias(name: &str, value: &str) -> String {
+    format!("alias {}={}", name, shell_quote(value))
+}
+
+fn execute(cmd: &SimpleCommand, aliases: &mut Aliases) -> Result<BuiltinResult, String> {
+    // Usage: alias [-p] [name[=value] ...]
+    let mut args = cmd.args.as_slice();
+    let mut print_all = false;
+    while let Some(first) = args.first() {
+        match first.as_str() {
+            "-p" => print_all = true,
+            "--" => {
+                args = &args[1..];
+                break;
+            }
+            opt if opt.starts_with('-') && opt.len() > 1 => {
+                return Err(format!("alias: {}: invalid option", opt));
+            }
+            _ => break,
+        }
+        args = &args[1..];
+    }
+
+    if args.is_empty() || print_all {
+        for (name, value) in aliases.iter() {
+            println!("{}", format_alias(name, value));
+        }
+    }
+
+    let mut status = 0;
+    for arg in args {
+        match arg.split_once('=') {
+            Some((name, value)) => {
+                if !Aliases::is_valid_name(name) {
+                    eprintln!("alias: `{}': invalid alias name", name);
+                    status = 1;
+                    continue;
+                }
+                aliases.set(name.to_string(), value.to_string());
+            }
+            None => match aliases.get(arg) {
+                Some(value) => println!("{}", format_alias(arg, value)),
+                None => {
+                    eprintln!("alias: {}: not found", arg);
+                    status = 1;
+                }
+            },
+        }
+    }
+
+    Ok(match status {
+        0 => BuiltinResult::HandledContinue,
+        status => BuiltinResult::HandledStatus(status),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_cmd(args: Vec<&str>) -> SimpleCommand {
+        SimpleCommand {
+            name: "alias".into(),
+            args: args.into_iter().map(String::from).collect(),
+            assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_alias_define() {
+        let mut aliases = Aliases::new();
+        let res = execute(&make_cmd(vec!["alias_test_ll=ls -l"]), &mut aliases).unwrap();
+        assert!(matches!(res, BuiltinResult::HandledContinue));
+        assert_eq!(
+            aliases.get("alias_test_ll").map(|s| s.as_str()),
+            Some("ls -l")
+        );
+    }
+
+    #[test]
+    fn test_alias_list_and_lookup() {
+        let mut aliases = Aliases::new();
+        execute(&make_cmd(vec!["alias_test_list=echo hi"]), &mut aliases).unwrap();
+        assert!(execute(&make_cmd(vec![]), &mut aliases).is_ok());
+        assert!(execute(&make_cmd(vec!["-p"]), &mut aliases).is_ok());
+        let res = execute(&make_cmd(vec!["alias_test_list"]), &mut aliases).unwrap();
+        assert!(matches!(res, BuiltinResult::HandledContinue));
+    }
+
+    #[test]
+    fn test_alias_missing_name_fails() {
+        let mut aliases = Aliases::new();
+        execute(&make_cmd(vec!["alias_test_found=echo hi"]), &mut aliases).unwrap();
+        let res = execute(
+            &make_cmd(vec!["alias_test_found", "alias_test_missing"]),
+            &mut aliases,
+        )
+        .unwrap();
+        assert!(matches!(res, BuiltinResult::HandledStatus(1)));
+    }
+
+    #[test]
+    fn test_alias_invalid_name_is_skipped() {
+        let mut aliases = Aliases::new();
+        let res = execute(&make_cmd(vec!["alias/test=x"]), &mut aliases).unwrap();
+        assert!(matches!(res, BuiltinResult::HandledStatus(1)));
+        assert!(aliases.get("alias/test").is_none());
+    }
+
+    #[test]
+    fn test_alias_invalid_option() {
+        let mut aliases = Aliases::new();
+        let res = execute(&make_cmd(vec!["-z"]), &mut aliases);
+        assert_eq!(res.err().unwrap(), "alias: -z: invalid option");
+    }
+
+    #[test]
+    fn test_format_alias_is_resourceable() {
+        assert_eq!(format_alias("ll", "ls -l"), "alias ll='ls -l'");
+        assert_eq!(
+            format_alias("q", "echo 'hi'"),
+            "alias q='echo '\\''hi'\\'''"
+        );
+    }
+}
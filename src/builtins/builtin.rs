@@ -0,0 +1,86 @@
+use crate::parser::SimpleCommand;
+
+use super::{dispatch, is_builtin, Builtin, BuiltinResult, ShellContext};
+
+pub struct BuiltinCommand;
+
+impl Builtin for BuiltinCommand {
+    fn name(&self) -> &'static str {
+        "builtin"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "builtin [name [arg ...]]"
+    }
+
+    fn about(&self) -> &'static str {
+        "Run a shell builtin, bypassing functions of the same name."
+    }
+
+    fn run(&self, ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+        execute(ctx, cmd)
+    }
+}
+
+fn execute(ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+    // Usage: builtin [name [arg ...]]
+    let Some((name, args)) = cmd.args.split_first() else {
+        return Ok(BuiltinResult::HandledContinue);
+    };
+    // The arguments are left for the builtin itself to expand
+    let name = ctx.vars.expand(name)?;
+    if !is_builtin(&name) {
+        eprintln!("builtin: {}: not a shell builtin", name);
+        return Ok(BuiltinResult::HandledStatus(127));
+    }
+    let inner = SimpleCommand {
+        name,
+        args: args.to_vec(),
+        assignments: cmd.assignments.clone(),
+        line: cmd.line,
+        quoted: Vec::new(),
+    };
+    dispatch(ctx, &inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aliases::Aliases;
+    use crate::functions::Functions;
+    use crate::history::HistoryManager;
+    use crate::variables::Variables;
+
+    fn run(args: &[&str], vars: &mut Variables) -> Result<BuiltinResult, String> {
+        let cmd = SimpleCommand {
+            name: "builtin".into(),
+            args: args.iter().map(|s| s.to_string()).collect(),
+            assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
+        };
+        let mut ctx = ShellContext {
+            history_mgr: &HistoryManager::default(),
+            command_history: &mut vec![],
+            vars,
+            functions: &mut Functions::new(),
+            aliases: &mut Aliases::new(),
+        };
+        execute(&mut ctx, &cmd)
+    }
+
+    #[test]
+    fn test_builtin_runs_only_builtins() {
+        let mut vars = Variables::new();
+        vars.set("NAME".into(), "set".into());
+        run(&["$NAME", "--", "a", "b"], &mut vars).unwrap();
+        assert_eq!(vars.get_positional_args(), ["a", "b"]);
+
+        let res = run(&["ls"], &mut vars).unwrap();
+        assert!(matches!(res, BuiltinResult::HandledStatus(127)));
+        assert!(matches!(
+            run(&[], &mut vars).unwrap(),
+            BuiltinResult::HandledContinue
+        ));
+    }
+}
@@ -1,31 +1,118 @@
 use crate::builtins::common::SHELL_HELP_TEMPLATE;
-use crate::history::HistoryManager;
 use crate::parser::SimpleCommand;
-use crate::path_utils::collapse_tilde;
-use clap::Parser;
+use crate::path_utils::normalize_logical;
+use crate::variables::Variables;
+use clap::{CommandFactory, Parser};
+use std::path::Path;
 
-use super::BuiltinResult;
+use super::{Builtin, BuiltinResult, ShellContext};
+
+pub struct Cd;
+
+impl Builtin for Cd {
+    fn name(&self) -> &'static str {
+        "cd"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "cd [-L|[-P [-e]]] [dir|-N]"
+    }
+
+    fn about(&self) -> &'static str {
+        "Change the shell working directory."
+    }
+
+    fn command(&self) -> clap::Command {
+        CdArgs::command()
+    }
+
+    fn run(&self, ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+        execute(cmd, ctx.vars)
+    }
+}
 
 /// Change the shell working directory
 #[derive(Parser, Debug)]
 #[command(name = "cd")]
 #[command(about = "Change the shell working directory", long_about = None)]
-#[command(help_template = SHELL_HELP_TEMPLATE)]
+#[command(help_template = SHELL_HELP_TEMPLATE, allow_negative_numbers = true)]
 struct CdArgs {
-    /// The target directory to change to. Use '-' for previous directory, '~' for HOME
+    /// Keep symbolic links in PWD and resolve '..' before them (the default)
+    #[arg(short = 'L', overrides_with = "physical")]
+    logical: bool,
+
+    /// Resolve symbolic links, so that PWD is the physical directory
+    #[arg(short = 'P', overrides_with = "logical")]
+    physical: bool,
+
+    /// With -P, fail if the new directory's path cannot be determined
+    #[arg(short = 'e')]
+    check: bool,
+
+    /// The target directory to change to. Use '-' for previous directory, '~' for HOME,
+    /// '-N' for entry N of the directory stack. Relative names are looked up in CDPATH
     #[arg(value_name = "DIR")]
     dir: Option<String>,
 }
 
-pub fn execute(
-    cmd: &SimpleCommand,
-    history_mgr: &HistoryManager,
-    command_history: &mut Vec<String>,
-    oldpwd: &mut Option<String>,
-) -> Result<BuiltinResult, String> {
+/// Make `target` the working directory and update `PWD` and `OLDPWD` to
+/// match. `PWD` is the logical path, which keeps symlinks and
+/// drops `..` lexically, unless `physical`; a logical path that does not
+/// lead anywhere falls back to the physical one, as it does in other
+/// shells. Returns false if the new directory's path could not be found.
+pub fn change_dir(target: &str, physical: bool, vars: &mut Variables) -> Result<bool, String> {
+    let previous = vars.pwd();
+    let mut pwd = None;
+    if !physical {
+        let path = match target.starts_with('/') {
+            true => normalize_logical(target),
+            false => normalize_logical(&format!("{}/{}", previous, target)),
+        };
+        if std::env::set_current_dir(&path).is_ok() {
+            pwd = Some(path);
+        }
+    }
+    if pwd.is_none() {
+        std::env::set_current_dir(target).map_err(|e| format!("{}: {}", target, e))?;
+        pwd = std::env::current_dir()
+            .ok()
+            .map(|d| d.to_string_lossy().into_owned());
+    }
+
+    vars.set("OLDPWD".to_string(), previous);
+    vars.export("OLDPWD");
+    let found = pwd.is_some();
+    if let Some(pwd) = pwd {
+        vars.set("PWD".to_string(), pwd);
+        vars.export("PWD");
+    }
+    Ok(found)
+}
+
+/// Look a relative `dir` up in `CDPATH`. Returns the directory found and
+/// whether it came from a non-empty entry, in which case `cd` prints the
+/// new directory.
+fn search_cdpath(dir: &str, vars: &Variables) -> Option<(String, bool)> {
+    let first = dir.split('/').next().unwrap_or_default();
+    if dir.starts_with('/') || first == "." || first == ".." {
+        return None;
+    }
+    let cdpath = vars.get("CDPATH")?;
+    cdpath.split(':').find_map(|entry| {
+        let candidate = match entry {
+            "" => format!("./{}", dir),
+            entry => format!("{}/{}", entry.trim_end_matches('/'), dir),
+        };
+        Path::new(&candidate)
+            .is_dir()
+            .then_some((candidate, !entry.is_empty()))
+    })
+}
+
+fn execute(cmd: &SimpleCommand, vars: &mut Variables) -> Result<BuiltinResult, String> {
     // Parse arguments using clap
     let args_iter = std::iter::once("cd".to_string())
-        .chain(cmd.args.iter().cloned())
+        .chain(vars.expand_words(&cmd.args)?)
         .collect::<Vec<_>>();
 
     let parsed_args = match CdArgs::try_parse_from(&args_iter) {
@@ -33,59 +120,87 @@ pub fn execute(
         Err(e) => {
             // Clap handles --help and errors; just print and return
             print!("{}", e);
-            return Ok(BuiltinResult::HandledContinue);
+            return Ok(match e.exit_code() {
+                0 => BuiltinResult::HandledContinue,
+                code => BuiltinResult::HandledStatus(code),
+            });
         }
     };
 
-    let target = if let Some(ref dir) = parsed_args.dir {
-        if dir == "-" {
-            // cd - switches to OLDPWD
-            match oldpwd.as_ref() {
-                Some(prev) => prev.clone(),
+    // Entry of the directory stack that `cd -N` goes to
+    let mut stack_index = None;
+    // `cd -` and directories found through CDPATH print where they went
+    let mut print = false;
+    let target = match parsed_args.dir.as_deref() {
+        None => match vars.get("HOME") {
+            Some(home) if !home.is_empty() => home.clone(),
+            _ => {
+                eprintln!("cd: HOME not set");
+                return Ok(BuiltinResult::HandledStatus(1));
+            }
+        },
+        Some("-") => match vars.get("OLDPWD") {
+            Some(prev) if !prev.is_empty() => {
+                print = true;
+                prev.clone()
+            }
+            _ => {
+                eprintln!("cd: OLDPWD not set");
+                return Ok(BuiltinResult::HandledStatus(1));
+            }
+        },
+        Some(dir) => match dir.strip_prefix('-').and_then(|n| n.parse::<usize>().ok()) {
+            Some(n) => match vars.dir_stack().get(n, &vars.pwd()) {
+                Some(entry) => {
+                    stack_index = Some(n);
+                    entry
+                }
                 None => {
-                    eprintln!("cd: OLDPWD not set");
-                    return Ok(BuiltinResult::HandledContinue);
+                    eprintln!("cd: {}: directory stack index out of range", dir);
+                    return Ok(BuiltinResult::HandledStatus(1));
                 }
-            }
-        } else {
-            let p = collapse_tilde(dir);
-            p.to_string_lossy().to_string()
-        }
-    } else {
-        // No argument provided, use HOME
-        std::env::var("HOME").unwrap_or_else(|_| "/".to_string())
+            },
+            None => match search_cdpath(dir, vars) {
+                Some((found, named)) => {
+                    print = named;
+                    found
+                }
+                None => dir.to_string(),
+            },
+        },
     };
 
-    // Save current directory before changing
-    let current = std::env::current_dir()
-        .ok()
-        .and_then(|p| p.to_str().map(|s| s.to_string()));
-
-    match std::env::set_current_dir(&target) {
-        Ok(()) => {
-            // Update OLDPWD to the previous current directory
-            *oldpwd = current;
-
-            // Print new directory for cd -
-            if parsed_args.dir.as_deref() == Some("-") {
-                println!("{}", target);
-            }
-
-            // persist on success
-            history_mgr.add_entry(&format!("cd {}", target), command_history)?;
-            Ok(BuiltinResult::HandledContinue)
-        }
+    let previous = vars.pwd();
+    let found = match change_dir(&target, parsed_args.physical, vars) {
+        Ok(found) => found,
         Err(e) => {
-            eprintln!("cd: {}: {}", target, e);
-            Ok(BuiltinResult::HandledContinue)
+            eprintln!("cd: {}", e);
+            return Ok(BuiltinResult::HandledStatus(1));
         }
+    };
+
+    // With autopushd the stack keeps the directories most recently left,
+    // so the one `cd -N` went to moves off it
+    if vars.options().autopushd {
+        let stack = vars.dir_stack_mut();
+        if let Some(n) = stack_index {
+            stack.remove(n);
+        }
+        stack.push(previous);
+    }
+
+    if print {
+        println!("{}", vars.pwd());
     }
+    if !found && parsed_args.physical && parsed_args.check {
+        return Ok(BuiltinResult::HandledStatus(1));
+    }
+    Ok(BuiltinResult::HandledContinue)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::history::HistoryManager;
     use tempfile::TempDir;
 
     struct EnvVarGuard {
@@ -123,7 +238,6 @@ mod tests {
         let home_guard = EnvVarGuard::new("HOME");
         home_guard.set(home_tmp.path().to_string_lossy().as_ref());
 
-        let mgr = HistoryManager::new().unwrap();
         let tmp = TempDir::new().unwrap();
         let tmp_path = tmp.path().to_string_lossy().to_string();
 
@@ -133,13 +247,20 @@ mod tests {
             name: "cd".into(),
             args: vec![tmp_path.clone()],
             assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
         };
-        let mut history = Vec::new();
-        let mut oldpwd = None;
-        let res = execute(&cmd, &mgr, &mut history, &mut oldpwd).unwrap();
+        let mut vars = Variables::new();
+        vars.init_pwd();
+        let res = execute(&cmd, &mut vars).unwrap();
         assert!(matches!(res, BuiltinResult::HandledContinue));
 
-        assert!(history.iter().any(|h| h.starts_with("cd ")));
+        let cwd = std::env::current_dir().unwrap();
+        assert_eq!(vars.get("PWD"), Some(&cwd.to_string_lossy().into_owned()));
+        assert_eq!(
+            vars.get("OLDPWD"),
+            Some(&orig.to_string_lossy().into_owned())
+        );
 
         let _ = std::env::set_current_dir(orig);
         drop(home_guard);
@@ -152,41 +273,46 @@ mod tests {
         let home_guard = EnvVarGuard::new("HOME");
         home_guard.set(home_tmp.path().to_string_lossy().as_ref());
 
-        let mgr = HistoryManager::new().unwrap();
         let tmp1 = TempDir::new().unwrap();
         let tmp2 = TempDir::new().unwrap();
         let tmp1_path = tmp1.path().to_string_lossy().to_string();
         let tmp2_path = tmp2.path().to_string_lossy().to_string();
 
         let orig = std::env::current_dir().unwrap();
-        let mut history = Vec::new();
-        let mut oldpwd = None;
+        let mut vars = Variables::new();
+        vars.init_pwd();
 
         let cmd1 = SimpleCommand {
             name: "cd".into(),
             args: vec![tmp1_path.clone()],
             assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
         };
-        execute(&cmd1, &mgr, &mut history, &mut oldpwd).unwrap();
-        assert!(oldpwd.is_some());
+        execute(&cmd1, &mut vars).unwrap();
+        assert!(vars.get("OLDPWD").is_some());
 
         let cmd2 = SimpleCommand {
             name: "cd".into(),
             args: vec![tmp2_path.clone()],
             assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
         };
-        execute(&cmd2, &mgr, &mut history, &mut oldpwd).unwrap();
-        assert_eq!(oldpwd.as_ref().unwrap(), &tmp1_path);
+        execute(&cmd2, &mut vars).unwrap();
+        assert_eq!(vars.get("OLDPWD"), Some(&tmp1_path));
 
         let cmd_dash = SimpleCommand {
             name: "cd".into(),
             args: vec!["-".into()],
             assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
         };
-        execute(&cmd_dash, &mgr, &mut history, &mut oldpwd).unwrap();
+        execute(&cmd_dash, &mut vars).unwrap();
         let current = std::env::current_dir().unwrap();
         assert_eq!(current.to_string_lossy(), tmp1_path);
-        assert_eq!(oldpwd.as_ref().unwrap(), &tmp2_path);
+        assert_eq!(vars.get("OLDPWD"), Some(&tmp2_path));
 
         let _ = std::env::set_current_dir(orig);
         drop(home_guard);
@@ -199,18 +325,20 @@ mod tests {
         let home_guard = EnvVarGuard::new("HOME");
         home_guard.set(home_tmp.path().to_string_lossy().as_ref());
 
-        let mgr = HistoryManager::new().unwrap();
-        let mut history = Vec::new();
-        let mut oldpwd = None;
+        let mut vars = Variables::new();
+        vars.init_pwd();
+        vars.unset("OLDPWD");
 
         let orig = std::env::current_dir().unwrap();
         let cmd = SimpleCommand {
             name: "cd".into(),
             args: vec!["-".into()],
             assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
         };
-        let res = execute(&cmd, &mgr, &mut history, &mut oldpwd).unwrap();
-        assert!(matches!(res, BuiltinResult::HandledContinue));
+        let res = execute(&cmd, &mut vars).unwrap();
+        assert!(matches!(res, BuiltinResult::HandledStatus(1)));
         let current = std::env::current_dir().unwrap();
         assert_eq!(current, orig);
 
@@ -219,16 +347,16 @@ mod tests {
 
     #[test]
     fn test_cd_help() {
-        let mgr = HistoryManager::new().unwrap_or_else(|_| HistoryManager::default());
-        let mut history = Vec::new();
-        let mut oldpwd = None;
+        let mut vars = Variables::new();
 
         let cmd = SimpleCommand {
             name: "cd".into(),
             args: vec!["--help".into()],
             assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
         };
-        let res = execute(&cmd, &mgr, &mut history, &mut oldpwd).unwrap();
+        let res = execute(&cmd, &mut vars).unwrap();
         assert!(matches!(res, BuiltinResult::HandledContinue));
     }
 }
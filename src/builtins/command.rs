@@ -0,0 +1,130 @@
+use crate::aliases::Aliases;
+use crate::builtins::common::{parse_flags, shell_quote, write_output};
+use crate::functions::Functions;
+use crate::parser::SimpleCommand;
+use crate::resolve::{self, Resolution};
+use crate::variables::Variables;
+
+use super::{dispatch, is_builtin, Builtin, BuiltinResult, ShellContext};
+
+pub struct CommandCommand;
+
+impl Builtin for CommandCommand {
+    fn name(&self) -> &'static str {
+        "command"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "command [-pvV] [name [arg ...]]"
+    }
+
+    fn about(&self) -> &'static str {
+        "Run a command bypassing functions, or describe commands."
+    }
+
+    fn run(&self, ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+        execute(ctx, cmd)
+    }
+}
+
+fn execute(ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+    // Usage: command [-pvV] [name [arg ...]]
+    // Options are expanded up to the name; the arguments are left for the
+    // command that runs
+    let mut options = Vec::new();
+    let mut operands = cmd.args.as_slice();
+    while let Some(first) = operands.first() {
+        let word = ctx.vars.expand(first)?;
+        if !word.starts_with('-') || word.len() < 2 {
+            break;
+        }
+        operands = &operands[1..];
+        let end = word == "--";
+        options.push(word);
+        if end {
+            break;
+        }
+    }
+    let (flags, _) = parse_flags("command", &options, "pvV")?;
+    if flags.contains('v') || flags.contains('V') {
+        let names = ctx.vars.expand_words(operands)?;
+        return describe(
+            &names,
+            flags.contains('V'),
+            ctx.vars,
+            ctx.functions,
+            ctx.aliases,
+        );
+    }
+
+    let Some((name, args)) = operands.split_first() else {
+        return Ok(BuiltinResult::HandledContinue);
+    };
+    let name = ctx.vars.expand(name)?;
+    let inner = SimpleCommand {
+        name: name.clone(),
+        args: args.to_vec(),
+        assignments: cmd.assignments.clone(),
+        line: cmd.line,
+        quoted: Vec::new(),
+    };
+    // Functions are skipped; builtins still run in the shell
+    if is_builtin(&name) {
+        return dispatch(ctx, &inner);
+    }
+    let mut program = None;
+    let found = match (name.contains('/'), flags.contains('p')) {
+        (true, _) => true,
+        // `-p` looks in a PATH that finds the standard utilities, without
+        // remembering them
+        (false, true) => {
+            program = resolve::search(&name, resolve::DEFAULT_PATH).next();
+            program.is_some()
+        }
+        (false, false) => {
+            let search_path = ctx.vars.get("PATH").cloned().unwrap_or_default();
+            ctx.vars.command_hash().remember(&name, &search_path)
+        }
+    };
+    if !found {
+        eprintln!("command: {}: not found", name);
+        return Ok(BuiltinResult::HandledStatus(127));
+    }
+    Ok(BuiltinResult::External(inner, program))
+}
+
+/// Print what each of `names` is, as `command -v` or, if `verbose`,
+/// `command -V` does.
+fn describe(
+    names: &[String],
+    verbose: bool,
+    vars: &Variables,
+    functions: &Functions,
+    aliases: &Aliases,
+) -> Result<BuiltinResult, String> {
+    let mut output = String::new();
+    let mut status = 0;
+    for name in names {
+        let Some(resolution) = resolve::resolve(name, vars, functions, aliases, false).pop() else {
+            if verbose {
+                eprintln!("command: {}: not found", name);
+            }
+            status = 1;
+            continue;
+        };
+        let line = match (verbose, resolution) {
+            (true, resolution) => resolution.describe(name),
+            // `-v` prints something that would run the same command again
+            (false, Resolution::Alias(value)) => format!("alias {}={}", name, shell_quote(&value)),
+            (false, Resolution::File(path, _)) => path.display().to_string(),
+            (false, _) => name.clone(),
+        };
+        output.push_str(&line);
+        output.push('\n');
+    }
+    write_output("command", &output)?;
+    Ok(match status {
+        0 => BuiltinResult::HandledContinue,
+        status => BuiltinResult::HandledStatus(status),
+    })
+}
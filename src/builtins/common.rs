@@ -1,3 +1,5 @@
+use std::io::Write;
+
 // Help template used by shell builtins
 pub const SHELL_HELP_TEMPLATE: &str = "\
 {usage-heading} {usage}
@@ -6,3 +8,45 @@ pub const SHELL_HELP_TEMPLATE: &str = "\
 
 {after-help}
 ";
+
+/// Quote a string with single quotes so it can be read back by the shell.
+pub fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// Write a builtin's output to standard output. Unlike `print!`, a failed
+/// write, such as to a closed pipe, is an error rather than a panic.
+pub fn write_output(name: &str, text: &str) -> Result<(), String> {
+    let mut stdout = std::io::stdout().lock();
+    stdout
+        .write_all(text.as_bytes())
+        .and_then(|_| stdout.flush())
+        .map_err(|e| format!("{}: write error: {}", name, e))
+}
+
+/// Split leading option words such as `-at` off `args`, up to the first
+/// operand or `--`. Returns the letters given, each of which must be in
+/// `allowed`, and the remaining operands.
+pub fn parse_flags<'a>(
+    name: &str,
+    args: &'a [String],
+    allowed: &str,
+) -> Result<(String, &'a [String]), String> {
+    let mut flags = String::new();
+    let mut args = args;
+    while let Some(first) = args.first() {
+        if first == "--" {
+            args = &args[1..];
+            break;
+        }
+        let Some(letters) = first.strip_prefix('-').filter(|l| !l.is_empty()) else {
+            break;
+        };
+        if let Some(bad) = letters.chars().find(|c| !allowed.contains(*c)) {
+            return Err(format!("{}: -{}: invalid option", name, bad));
+        }
+        flags.push_str(letters);
+        args = &args[1..];
+    }
+    Ok((flags, args))
+}
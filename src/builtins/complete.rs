@@ -1,7 +1,30 @@
 use crate::completion_registry::{CompSpec, COMP_REGISTRY};
 use crate::parser::SimpleCommand;
 
-pub fn execute(cmd: &SimpleCommand) -> Result<(), String> {
+use super::{Builtin, BuiltinResult, ShellContext};
+
+pub struct Complete;
+
+impl Builtin for Complete {
+    fn name(&self) -> &'static str {
+        "complete"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "complete [-r] [-W wordlist] name ..."
+    }
+
+    fn about(&self) -> &'static str {
+        "Specify how the arguments of commands are completed."
+    }
+
+    fn run(&self, _ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+        execute(cmd)?;
+        Ok(BuiltinResult::HandledContinue)
+    }
+}
+
+fn execute(cmd: &SimpleCommand) -> Result<(), String> {
     // Usage: complete [-r] [-W wordlist] command...
     let mut args = cmd.args.iter().peekable();
     let mut wordlist = None;
@@ -56,6 +79,8 @@ mod tests {
             name: "complete".to_string(),
             args: vec![],
             assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
         };
         assert!(execute(&cmd).is_ok());
     }
@@ -66,6 +91,8 @@ mod tests {
             name: "complete".to_string(),
             args: vec!["-W".to_string(), "foo bar".to_string(), "mycmd".to_string()],
             assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
         };
         assert!(execute(&cmd).is_ok());
 
@@ -82,6 +109,8 @@ mod tests {
             name: "complete".to_string(),
             args: vec!["-W".to_string(), "foo".to_string(), "rmcmd".to_string()],
             assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
         };
         assert!(execute(&cmd_reg).is_ok());
 
@@ -92,6 +121,8 @@ mod tests {
             name: "complete".to_string(),
             args: vec!["-r".to_string(), "rmcmd".to_string()],
             assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
         };
         assert!(execute(&cmd_rm).is_ok());
 
@@ -104,6 +135,8 @@ mod tests {
             name: "complete".to_string(),
             args: vec!["-W".to_string()],
             assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
         };
         let result = execute(&cmd);
         assert!(result.is_err());
@@ -0,0 +1,426 @@
+use crate::arrays::{self, Target};
+use crate::functions::Functions;
+use crate::parser::{Command, SimpleCommand};
+use crate::unparse;
+use crate::variables::{Attributes, Variables};
+
+use super::{Builtin, BuiltinResult, ShellContext};
+
+pub struct Declare;
+
+impl Builtin for Declare {
+    fn name(&self) -> &'static str {
+        "declare"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "declare [-aAfFgilnprux] [name[=value] ...]"
+    }
+
+    fn about(&self) -> &'static str {
+        "Set variable values and attributes."
+    }
+
+    fn run(&self, ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+        execute(cmd, ctx.vars, ctx.functions)
+    }
+}
+
+pub struct Typeset;
+
+impl Builtin for Typeset {
+    fn name(&self) -> &'static str {
+        "typeset"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "typeset [-aAfFgilnprux] [name[=value] ...]"
+    }
+
+    fn about(&self) -> &'static str {
+        "Set variable values and attributes, like declare."
+    }
+
+    fn run(&self, ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+        execute(cmd, ctx.vars, ctx.functions)
+    }
+}
+
+pub struct FunctionsCommand;
+
+impl Builtin for FunctionsCommand {
+    fn name(&self) -> &'static str {
+        "functions"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "functions [name ...]"
+    }
+
+    fn about(&self) -> &'static str {
+        "Show the definitions of functions, like declare -f."
+    }
+
+    fn run(&self, ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+        let opts = Options {
+            functions: true,
+            ..Default::default()
+        };
+        print_functions("functions", &opts, &cmd.args, ctx.functions)
+    }
+}
+
+pub struct Readonly;
+
+impl Builtin for Readonly {
+    fn name(&self) -> &'static str {
+        "readonly"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "readonly [-aAp] [name[=value] ...]"
+    }
+
+    fn about(&self) -> &'static str {
+        "Mark variables as unchangeable."
+    }
+
+    fn run(&self, ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+        readonly(cmd, ctx.vars)
+    }
+}
+
+/// The options `declare` was given.
+#[derive(Default)]
+pub(super) struct Options {
+    indexed: bool,
+    assoc: bool,
+    /// Attributes to set (`-x`) and to remove (`+x`).
+    add: Attributes,
+    remove: Attributes,
+    print: bool,
+    functions: bool,
+    function_names: bool,
+    global: bool,
+}
+
+impl Options {
+    pub(super) fn parse<'a>(
+        name: &str,
+        mut args: &'a [String],
+    ) -> Result<(Options, &'a [String]), String> {
+        let mut opts = Options::default();
+        while let Some(first) = args.first() {
+            if first == "--" {
+                args = &args[1..];
+                break;
+            }
+            let (flags, adding) = match (first.strip_prefix('-'), first.strip_prefix('+')) {
+                (Some(flags), _) if !flags.is_empty() => (flags, true),
+                (_, Some(flags)) if !flags.is_empty() => (flags, false),
+                _ => break,
+            };
+            let attrs = if adding {
+                &mut opts.add
+            } else {
+                &mut opts.remove
+            };
+            for flag in flags.chars() {
+                match flag {
+                    'i' => attrs.integer = true,
+                    'l' => attrs.lowercase = true,
+                    'u' => attrs.uppercase = true,
+                    'n' => attrs.nameref = true,
+                    'r' => attrs.readonly = true,
+                    'x' => attrs.exported = true,
+                    'a' => opts.indexed = true,
+                    'A' => opts.assoc = true,
+                    'p' => opts.print = true,
+                    'f' => opts.functions = true,
+                    'F' => opts.function_names = true,
+                    'g' => opts.global = true,
+                    _ => return Err(format!("{}: -{}: invalid option", name, flag)),
+                }
+            }
+            args = &args[1..];
+        }
+        Ok((opts, args))
+    }
+}
+
+fn execute(
+    cmd: &SimpleCommand,
+    vars: &mut Variables,
+    functions: &mut Functions,
+) -> Result<BuiltinResult, String> {
+    // Usage: declare [-aAfFgilnprux] [name[=value] ...]
+    let name = cmd.name.as_str();
+    let (opts, args) = Options::parse(name, &cmd.args)?;
+    // Inside a function, declarations are local unless `-g` is given
+    let local = vars.in_function() && !opts.global;
+    run(name, &opts, args, local, vars, functions)
+}
+
+/// `readonly [-aAp] [name[=value] ...]`: `declare -gr`, printing the
+/// readonly variables when no names are given.
+fn readonly(cmd: &SimpleCommand, vars: &mut Variables) -> Result<BuiltinResult, String> {
+    let (mut opts, args) = Options::parse("readonly", &cmd.args)?;
+    opts.add.readonly = true;
+    run("readonly", &opts, args, false, vars, &mut Functions::new())
+}
+
+fn run(
+    name: &str,
+    opts: &Options,
+    args: &[String],
+    local: bool,
+    vars: &mut Variables,
+    functions: &mut Functions,
+) -> Result<BuiltinResult, String> {
+    if opts.functions || opts.function_names {
+        return print_functions(name, opts, args, functions);
+    }
+
+    if args.is_empty() {
+        // List the variables that have every attribute asked for
+        for var in vars.names() {
+            let is_array = vars
+                .array(&var)
+                .is_some_and(|a| a.is_assoc() || !opts.assoc);
+            if !vars.attributes(&var).contains(opts.add)
+                || ((opts.indexed || opts.assoc) && !is_array)
+            {
+                continue;
+            }
+            if let Some(declaration) = vars.declaration(&var) {
+                println!("{}", declaration);
+            }
+        }
+        return Ok(BuiltinResult::HandledContinue);
+    }
+
+    let mut status = 0;
+    for arg in args {
+        if opts.print {
+            match vars.declaration(arg) {
+                Some(declaration) => println!("{}", declaration),
+                None => {
+                    eprintln!("{}: {}: not found", name, arg);
+                    status = 1;
+                }
+            }
+            continue;
+        }
+        declare(opts, arg, local, vars).map_err(|e| format!("{}: {}", name, e))?;
+    }
+
+    Ok(match status {
+        0 => BuiltinResult::HandledContinue,
+        status => BuiltinResult::HandledStatus(status),
+    })
+}
+
+/// Declare one `name[=value]` with the attributes in `opts`.
+pub(super) fn declare(
+    opts: &Options,
+    arg: &str,
+    local: bool,
+    vars: &mut Variables,
+) -> Result<(), String> {
+    let (lhs, value) = match arrays::split_assignment(arg) {
+        Some((lhs, value)) => (lhs, Some(value)),
+        None => (arg, None),
+    };
+    let name = match Target::parse(lhs) {
+        Some(target) => target.name,
+        None => return Err(format!("`{}': not a valid identifier", arg)),
+    };
+    if local && !vars.is_local(name) {
+        vars.declare_local(name, None, Attributes::default())?;
+    }
+    // Readonly is added last so that the value can still be assigned
+    let add = Attributes {
+        readonly: false,
+        ..opts.add
+    };
+    vars.set_attributes(name, add, opts.remove)?;
+    if opts.indexed || opts.assoc {
+        vars.declare_array(name, opts.assoc)?;
+    }
+    if let Some(value) = value {
+        if vars.attributes(name).nameref {
+            vars.set_nameref(name, &vars.expand(value)?)?;
+        } else {
+            vars.assign(lhs, value)?;
+        }
+    }
+    if opts.add.readonly {
+        let readonly = Attributes {
+            readonly: true,
+            ..Default::default()
+        };
+        vars.set_attributes(name, readonly, Attributes::default())?;
+    }
+    Ok(())
+}
+
+/// `declare -f` and `-F`: list the named functions, or all of them.
+/// `declare -f`: the definitions of the functions named, or of all of
+/// them. With `-F`, only their names.
+fn print_functions(
+    name: &str,
+    opts: &Options,
+    args: &[String],
+    functions: &Functions,
+) -> Result<BuiltinResult, String> {
+    let show = |function: &str, body: &[Vec<Command>]| match opts.function_names {
+        true => println!("declare -f {}", function),
+        false => print!("{}", unparse::function(function, body)),
+    };
+    if args.is_empty() {
+        for function in functions.names() {
+            if let Some(body) = functions.get(function) {
+                show(function, body);
+            }
+        }
+        return Ok(BuiltinResult::HandledContinue);
+    }
+    let mut status = 0;
+    for arg in args {
+        match functions.get(arg) {
+            Some(body) => show(arg, body),
+            None => {
+                eprintln!("{}: {}: not found", name, arg);
+                status = 1;
+            }
+        }
+    }
+    Ok(match status {
+        0 => BuiltinResult::HandledContinue,
+        status => BuiltinResult::HandledStatus(status),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arrays::{ELEMENT_SEP, LITERAL_START};
+
+    fn make_cmd(args: Vec<&str>) -> SimpleCommand {
+        SimpleCommand {
+            name: "declare".into(),
+            args: args.into_iter().map(String::from).collect(),
+            assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_declare_assoc() {
+        let mut vars = Variables::new();
+        execute(&make_cmd(vec!["-A", "m"]), &mut vars, &mut Functions::new()).unwrap();
+        vars.assign("m[key]", "v").unwrap();
+        vars.assign("m[other key]", "w").unwrap();
+        assert_eq!(vars.expand("${m[key]}").unwrap(), "v");
+        assert_eq!(vars.expand("${!m[@]}").unwrap(), "key other key");
+        assert!(vars.array("m").unwrap().is_assoc());
+
+        // An indexed array cannot become associative
+        execute(
+            &make_cmd(vec!["-a", "idx"]),
+            &mut vars,
+            &mut Functions::new(),
+        )
+        .unwrap();
+        assert!(execute(
+            &make_cmd(vec!["-A", "idx"]),
+            &mut vars,
+            &mut Functions::new()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_declare_with_literal() {
+        let mut vars = Variables::new();
+        let literal = format!("m={}[a]=1{}[b]=2", LITERAL_START, ELEMENT_SEP);
+        execute(
+            &make_cmd(vec!["-A", &literal]),
+            &mut vars,
+            &mut Functions::new(),
+        )
+        .unwrap();
+        assert_eq!(
+            vars.array("m").unwrap().to_literal(),
+            r#"([a]="1" [b]="2")"#
+        );
+    }
+
+    #[test]
+    fn test_declare_invalid() {
+        let mut vars = Variables::new();
+        assert!(execute(&make_cmd(vec!["-q", "x"]), &mut vars, &mut Functions::new()).is_err());
+        assert!(execute(&make_cmd(vec!["1x=2"]), &mut vars, &mut Functions::new()).is_err());
+    }
+
+    #[test]
+    fn test_declare_attributes() {
+        let mut vars = Variables::new();
+        let mut functions = Functions::new();
+        execute(
+            &make_cmd(vec!["-ix", "count=6*7"]),
+            &mut vars,
+            &mut functions,
+        )
+        .unwrap();
+        assert_eq!(vars.get("count").map(|s| s.as_str()), Some("42"));
+        assert!(vars.is_exported("count"));
+
+        execute(&make_cmd(vec!["+x", "count"]), &mut vars, &mut functions).unwrap();
+        assert!(!vars.is_exported("count"));
+
+        execute(&make_cmd(vec!["-r", "fixed=1"]), &mut vars, &mut functions).unwrap();
+        assert!(vars.is_readonly("fixed"));
+        assert!(execute(&make_cmd(vec!["fixed=2"]), &mut vars, &mut functions).is_err());
+        assert!(execute(&make_cmd(vec!["+r", "fixed"]), &mut vars, &mut functions).is_err());
+    }
+
+    #[test]
+    fn test_declare_in_function_is_local() {
+        let mut vars = Variables::new();
+        let mut functions = Functions::new();
+        vars.push_scope(vec![]);
+        execute(&make_cmd(vec!["inner=1"]), &mut vars, &mut functions).unwrap();
+        execute(&make_cmd(vec!["-g", "outer=2"]), &mut vars, &mut functions).unwrap();
+        vars.pop_scope();
+        assert!(vars.get("inner").is_none());
+        assert_eq!(vars.get("outer").map(|s| s.as_str()), Some("2"));
+    }
+
+    #[test]
+    fn test_declare_functions() {
+        let mut vars = Variables::new();
+        let mut functions = Functions::new();
+        functions.set("f".to_string(), vec![]);
+        let res = execute(&make_cmd(vec!["-F", "f"]), &mut vars, &mut functions).unwrap();
+        assert!(matches!(res, BuiltinResult::HandledContinue));
+        let res = execute(&make_cmd(vec!["-f", "g"]), &mut vars, &mut functions).unwrap();
+        assert!(matches!(res, BuiltinResult::HandledStatus(1)));
+    }
+
+    #[test]
+    fn test_readonly() {
+        let mut vars = Variables::new();
+        let cmd = SimpleCommand {
+            name: "readonly".into(),
+            args: vec!["ro=x".into()],
+            assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
+        };
+        readonly(&cmd, &mut vars).unwrap();
+        assert!(vars.is_readonly("ro"));
+        assert_eq!(vars.declaration("ro").unwrap(), r#"declare -r ro="x""#);
+    }
+}
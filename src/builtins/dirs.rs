@@ -0,0 +1,222 @@
+use crate::builtins::common::{parse_flags, write_output};
+use crate::parser::SimpleCommand;
+use crate::variables::Variables;
+
+use super::cd::change_dir;
+use super::{Builtin, BuiltinResult, ShellContext};
+
+pub struct Pushd;
+
+impl Builtin for Pushd {
+    fn name(&self) -> &'static str {
+        "pushd"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "pushd [dir | +N | -N]"
+    }
+
+    fn about(&self) -> &'static str {
+        "Add a directory to the directory stack, or rotate the stack."
+    }
+
+    fn run(&self, ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+        pushd(cmd, ctx.vars)
+    }
+}
+
+pub struct Popd;
+
+impl Builtin for Popd {
+    fn name(&self) -> &'static str {
+        "popd"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "popd [+N | -N]"
+    }
+
+    fn about(&self) -> &'static str {
+        "Remove a directory from the directory stack."
+    }
+
+    fn run(&self, ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+        popd(cmd, ctx.vars)
+    }
+}
+
+pub struct Dirs;
+
+impl Builtin for Dirs {
+    fn name(&self) -> &'static str {
+        "dirs"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "dirs [-clpv] [+N | -N]"
+    }
+
+    fn about(&self) -> &'static str {
+        "Display the directory stack."
+    }
+
+    fn run(&self, ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+        dirs(cmd, ctx.vars)
+    }
+}
+
+/// Whether `arg` is a `+N` or `-N` stack index rather than a directory.
+fn is_index(arg: &str) -> bool {
+    arg.strip_prefix(['+', '-'])
+        .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+}
+
+fn out_of_range(name: &str, arg: &str) -> Result<BuiltinResult, String> {
+    eprintln!("{}: {}: directory stack index out of range", name, arg);
+    Ok(BuiltinResult::HandledStatus(1))
+}
+
+fn pushd(cmd: &SimpleCommand, vars: &mut Variables) -> Result<BuiltinResult, String> {
+    let words = vars.expand_words(&cmd.args)?;
+    let previous = vars.pwd();
+    let mut stack = vars.dir_stack().clone();
+    let target = match words.as_slice() {
+        // Swap the top two entries
+        [] => match stack.remove(1) {
+            Some(top) => {
+                stack.push(previous);
+                top
+            }
+            None => {
+                eprintln!("pushd: no other directory");
+                return Ok(BuiltinResult::HandledStatus(1));
+            }
+        },
+        [arg] if is_index(arg) => match stack.index(arg) {
+            Some(index) => stack.rotate(index, &previous).unwrap_or(previous),
+            None => return out_of_range("pushd", arg),
+        },
+        [dir] => {
+            stack.push(previous);
+            dir.clone()
+        }
+        _ => return Err("pushd: too many arguments".to_string()),
+    };
+
+    // The stack only changes if the directory does
+    if let Err(e) = change_dir(&target, false, vars) {
+        eprintln!("pushd: {}", e);
+        return Ok(BuiltinResult::HandledStatus(1));
+    }
+    *vars.dir_stack_mut() = stack;
+    write_output("pushd", &format!("{}\n", listing(vars, false).join(" ")))?;
+    Ok(BuiltinResult::HandledContinue)
+}
+
+fn popd(cmd: &SimpleCommand, vars: &mut Variables) -> Result<BuiltinResult, String> {
+    let words = vars.expand_words(&cmd.args)?;
+    let index = match words.as_slice() {
+        [] => 0,
+        [arg] if is_index(arg) => match vars.dir_stack().index(arg) {
+            Some(index) => index,
+            None => return out_of_range("popd", arg),
+        },
+        [arg] => return Err(format!("popd: {}: invalid argument", arg)),
+        _ => return Err("popd: too many arguments".to_string()),
+    };
+    if vars.dir_stack().len() < 2 {
+        eprintln!("popd: directory stack empty");
+        return Ok(BuiltinResult::HandledStatus(1));
+    }
+
+    if index == 0 {
+        // Removing the current directory moves to the next one
+        let target = vars.dir_stack().get(1, "").unwrap_or_default();
+        if let Err(e) = change_dir(&target, false, vars) {
+            eprintln!("popd: {}", e);
+            return Ok(BuiltinResult::HandledStatus(1));
+        }
+        vars.dir_stack_mut().remove(1);
+    } else {
+        vars.dir_stack_mut().remove(index);
+    }
+    write_output("popd", &format!("{}\n", listing(vars, false).join(" ")))?;
+    Ok(BuiltinResult::HandledContinue)
+}
+
+fn dirs(cmd: &SimpleCommand, vars: &mut Variables) -> Result<BuiltinResult, String> {
+    let words = vars.expand_words(&cmd.args)?;
+    // `+N` and `-N` may come among the options; the last one counts
+    let (indexes, options): (Vec<String>, Vec<String>) =
+        words.into_iter().partition(|word| is_index(word));
+    let (flags, rest) = parse_flags("dirs", &options, "clpv")?;
+    if let Some(arg) = rest.first() {
+        return Err(format!("dirs: {}: invalid argument", arg));
+    }
+    if flags.contains('c') {
+        vars.dir_stack_mut().clear();
+        return Ok(BuiltinResult::HandledContinue);
+    }
+
+    let entries = listing(vars, flags.contains('l'));
+    let numbered = |(i, dir): (usize, &String)| format!("{:2}  {}\n", i, dir);
+    let output = match indexes.last() {
+        Some(arg) => match vars.dir_stack().index(arg) {
+            Some(index) if flags.contains('v') => numbered((index, &entries[index])),
+            Some(index) => format!("{}\n", entries[index]),
+            None => return out_of_range("dirs", arg),
+        },
+        None if flags.contains('v') => entries.iter().enumerate().map(numbered).collect(),
+        None if flags.contains('p') => entries.iter().map(|dir| format!("{}\n", dir)).collect(),
+        None => format!("{}\n", entries.join(" ")),
+    };
+    write_output("dirs", &output)?;
+    Ok(BuiltinResult::HandledContinue)
+}
+
+/// The directory stack, current directory first. Unless `long`, a leading
+/// `HOME` is written as `~`.
+fn listing(vars: &Variables, long: bool) -> Vec<String> {
+    let home = vars
+        .get("HOME")
+        .filter(|h| !long && !h.is_empty() && *h != "/");
+    vars.dir_stack()
+        .entries(&vars.pwd())
+        .into_iter()
+        .map(
+            |dir| match home.and_then(|h| dir.strip_prefix(h.as_str())) {
+                Some(rest) if rest.is_empty() || rest.starts_with('/') => format!("~{}", rest),
+                _ => dir,
+            },
+        )
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_index() {
+        assert!(is_index("+0"));
+        assert!(is_index("-12"));
+        assert!(!is_index("+"));
+        assert!(!is_index("3"));
+        assert!(!is_index("-x"));
+        assert!(!is_index("dir"));
+    }
+
+    #[test]
+    fn test_listing_abbreviates_home() {
+        let mut vars = Variables::new();
+        vars.set("HOME".to_string(), "/home/u".to_string());
+        vars.set("PWD".to_string(), "/home/u/src".to_string());
+        vars.dir_stack_mut().push("/home/user".to_string());
+        vars.dir_stack_mut().push("/home/u".to_string());
+        assert_eq!(listing(&vars, false), ["~/src", "~", "/home/user"]);
+        assert_eq!(
+            listing(&vars, true),
+            ["/home/u/src", "/home/u", "/home/user"]
+        );
+    }
+}
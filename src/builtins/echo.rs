@@ -0,0 +1,87 @@
+use crate::builtins::common::write_output;
+use crate::parser::SimpleCommand;
+use crate::variables::Variables;
+
+use super::printf::unescape;
+use super::{Builtin, BuiltinResult, ShellContext};
+
+pub struct Echo;
+
+impl Builtin for Echo {
+    fn name(&self) -> &'static str {
+        "echo"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "echo [-neE] [arg ...]"
+    }
+
+    fn about(&self) -> &'static str {
+        "Write arguments to standard output."
+    }
+
+    fn run(&self, ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+        execute(cmd, ctx.vars)
+    }
+}
+
+fn execute(cmd: &SimpleCommand, vars: &mut Variables) -> Result<BuiltinResult, String> {
+    // Usage: echo [-neE] [arg ...]
+    let args = vars.expand_words(&cmd.args)?;
+    write_output("echo", &render(&args))?;
+    Ok(BuiltinResult::HandledContinue)
+}
+
+/// The text `echo` prints for `args`, options included.
+fn render(args: &[String]) -> String {
+    let mut newline = true;
+    let mut escapes = false;
+    let mut args = args;
+    // Only words made entirely of option letters are options
+    while let Some(flags) = args.first().and_then(|a| a.strip_prefix('-')) {
+        if flags.is_empty() || !flags.chars().all(|f| "neE".contains(f)) {
+            break;
+        }
+        for flag in flags.chars() {
+            match flag {
+                'n' => newline = false,
+                'e' => escapes = true,
+                _ => escapes = false,
+            }
+        }
+        args = &args[1..];
+    }
+
+    let mut output = args.join(" ");
+    if escapes {
+        let (text, stop) = unescape(&output, true);
+        output = text;
+        // `\c` also drops the newline
+        newline &= !stop;
+    }
+    if newline {
+        output.push('\n');
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn echo(args: &[&str]) -> String {
+        let args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+        render(&args)
+    }
+
+    #[test]
+    fn test_echo_options() {
+        assert_eq!(echo(&["a", "b"]), "a b\n");
+        assert_eq!(echo(&["-n", "a"]), "a");
+        assert_eq!(echo(&["-e", "a\\tb"]), "a\tb\n");
+        assert_eq!(echo(&["-eE", "a\\tb"]), "a\\tb\n");
+        assert_eq!(echo(&["-e", "a\\cb", "c"]), "a");
+        // Anything else starting with `-` is printed
+        assert_eq!(echo(&["-x", "-"]), "-x -\n");
+    }
+}
@@ -0,0 +1,55 @@
+use crate::builtins::common::{parse_flags, write_output};
+use crate::parser::SimpleCommand;
+
+use super::{all, find, is_enabled, set_enabled, Builtin, BuiltinResult, ShellContext};
+
+pub struct Enable;
+
+impl Builtin for Enable {
+    fn name(&self) -> &'static str {
+        "enable"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "enable [-an] [name ...]"
+    }
+
+    fn about(&self) -> &'static str {
+        "Enable and disable builtins."
+    }
+
+    fn run(&self, ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+        let words = ctx.vars.expand_words(&cmd.args)?;
+        let (flags, names) = parse_flags("enable", &words, "an")?;
+        let disable = flags.contains('n');
+
+        if names.is_empty() {
+            // Lists the enabled builtins, the disabled ones with `-n`, or
+            // all of them with `-a`, in a form that can be run again
+            let mut output = String::new();
+            for builtin in all() {
+                let enabled = is_enabled(builtin.name());
+                if flags.contains('a') || enabled != disable {
+                    let flag = if enabled { "" } else { "-n " };
+                    output.push_str(&format!("enable {}{}\n", flag, builtin.name()));
+                }
+            }
+            write_output("enable", &output)?;
+            return Ok(BuiltinResult::HandledContinue);
+        }
+
+        let mut status = 0;
+        for name in names {
+            if find(name).is_none() {
+                eprintln!("enable: {}: not a shell builtin", name);
+                status = 1;
+                continue;
+            }
+            set_enabled(name, !disable);
+        }
+        Ok(match status {
+            0 => BuiltinResult::HandledContinue,
+            status => BuiltinResult::HandledStatus(status),
+        })
+    }
+}
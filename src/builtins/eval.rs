@@ -0,0 +1,35 @@
+use crate::parser::SimpleCommand;
+use crate::variables::Variables;
+
+use super::{Builtin, BuiltinResult, ShellContext};
+
+pub struct Eval;
+
+impl Builtin for Eval {
+    fn name(&self) -> &'static str {
+        "eval"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "eval [arg ...]"
+    }
+
+    fn about(&self) -> &'static str {
+        "Run the arguments as a shell command."
+    }
+
+    fn run(&self, ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+        execute(cmd, ctx.vars)
+    }
+}
+
+fn execute(cmd: &SimpleCommand, vars: &mut Variables) -> Result<BuiltinResult, String> {
+    // Usage: eval [arg ...]
+    let args = vars.expand_words(&cmd.args)?;
+    let script = args.join(" ");
+    if script.trim().is_empty() {
+        return Ok(BuiltinResult::HandledContinue);
+    }
+    // The caller parses and runs it, since that needs the executor
+    Ok(BuiltinResult::Eval(script))
+}
@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::io::Write;
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::path::Path;
+
+use nix::errno::Errno;
+
+use crate::history::HistoryManager;
+use crate::parser::SimpleCommand;
+use crate::resolve;
+use crate::variables::Variables;
+
+use super::{Builtin, BuiltinResult, ShellContext};
+
+pub struct Exec;
+
+impl Builtin for Exec {
+    fn name(&self) -> &'static str {
+        "exec"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "exec [-cl] [-a name] [command [argument ...]]"
+    }
+
+    fn about(&self) -> &'static str {
+        "Replace the shell with a command, or keep redirections."
+    }
+
+    fn run(&self, ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+        execute(cmd, ctx.history_mgr, ctx.command_history, ctx.vars)
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Options {
+    /// `-a`: the name the new program sees as its `argv[0]`
+    name: Option<String>,
+    /// `-c`: start the program with an empty environment
+    clean: bool,
+    /// `-l`: put a dash in front of `argv[0]`, as login shells do
+    login: bool,
+}
+
+impl Options {
+    fn parse(mut args: &[String]) -> Result<(Options, &[String]), String> {
+        let mut opts = Options::default();
+        while let Some(first) = args.first() {
+            if first == "--" {
+                args = &args[1..];
+                break;
+            }
+            let Some(flags) = first.strip_prefix('-').filter(|f| !f.is_empty()) else {
+                break;
+            };
+            args = &args[1..];
+            for (i, flag) in flags.char_indices() {
+                match flag {
+                    'c' => opts.clean = true,
+                    'l' => opts.login = true,
+                    'a' => {
+                        // The name is the rest of this word, or else the next word
+                        let rest = &flags[i + 1..];
+                        if !rest.is_empty() {
+                            opts.name = Some(rest.to_string());
+                        } else {
+                            let (name, remaining) = args
+                                .split_first()
+                                .ok_or("exec: -a: option requires an argument")?;
+                            opts.name = Some(name.clone());
+                            args = remaining;
+                        }
+                        break;
+                    }
+                    _ => return Err(format!("exec: -{}: invalid option", flag)),
+                }
+            }
+        }
+        Ok((opts, args))
+    }
+}
+
+fn execute(
+    cmd: &SimpleCommand,
+    history_mgr: &HistoryManager,
+    command_history: &[String],
+    vars: &mut Variables,
+) -> Result<BuiltinResult, String> {
+    // Usage: exec [-cl] [-a name] [command [argument ...]]
+    let args = vars.expand_words(&cmd.args)?;
+    let (opts, words) = Options::parse(&args)?;
+    let Some((command, args)) = words.split_first() else {
+        // Redirections on their own are applied, and kept, by the executor
+        return Ok(BuiltinResult::HandledContinue);
+    };
+
+    let mut argv0 = opts.name.unwrap_or_else(|| command.clone());
+    if opts.login {
+        argv0.insert(0, '-');
+    }
+    let mut env = match opts.clean {
+        true => HashMap::new(),
+        false => vars.to_env_vars(),
+    };
+    // Prefix assignments (`VAR=value exec cmd`) reach the new program
+    for (key, _) in &cmd.assignments {
+        if let Some(value) = vars.get(key) {
+            env.insert(key.clone(), value.clone());
+        }
+    }
+    // The EXIT trap does not run, but history is kept as on `exit`
+    if vars.is_interactive() {
+        if let Err(e) = history_mgr.save(command_history) {
+            eprintln!("exec: {}", e);
+        }
+    }
+
+    let errno = match resolve::find_command(command, vars) {
+        Some(program) => replace_process(&program, &argv0, args, &env),
+        None => Errno::ENOENT,
+    };
+    let status = match errno {
+        Errno::ENOENT => {
+            eprintln!("exec: {}: not found", command);
+            127
+        }
+        _ => {
+            eprintln!("exec: {}: {}", command, errno.desc());
+            126
+        }
+    };
+    // Only an interactive shell survives a failed exec
+    Ok(match vars.is_interactive() {
+        true => BuiltinResult::HandledStatus(status),
+        false => BuiltinResult::HandledExit(status),
+    })
+}
+
+/// Replace the shell with the program at `path`. A file the kernel cannot
+/// run, such as a script without a `#!` line, is run by a new pmsh instead.
+/// Returns only if that fails.
+fn replace_process(
+    path: &Path,
+    argv0: &str,
+    args: &[String],
+    env: &HashMap<String, String>,
+) -> Errno {
+    let argv: Result<Vec<CString>, _> = std::iter::once(argv0)
+        .chain(args.iter().map(String::as_str))
+        .map(CString::new)
+        .collect();
+    let envp: Result<Vec<CString>, _> = env
+        .iter()
+        .map(|(key, value)| CString::new(format!("{}={}", key, value)))
+        .collect();
+    let (Ok(program), Ok(mut argv), Ok(envp)) =
+        (CString::new(path.as_os_str().as_bytes()), argv, envp)
+    else {
+        return Errno::EINVAL;
+    };
+
+    let _ = std::io::stdout().flush();
+    let _ = std::io::stderr().flush();
+    let Err(errno) = nix::unistd::execve(&program, &argv, &envp);
+    if errno != Errno::ENOEXEC {
+        return errno;
+    }
+    let Some(shell) = std::env::current_exe()
+        .ok()
+        .and_then(|exe| CString::new(exe.into_os_string().into_vec()).ok())
+    else {
+        return errno;
+    };
+    argv[0] = program;
+    argv.insert(0, shell.clone());
+    let Err(errno) = nix::unistd::execve(&shell, &argv, &envp);
+    errno
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<(Options, Vec<String>), String> {
+        let args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+        Options::parse(&args).map(|(opts, rest)| (opts, rest.to_vec()))
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let (opts, rest) = parse(&["-cl", "-a", "name", "prog", "-x"]).unwrap();
+        assert!(opts.clean && opts.login);
+        assert_eq!(opts.name.as_deref(), Some("name"));
+        assert_eq!(rest, ["prog", "-x"]);
+
+        let (opts, rest) = parse(&["-aname", "--", "-prog"]).unwrap();
+        assert_eq!(opts.name.as_deref(), Some("name"));
+        assert_eq!(rest, ["-prog"]);
+
+        assert!(parse(&["-a"]).is_err());
+        assert!(parse(&["-z", "prog"]).is_err());
+    }
+}
@@ -1,9 +1,33 @@
 use crate::builtins::common::SHELL_HELP_TEMPLATE;
 use crate::history::HistoryManager;
 use crate::parser::SimpleCommand;
-use clap::Parser;
+use clap::{CommandFactory, Parser};
 
-use super::BuiltinResult;
+use super::{Builtin, BuiltinResult, ShellContext};
+
+pub struct Exit;
+
+impl Builtin for Exit {
+    fn name(&self) -> &'static str {
+        "exit"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "exit [n]"
+    }
+
+    fn about(&self) -> &'static str {
+        "Exit the shell."
+    }
+
+    fn command(&self) -> clap::Command {
+        ExitArgs::command()
+    }
+
+    fn run(&self, ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+        execute(cmd, ctx.history_mgr, ctx.command_history)
+    }
+}
 
 /// Exit the shell
 #[derive(Parser, Debug)]
@@ -17,7 +41,7 @@ struct ExitArgs {
 }
 
 #[allow(clippy::ptr_arg)]
-pub fn execute(
+fn execute(
     cmd: &SimpleCommand,
     history_mgr: &HistoryManager,
     command_history: &mut Vec<String>,
@@ -91,6 +115,8 @@ mod tests {
             name: "exit".into(),
             args: vec![],
             assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
         };
 
         let res = execute(&cmd, &mgr, &mut history).unwrap();
@@ -114,6 +140,8 @@ mod tests {
             name: "exit".into(),
             args: vec![],
             assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
         };
 
         let res = execute(&cmd, &mgr, &mut history);
@@ -135,6 +163,8 @@ mod tests {
             name: "exit".into(),
             args: vec!["--help".into()],
             assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
         };
         let res = execute(&cmd, &mgr, &mut history).unwrap();
         assert!(matches!(res, BuiltinResult::HandledContinue));
@@ -1,9 +1,29 @@
 use crate::parser::SimpleCommand;
 use crate::variables::Variables;
 
-use super::BuiltinResult;
+use super::{Builtin, BuiltinResult, ShellContext};
 
-pub fn execute(cmd: &SimpleCommand, vars: &mut Variables) -> Result<BuiltinResult, String> {
+pub struct Export;
+
+impl Builtin for Export {
+    fn name(&self) -> &'static str {
+        "export"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "export [name[=value] ...]"
+    }
+
+    fn about(&self) -> &'static str {
+        "Mark variables to be passed to child processes."
+    }
+
+    fn run(&self, ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+        execute(cmd, ctx.vars)
+    }
+}
+
+fn execute(cmd: &SimpleCommand, vars: &mut Variables) -> Result<BuiltinResult, String> {
     if cmd.args.is_empty() {
         // Print all exported variables
         let mut exported: Vec<(String, String)> = vars
@@ -22,8 +42,8 @@ pub fn execute(cmd: &SimpleCommand, vars: &mut Variables) -> Result<BuiltinResul
             // export NAME=value — set and export
             let name = &arg[..eq_pos];
             let value = &arg[eq_pos + 1..];
-            let expanded = vars.expand(value);
-            vars.set(name.to_string(), expanded);
+            vars.assign(name, value)
+                .map_err(|e| format!("export: {}", e))?;
             vars.export(name);
         } else {
             // export NAME — mark existing variable as exported
@@ -44,6 +64,8 @@ mod tests {
             name: "export".into(),
             args: args.into_iter().map(String::from).collect(),
             assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
         }
     }
 
@@ -0,0 +1,234 @@
+use crate::arrays;
+use crate::parser::SimpleCommand;
+use crate::variables::Variables;
+
+use super::{Builtin, BuiltinResult, ShellContext};
+
+pub struct Getopts;
+
+impl Builtin for Getopts {
+    fn name(&self) -> &'static str {
+        "getopts"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "getopts optstring name [arg ...]"
+    }
+
+    fn about(&self) -> &'static str {
+        "Parse options from the positional parameters."
+    }
+
+    fn run(&self, ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+        execute(cmd, ctx.vars)
+    }
+}
+
+/// What one call to `getopts` found.
+#[derive(Debug, PartialEq)]
+enum Found {
+    /// An option, with its argument if it takes one
+    Option(char, Option<String>),
+    /// A letter that is not in the option string
+    Unknown(char),
+    /// An option whose argument is missing
+    MissingArgument(char),
+    /// There are no more options
+    End,
+}
+
+fn execute(cmd: &SimpleCommand, vars: &mut Variables) -> Result<BuiltinResult, String> {
+    // Usage: getopts optstring name [arg ...]
+    let words = vars.expand_words(&cmd.args)?;
+    let [optstring, name, args @ ..] = words.as_slice() else {
+        return Err("getopts: usage: getopts optstring name [arg ...]".to_string());
+    };
+    if !arrays::is_name(name) {
+        return Err(format!("getopts: `{}': not a valid identifier", name));
+    }
+    // Nothing is changed unless every variable getopts sets can be
+    for target in ["OPTIND", name, "OPTARG"] {
+        vars.check_writable(target)
+            .map_err(|e| format!("getopts: {}", e))?;
+    }
+    let args = match args {
+        [] => vars.get_positional_args(),
+        args => args.to_vec(),
+    };
+    // A leading `:` reports errors through the variables instead
+    let (silent, optstring) = match optstring.strip_prefix(':') {
+        Some(rest) => (true, rest),
+        None => (false, optstring.as_str()),
+    };
+    let report = !silent && vars.get("OPTERR").is_none_or(|v| v != "0");
+
+    let optind = vars
+        .get("OPTIND")
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .filter(|&optind| optind > 0)
+        .unwrap_or(1);
+    // Within a word of clustered options, OPTIND stays on the word
+    let offset = match vars.getopts_cursor() {
+        Some((index, offset)) if index == optind => offset,
+        _ => 0,
+    };
+    let (found, optind, offset) = next_option(optstring, &args, optind, offset);
+    vars.set("OPTIND".to_string(), optind.to_string());
+    vars.set_getopts_cursor((offset != 0).then_some((optind, offset)));
+
+    let arg0 = vars.expand("$0")?;
+    let end = found == Found::End;
+    let (value, optarg) = match found {
+        Found::Option(letter, arg) => (letter, arg),
+        Found::Unknown(letter) => {
+            if report {
+                eprintln!("{}: illegal option -- {}", arg0, letter);
+            }
+            ('?', silent.then(|| letter.to_string()))
+        }
+        Found::MissingArgument(letter) if silent => (':', Some(letter.to_string())),
+        Found::MissingArgument(letter) => {
+            if report {
+                eprintln!("{}: option requires an argument -- {}", arg0, letter);
+            }
+            ('?', None)
+        }
+        Found::End => ('?', None),
+    };
+    vars.set(name.clone(), value.to_string());
+    match optarg {
+        Some(arg) => vars.set("OPTARG".to_string(), arg),
+        None => vars.unset("OPTARG"),
+    }
+    Ok(match end {
+        true => BuiltinResult::HandledStatus(1),
+        false => BuiltinResult::HandledContinue,
+    })
+}
+
+/// Look for an option in word `optind` of `args`, counting from 1,
+/// `offset` characters into it, or from its start if `offset` is 0.
+/// Returns what was found and the `optind` and `offset` to continue from.
+fn next_option(
+    optstring: &str,
+    args: &[String],
+    optind: usize,
+    offset: usize,
+) -> (Found, usize, usize) {
+    let Some(word) = args.get(optind - 1) else {
+        return (Found::End, optind, 0);
+    };
+    let word: Vec<char> = word.chars().collect();
+    let mut offset = offset;
+    if offset == 0 || offset >= word.len() {
+        // Options end at the first word that is not one, or after `--`
+        if word.len() < 2 || word[0] != '-' {
+            return (Found::End, optind, 0);
+        }
+        if word == ['-', '-'] {
+            return (Found::End, optind + 1, 0);
+        }
+        offset = 1;
+    }
+
+    let letter = word[offset];
+    offset += 1;
+    let (optind, offset) = match offset == word.len() {
+        true => (optind + 1, 0),
+        false => (optind, offset),
+    };
+    let takes_argument = match optstring.find(letter) {
+        Some(pos) if letter != ':' => optstring[pos + letter.len_utf8()..].starts_with(':'),
+        _ => return (Found::Unknown(letter), optind, offset),
+    };
+    if !takes_argument {
+        return (Found::Option(letter, None), optind, offset);
+    }
+    // The argument is the rest of the word, or else the next word
+    if offset != 0 {
+        let rest = word[offset..].iter().collect();
+        return (Found::Option(letter, Some(rest)), optind + 1, 0);
+    }
+    match args.get(optind - 1) {
+        Some(arg) => (Found::Option(letter, Some(arg.clone())), optind + 1, 0),
+        None => (Found::MissingArgument(letter), optind, 0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every result of calling getopts until the options end.
+    fn all_options(optstring: &str, args: &[&str]) -> (Vec<Found>, usize) {
+        let args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+        let (mut optind, mut offset) = (1, 0);
+        let mut found = Vec::new();
+        loop {
+            let next;
+            (next, optind, offset) = next_option(optstring, &args, optind, offset);
+            if next == Found::End {
+                return (found, optind);
+            }
+            found.push(next);
+        }
+    }
+
+    #[test]
+    fn test_clustered_options_and_arguments() {
+        let (found, optind) = all_options("ab:c", &["-ac", "-b", "val", "-bx", "file"]);
+        assert_eq!(
+            found,
+            [
+                Found::Option('a', None),
+                Found::Option('c', None),
+                Found::Option('b', Some("val".into())),
+                Found::Option('b', Some("x".into())),
+            ]
+        );
+        assert_eq!(optind, 5);
+    }
+
+    #[test]
+    fn test_end_of_options() {
+        assert_eq!(
+            all_options("a", &["-a", "--", "-a"]),
+            (vec![Found::Option('a', None)], 3)
+        );
+        assert_eq!(all_options("a", &["-", "-a"]), (vec![], 1));
+        assert_eq!(all_options("a", &[]), (vec![], 1));
+    }
+
+    #[test]
+    fn test_errors() {
+        let (found, _) = all_options("ab:", &["-x:", "-b"]);
+        assert_eq!(
+            found,
+            [
+                Found::Unknown('x'),
+                Found::Unknown(':'),
+                Found::MissingArgument('b')
+            ]
+        );
+    }
+
+    #[test]
+    fn test_getopts_sets_variables() {
+        let mut vars = Variables::new();
+        vars.set_positional_args(vec!["-ab".into(), "-c".into(), "arg".into(), "rest".into()]);
+        let cmd = SimpleCommand {
+            name: "getopts".into(),
+            args: vec![":abc:".into(), "opt".into()],
+            assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
+        };
+        let mut seen = Vec::new();
+        while let Ok(BuiltinResult::HandledContinue) = execute(&cmd, &mut vars) {
+            let optarg = vars.get("OPTARG").cloned().unwrap_or_default();
+            seen.push(format!("{}{}", vars.get("opt").unwrap(), optarg));
+        }
+        assert_eq!(seen, ["a", "b", "carg"]);
+        assert_eq!(vars.get("OPTIND").unwrap(), "4");
+    }
+}
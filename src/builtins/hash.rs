@@ -0,0 +1,108 @@
+use crate::builtins::common::write_output;
+use crate::builtins::is_builtin;
+use crate::functions::Functions;
+use crate::parser::SimpleCommand;
+use crate::variables::Variables;
+
+use super::{Builtin, BuiltinResult, ShellContext};
+
+pub struct Hash;
+
+impl Builtin for Hash {
+    fn name(&self) -> &'static str {
+        "hash"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "hash [-r] [-p path] [name ...]"
+    }
+
+    fn about(&self) -> &'static str {
+        "Remember or display where commands are found."
+    }
+
+    fn run(&self, ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+        execute(cmd, ctx.vars, ctx.functions)
+    }
+}
+
+fn execute(
+    cmd: &SimpleCommand,
+    vars: &mut Variables,
+    functions: &Functions,
+) -> Result<BuiltinResult, String> {
+    // Usage: hash [-r] [-p path] [name ...]
+    let words = vars.expand_words(&cmd.args)?;
+    let mut args = words.as_slice();
+    let mut clear = false;
+    let mut path = None;
+    while let Some(first) = args.first() {
+        match first.as_str() {
+            "--" => {
+                args = &args[1..];
+                break;
+            }
+            "-r" => clear = true,
+            "-p" => {
+                let (value, rest) = args[1..]
+                    .split_first()
+                    .ok_or("hash: -p: option requires an argument")?;
+                path = Some(value.clone());
+                args = rest;
+                continue;
+            }
+            opt if opt.starts_with('-') && opt.len() > 1 => {
+                return Err(format!("hash: {}: invalid option", opt));
+            }
+            _ => break,
+        }
+        args = &args[1..];
+    }
+
+    let search_path = vars.get("PATH").cloned().unwrap_or_default();
+    let mut hash = vars.command_hash();
+    if clear {
+        hash.clear();
+    }
+    if let Some(path) = path {
+        if args.is_empty() {
+            return Err("hash: -p: a command name is required".to_string());
+        }
+        for name in args {
+            hash.insert(name.clone(), path.clone().into(), &search_path);
+        }
+        return Ok(BuiltinResult::HandledContinue);
+    }
+    if args.is_empty() {
+        if !clear {
+            let mut output = String::new();
+            for (_, hits, path) in hash.entries(&search_path) {
+                if output.is_empty() {
+                    output.push_str("hits\tcommand\n");
+                }
+                output.push_str(&format!("{:4}\t{}\n", hits, path.display()));
+            }
+            if output.is_empty() {
+                output.push_str("hash: hash table empty\n");
+            }
+            write_output("hash", &output)?;
+        }
+        return Ok(BuiltinResult::HandledContinue);
+    }
+
+    let mut status = 0;
+    for name in args {
+        // Paths, builtins and functions are never looked up in PATH
+        if name.contains('/') || is_builtin(name) || functions.get(name).is_some() {
+            continue;
+        }
+        if !hash.remember(name, &search_path) {
+            eprintln!("hash: {}: not found", name);
+            status = 1;
+        }
+    }
+    Ok(match status {
+        0 => BuiltinResult::HandledContinue,
+        status => BuiltinResult::HandledStatus(status),
+    })
+}
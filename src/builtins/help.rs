@@ -0,0 +1,76 @@
+use crate::builtins::common::{parse_flags, write_output};
+use crate::parser::SimpleCommand;
+use crate::pattern;
+
+use super::{all, is_enabled, Builtin, BuiltinResult, ShellContext};
+
+pub struct Help;
+
+impl Builtin for Help {
+    fn name(&self) -> &'static str {
+        "help"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "help [-ds] [pattern ...]"
+    }
+
+    fn about(&self) -> &'static str {
+        "Display information about builtins."
+    }
+
+    fn run(&self, ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+        let words = ctx.vars.expand_words(&cmd.args)?;
+        let (flags, patterns) = parse_flags("help", &words, "ds")?;
+        match patterns.is_empty() {
+            true => write_output("help", &summary())?,
+            false => return describe(patterns, &flags),
+        }
+        Ok(BuiltinResult::HandledContinue)
+    }
+}
+
+/// The synopsis of every builtin, marking the disabled ones.
+fn summary() -> String {
+    let mut output = String::from(
+        "These shell commands are defined internally. \
+         Type `help name' to find out more about `name'.\n\
+         A star (*) next to a name means that the command is disabled.\n\n",
+    );
+    for builtin in all() {
+        let marker = if is_enabled(builtin.name()) { ' ' } else { '*' };
+        output.push_str(&format!("{}{}\n", marker, builtin.synopsis()));
+    }
+    output
+}
+
+/// Describe the builtins whose names match `patterns`: only the synopsis
+/// with `-s`, the one-line description with `-d`, or else in full.
+fn describe(patterns: &[String], flags: &str) -> Result<BuiltinResult, String> {
+    let mut output = String::new();
+    let mut status = 0;
+    for pat in patterns {
+        let mut matched = false;
+        for builtin in all().filter(|b| pattern::matches(pat, b.name())) {
+            matched = true;
+            if flags.contains('s') {
+                output.push_str(&format!("{}: {}\n", builtin.name(), builtin.synopsis()));
+            } else if flags.contains('d') {
+                output.push_str(&format!("{} - {}\n", builtin.name(), builtin.about()));
+            } else {
+                let help = builtin.command().render_help().to_string();
+                output.push_str(help.trim_end());
+                output.push('\n');
+            }
+        }
+        if !matched {
+            eprintln!("help: no help topics match `{}'", pat);
+            status = 1;
+        }
+    }
+    write_output("help", &output)?;
+    Ok(match status {
+        0 => BuiltinResult::HandledContinue,
+        status => BuiltinResult::HandledStatus(status),
+    })
+}
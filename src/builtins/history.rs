@@ -1,9 +1,33 @@
 use crate::builtins::common::SHELL_HELP_TEMPLATE;
 use crate::history::HistoryManager;
 use crate::parser::SimpleCommand;
-use clap::Parser;
+use clap::{CommandFactory, Parser};
 
-use super::BuiltinResult;
+use super::{Builtin, BuiltinResult, ShellContext};
+
+pub struct History;
+
+impl Builtin for History {
+    fn name(&self) -> &'static str {
+        "history"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "history"
+    }
+
+    fn about(&self) -> &'static str {
+        "Display the command history list with line numbers."
+    }
+
+    fn command(&self) -> clap::Command {
+        HistoryArgs::command()
+    }
+
+    fn run(&self, ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+        execute(cmd, ctx.history_mgr, ctx.command_history)
+    }
+}
 
 /// Display command history
 #[derive(Parser, Debug)]
@@ -14,7 +38,7 @@ use super::BuiltinResult;
 struct HistoryArgs {}
 
 #[allow(clippy::ptr_arg)]
-pub fn execute(
+fn execute(
     cmd: &SimpleCommand,
     _history_mgr: &HistoryManager,
     command_history: &mut Vec<String>,
@@ -52,6 +76,8 @@ mod tests {
             name: "history".to_string(),
             args: vec![],
             assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
         };
 
         let res = execute(&cmd, &mgr, &mut history).unwrap();
@@ -67,6 +93,8 @@ mod tests {
             name: "history".into(),
             args: vec!["-h".into()],
             assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
         };
         let res = execute(&cmd, &mgr, &mut history).unwrap();
         assert!(matches!(res, BuiltinResult::HandledContinue));
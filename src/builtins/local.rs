@@ -0,0 +1,123 @@
+use crate::builtins::common::shell_quote;
+use crate::parser::SimpleCommand;
+use crate::variables::Variables;
+
+use super::declare::{self, Options};
+use super::{Builtin, BuiltinResult, ShellContext};
+
+pub struct Local;
+
+impl Builtin for Local {
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "local [-aAilnrux] name[=value] ..."
+    }
+
+    fn about(&self) -> &'static str {
+        "Define variables local to a function."
+    }
+
+    fn run(&self, ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+        execute(cmd, ctx.vars)
+    }
+}
+
+fn execute(cmd: &SimpleCommand, vars: &mut Variables) -> Result<BuiltinResult, String> {
+    // Usage: local [-aAilnrux] name[=value] ...
+    if !vars.in_function() {
+        return Err("local: can only be used in a function".to_string());
+    }
+
+    let (opts, args) = Options::parse("local", &cmd.args)?;
+    if args.is_empty() {
+        for (name, value) in vars.current_locals() {
+            match value {
+                Some(v) => println!("{}={}", name, shell_quote(&v)),
+                None => println!("{}", name),
+            }
+        }
+        return Ok(BuiltinResult::HandledContinue);
+    }
+
+    for arg in args {
+        declare::declare(&opts, arg, true, vars).map_err(|e| format!("local: {}", e))?;
+    }
+
+    Ok(BuiltinResult::HandledContinue)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_cmd(args: Vec<&str>) -> SimpleCommand {
+        SimpleCommand {
+            name: "local".into(),
+            args: args.into_iter().map(String::from).collect(),
+            assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_local_outside_function_fails() {
+        let mut vars = Variables::new();
+        let res = execute(&make_cmd(vec!["x=1"]), &mut vars);
+        assert_eq!(res.err().unwrap(), "local: can only be used in a function");
+    }
+
+    #[test]
+    fn test_local_shadows_and_is_dropped_with_scope() {
+        let mut vars = Variables::new();
+        vars.set("LOCAL_TEST".to_string(), "global".to_string());
+        vars.push_scope(vec![]);
+
+        execute(&make_cmd(vec!["LOCAL_TEST=inner"]), &mut vars).unwrap();
+        assert_eq!(vars.get("LOCAL_TEST").map(|s| s.as_str()), Some("inner"));
+
+        vars.pop_scope();
+        assert_eq!(vars.get("LOCAL_TEST").map(|s| s.as_str()), Some("global"));
+    }
+
+    #[test]
+    fn test_local_expands_value() {
+        let mut vars = Variables::new();
+        vars.push_scope(vec!["first".to_string()]);
+        execute(&make_cmd(vec!["arg=$1"]), &mut vars).unwrap();
+        assert_eq!(vars.get("arg").map(|s| s.as_str()), Some("first"));
+    }
+
+    #[test]
+    fn test_local_flags() {
+        let mut vars = Variables::new();
+        vars.push_scope(vec![]);
+        execute(&make_cmd(vec!["-rx", "FLAGGED=1"]), &mut vars).unwrap();
+        assert!(vars.is_readonly("FLAGGED"));
+        assert!(vars.is_exported("FLAGGED"));
+
+        // Redeclaring a readonly local is an error
+        assert!(execute(&make_cmd(vec!["FLAGGED=2"]), &mut vars).is_err());
+        assert_eq!(vars.get("FLAGGED").map(|s| s.as_str()), Some("1"));
+    }
+
+    #[test]
+    fn test_local_invalid_identifier_and_option() {
+        let mut vars = Variables::new();
+        vars.push_scope(vec![]);
+        assert!(execute(&make_cmd(vec!["1abc=x"]), &mut vars).is_err());
+        assert!(execute(&make_cmd(vec!["-q", "x"]), &mut vars).is_err());
+    }
+
+    #[test]
+    fn test_local_without_args_lists() {
+        let mut vars = Variables::new();
+        vars.push_scope(vec![]);
+        execute(&make_cmd(vec!["a=1", "b"]), &mut vars).unwrap();
+        let res = execute(&make_cmd(vec![]), &mut vars).unwrap();
+        assert!(matches!(res, BuiltinResult::HandledContinue));
+    }
+}
@@ -1,57 +1,222 @@
+mod alias;
+mod builtin;
 mod cd;
+mod command;
 pub mod common;
 mod compgen;
 mod complete;
+mod declare;
+mod dirs;
+mod echo;
+mod enable;
+mod eval;
+mod exec;
 mod exit;
 mod export;
+mod getopts;
+mod hash;
+mod help;
 mod history;
+mod local;
+mod printf;
+mod read;
+mod r#return;
+mod set;
+mod shift;
+mod source;
+mod test;
+mod trap;
+mod r#type;
+mod ulimit;
+mod umask;
+mod unalias;
 mod unset;
 mod version;
+mod which;
 
+use crate::aliases::Aliases;
+use crate::functions::Functions;
 use crate::history::HistoryManager;
 use crate::parser::SimpleCommand;
 use crate::variables::Variables;
+use common::SHELL_HELP_TEMPLATE;
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::sync::RwLock;
 
 pub enum BuiltinResult {
     HandledContinue,
-    HandledExit(i32),   // Exit with code
-    SourceFile(String), // Source a file
+    HandledExit(i32),                         // Exit with code
+    HandledStatus(i32),                       // Handled, finishing with a non-default exit status
+    SourceFile(String),                       // Source a file
+    Eval(String),                             // Parse and run a string in the current shell
+    External(SimpleCommand, Option<PathBuf>), // Run a program only, from the file if found
     NotHandled,
 }
 
+/// Everything of the shell's state a builtin may use or change.
+pub struct ShellContext<'a> {
+    pub history_mgr: &'a HistoryManager,
+    pub command_history: &'a mut Vec<String>,
+    pub vars: &'a mut Variables,
+    pub functions: &'a mut Functions,
+    pub aliases: &'a mut Aliases,
+}
+
+/// A command the shell runs itself, describing itself for `help`.
+pub trait Builtin: Sync {
+    /// The name the builtin is run by.
+    fn name(&self) -> &'static str;
+
+    /// How it is called, as `help` lists it.
+    fn synopsis(&self) -> &'static str;
+
+    /// What it does, in one line.
+    fn about(&self) -> &'static str;
+
+    /// Its usage and description, for `help name`. Builtins that parse their
+    /// arguments with clap return that parser instead.
+    fn command(&self) -> clap::Command {
+        clap::Command::new(self.name())
+            .about(self.about())
+            .override_usage(self.synopsis())
+            .help_template(SHELL_HELP_TEMPLATE)
+    }
+
+    fn run(&self, ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String>;
+}
+
+/// Every builtin, sorted by name.
+static REGISTRY: &[&dyn Builtin] = &[
+    &source::Dot,
+    &test::Bracket,
+    &alias::Alias,
+    &builtin::BuiltinCommand,
+    &cd::Cd,
+    &command::CommandCommand,
+    &compgen::Compgen,
+    &complete::Complete,
+    &declare::Declare,
+    &dirs::Dirs,
+    &echo::Echo,
+    &enable::Enable,
+    &eval::Eval,
+    &exec::Exec,
+    &exit::Exit,
+    &export::Export,
+    &declare::FunctionsCommand,
+    &getopts::Getopts,
+    &hash::Hash,
+    &help::Help,
+    &history::History,
+    &local::Local,
+    &dirs::Popd,
+    &printf::Printf,
+    &dirs::Pushd,
+    &read::Read,
+    &declare::Readonly,
+    &r#return::Return,
+    &set::Set,
+    &shift::Shift,
+    &source::Source,
+    &test::Test,
+    &trap::Trap,
+    &r#type::Type,
+    &declare::Typeset,
+    &ulimit::Ulimit,
+    &umask::Umask,
+    &unalias::Unalias,
+    &unset::Unset,
+    &version::Version,
+    &which::Which,
+];
+
+/// Names of the builtins turned off with `enable -n`.
+static DISABLED: RwLock<BTreeSet<String>> = RwLock::new(BTreeSet::new());
+
+/// Every builtin, enabled or not, sorted by name.
+pub fn all() -> impl Iterator<Item = &'static dyn Builtin> {
+    REGISTRY.iter().copied()
+}
+
+/// The builtin called `name`, whether or not it is enabled.
+pub fn find(name: &str) -> Option<&'static dyn Builtin> {
+    all().find(|builtin| builtin.name() == name)
+}
+
+/// The builtin called `name`, unless it has been disabled.
+pub fn lookup(name: &str) -> Option<&'static dyn Builtin> {
+    find(name).filter(|_| is_enabled(name))
+}
+
+pub fn is_builtin(name: &str) -> bool {
+    lookup(name).is_some()
+}
+
+pub fn is_enabled(name: &str) -> bool {
+    DISABLED
+        .read()
+        .map_or(true, |disabled| !disabled.contains(name))
+}
+
+/// Turn the builtin `name` on or off. Disabled builtins are looked up as
+/// other commands are.
+pub fn set_enabled(name: &str, enabled: bool) {
+    if let Ok(mut disabled) = DISABLED.write() {
+        match enabled {
+            true => disabled.remove(name),
+            false => disabled.insert(name.to_string()),
+        };
+    }
+}
+
+/// Run `cmd` if it names an enabled builtin.
+pub fn dispatch(ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+    match lookup(&cmd.name) {
+        Some(builtin) => builtin.run(ctx, cmd),
+        None => Ok(BuiltinResult::NotHandled),
+    }
+}
+
 pub fn handle_builtin(
     cmd: &SimpleCommand,
     history_mgr: &HistoryManager,
     command_history: &mut Vec<String>,
-    oldpwd: &mut Option<String>,
     vars: &mut Variables,
+    functions: &mut Functions,
+    aliases: &mut Aliases,
 ) -> Result<BuiltinResult, String> {
-    let simple_cmd = cmd;
-
-    match simple_cmd.name.as_str() {
-        "exit" => exit::execute(simple_cmd, history_mgr, command_history),
-        "history" => history::execute(simple_cmd, history_mgr, command_history),
-        "cd" => cd::execute(simple_cmd, history_mgr, command_history, oldpwd),
-        "complete" => {
-            complete::execute(simple_cmd)?;
-            Ok(BuiltinResult::HandledContinue)
-        }
-        "compgen" => {
-            compgen::execute(simple_cmd)?;
-            Ok(BuiltinResult::HandledContinue)
-        }
-        "version" => {
-            version::execute(simple_cmd)?;
-            Ok(BuiltinResult::HandledContinue)
-        }
-        "unset" => unset::execute(simple_cmd, vars),
-        "export" => export::execute(simple_cmd, vars),
-        "source" | "." => {
-            if simple_cmd.args.len() != 1 {
-                return Err(format!("{}: expected 1 argument", simple_cmd.name));
-            }
-            Ok(BuiltinResult::SourceFile(simple_cmd.args[0].clone()))
+    let mut ctx = ShellContext {
+        history_mgr,
+        command_history,
+        vars,
+        functions,
+        aliases,
+    };
+    dispatch(&mut ctx, cmd)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_is_sorted_and_unique() {
+        let names: Vec<&str> = all().map(|builtin| builtin.name()).collect();
+        assert!(
+            names.windows(2).all(|pair| pair[0] < pair[1]),
+            "{:?}",
+            names
+        );
+    }
+
+    #[test]
+    fn test_every_builtin_describes_itself() {
+        for builtin in all() {
+            assert!(builtin.synopsis().starts_with(builtin.name()));
+            assert!(!builtin.about().is_empty());
+            let help = builtin.command().render_help().to_string();
+            assert!(help.contains(builtin.name()), "{}", help);
         }
-        _ => Ok(BuiltinResult::NotHandled),
     }
 }
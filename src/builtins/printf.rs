@@ -0,0 +1,682 @@
+use crate::arrays;
+use crate::builtins::common::write_output;
+use crate::parser::SimpleCommand;
+use crate::variables::Variables;
+
+use super::{Builtin, BuiltinResult, ShellContext};
+
+pub struct Printf;
+
+impl Builtin for Printf {
+    fn name(&self) -> &'static str {
+        "printf"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "printf [-v var] format [arguments ...]"
+    }
+
+    fn about(&self) -> &'static str {
+        "Format and print arguments."
+    }
+
+    fn run(&self, ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+        execute(cmd, ctx.vars)
+    }
+}
+
+fn execute(cmd: &SimpleCommand, vars: &mut Variables) -> Result<BuiltinResult, String> {
+    // Usage: printf [-v var] format [arguments ...]
+    let args = vars.expand_words(&cmd.args)?;
+    let mut args = args.as_slice();
+    let mut target = None;
+    while let Some(first) = args.first() {
+        match first.as_str() {
+            "--" => {
+                args = &args[1..];
+                break;
+            }
+            "-v" => {
+                let name = args
+                    .get(1)
+                    .ok_or("printf: -v: option requires an argument")?;
+                if !arrays::is_name(name) {
+                    return Err(format!("printf: `{}': not a valid identifier", name));
+                }
+                target = Some(name.clone());
+                args = &args[2..];
+            }
+            _ => break,
+        }
+    }
+    let Some((format, args)) = args.split_first() else {
+        return Err("printf: usage: printf [-v var] format [arguments]".to_string());
+    };
+
+    let (output, ok) = format_all(format, args);
+    match target {
+        Some(name) => {
+            vars.check_writable(&name)
+                .map_err(|e| format!("printf: {}", e))?;
+            vars.set(name, output);
+        }
+        None => write_output("printf", &output)?,
+    }
+    Ok(if ok {
+        BuiltinResult::HandledContinue
+    } else {
+        BuiltinResult::HandledStatus(1)
+    })
+}
+
+/// A width or precision: given in the format, or taken from an argument
+/// by `*`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Count {
+    Fixed(usize),
+    Arg,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Piece {
+    Literal(String),
+    Conversion {
+        flags: String,
+        width: Option<Count>,
+        precision: Option<Count>,
+        conversion: char,
+    },
+}
+
+/// Split a format into literal text, with its escapes interpreted, and
+/// conversion specifications.
+fn parse_format(format: &str) -> Result<Vec<Piece>, String> {
+    let mut pieces = Vec::new();
+    let mut literal = String::new();
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        // `\c` only stops the output in a `%b` argument
+        if c == '\\' && chars.peek() != Some(&'c') {
+            literal.push_str(&unescape_one(&mut chars, false).0);
+            continue;
+        }
+        if c != '%' {
+            literal.push(c);
+            continue;
+        }
+        if chars.peek() == Some(&'%') {
+            chars.next();
+            literal.push('%');
+            continue;
+        }
+        let mut flags = String::new();
+        while let Some(&flag) = chars.peek().filter(|f| "-+ #0".contains(**f)) {
+            flags.push(flag);
+            chars.next();
+        }
+        let count = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+            if chars.peek() == Some(&'*') {
+                chars.next();
+                return Some(Count::Arg);
+            }
+            let mut digits = String::new();
+            while let Some(&d) = chars.peek().filter(|d| d.is_ascii_digit()) {
+                digits.push(d);
+                chars.next();
+            }
+            digits.parse().ok().map(Count::Fixed)
+        };
+        let width = count(&mut chars);
+        let precision = match chars.peek() {
+            Some('.') => {
+                chars.next();
+                Some(count(&mut chars).unwrap_or(Count::Fixed(0)))
+            }
+            _ => None,
+        };
+        // Length modifiers mean nothing here
+        while chars.peek().is_some_and(|m| "hlLjzt".contains(*m)) {
+            chars.next();
+        }
+        let conversion = match chars.next() {
+            Some(c) if "sbqcdiuoxXfFeEgGaA".contains(c) => c,
+            Some(c) => return Err(format!("`%{}': invalid format character", c)),
+            None => return Err("`%': missing format character".to_string()),
+        };
+        if !literal.is_empty() {
+            pieces.push(Piece::Literal(std::mem::take(&mut literal)));
+        }
+        pieces.push(Piece::Conversion {
+            flags,
+            width,
+            precision,
+            conversion,
+        });
+    }
+    if !literal.is_empty() {
+        pieces.push(Piece::Literal(literal));
+    }
+    Ok(pieces)
+}
+
+/// Format `args` by `format`, reusing the format while arguments remain.
+/// Returns the output and false if anything was invalid; invalid numbers
+/// count as 0.
+fn format_all(format: &str, args: &[String]) -> (String, bool) {
+    let pieces = match parse_format(format) {
+        Ok(pieces) => pieces,
+        Err(e) => {
+            eprintln!("printf: {}", e);
+            return (String::new(), false);
+        }
+    };
+    let mut out = String::new();
+    let mut ok = true;
+    let mut args = Args { args, next: 0 };
+    loop {
+        let start = args.next;
+        for piece in &pieces {
+            let (flags, width, precision, conversion) = match piece {
+                Piece::Literal(text) => {
+                    out.push_str(text);
+                    continue;
+                }
+                Piece::Conversion {
+                    flags,
+                    width,
+                    precision,
+                    conversion,
+                } => (flags, width, precision, *conversion),
+            };
+            let mut count = |count: &Option<Count>| match count {
+                Some(Count::Fixed(n)) => Some(*n as i64),
+                Some(Count::Arg) => Some(integer(args.take(), &mut ok)),
+                None => None,
+            };
+            let width = count(width);
+            let precision = count(precision).map(|p| p.max(0) as usize);
+            let arg = args.take();
+            // A negative width from `*` means left-justified
+            let mut spec = Spec {
+                left: flags.contains('-') || width.is_some_and(|w| w < 0),
+                plus: flags.contains('+'),
+                space: flags.contains(' '),
+                alternate: flags.contains('#'),
+                zero: flags.contains('0'),
+                width: width.map_or(0, |w| w.unsigned_abs() as usize),
+                precision,
+            };
+            let text = match conversion {
+                's' => {
+                    let s = arg.unwrap_or_default();
+                    match spec.precision {
+                        Some(p) => s.chars().take(p).collect(),
+                        None => s.to_string(),
+                    }
+                }
+                'b' => {
+                    let (text, stop) = unescape(arg.unwrap_or_default(), true);
+                    if stop {
+                        spec.zero = false;
+                        out.push_str(&spec.pad(String::new(), text));
+                        return (out, ok);
+                    }
+                    text
+                }
+                'q' => quote(arg.unwrap_or_default()),
+                'c' => arg
+                    .and_then(|s| s.chars().next())
+                    .map(String::from)
+                    .unwrap_or_default(),
+                'd' | 'i' => {
+                    let n = integer(arg, &mut ok);
+                    let sign = spec.sign(n < 0);
+                    push_number(&mut spec, sign, n.unsigned_abs().to_string(), &mut out);
+                    continue;
+                }
+                'u' | 'o' | 'x' | 'X' => {
+                    let n = integer(arg, &mut ok) as u64;
+                    let (digits, prefix) = match conversion {
+                        'u' => (n.to_string(), ""),
+                        'o' => (format!("{:o}", n), "0"),
+                        'x' => (format!("{:x}", n), "0x"),
+                        _ => (format!("{:X}", n), "0X"),
+                    };
+                    let prefix = if spec.alternate && n != 0 { prefix } else { "" };
+                    push_number(&mut spec, prefix.to_string(), digits, &mut out);
+                    continue;
+                }
+                _ => {
+                    let f = float(arg, &mut ok);
+                    let sign = spec.sign(f.is_sign_negative() && !f.is_nan());
+                    let digits = format_float(f.abs(), conversion, &spec);
+                    if !f.is_finite() {
+                        spec.zero = false;
+                    }
+                    out.push_str(&spec.pad(sign, digits));
+                    continue;
+                }
+            };
+            spec.zero = false;
+            out.push_str(&spec.pad(String::new(), text));
+        }
+        if args.next >= args.args.len() || args.next == start {
+            break;
+        }
+    }
+    (out, ok)
+}
+
+/// The arguments of `printf`, taken in order as conversions need them.
+struct Args<'a> {
+    args: &'a [String],
+    next: usize,
+}
+
+impl<'a> Args<'a> {
+    /// The next argument, or `None` once they have run out.
+    fn take(&mut self) -> Option<&'a str> {
+        let arg = self.args.get(self.next)?;
+        self.next += 1;
+        Some(arg)
+    }
+}
+
+/// Pad an integer's digits to the precision, then the field width.
+fn push_number(spec: &mut Spec, sign: String, mut digits: String, out: &mut String) {
+    if let Some(precision) = spec.precision {
+        // An explicit precision turns off zero padding
+        spec.zero = false;
+        if digits == "0" && precision == 0 {
+            digits.clear();
+        }
+        if digits.len() < precision {
+            digits = format!("{}{}", "0".repeat(precision - digits.len()), digits);
+        }
+    }
+    out.push_str(&spec.pad(sign, digits));
+}
+
+/// The flags, width and precision of one conversion.
+struct Spec {
+    left: bool,
+    plus: bool,
+    space: bool,
+    alternate: bool,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+impl Spec {
+    fn sign(&self, negative: bool) -> String {
+        match (negative, self.plus, self.space) {
+            (true, _, _) => "-",
+            (false, true, _) => "+",
+            (false, false, true) => " ",
+            _ => "",
+        }
+        .to_string()
+    }
+
+    /// Pad `prefix` and `body` to the width. Zeros go between them.
+    fn pad(&self, prefix: String, body: String) -> String {
+        let len = prefix.chars().count() + body.chars().count();
+        if len >= self.width {
+            return prefix + &body;
+        }
+        let fill = self.width - len;
+        if self.left {
+            format!("{}{}{}", prefix, body, " ".repeat(fill))
+        } else if self.zero {
+            format!("{}{}{}", prefix, "0".repeat(fill), body)
+        } else {
+            format!("{}{}{}", " ".repeat(fill), prefix, body)
+        }
+    }
+}
+
+/// Format a non-negative float for `%f`, `%e`, `%g` and their uppercase
+/// forms.
+fn format_float(f: f64, conversion: char, spec: &Spec) -> String {
+    let upper = conversion.is_ascii_uppercase();
+    if !f.is_finite() {
+        let text = if f.is_nan() { "nan" } else { "inf" };
+        return if upper {
+            text.to_uppercase()
+        } else {
+            text.to_string()
+        };
+    }
+    let precision = spec.precision.unwrap_or(6);
+    let text = match conversion.to_ascii_lowercase() {
+        'e' => exponential(f, precision),
+        'g' | 'a' => {
+            let precision = precision.max(1);
+            let exponent = exponential(f, precision - 1)
+                .rsplit('e')
+                .next()
+                .and_then(|e| e.parse::<i32>().ok())
+                .unwrap_or(0);
+            let text = if exponent < -4 || exponent >= precision as i32 {
+                exponential(f, precision - 1)
+            } else {
+                format!("{:.*}", (precision as i32 - 1 - exponent) as usize, f)
+            };
+            if spec.alternate {
+                text
+            } else {
+                strip_zeros(&text)
+            }
+        }
+        _ => format!("{:.*}", precision, f),
+    };
+    let text = if spec.alternate && !text.contains('.') {
+        match text.split_once('e') {
+            Some((mantissa, exponent)) => format!("{}.e{}", mantissa, exponent),
+            None => format!("{}.", text),
+        }
+    } else {
+        text
+    };
+    if upper {
+        text.to_uppercase()
+    } else {
+        text
+    }
+}
+
+/// `d.ddde+XX`, as C writes it.
+fn exponential(f: f64, precision: usize) -> String {
+    let text = format!("{:.*e}", precision, f);
+    let (mantissa, exponent) = text.split_once('e').unwrap_or((&text, "0"));
+    let exponent: i32 = exponent.parse().unwrap_or(0);
+    let sign = if exponent < 0 { '-' } else { '+' };
+    format!("{}e{}{:02}", mantissa, sign, exponent.abs())
+}
+
+/// Remove the trailing zeros of a fraction, and the point if nothing is
+/// left after it.
+fn strip_zeros(text: &str) -> String {
+    let (mantissa, exponent) = match text.split_once('e') {
+        Some((mantissa, exponent)) => (mantissa, format!("e{}", exponent)),
+        None => (text, String::new()),
+    };
+    let mantissa = if mantissa.contains('.') {
+        mantissa.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        mantissa
+    };
+    format!("{}{}", mantissa, exponent)
+}
+
+/// Parse a numeric argument: decimal, `0x` hex, `0` octal, or `'c` for
+/// the character code of `c`. Missing arguments are 0.
+fn integer(arg: Option<&str>, ok: &mut bool) -> i64 {
+    let Some(arg) = arg else {
+        return 0;
+    };
+    if let Some(quoted) = arg.strip_prefix(['\'', '"']) {
+        return quoted.chars().next().map_or(0, |c| c as i64);
+    }
+    let trimmed = arg.trim();
+    let (negative, digits) = match trimmed.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+    };
+    let parsed = if let Some(hex) = digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        i64::from_str_radix(hex, 16)
+    } else if digits.len() > 1 && digits.starts_with('0') {
+        i64::from_str_radix(&digits[1..], 8)
+    } else {
+        digits.parse()
+    };
+    match parsed {
+        Ok(n) if negative => n.wrapping_neg(),
+        Ok(n) => n,
+        Err(_) if trimmed.is_empty() => 0,
+        Err(_) => {
+            eprintln!("printf: {}: invalid number", arg);
+            *ok = false;
+            0
+        }
+    }
+}
+
+fn float(arg: Option<&str>, ok: &mut bool) -> f64 {
+    let Some(arg) = arg else {
+        return 0.0;
+    };
+    if let Some(quoted) = arg.strip_prefix(['\'', '"']) {
+        return quoted.chars().next().map_or(0.0, |c| c as u32 as f64);
+    }
+    let trimmed = arg.trim();
+    match trimmed.parse() {
+        Ok(f) => f,
+        Err(_) if trimmed.is_empty() => 0.0,
+        Err(_) => match trimmed.parse::<i64>() {
+            Ok(n) => n as f64,
+            Err(_) => {
+                eprintln!("printf: {}: invalid number", arg);
+                *ok = false;
+                0.0
+            }
+        },
+    }
+}
+
+/// Quote `s` so that the shell reads it back as the same word, as `%q`
+/// does: backslashes before special characters, or `$'...'` when there
+/// are control characters.
+fn quote(s: &str) -> String {
+    if s.is_empty() {
+        return "''".to_string();
+    }
+    if s.chars().any(|c| c.is_control()) {
+        let mut quoted = String::from("$'");
+        for c in s.chars() {
+            match c {
+                '\n' => quoted.push_str("\\n"),
+                '\t' => quoted.push_str("\\t"),
+                '\r' => quoted.push_str("\\r"),
+                '\x1b' => quoted.push_str("\\E"),
+                '\'' | '\\' => {
+                    quoted.push('\\');
+                    quoted.push(c);
+                }
+                c if c.is_control() => quoted.push_str(&format!("\\{:03o}", c as u32)),
+                c => quoted.push(c),
+            }
+        }
+        quoted.push('\'');
+        return quoted;
+    }
+    let mut quoted = String::with_capacity(s.len());
+    for c in s.chars() {
+        if !(c.is_alphanumeric() || "_-+=./,:@%^".contains(c)) {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted
+}
+
+/// Interpret the backslash escapes in `s`, as `echo -e` and `%b` do.
+/// With `octal_zero`, octal escapes are written `\0nnn`; otherwise
+/// `\nnn`. Returns the text and whether a `\c` ended the output.
+pub fn unescape(s: &str, octal_zero: bool) -> (String, bool) {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        let (text, stop) = unescape_one(&mut chars, octal_zero);
+        out.push_str(&text);
+        if stop {
+            return (out, true);
+        }
+    }
+    (out, false)
+}
+
+/// Interpret one escape, after its backslash.
+fn unescape_one(
+    chars: &mut std::iter::Peekable<std::str::Chars>,
+    octal_zero: bool,
+) -> (String, bool) {
+    let Some(&c) = chars.peek() else {
+        return ("\\".to_string(), false);
+    };
+    let simple = match c {
+        'a' => Some('\x07'),
+        'b' => Some('\x08'),
+        'e' | 'E' => Some('\x1b'),
+        'f' => Some('\x0c'),
+        'n' => Some('\n'),
+        'r' => Some('\r'),
+        't' => Some('\t'),
+        'v' => Some('\x0b'),
+        '\\' => Some('\\'),
+        _ => None,
+    };
+    if let Some(simple) = simple {
+        chars.next();
+        return (simple.to_string(), false);
+    }
+    let code = match c {
+        'c' => {
+            chars.next();
+            return (String::new(), true);
+        }
+        '0' if octal_zero => {
+            chars.next();
+            Some(digits(chars, 8, 3).unwrap_or(0))
+        }
+        '0'..='7' if !octal_zero => digits(chars, 8, 3),
+        'x' | 'u' | 'U' => {
+            chars.next();
+            let max = match c {
+                'x' => 2,
+                'u' => 4,
+                _ => 8,
+            };
+            match digits(chars, 16, max) {
+                Some(code) => Some(code),
+                None => return (format!("\\{}", c), false),
+            }
+        }
+        _ => None,
+    };
+    match code {
+        Some(code) => (
+            char::from_u32(code).map(String::from).unwrap_or_default(),
+            false,
+        ),
+        None => {
+            chars.next();
+            (format!("\\{}", c), false)
+        }
+    }
+}
+
+/// Read up to `max` digits in `radix`, returning their value if there
+/// were any.
+fn digits(chars: &mut std::iter::Peekable<std::str::Chars>, radix: u32, max: usize) -> Option<u32> {
+    let mut value = None;
+    for _ in 0..max {
+        match chars.peek().and_then(|d| d.to_digit(radix)) {
+            Some(d) => {
+                value = Some(value.unwrap_or(0) * radix + d);
+                chars.next();
+            }
+            None => break,
+        }
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn printf(format: &str, args: &[&str]) -> String {
+        let args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+        format_all(format, &args).0
+    }
+
+    #[test]
+    fn test_strings_and_reuse() {
+        assert_eq!(printf("%s-%s\\n", &["a", "b", "c"]), "a-b\nc-\n");
+        assert_eq!(
+            printf("[%5s|%-5s|%.2s]", &["ab", "cd", "xyz"]),
+            "[   ab|cd   |xy]"
+        );
+        assert_eq!(printf("[%*s]", &["4", "x"]), "[   x]");
+        assert_eq!(printf("100%%\\n", &["ignored"]), "100%\n");
+        assert_eq!(printf("%c%c", &["hello", "world"]), "hw");
+    }
+
+    #[test]
+    fn test_integers() {
+        assert_eq!(
+            printf("%d %i %05d %+d", &["42", "-7", "-42", "3"]),
+            "42 -7 -0042 +3"
+        );
+        assert_eq!(
+            printf("%x %X %#o %#x", &["255", "255", "8", "0"]),
+            "ff FF 010 0"
+        );
+        assert_eq!(printf("%.3d|%u", &["7", "-1"]), "007|18446744073709551615");
+        assert_eq!(printf("%d %d", &["0x10", "'A"]), "16 65");
+        assert_eq!(
+            format_all("%d", &["abc".to_string()]),
+            ("0".to_string(), false)
+        );
+    }
+
+    #[test]
+    fn test_floats() {
+        assert_eq!(
+            printf("%f %.2f %8.3f", &["1.5", "2.345", "3.14159"]),
+            "1.500000 2.35    3.142"
+        );
+        assert_eq!(
+            printf("%e %.2E", &["12345.678", "0.000123"]),
+            "1.234568e+04 1.23E-04"
+        );
+        assert_eq!(
+            printf("%g %g %g %G", &["100000", "1000000", "0.0001", "1e-5"]),
+            "100000 1e+06 0.0001 1E-05"
+        );
+        assert_eq!(printf("%g %#g", &["2.5", "2"]), "2.5 2.00000");
+    }
+
+    #[test]
+    fn test_escapes_and_quoting() {
+        assert_eq!(printf("\\t\\x41\\101\\u00e9", &[]), "\tAAé");
+        assert_eq!(printf("%b|%s", &["a\\tb\\0101", "a\\tb"]), "a\tbA|a\\tb");
+        assert_eq!(printf("x%bnot\\n", &["y\\cz"]), "xy");
+        assert_eq!(printf("%q %q %q", &["a b", "", "it's"]), "a\\ b '' it\\'s");
+        assert_eq!(printf("%q", &["a\nb"]), "$'a\\nb'");
+        assert_eq!(unescape("\\q\\", true), ("\\q\\".to_string(), false));
+    }
+
+    #[test]
+    fn test_printf_v_assigns() {
+        let mut vars = Variables::new();
+        let cmd = SimpleCommand {
+            name: "printf".into(),
+            args: vec!["-v".into(), "out".into(), "%03d".into(), "5".into()],
+            assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
+        };
+        execute(&cmd, &mut vars).unwrap();
+        assert_eq!(vars.get("out").map(|s| s.as_str()), Some("005"));
+    }
+}
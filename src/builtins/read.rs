@@ -0,0 +1,485 @@
+use std::io::Write;
+use std::os::fd::{BorrowedFd, RawFd};
+use std::time::{Duration, Instant};
+
+use nix::errno::Errno;
+use nix::fcntl::{fcntl, FcntlArg};
+use nix::poll::{poll, PollFd, PollFlags, PollTimeout};
+use nix::sys::termios::{
+    tcgetattr, tcsetattr, LocalFlags, SetArg, SpecialCharacterIndices, Termios,
+};
+
+use crate::arrays;
+use crate::parser::SimpleCommand;
+use crate::variables::Variables;
+
+use super::{Builtin, BuiltinResult, ShellContext};
+
+pub struct Read;
+
+impl Builtin for Read {
+    fn name(&self) -> &'static str {
+        "read"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "read [-rs] [-a array] [-d delim] [-n nchars] [-N nchars] [-p prompt] [-t timeout] [-u fd] [name ...]"
+    }
+
+    fn about(&self) -> &'static str {
+        "Read a line from standard input and split it into fields."
+    }
+
+    fn run(&self, ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+        execute(cmd, ctx.vars)
+    }
+}
+
+/// Exit status when the timeout expires: 128 + SIGALRM, as in bash.
+const TIMED_OUT_STATUS: i32 = 142;
+
+#[derive(Debug, Default, PartialEq)]
+struct Options {
+    raw: bool,
+    silent: bool,
+    prompt: Option<String>,
+    timeout: Option<Duration>,
+    count: Option<usize>,
+    // `-N`: read exactly `count` characters, ignoring the delimiter
+    exact: bool,
+    delimiter: Option<char>,
+    array: Option<String>,
+    fd: RawFd,
+}
+
+impl Options {
+    fn parse(mut args: &[String]) -> Result<(Options, &[String]), String> {
+        let mut opts = Options::default();
+        while let Some(first) = args.first() {
+            if first == "--" {
+                args = &args[1..];
+                break;
+            }
+            let Some(flags) = first.strip_prefix('-').filter(|f| !f.is_empty()) else {
+                break;
+            };
+            args = &args[1..];
+            for (i, flag) in flags.char_indices() {
+                match flag {
+                    'r' => opts.raw = true,
+                    's' => opts.silent = true,
+                    'a' | 'd' | 'n' | 'N' | 'p' | 't' | 'u' => {
+                        // The value is the rest of this word, or else the next word
+                        let rest = &flags[i + 1..];
+                        let value = if !rest.is_empty() {
+                            rest
+                        } else {
+                            let (value, remaining) = args.split_first().ok_or_else(|| {
+                                format!("read: -{}: option requires an argument", flag)
+                            })?;
+                            args = remaining;
+                            value.as_str()
+                        };
+                        opts.set(flag, value)?;
+                        break;
+                    }
+                    _ => return Err(format!("read: -{}: invalid option", flag)),
+                }
+            }
+        }
+        Ok((opts, args))
+    }
+
+    /// Set the option `flag`, which takes `value` as its argument.
+    fn set(&mut self, flag: char, value: &str) -> Result<(), String> {
+        match flag {
+            'a' => {
+                if !arrays::is_name(value) {
+                    return Err(format!("read: `{}': not a valid identifier", value));
+                }
+                self.array = Some(value.to_string());
+            }
+            // `-d ''` reads up to a NUL byte
+            'd' => self.delimiter = Some(value.chars().next().unwrap_or('\0')),
+            'n' | 'N' => {
+                let count = value
+                    .parse()
+                    .map_err(|_| format!("read: {}: invalid number", value))?;
+                self.count = Some(count);
+                self.exact = flag == 'N';
+            }
+            'p' => self.prompt = Some(value.to_string()),
+            't' => {
+                let timeout = value
+                    .parse::<f64>()
+                    .ok()
+                    .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+                    .ok_or_else(|| format!("read: {}: invalid timeout specification", value))?;
+                self.timeout = Some(timeout);
+            }
+            _ => {
+                self.fd = value
+                    .parse()
+                    .ok()
+                    .filter(|fd: &RawFd| *fd >= 0)
+                    .ok_or_else(|| {
+                        format!("read: {}: invalid file descriptor specification", value)
+                    })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Why reading the line stopped.
+#[derive(Debug, PartialEq)]
+enum End {
+    Delimiter,
+    Count,
+    Eof,
+    TimedOut,
+    Failed(String),
+}
+
+fn execute(cmd: &SimpleCommand, vars: &mut Variables) -> Result<BuiltinResult, String> {
+    // Usage: read [-rs] [-a array] [-d delim] [-n nchars] [-N nchars]
+    //             [-p prompt] [-t timeout] [-u fd] [name ...]
+    let args = vars.expand_words(&cmd.args)?;
+    let (opts, names) = Options::parse(&args)?;
+    if let Some(name) = names.iter().find(|name| !arrays::is_name(name)) {
+        return Err(format!("read: `{}': not a valid identifier", name));
+    }
+    if let Err(e) = fcntl(opts.fd, FcntlArg::F_GETFD) {
+        return Err(format!(
+            "read: {}: invalid file descriptor: {}",
+            opts.fd,
+            e.desc()
+        ));
+    }
+
+    let terminal = nix::unistd::isatty(opts.fd).unwrap_or(false);
+    if let (Some(prompt), true) = (&opts.prompt, terminal) {
+        let mut stderr = std::io::stderr();
+        let _ = write!(stderr, "{}", prompt).and_then(|_| stderr.flush());
+    }
+    // `-t 0` only reports whether there is input waiting
+    if opts.timeout == Some(Duration::ZERO) {
+        let input = Input::new(opts.fd, opts.timeout);
+        return Ok(match input.wait() {
+            Ok(()) => BuiltinResult::HandledContinue,
+            Err(_) => BuiltinResult::HandledStatus(1),
+        });
+    }
+
+    let (line, end) = {
+        let _mode = if terminal {
+            TerminalMode::set(opts.fd, &opts)
+        } else {
+            None
+        };
+        read_line(&opts, &Input::new(opts.fd, opts.timeout))
+    };
+    // Whatever was read before the line ended is still assigned
+    assign(&opts, names, &line, vars)?;
+    Ok(match end {
+        End::Delimiter | End::Count => BuiltinResult::HandledContinue,
+        End::Eof => BuiltinResult::HandledStatus(1),
+        End::TimedOut => BuiltinResult::HandledStatus(TIMED_OUT_STATUS),
+        End::Failed(e) => {
+            eprintln!("read: {}", e);
+            BuiltinResult::HandledStatus(1)
+        }
+    })
+}
+
+/// Read one line of input. Each character comes with whether it was
+/// escaped by a backslash, which stops it from separating fields.
+fn read_line(opts: &Options, input: &Input) -> (Vec<(char, bool)>, End) {
+    let delimiter = match opts.exact {
+        true => None,
+        false => Some(opts.delimiter.unwrap_or('\n')),
+    };
+    let mut line = Vec::new();
+    loop {
+        if opts.count.is_some_and(|count| line.len() >= count) {
+            return (line, End::Count);
+        }
+        let c = match input.next() {
+            Ok(c) => c,
+            Err(end) => return (line, end),
+        };
+        if Some(c) == delimiter {
+            return (line, End::Delimiter);
+        }
+        if c != '\\' || opts.raw {
+            line.push((c, false));
+            continue;
+        }
+        match input.next() {
+            // A backslash-newline continues the line
+            Ok('\n') => {}
+            Ok(escaped) => line.push((escaped, true)),
+            Err(end) => return (line, end),
+        }
+    }
+}
+
+/// Unbuffered input from a file descriptor, so that whatever follows the
+/// line is left for the next command to read.
+struct Input {
+    fd: RawFd,
+    deadline: Option<Instant>,
+}
+
+impl Input {
+    fn new(fd: RawFd, timeout: Option<Duration>) -> Self {
+        Self {
+            fd,
+            deadline: timeout.map(|timeout| Instant::now() + timeout),
+        }
+    }
+
+    /// Wait until there is input to read, or the deadline has passed.
+    fn wait(&self) -> Result<(), End> {
+        let Some(deadline) = self.deadline else {
+            return Ok(());
+        };
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let timeout = PollTimeout::try_from(remaining).unwrap_or(PollTimeout::MAX);
+        // SAFETY: the descriptor was checked to be open and outlives the poll
+        let fd = unsafe { BorrowedFd::borrow_raw(self.fd) };
+        let mut fds = [PollFd::new(fd, PollFlags::POLLIN)];
+        loop {
+            match poll(&mut fds, timeout) {
+                Ok(0) => return Err(End::TimedOut),
+                Ok(_) => return Ok(()),
+                Err(Errno::EINTR) => continue,
+                Err(e) => return Err(End::Failed(format!("{}: {}", self.fd, e.desc()))),
+            }
+        }
+    }
+
+    fn byte(&self) -> Result<u8, End> {
+        self.wait()?;
+        let mut buf = [0u8; 1];
+        loop {
+            return match nix::unistd::read(self.fd, &mut buf) {
+                Ok(0) => Err(End::Eof),
+                Ok(_) => Ok(buf[0]),
+                Err(Errno::EINTR) => continue,
+                Err(e) => Err(End::Failed(format!(
+                    "read error: {}: {}",
+                    self.fd,
+                    e.desc()
+                ))),
+            };
+        }
+    }
+
+    /// Read one UTF-8 character, a byte at a time.
+    fn next(&self) -> Result<char, End> {
+        let lead = self.byte()?;
+        let len = match lead {
+            0xf0.. => 4,
+            0xe0.. => 3,
+            0xc0.. => 2,
+            _ => 1,
+        };
+        let mut bytes = vec![lead];
+        for _ in 1..len {
+            bytes.push(self.byte()?);
+        }
+        Ok(String::from_utf8_lossy(&bytes)
+            .chars()
+            .next()
+            .unwrap_or(char::REPLACEMENT_CHARACTER))
+    }
+}
+
+/// Terminal settings for `-s`, `-n` and `-d`, restored when dropped.
+struct TerminalMode {
+    fd: RawFd,
+    saved: Termios,
+}
+
+impl TerminalMode {
+    fn set(fd: RawFd, opts: &Options) -> Option<Self> {
+        // Without canonical mode, input arrives before the user hits return
+        let uncooked = opts.count.is_some() || opts.delimiter.is_some_and(|d| d != '\n');
+        if !opts.silent && !uncooked {
+            return None;
+        }
+        // SAFETY: the descriptor was checked to be open
+        let borrowed = unsafe { BorrowedFd::borrow_raw(fd) };
+        let saved = tcgetattr(borrowed).ok()?;
+        let mut mode = saved.clone();
+        if opts.silent {
+            mode.local_flags.remove(LocalFlags::ECHO);
+        }
+        if uncooked {
+            mode.local_flags.remove(LocalFlags::ICANON);
+            mode.control_chars[SpecialCharacterIndices::VMIN as usize] = 1;
+            mode.control_chars[SpecialCharacterIndices::VTIME as usize] = 0;
+        }
+        tcsetattr(borrowed, SetArg::TCSADRAIN, &mode).ok()?;
+        Some(Self { fd, saved })
+    }
+}
+
+impl Drop for TerminalMode {
+    fn drop(&mut self) {
+        // SAFETY: the descriptor is still open, as it was while reading
+        let borrowed = unsafe { BorrowedFd::borrow_raw(self.fd) };
+        let _ = tcsetattr(borrowed, SetArg::TCSADRAIN, &self.saved);
+    }
+}
+
+/// Store the line in the array given by `-a`, split across `names`, or
+/// unchanged in `REPLY` when there are no names.
+fn assign(
+    opts: &Options,
+    names: &[String],
+    line: &[(char, bool)],
+    vars: &mut Variables,
+) -> Result<(), String> {
+    let ifs = vars
+        .get("IFS")
+        .cloned()
+        .unwrap_or_else(|| " \t\n".to_string());
+    let targets: Vec<&str> = match &opts.array {
+        Some(array) => vec![array],
+        None if names.is_empty() => vec!["REPLY"],
+        None => names.iter().map(|name| name.as_str()).collect(),
+    };
+    for name in targets {
+        vars.check_writable(name)
+            .map_err(|e| format!("read: {}", e))?;
+    }
+
+    if let Some(array) = &opts.array {
+        vars.set_array(array, split(line, &ifs, usize::MAX));
+    } else if names.is_empty() {
+        vars.set("REPLY".to_string(), line.iter().map(|&(c, _)| c).collect());
+    } else {
+        let mut fields = split(line, &ifs, names.len()).into_iter();
+        for name in names {
+            vars.set(name.clone(), fields.next().unwrap_or_default());
+        }
+    }
+    Ok(())
+}
+
+/// Split `line` into at most `limit` fields at the unescaped characters in
+/// `ifs`. The last field holds the rest of the line, less any trailing
+/// separator.
+fn split(line: &[(char, bool)], ifs: &str, limit: usize) -> Vec<String> {
+    let is_separator = |&(c, escaped): &(char, bool)| !escaped && ifs.contains(c);
+    let is_space = |&(c, escaped): &(char, bool)| {
+        !escaped && ifs.contains(c) && matches!(c, ' ' | '\t' | '\n')
+    };
+    let text = |chars: &[(char, bool)]| chars.iter().map(|&(c, _)| c).collect::<String>();
+    let skip_spaces = |mut i: usize| {
+        while line.get(i).is_some_and(is_space) {
+            i += 1;
+        }
+        i
+    };
+
+    let mut fields = Vec::new();
+    let mut i = skip_spaces(0);
+    while i < line.len() {
+        if fields.len() + 1 == limit {
+            let mut rest = trim_end(&line[i..], is_space);
+            // A single field with one separator after it loses the separator
+            if let Some((last, init)) = rest.split_last() {
+                if is_separator(last) && !init.iter().any(is_separator) {
+                    rest = trim_end(init, is_space);
+                }
+            }
+            fields.push(text(rest));
+            break;
+        }
+        let start = i;
+        while i < line.len() && !is_separator(&line[i]) {
+            i += 1;
+        }
+        fields.push(text(&line[start..i]));
+        // A separator is any IFS whitespace around at most one other
+        // IFS character
+        i = skip_spaces(i);
+        if line.get(i).is_some_and(|c| is_separator(c) && !is_space(c)) {
+            i = skip_spaces(i + 1);
+        }
+    }
+    fields
+}
+
+/// `chars` without the IFS whitespace at its end.
+fn trim_end(
+    mut chars: &[(char, bool)],
+    is_space: impl Fn(&(char, bool)) -> bool,
+) -> &[(char, bool)] {
+    while chars.last().is_some_and(&is_space) {
+        chars = &chars[..chars.len() - 1];
+    }
+    chars
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unescaped(s: &str) -> Vec<(char, bool)> {
+        s.chars().map(|c| (c, false)).collect()
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let args: Vec<String> = ["-rsp", "> ", "-n3", "-d", "", "-t", "1.5", "a", "b"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let (opts, names) = Options::parse(&args).unwrap();
+        assert!(opts.raw && opts.silent);
+        assert_eq!(opts.prompt.as_deref(), Some("> "));
+        assert_eq!(opts.count, Some(3));
+        assert_eq!(opts.delimiter, Some('\0'));
+        assert_eq!(opts.timeout, Some(Duration::from_millis(1500)));
+        assert_eq!(names, ["a", "b"]);
+
+        assert!(Options::parse(&["-u".to_string()]).is_err());
+        assert!(Options::parse(&["-x".to_string()]).is_err());
+        assert!(Options::parse(&["-t".to_string(), "soon".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_split_default_ifs() {
+        let line = unescaped("  one  two   three  ");
+        assert_eq!(split(&line, " \t\n", usize::MAX), ["one", "two", "three"]);
+        assert_eq!(split(&line, " \t\n", 2), ["one", "two   three"]);
+        assert_eq!(split(&line, " \t\n", 1), ["one  two   three"]);
+        assert!(split(&unescaped("   "), " \t\n", usize::MAX).is_empty());
+    }
+
+    #[test]
+    fn test_split_other_ifs() {
+        assert_eq!(split(&unescaped("a::b:"), ":", usize::MAX), ["a", "", "b"]);
+        assert_eq!(split(&unescaped("x:y:"), ":", 2), ["x", "y"]);
+        assert_eq!(split(&unescaped("x:y:z:"), ":", 2), ["x", "y:z:"]);
+        assert_eq!(split(&unescaped("a , b"), " ,", usize::MAX), ["a", "b"]);
+        // An empty IFS does no splitting
+        assert_eq!(split(&unescaped(" a b "), "", 2), [" a b "]);
+    }
+
+    #[test]
+    fn test_escaped_separators() {
+        let line = vec![
+            ('a', false),
+            (' ', true),
+            ('b', false),
+            (' ', false),
+            ('c', false),
+        ];
+        assert_eq!(split(&line, " \t\n", usize::MAX), ["a b", "c"]);
+    }
+}
@@ -0,0 +1,101 @@
+use crate::parser::SimpleCommand;
+use crate::variables::Variables;
+
+use super::{Builtin, BuiltinResult, ShellContext};
+
+pub struct Return;
+
+impl Builtin for Return {
+    fn name(&self) -> &'static str {
+        "return"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "return [n]"
+    }
+
+    fn about(&self) -> &'static str {
+        "Return from a function or sourced file."
+    }
+
+    fn run(&self, ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+        execute(cmd, ctx.vars)
+    }
+}
+
+fn execute(cmd: &SimpleCommand, vars: &mut Variables) -> Result<BuiltinResult, String> {
+    // Usage: return [n]
+    if !vars.can_return() {
+        return Err("return: can only `return' from a function or sourced script".to_string());
+    }
+
+    let status = match cmd.args.as_slice() {
+        [] => vars.last_status(),
+        [n] => {
+            let n = vars.expand(n)?;
+            match n.parse::<i32>() {
+                // Like exit statuses, return values are truncated to 8 bits
+                Ok(value) => value & 0xff,
+                Err(_) => return Err(format!("return: {}: numeric argument required", n)),
+            }
+        }
+        _ => return Err("return: too many arguments".to_string()),
+    };
+
+    vars.request_return(status);
+    Ok(BuiltinResult::HandledStatus(status))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_cmd(args: Vec<&str>) -> SimpleCommand {
+        SimpleCommand {
+            name: "return".into(),
+            args: args.into_iter().map(String::from).collect(),
+            assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_return_outside_function_fails() {
+        let mut vars = Variables::new();
+        let res = execute(&make_cmd(vec!["1"]), &mut vars);
+        assert!(res.is_err());
+        assert!(!vars.is_returning());
+    }
+
+    #[test]
+    fn test_return_with_status() {
+        let mut vars = Variables::new();
+        vars.push_scope(vec!["7".to_string()]);
+        let res = execute(&make_cmd(vec!["$1"]), &mut vars).unwrap();
+        assert!(matches!(res, BuiltinResult::HandledStatus(7)));
+        assert_eq!(vars.take_return(), Some(7));
+
+        execute(&make_cmd(vec!["-1"]), &mut vars).unwrap();
+        assert_eq!(vars.take_return(), Some(255));
+    }
+
+    #[test]
+    fn test_return_defaults_to_last_status() {
+        let mut vars = Variables::new();
+        vars.enter_source();
+        vars.set_last_status(4);
+        let res = execute(&make_cmd(vec![]), &mut vars).unwrap();
+        assert!(matches!(res, BuiltinResult::HandledStatus(4)));
+        assert_eq!(vars.take_return(), Some(4));
+    }
+
+    #[test]
+    fn test_return_invalid_arguments() {
+        let mut vars = Variables::new();
+        vars.push_scope(vec![]);
+        assert!(execute(&make_cmd(vec!["abc"]), &mut vars).is_err());
+        assert!(execute(&make_cmd(vec!["1", "2"]), &mut vars).is_err());
+        assert!(!vars.is_returning());
+    }
+}
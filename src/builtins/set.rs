@@ -0,0 +1,169 @@
+use crate::builtins::common::shell_quote;
+use crate::options::ShellOptions;
+use crate::parser::SimpleCommand;
+use crate::variables::Variables;
+
+use super::{Builtin, BuiltinResult, ShellContext};
+
+pub struct Set;
+
+impl Builtin for Set {
+    fn name(&self) -> &'static str {
+        "set"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "set [-euC] [+euC] [-o name] [+o name] [--] [arg ...]"
+    }
+
+    fn about(&self) -> &'static str {
+        "Set shell options and positional parameters."
+    }
+
+    fn run(&self, ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+        execute(cmd, ctx.vars)
+    }
+}
+
+/// `set -o`: a human-readable table of all options.
+fn print_options(vars: &Variables) {
+    for name in ShellOptions::names() {
+        let state = if vars.options().get(name) == Some(true) {
+            "on"
+        } else {
+            "off"
+        };
+        println!("{:<15}\t{}", name, state);
+    }
+}
+
+/// `set +o`: the commands that would recreate the current settings.
+fn print_option_commands(vars: &Variables) {
+    for name in ShellOptions::names() {
+        let sign = if vars.options().get(name) == Some(true) {
+            '-'
+        } else {
+            '+'
+        };
+        println!("set {}o {}", sign, name);
+    }
+}
+
+fn execute(cmd: &SimpleCommand, vars: &mut Variables) -> Result<BuiltinResult, String> {
+    // Usage: set [-euC] [+euC] [-o name] [+o name] [--] [arg ...]
+    if cmd.args.is_empty() {
+        for (name, value) in vars.all_vars() {
+            println!("{}={}", name, shell_quote(&value));
+        }
+        return Ok(BuiltinResult::HandledContinue);
+    }
+
+    let args = &cmd.args;
+    let mut i = 0;
+    let mut replace_positional = false;
+    while i < args.len() {
+        let arg = &args[i];
+        if arg == "--" {
+            replace_positional = true;
+            i += 1;
+            break;
+        }
+        let (enable, flags) = match arg.split_at(arg.len().min(1)) {
+            ("-", flags) if !flags.is_empty() => (true, flags),
+            ("+", flags) if !flags.is_empty() => (false, flags),
+            _ => break,
+        };
+        for flag in flags.chars() {
+            if flag == 'o' {
+                // -o takes the next argument as an option name; alone it lists
+                match args.get(i + 1) {
+                    Some(name) => {
+                        vars.options_mut().set(name, enable)?;
+                        i += 1;
+                    }
+                    None if enable => print_options(vars),
+                    None => print_option_commands(vars),
+                }
+                continue;
+            }
+            match ShellOptions::name_for_letter(flag) {
+                Some(name) => vars.options_mut().set(name, enable)?,
+                None => {
+                    let sign = if enable { '-' } else { '+' };
+                    return Err(format!("set: {}{}: invalid option", sign, flag));
+                }
+            }
+        }
+        i += 1;
+    }
+
+    // Remaining arguments replace the positional parameters
+    if replace_positional || i < args.len() {
+        let positional = vars.expand_words(&args[i..])?;
+        vars.set_positional_args(positional);
+    }
+
+    Ok(BuiltinResult::HandledContinue)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_cmd(args: Vec<&str>) -> SimpleCommand {
+        SimpleCommand {
+            name: "set".into(),
+            args: args.into_iter().map(String::from).collect(),
+            assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_set_clustered_flags_and_long_option() {
+        let mut vars = Variables::new();
+        execute(&make_cmd(vec!["-euo", "pipefail"]), &mut vars).unwrap();
+        assert!(vars.options().errexit);
+        assert!(vars.options().nounset);
+        assert!(vars.options().pipefail);
+        assert_eq!(vars.expand("$-").unwrap(), "eu");
+
+        execute(&make_cmd(vec!["+e", "+o", "pipefail", "-C"]), &mut vars).unwrap();
+        assert!(!vars.options().errexit);
+        assert!(!vars.options().pipefail);
+        assert!(vars.options().noclobber);
+        assert_eq!(vars.expand("$-").unwrap(), "Cu");
+    }
+
+    #[test]
+    fn test_set_invalid_options() {
+        let mut vars = Variables::new();
+        let res = execute(&make_cmd(vec!["-z"]), &mut vars);
+        assert_eq!(res.err().unwrap(), "set: -z: invalid option");
+        let res = execute(&make_cmd(vec!["-o", "nosuch"]), &mut vars);
+        assert_eq!(res.err().unwrap(), "set: nosuch: invalid option name");
+    }
+
+    #[test]
+    fn test_set_listing() {
+        let mut vars = Variables::new();
+        assert!(execute(&make_cmd(vec!["-o"]), &mut vars).is_ok());
+        assert!(execute(&make_cmd(vec!["+o"]), &mut vars).is_ok());
+        assert!(execute(&make_cmd(vec![]), &mut vars).is_ok());
+    }
+
+    #[test]
+    fn test_set_positional_parameters() {
+        let mut vars = Variables::new();
+        execute(&make_cmd(vec!["-e", "a", "b"]), &mut vars).unwrap();
+        assert!(vars.options().errexit);
+        assert_eq!(vars.get_positional_args(), vec!["a", "b"]);
+
+        execute(&make_cmd(vec!["--", "-x"]), &mut vars).unwrap();
+        assert_eq!(vars.get_positional_args(), vec!["-x"]);
+
+        execute(&make_cmd(vec!["--"]), &mut vars).unwrap();
+        assert!(vars.get_positional_args().is_empty());
+    }
+}
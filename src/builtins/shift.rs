@@ -0,0 +1,93 @@
+use crate::parser::SimpleCommand;
+use crate::variables::Variables;
+
+use super::{Builtin, BuiltinResult, ShellContext};
+
+pub struct Shift;
+
+impl Builtin for Shift {
+    fn name(&self) -> &'static str {
+        "shift"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "shift [n]"
+    }
+
+    fn about(&self) -> &'static str {
+        "Shift the positional parameters."
+    }
+
+    fn run(&self, ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+        execute(cmd, ctx.vars)
+    }
+}
+
+fn execute(cmd: &SimpleCommand, vars: &mut Variables) -> Result<BuiltinResult, String> {
+    // Usage: shift [n]
+    let count = match cmd.args.as_slice() {
+        [] => 1,
+        [n] => {
+            let n = vars.expand(n)?;
+            n.trim()
+                .parse::<i64>()
+                .map_err(|_| format!("shift: {}: numeric argument required", n))?
+        }
+        _ => return Err("shift: too many arguments".to_string()),
+    };
+
+    let mut args = vars.get_positional_args();
+    // Shifting more than there are leaves them all in place
+    if count < 0 || count as u64 > args.len() as u64 {
+        eprintln!("shift: {}: shift count out of range", count);
+        return Ok(BuiltinResult::HandledStatus(1));
+    }
+    args.drain(..count as usize);
+    vars.set_positional_args(args);
+    Ok(BuiltinResult::HandledContinue)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_cmd(args: Vec<&str>) -> SimpleCommand {
+        SimpleCommand {
+            name: "shift".into(),
+            args: args.into_iter().map(String::from).collect(),
+            assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
+        }
+    }
+
+    fn positional(vars: &mut Variables, args: &[&str]) {
+        vars.set_positional_args(args.iter().map(|s| s.to_string()).collect());
+    }
+
+    #[test]
+    fn test_shift() {
+        let mut vars = Variables::new();
+        positional(&mut vars, &["a", "b", "c", "d"]);
+        execute(&make_cmd(vec![]), &mut vars).unwrap();
+        assert_eq!(vars.get_positional_args(), ["b", "c", "d"]);
+        execute(&make_cmd(vec!["2"]), &mut vars).unwrap();
+        assert_eq!(vars.get_positional_args(), ["d"]);
+        execute(&make_cmd(vec!["1"]), &mut vars).unwrap();
+        assert!(vars.get_positional_args().is_empty());
+    }
+
+    #[test]
+    fn test_shift_out_of_range() {
+        let mut vars = Variables::new();
+        positional(&mut vars, &["a", "b"]);
+        let res = execute(&make_cmd(vec!["3"]), &mut vars).unwrap();
+        assert!(matches!(res, BuiltinResult::HandledStatus(1)));
+        let res = execute(&make_cmd(vec!["-1"]), &mut vars).unwrap();
+        assert!(matches!(res, BuiltinResult::HandledStatus(1)));
+        assert_eq!(vars.get_positional_args(), ["a", "b"]);
+
+        assert!(execute(&make_cmd(vec!["x"]), &mut vars).is_err());
+        assert!(execute(&make_cmd(vec!["1", "2"]), &mut vars).is_err());
+    }
+}
@@ -0,0 +1,51 @@
+use crate::parser::SimpleCommand;
+
+use super::{Builtin, BuiltinResult, ShellContext};
+
+pub struct Source;
+
+impl Builtin for Source {
+    fn name(&self) -> &'static str {
+        "source"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "source filename"
+    }
+
+    fn about(&self) -> &'static str {
+        "Run the commands in a file in the current shell."
+    }
+
+    fn run(&self, _ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+        execute(cmd)
+    }
+}
+
+pub struct Dot;
+
+impl Builtin for Dot {
+    fn name(&self) -> &'static str {
+        "."
+    }
+
+    fn synopsis(&self) -> &'static str {
+        ". filename"
+    }
+
+    fn about(&self) -> &'static str {
+        "Run the commands in a file in the current shell, like source."
+    }
+
+    fn run(&self, _ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+        execute(cmd)
+    }
+}
+
+fn execute(cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+    // The caller runs the file, since that needs the executor
+    if cmd.args.len() != 1 {
+        return Err(format!("{}: expected 1 argument", cmd.name));
+    }
+    Ok(BuiltinResult::SourceFile(cmd.args[0].clone()))
+}
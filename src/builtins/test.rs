@@ -0,0 +1,111 @@
+use crate::conditional::{self, Syntax};
+use crate::parser::SimpleCommand;
+use crate::variables::Variables;
+
+use super::{Builtin, BuiltinResult, ShellContext};
+
+pub struct Test;
+
+impl Builtin for Test {
+    fn name(&self) -> &'static str {
+        "test"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "test [expr]"
+    }
+
+    fn about(&self) -> &'static str {
+        "Evaluate a conditional expression."
+    }
+
+    fn run(&self, ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+        execute(cmd, ctx.vars)
+    }
+}
+
+pub struct Bracket;
+
+impl Builtin for Bracket {
+    fn name(&self) -> &'static str {
+        "["
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "[ [expr] ]"
+    }
+
+    fn about(&self) -> &'static str {
+        "Evaluate a conditional expression, like test."
+    }
+
+    fn run(&self, ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+        execute(cmd, ctx.vars)
+    }
+}
+
+fn execute(cmd: &SimpleCommand, vars: &mut Variables) -> Result<BuiltinResult, String> {
+    // Usage: test [expr], or [ [expr] ]
+    let mut args = vars.expand_words(&cmd.args)?;
+    if cmd.name == "[" {
+        if args.last().map(|s| s.as_str()) != Some("]") {
+            eprintln!("[: missing `]'");
+            return Ok(BuiltinResult::HandledStatus(2));
+        }
+        args.pop();
+    }
+    // No expression is false
+    if args.is_empty() {
+        return Ok(BuiltinResult::HandledStatus(1));
+    }
+
+    let result = conditional::parse(&args, Syntax::Test)
+        .and_then(|expr| conditional::eval(&expr, vars, Syntax::Test));
+    match result {
+        Ok(true) => Ok(BuiltinResult::HandledContinue),
+        Ok(false) => Ok(BuiltinResult::HandledStatus(1)),
+        Err(e) => {
+            eprintln!("{}: {}", cmd.name, e);
+            Ok(BuiltinResult::HandledStatus(2))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(name: &str, args: Vec<&str>, vars: &mut Variables) -> i32 {
+        let cmd = SimpleCommand {
+            name: name.into(),
+            args: args.into_iter().map(String::from).collect(),
+            assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
+        };
+        match execute(&cmd, vars).unwrap() {
+            BuiltinResult::HandledContinue => 0,
+            BuiltinResult::HandledStatus(status) => status,
+            _ => panic!("unexpected result"),
+        }
+    }
+
+    #[test]
+    fn test_sees_unexported_variables() {
+        let mut vars = Variables::new();
+        vars.set("LOCAL_ONLY".into(), "5".into());
+        assert_eq!(
+            status("[", vec!["$LOCAL_ONLY", "-eq", "5", "]"], &mut vars),
+            0
+        );
+        assert_eq!(status("test", vec!["-z", "$LOCAL_ONLY"], &mut vars), 1);
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut vars = Variables::new();
+        assert_eq!(status("[", vec!["a", "=", "a"], &mut vars), 2);
+        assert_eq!(status("test", vec!["1", "-lt", "x"], &mut vars), 2);
+        assert_eq!(status("test", vec![], &mut vars), 1);
+    }
+}
@@ -0,0 +1,145 @@
+use crate::builtins::common::shell_quote;
+use crate::parser::SimpleCommand;
+use crate::traps::TrapCondition;
+use crate::variables::Variables;
+
+use super::{Builtin, BuiltinResult, ShellContext};
+
+pub struct Trap;
+
+impl Builtin for Trap {
+    fn name(&self) -> &'static str {
+        "trap"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "trap [-p] [[action] condition ...]"
+    }
+
+    fn about(&self) -> &'static str {
+        "Run commands when the shell receives signals or exits."
+    }
+
+    fn run(&self, ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+        execute(cmd, ctx.vars)
+    }
+}
+
+fn parse_condition(name: &str, vars: &Variables) -> Result<TrapCondition, String> {
+    let name = vars.expand(name)?;
+    TrapCondition::parse(&name)
+        .ok_or_else(|| format!("trap: {}: invalid signal specification", name))
+}
+
+/// `trap -p`: the commands that would recreate the given traps, or all of them.
+fn print_traps(names: &[String], vars: &Variables) -> Result<(), String> {
+    let conditions = names
+        .iter()
+        .map(|name| parse_condition(name, vars))
+        .collect::<Result<Vec<TrapCondition>, String>>()?;
+    for (condition, action) in vars.traps().listed() {
+        if conditions.is_empty() || conditions.contains(&condition) {
+            println!("trap -- {} {}", shell_quote(action), condition.name());
+        }
+    }
+    Ok(())
+}
+
+fn execute(cmd: &SimpleCommand, vars: &mut Variables) -> Result<BuiltinResult, String> {
+    // Usage: trap [-p] [[action] condition ...]
+    let mut args = cmd.args.as_slice();
+    let mut print = false;
+    while let Some(first) = args.first() {
+        match first.as_str() {
+            "--" => {
+                args = &args[1..];
+                break;
+            }
+            "-p" => print = true,
+            flag if flag.len() > 1 && flag.starts_with('-') => {
+                return Err(format!("trap: {}: invalid option", flag));
+            }
+            _ => break,
+        }
+        args = &args[1..];
+    }
+
+    if print || args.is_empty() {
+        print_traps(args, vars)?;
+        return Ok(BuiltinResult::HandledContinue);
+    }
+
+    // A lone condition, or a number first, means every operand is reset
+    let reset_all = args.len() == 1 || args[0].parse::<u32>().is_ok();
+    let (action, names) = if reset_all {
+        (None, args)
+    } else if args[0] == "-" {
+        (None, &args[1..])
+    } else {
+        // The action is parsed when it runs, so expansions in it happen then
+        (Some(&args[0]), &args[1..])
+    };
+
+    for name in names {
+        let condition = parse_condition(name, vars)?;
+        match action {
+            Some(action) => vars.traps_mut().set(condition, action.clone())?,
+            None => vars.traps_mut().reset(condition)?,
+        }
+    }
+    Ok(BuiltinResult::HandledContinue)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_cmd(args: Vec<&str>) -> SimpleCommand {
+        SimpleCommand {
+            name: "trap".into(),
+            args: args.into_iter().map(String::from).collect(),
+            assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_trap_set_and_reset() {
+        let mut vars = Variables::new();
+        execute(&make_cmd(vec!["rm -f \"$tmp\"", "EXIT", "ERR"]), &mut vars).unwrap();
+        assert_eq!(
+            vars.traps().get(TrapCondition::Exit),
+            Some("rm -f \"$tmp\"")
+        );
+        assert!(vars.traps().get(TrapCondition::Err).is_some());
+
+        execute(&make_cmd(vec!["-", "EXIT"]), &mut vars).unwrap();
+        assert_eq!(vars.traps().get(TrapCondition::Exit), None);
+
+        // A lone condition is reset too
+        execute(&make_cmd(vec!["ERR"]), &mut vars).unwrap();
+        assert_eq!(vars.traps().get(TrapCondition::Err), None);
+    }
+
+    #[test]
+    fn test_trap_ignore_and_list() {
+        let mut vars = Variables::new();
+        execute(&make_cmd(vec!["", "DEBUG"]), &mut vars).unwrap();
+        assert_eq!(vars.traps().get(TrapCondition::Debug), Some(""));
+        assert!(execute(&make_cmd(vec!["-p"]), &mut vars).is_ok());
+        assert!(execute(&make_cmd(vec!["-p", "DEBUG"]), &mut vars).is_ok());
+        assert!(execute(&make_cmd(vec![]), &mut vars).is_ok());
+    }
+
+    #[test]
+    fn test_trap_invalid_condition_and_option() {
+        let mut vars = Variables::new();
+        let res = execute(&make_cmd(vec!["echo", "NOSUCH"]), &mut vars);
+        assert_eq!(
+            res.err().unwrap(),
+            "trap: NOSUCH: invalid signal specification"
+        );
+        assert!(execute(&make_cmd(vec!["-z"]), &mut vars).is_err());
+    }
+}
@@ -0,0 +1,65 @@
+use crate::aliases::Aliases;
+use crate::builtins::common::{parse_flags, write_output};
+use crate::functions::Functions;
+use crate::parser::SimpleCommand;
+use crate::resolve;
+use crate::variables::Variables;
+
+use super::{Builtin, BuiltinResult, ShellContext};
+
+pub struct Type;
+
+impl Builtin for Type {
+    fn name(&self) -> &'static str {
+        "type"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "type [-at] name ..."
+    }
+
+    fn about(&self) -> &'static str {
+        "Describe how each name would be run as a command."
+    }
+
+    fn run(&self, ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+        execute(cmd, ctx.vars, ctx.functions, ctx.aliases)
+    }
+}
+
+fn execute(
+    cmd: &SimpleCommand,
+    vars: &mut Variables,
+    functions: &Functions,
+    aliases: &Aliases,
+) -> Result<BuiltinResult, String> {
+    // Usage: type [-at] name ...
+    let words = vars.expand_words(&cmd.args)?;
+    let (flags, names) = parse_flags("type", &words, "at")?;
+    let all = flags.contains('a');
+    let kind_only = flags.contains('t');
+
+    let mut output = String::new();
+    let mut status = 0;
+    for name in names {
+        let found = resolve::resolve(name, vars, functions, aliases, all);
+        if found.is_empty() {
+            if !kind_only {
+                eprintln!("type: {}: not found", name);
+            }
+            status = 1;
+        }
+        for resolution in found {
+            match kind_only {
+                true => output.push_str(resolution.kind()),
+                false => output.push_str(&resolution.describe(name)),
+            }
+            output.push('\n');
+        }
+    }
+    write_output("type", &output)?;
+    Ok(match status {
+        0 => BuiltinResult::HandledContinue,
+        status => BuiltinResult::HandledStatus(status),
+    })
+}
@@ -0,0 +1,229 @@
+use crate::builtins::common::{parse_flags, write_output};
+use crate::parser::SimpleCommand;
+use crate::variables::Variables;
+use nix::sys::resource::{getrlimit, rlim_t, setrlimit, Resource, RLIM_INFINITY};
+
+use super::{Builtin, BuiltinResult, ShellContext};
+
+pub struct Ulimit;
+
+impl Builtin for Ulimit {
+    fn name(&self) -> &'static str {
+        "ulimit"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "ulimit [-SHa] [-cdfnstuv] [limit]"
+    }
+
+    fn about(&self) -> &'static str {
+        "Display or set the shell's resource limits."
+    }
+
+    fn run(&self, ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+        execute(cmd, ctx.vars)
+    }
+}
+
+/// A limit `ulimit` knows: its option letter, description, the unit it is
+/// shown in and how many bytes (or other base units) that unit is.
+struct Limit {
+    letter: char,
+    description: &'static str,
+    unit: Option<&'static str>,
+    scale: rlim_t,
+    resource: Resource,
+}
+
+/// The limits in the order `ulimit -a` lists them.
+const LIMITS: &[Limit] = &[
+    Limit {
+        letter: 'c',
+        description: "core file size",
+        unit: Some("blocks"),
+        scale: 512,
+        resource: Resource::RLIMIT_CORE,
+    },
+    Limit {
+        letter: 'd',
+        description: "data seg size",
+        unit: Some("kbytes"),
+        scale: 1024,
+        resource: Resource::RLIMIT_DATA,
+    },
+    Limit {
+        letter: 'f',
+        description: "file size",
+        unit: Some("blocks"),
+        scale: 512,
+        resource: Resource::RLIMIT_FSIZE,
+    },
+    Limit {
+        letter: 'n',
+        description: "open files",
+        unit: None,
+        scale: 1,
+        resource: Resource::RLIMIT_NOFILE,
+    },
+    Limit {
+        letter: 's',
+        description: "stack size",
+        unit: Some("kbytes"),
+        scale: 1024,
+        resource: Resource::RLIMIT_STACK,
+    },
+    Limit {
+        letter: 't',
+        description: "cpu time",
+        unit: Some("seconds"),
+        scale: 1,
+        resource: Resource::RLIMIT_CPU,
+    },
+    Limit {
+        letter: 'u',
+        description: "max user processes",
+        unit: None,
+        scale: 1,
+        resource: Resource::RLIMIT_NPROC,
+    },
+    Limit {
+        letter: 'v',
+        description: "virtual memory",
+        unit: Some("kbytes"),
+        scale: 1024,
+        resource: Resource::RLIMIT_AS,
+    },
+];
+
+fn execute(cmd: &SimpleCommand, vars: &mut Variables) -> Result<BuiltinResult, String> {
+    let words = vars.expand_words(&cmd.args)?;
+    let (flags, operands) = parse_flags("ulimit", &words, "SHacdfnstuv")?;
+    let soft = flags.contains('S');
+    let hard = flags.contains('H');
+
+    let mut limits: Vec<&Limit> = match flags.contains('a') {
+        true => LIMITS.iter().collect(),
+        false => LIMITS
+            .iter()
+            .filter(|limit| flags.contains(limit.letter))
+            .collect(),
+    };
+    if limits.is_empty() {
+        limits.push(&LIMITS[2]);
+    }
+
+    match operands {
+        [] => {
+            // Shows the soft limit unless only -H is given
+            let show_hard = hard && !soft;
+            let mut output = String::new();
+            for limit in &limits {
+                let (cur, max) = getrlimit(limit.resource).map_err(|e| {
+                    format!(
+                        "ulimit: {}: cannot get limit: {}",
+                        limit.description,
+                        e.desc()
+                    )
+                })?;
+                let value = format_value(if show_hard { max } else { cur }, limit.scale);
+                match limits.len() {
+                    1 => output.push_str(&format!("{}\n", value)),
+                    _ => output.push_str(&format!("{}{}\n", label(limit), value)),
+                }
+            }
+            write_output("ulimit", &output)?;
+        }
+        [value] => {
+            if flags.contains('a') {
+                return Err("ulimit: -a: cannot set a limit for all resources".to_string());
+            }
+            // Sets both limits unless told which
+            let (set_soft, set_hard) = match (soft, hard) {
+                (false, false) => (true, true),
+                both => both,
+            };
+            for limit in limits {
+                set_limit(limit, value, set_soft, set_hard)?;
+            }
+        }
+        _ => return Err("ulimit: too many arguments".to_string()),
+    }
+    Ok(BuiltinResult::HandledContinue)
+}
+
+/// `limit` as `ulimit -a` lists it, padded so that the values line up.
+fn label(limit: &Limit) -> String {
+    let unit = match limit.unit {
+        Some(unit) => format!("({}, -{}) ", unit, limit.letter),
+        None => format!("(-{}) ", limit.letter),
+    };
+    format!("{:<20} {:>20}", limit.description, unit)
+}
+
+fn format_value(value: rlim_t, scale: rlim_t) -> String {
+    match value {
+        RLIM_INFINITY => "unlimited".to_string(),
+        value => (value / scale).to_string(),
+    }
+}
+
+/// Change the soft and/or hard limit of `limit` to `value`: a number of
+/// its units, `unlimited`, or the current `soft` or `hard` limit.
+/// Processes started afterwards inherit the new limits.
+fn set_limit(limit: &Limit, value: &str, soft: bool, hard: bool) -> Result<(), String> {
+    let (cur, max) = getrlimit(limit.resource).map_err(|e| {
+        format!(
+            "ulimit: {}: cannot get limit: {}",
+            limit.description,
+            e.desc()
+        )
+    })?;
+    let new = match value {
+        "unlimited" => RLIM_INFINITY,
+        "soft" => cur,
+        "hard" => max,
+        value => value
+            .parse::<rlim_t>()
+            .ok()
+            .and_then(|n| n.checked_mul(limit.scale))
+            .filter(|n| *n != RLIM_INFINITY)
+            .ok_or_else(|| format!("ulimit: {}: invalid number", value))?,
+    };
+    let cur = if soft { new } else { cur };
+    let max = if hard { new } else { max };
+    setrlimit(limit.resource, cur, max).map_err(|e| {
+        format!(
+            "ulimit: {}: cannot modify limit: {}",
+            limit.description,
+            e.desc()
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limits_are_sorted_by_letter() {
+        let letters: Vec<char> = LIMITS.iter().map(|l| l.letter).collect();
+        let mut sorted = letters.clone();
+        sorted.sort();
+        assert_eq!(letters, sorted);
+        assert_eq!(LIMITS[2].letter, 'f');
+    }
+
+    #[test]
+    fn test_label_and_value() {
+        assert_eq!(
+            format!("{}0", label(&LIMITS[0])),
+            "core file size              (blocks, -c) 0"
+        );
+        assert_eq!(
+            format!("{}8", label(&LIMITS[3])),
+            "open files                          (-n) 8"
+        );
+        assert_eq!(format_value(RLIM_INFINITY, 1024), "unlimited");
+        assert_eq!(format_value(8192 * 1024, 1024), "8192");
+    }
+}
@@ -0,0 +1,168 @@
+use crate::builtins::common::{parse_flags, write_output};
+use crate::parser::SimpleCommand;
+use crate::variables::Variables;
+use nix::sys::stat::{umask, Mode};
+
+use super::{Builtin, BuiltinResult, ShellContext};
+
+pub struct Umask;
+
+impl Builtin for Umask {
+    fn name(&self) -> &'static str {
+        "umask"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "umask [-S] [mode]"
+    }
+
+    fn about(&self) -> &'static str {
+        "Display or set the file mode creation mask."
+    }
+
+    fn run(&self, ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+        execute(cmd, ctx.vars)
+    }
+}
+
+fn execute(cmd: &SimpleCommand, vars: &mut Variables) -> Result<BuiltinResult, String> {
+    let words = vars.expand_words(&cmd.args)?;
+    let (flags, operands) = parse_flags("umask", &words, "S")?;
+    let current = current_mask();
+    let mode = match operands {
+        [] => {
+            let text = match flags.contains('S') {
+                true => symbolic(current),
+                false => format!("{:04o}", current),
+            };
+            write_output("umask", &format!("{}\n", text))?;
+            return Ok(BuiltinResult::HandledContinue);
+        }
+        [mode] => mode,
+        _ => return Err("umask: too many arguments".to_string()),
+    };
+
+    let mask = match mode.starts_with(|c: char| c.is_ascii_digit()) {
+        true => parse_octal(mode)?,
+        false => parse_symbolic(mode, current)?,
+    };
+    // The mask belongs to the process, so programs started later inherit it
+    umask(Mode::from_bits_truncate(mask));
+    Ok(BuiltinResult::HandledContinue)
+}
+
+/// The file mode creation mask, which can only be read by setting it.
+fn current_mask() -> u32 {
+    let mask = umask(Mode::empty());
+    umask(mask);
+    mask.bits() as u32
+}
+
+fn parse_octal(mode: &str) -> Result<u32, String> {
+    u32::from_str_radix(mode, 8)
+        .ok()
+        .filter(|mask| *mask <= 0o777)
+        .ok_or_else(|| format!("umask: {}: octal number out of range", mode))
+}
+
+/// The permissions a mask leaves, as `u=rwx,g=rx,o=rx`.
+fn symbolic(mask: u32) -> String {
+    let perms = !mask & 0o777;
+    ["u", "g", "o"]
+        .iter()
+        .zip([6, 3, 0])
+        .map(|(who, shift)| {
+            let bits = perms >> shift;
+            let letters: String = [(0o4, 'r'), (0o2, 'w'), (0o1, 'x')]
+                .iter()
+                .filter(|(bit, _)| bits & bit != 0)
+                .map(|(_, letter)| letter)
+                .collect();
+            format!("{}={}", who, letters)
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Apply a symbolic mode like `u=rwx,g-w,o=` to the permissions `mask`
+/// leaves, and return the mask for the result. Each clause names who it
+/// applies to (`a` if nobody) and then one or more operators, each with
+/// permission letters or one of `u`, `g` and `o` to copy from.
+fn parse_symbolic(mode: &str, mask: u32) -> Result<u32, String> {
+    let mut perms = !mask & 0o777;
+    for clause in mode.split(',') {
+        let mut chars = clause.chars().peekable();
+        let mut who = 0;
+        while let Some(c) = chars.next_if(|c| "ugoa".contains(*c)) {
+            who |= match c {
+                'u' => 0o700,
+                'g' => 0o070,
+                'o' => 0o007,
+                _ => 0o777,
+            };
+        }
+        if who == 0 {
+            who = 0o777;
+        }
+
+        let mut op = match chars.next() {
+            Some(c @ ('+' | '-' | '=')) => c,
+            Some(c) => return Err(format!("umask: `{}': invalid symbolic mode operator", c)),
+            None => return Err(format!("umask: {}: invalid symbolic mode", mode)),
+        };
+        loop {
+            let mut bits = 0;
+            while let Some(c) = chars.next_if(|c| !"+-=".contains(*c)) {
+                bits |= match c {
+                    'r' => 0o444,
+                    'w' => 0o222,
+                    'x' => 0o111,
+                    // Copy another class's permissions
+                    'u' => (perms >> 6 & 0o7) * 0o111,
+                    'g' => (perms >> 3 & 0o7) * 0o111,
+                    'o' => (perms & 0o7) * 0o111,
+                    c => return Err(format!("umask: `{}': invalid symbolic mode character", c)),
+                };
+            }
+            perms = match op {
+                '+' => perms | (bits & who),
+                '-' => perms & !(bits & who),
+                _ => (perms & !who) | (bits & who),
+            };
+            match chars.next() {
+                Some(next) => op = next,
+                None => break,
+            }
+        }
+    }
+    Ok(!perms & 0o777)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_symbolic_output() {
+        assert_eq!(symbolic(0o022), "u=rwx,g=rx,o=rx");
+        assert_eq!(symbolic(0o077), "u=rwx,g=,o=");
+        assert_eq!(symbolic(0o777), "u=,g=,o=");
+    }
+
+    #[test]
+    fn test_parse_modes() {
+        assert_eq!(parse_octal("027"), Ok(0o027));
+        assert!(parse_octal("1000").is_err());
+        assert!(parse_octal("8").is_err());
+
+        assert_eq!(parse_symbolic("u=rwx,g=rx,o=", 0o022), Ok(0o027));
+        assert_eq!(parse_symbolic("g-w", 0o002), Ok(0o022));
+        assert_eq!(parse_symbolic("a+r", 0o777), Ok(0o333));
+        assert_eq!(parse_symbolic("=", 0o022), Ok(0o777));
+        assert_eq!(parse_symbolic("o=g", 0o027), Ok(0o022));
+        assert_eq!(parse_symbolic("u=rw-w+x", 0o000), Ok(0o200));
+        assert!(parse_symbolic("u", 0o022).is_err());
+        assert!(parse_symbolic("u=z", 0o022).is_err());
+        assert!(parse_symbolic("u*r", 0o022).is_err());
+    }
+}
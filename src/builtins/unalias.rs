@@ -0,0 +1,105 @@
+use crate::aliases::Aliases;
+use crate::parser::SimpleCommand;
+
+use super::{Builtin, BuiltinResult, ShellContext};
+
+pub struct Unalias;
+
+impl Builtin for Unalias {
+    fn name(&self) -> &'static str {
+        "unalias"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "unalias [-a] name [name ...]"
+    }
+
+    fn about(&self) -> &'static str {
+        "Remove aliases."
+    }
+
+    fn run(&self, ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+        execute(cmd, ctx.aliases)
+    }
+}
+
+fn execute(cmd: &SimpleCommand, aliases: &mut Aliases) -> Result<BuiltinResult, String> {
+    // Usage: unalias [-a] name [name ...]
+    let mut names = cmd.args.as_slice();
+    let mut remove_all = false;
+    if let Some(first) = names.first() {
+        if first == "-a" {
+            remove_all = true;
+            names = &names[1..];
+        } else if first == "--" {
+            names = &names[1..];
+        }
+    }
+
+    if !remove_all && names.is_empty() {
+        return Err("unalias: usage: unalias [-a] name [name ...]".to_string());
+    }
+
+    if remove_all {
+        aliases.clear();
+        return Ok(BuiltinResult::HandledContinue);
+    }
+
+    let mut status = 0;
+    for name in names {
+        if !aliases.remove(name) {
+            eprintln!("unalias: {}: not found", name);
+            status = 1;
+        }
+    }
+
+    Ok(match status {
+        0 => BuiltinResult::HandledContinue,
+        status => BuiltinResult::HandledStatus(status),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_cmd(args: Vec<&str>) -> SimpleCommand {
+        SimpleCommand {
+            name: "unalias".into(),
+            args: args.into_iter().map(String::from).collect(),
+            assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_unalias_removes_alias() {
+        let mut aliases = Aliases::new();
+        aliases.set("unalias_test".to_string(), "echo".to_string());
+
+        let res = execute(&make_cmd(vec!["unalias_test"]), &mut aliases).unwrap();
+        assert!(matches!(res, BuiltinResult::HandledContinue));
+        assert!(aliases.get("unalias_test").is_none());
+    }
+
+    #[test]
+    fn test_unalias_missing_returns_one() {
+        let mut aliases = Aliases::new();
+        aliases.set("unalias_test_other".to_string(), "echo".to_string());
+
+        let res = execute(
+            &make_cmd(vec!["unalias_test_missing", "unalias_test_other"]),
+            &mut aliases,
+        )
+        .unwrap();
+        assert!(matches!(res, BuiltinResult::HandledStatus(1)));
+        assert!(aliases.get("unalias_test_other").is_none());
+    }
+
+    #[test]
+    fn test_unalias_no_args_returns_error() {
+        let mut aliases = Aliases::new();
+        assert!(execute(&make_cmd(vec![]), &mut aliases).is_err());
+    }
+}
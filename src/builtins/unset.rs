@@ -1,16 +1,70 @@
+use crate::builtins::common::parse_flags;
+use crate::functions::Functions;
 use crate::parser::SimpleCommand;
 use crate::variables::Variables;
 
-use super::BuiltinResult;
+use super::{Builtin, BuiltinResult, ShellContext};
 
-pub fn execute(cmd: &SimpleCommand, vars: &mut Variables) -> Result<BuiltinResult, String> {
+pub struct Unset;
+
+impl Builtin for Unset {
+    fn name(&self) -> &'static str {
+        "unset"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "unset [-fv] name ..."
+    }
+
+    fn about(&self) -> &'static str {
+        "Remove variables and functions."
+    }
+
+    fn run(&self, ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+        execute(cmd, ctx.vars, ctx.functions)
+    }
+}
+
+fn execute(
+    cmd: &SimpleCommand,
+    vars: &mut Variables,
+    functions: &mut Functions,
+) -> Result<BuiltinResult, String> {
     if cmd.args.is_empty() {
         return Err("unset: expected at least one argument".to_string());
     }
-    for name in &cmd.args {
-        vars.unset(name);
+    let (flags, names) = parse_flags("unset", &cmd.args, "fv")?;
+    let only_functions = flags.contains('f');
+    let only_variables = flags.contains('v');
+    if only_functions && only_variables {
+        return Err("unset: cannot simultaneously unset a function and a variable".to_string());
+    }
+
+    // Names are expanded so that `unset "a[$i]"` names an element
+    let mut status = 0;
+    for name in names {
+        let name = vars.expand(name)?;
+        let base = name.split('[').next().unwrap_or_default();
+        // Without -v, a name that is not a variable may be a function
+        let function = only_functions
+            || (!only_variables
+                && vars.declaration(base).is_none()
+                && functions.get(&name).is_some());
+        if function {
+            functions.remove(&name);
+            continue;
+        }
+        if vars.is_readonly(base) {
+            eprintln!("unset: {}: cannot unset: readonly variable", base);
+            status = 1;
+            continue;
+        }
+        vars.unset(&name);
     }
-    Ok(BuiltinResult::HandledContinue)
+    Ok(match status {
+        0 => BuiltinResult::HandledContinue,
+        status => BuiltinResult::HandledStatus(status),
+    })
 }
 
 #[cfg(test)]
@@ -23,6 +77,8 @@ mod tests {
             name: "unset".into(),
             args: args.into_iter().map(String::from).collect(),
             assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
         }
     }
 
@@ -33,7 +89,7 @@ mod tests {
         assert!(vars.get("MY_VAR").is_some());
 
         let cmd = make_cmd(vec!["MY_VAR"]);
-        let res = execute(&cmd, &mut vars).unwrap();
+        let res = execute(&cmd, &mut vars, &mut Functions::new()).unwrap();
         assert!(matches!(res, BuiltinResult::HandledContinue));
         assert!(vars.get("MY_VAR").is_none());
     }
@@ -42,7 +98,7 @@ mod tests {
     fn test_unset_nonexistent_var_is_noop() {
         let mut vars = Variables::new();
         let cmd = make_cmd(vec!["DOES_NOT_EXIST_XYZ"]);
-        let res = execute(&cmd, &mut vars);
+        let res = execute(&cmd, &mut vars, &mut Functions::new());
         assert!(res.is_ok());
     }
 
@@ -53,17 +109,58 @@ mod tests {
         vars.set("B".to_string(), "2".to_string());
 
         let cmd = make_cmd(vec!["A", "B"]);
-        execute(&cmd, &mut vars).unwrap();
+        execute(&cmd, &mut vars, &mut Functions::new()).unwrap();
 
         assert!(vars.get("A").is_none());
         assert!(vars.get("B").is_none());
     }
 
+    #[test]
+    fn test_unset_array_element() {
+        let mut vars = Variables::new();
+        vars.assign("arr[0]", "a").unwrap();
+        vars.assign("arr[1]", "b").unwrap();
+        vars.set("i".to_string(), "1".to_string());
+
+        execute(&make_cmd(vec!["arr[$i]"]), &mut vars, &mut Functions::new()).unwrap();
+        assert_eq!(vars.array_values("arr"), vec!["a"]);
+        execute(&make_cmd(vec!["arr"]), &mut vars, &mut Functions::new()).unwrap();
+        assert!(vars.array("arr").is_none());
+    }
+
     #[test]
     fn test_unset_no_args_returns_error() {
         let mut vars = Variables::new();
         let cmd = make_cmd(vec![]);
-        let res = execute(&cmd, &mut vars);
+        let res = execute(&cmd, &mut vars, &mut Functions::new());
         assert!(res.is_err());
     }
+
+    #[test]
+    fn test_unset_functions() {
+        let mut vars = Variables::new();
+        let mut functions = Functions::new();
+        functions.set("f".to_string(), vec![]);
+        functions.set("g".to_string(), vec![]);
+        vars.set("g".to_string(), "1".to_string());
+
+        // A bare name is a variable first, then a function
+        execute(&make_cmd(vec!["g"]), &mut vars, &mut functions).unwrap();
+        assert!(vars.get("g").is_none());
+        assert!(functions.get("g").is_some());
+        execute(&make_cmd(vec!["f", "g"]), &mut vars, &mut functions).unwrap();
+        assert!(functions.names().is_empty());
+
+        functions.set("f".to_string(), vec![]);
+        vars.set("f".to_string(), "1".to_string());
+        execute(&make_cmd(vec!["-v", "f"]), &mut vars, &mut functions).unwrap();
+        execute(&make_cmd(vec!["-v", "f"]), &mut vars, &mut functions).unwrap();
+        assert!(functions.get("f").is_some());
+        vars.set("f".to_string(), "1".to_string());
+        execute(&make_cmd(vec!["-f", "f"]), &mut vars, &mut functions).unwrap();
+        assert!(functions.get("f").is_none());
+        assert!(vars.get("f").is_some());
+
+        assert!(execute(&make_cmd(vec!["-fv", "f"]), &mut vars, &mut functions).is_err());
+    }
 }
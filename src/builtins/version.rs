@@ -1,6 +1,29 @@
 use crate::parser::SimpleCommand;
 
-pub fn execute(cmd: &SimpleCommand) -> Result<(), String> {
+use super::{Builtin, BuiltinResult, ShellContext};
+
+pub struct Version;
+
+impl Builtin for Version {
+    fn name(&self) -> &'static str {
+        "version"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "version"
+    }
+
+    fn about(&self) -> &'static str {
+        "Display the version of the shell."
+    }
+
+    fn run(&self, _ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+        execute(cmd)?;
+        Ok(BuiltinResult::HandledContinue)
+    }
+}
+
+fn execute(cmd: &SimpleCommand) -> Result<(), String> {
     if !cmd.args.is_empty() {
         return Err(format!("{}: no arguments expected", cmd.name));
     }
@@ -26,6 +49,8 @@ mod tests {
             name: "version".to_string(),
             args: vec![],
             assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
         };
         assert!(execute(&cmd).is_ok());
     }
@@ -36,6 +61,8 @@ mod tests {
             name: "version".to_string(),
             args: vec!["extra".to_string()],
             assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
         };
         let result = execute(&cmd);
         assert!(result.is_err());
@@ -0,0 +1,65 @@
+use crate::aliases::Aliases;
+use crate::builtins::common::{parse_flags, write_output};
+use crate::functions::Functions;
+use crate::parser::SimpleCommand;
+use crate::resolve::{self, Resolution};
+use crate::variables::Variables;
+
+use super::{Builtin, BuiltinResult, ShellContext};
+
+pub struct Which;
+
+impl Builtin for Which {
+    fn name(&self) -> &'static str {
+        "which"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "which [-a] name ..."
+    }
+
+    fn about(&self) -> &'static str {
+        "Show where each command is found."
+    }
+
+    fn run(&self, ctx: &mut ShellContext, cmd: &SimpleCommand) -> Result<BuiltinResult, String> {
+        execute(cmd, ctx.vars, ctx.functions, ctx.aliases)
+    }
+}
+
+fn execute(
+    cmd: &SimpleCommand,
+    vars: &mut Variables,
+    functions: &Functions,
+    aliases: &Aliases,
+) -> Result<BuiltinResult, String> {
+    // Usage: which [-a] name ...
+    let words = vars.expand_words(&cmd.args)?;
+    let (flags, names) = parse_flags("which", &words, "a")?;
+    let all = flags.contains('a');
+
+    let mut output = String::new();
+    let mut status = 0;
+    for name in names {
+        let found = resolve::resolve(name, vars, functions, aliases, all);
+        if found.is_empty() {
+            eprintln!("which: {}: not found", name);
+            status = 1;
+        }
+        for resolution in found {
+            output.push_str(&match resolution {
+                Resolution::Alias(value) => format!("{}: aliased to {}", name, value),
+                Resolution::Keyword => format!("{}: shell reserved word", name),
+                Resolution::Function => format!("{}: shell function", name),
+                Resolution::Builtin => format!("{}: shell built-in command", name),
+                Resolution::File(path, _) => path.display().to_string(),
+            });
+            output.push('\n');
+        }
+    }
+    write_output("which", &output)?;
+    Ok(match status {
+        0 => BuiltinResult::HandledContinue,
+        status => BuiltinResult::HandledStatus(status),
+    })
+}
@@ -0,0 +1,185 @@
+use clap::Parser;
+use std::io::IsTerminal;
+use std::path::PathBuf;
+
+/// Command-line interface of the shell itself
+#[derive(Parser, Debug)]
+#[command(name = "pmsh", version)]
+#[command(about = "pmsh - a small POSIX-style shell", long_about = None)]
+pub struct Cli {
+    /// Run COMMAND; further arguments set $0 and the positional parameters
+    #[arg(short = 'c', value_name = "COMMAND")]
+    pub command: Option<String>,
+
+    /// Read commands from standard input; all arguments are positional parameters
+    #[arg(short = 's')]
+    pub stdin: bool,
+
+    /// Run interactively, even if standard input is not a terminal
+    #[arg(short = 'i')]
+    pub interactive: bool,
+
+    /// Act as a login shell
+    #[arg(short = 'l', long = "login")]
+    pub login: bool,
+
+    /// Do not read the interactive startup file
+    #[arg(long)]
+    pub norc: bool,
+
+    /// Read FILE instead of ~/.pmshrc in interactive shells
+    #[arg(long, value_name = "FILE")]
+    pub rcfile: Option<PathBuf>,
+
+    /// Exit when a command fails (set -e)
+    #[arg(short = 'e')]
+    pub errexit: bool,
+
+    /// Print commands as they run (set -x)
+    #[arg(short = 'x')]
+    pub xtrace: bool,
+
+    /// Treat expanding an unset variable as an error (set -u)
+    #[arg(short = 'u')]
+    pub nounset: bool,
+
+    /// Script to run, followed by its arguments
+    #[arg(
+        value_name = "ARGS",
+        trailing_var_arg = true,
+        allow_hyphen_values = true
+    )]
+    pub args: Vec<String>,
+}
+
+/// Where the shell reads its commands from.
+#[derive(Debug, PartialEq)]
+pub enum Input {
+    /// The string given with -c
+    Command(String),
+    /// A script file
+    Script(PathBuf),
+    /// Standard input, read as a script
+    Stdin,
+    /// The line editor
+    Interactive,
+}
+
+impl Cli {
+    /// Decide the input and the values of `$0` and the positional
+    /// parameters. `argv0` is the name the shell was invoked as.
+    pub fn input(&self, argv0: &str, stdin_is_tty: bool) -> (Input, String, Vec<String>) {
+        if let Some(command) = &self.command {
+            let (name, args) = match self.args.split_first() {
+                Some((name, args)) => (name.clone(), args.to_vec()),
+                None => (argv0.to_string(), Vec::new()),
+            };
+            return (Input::Command(command.clone()), name, args);
+        }
+        if !self.stdin {
+            if let Some((script, args)) = self.args.split_first() {
+                return (
+                    Input::Script(PathBuf::from(script)),
+                    script.clone(),
+                    args.to_vec(),
+                );
+            }
+        }
+        let input = if self.interactive || stdin_is_tty {
+            Input::Interactive
+        } else {
+            Input::Stdin
+        };
+        (input, argv0.to_string(), self.args.clone())
+    }
+
+    /// A login shell is requested with -l, or by a name starting with `-`.
+    pub fn is_login(&self, argv0: &str) -> bool {
+        self.login || argv0.starts_with('-')
+    }
+}
+
+/// Returns true if commands typed at a terminal arrive on standard input.
+pub fn stdin_is_tty() -> bool {
+    std::io::stdin().is_terminal()
+}
+
+/// Lines of standard input, including their newlines. Reads a byte at a
+/// time so nothing past the current line is consumed: commands the shell
+/// runs read the rest of the input themselves.
+pub fn stdin_lines() -> impl Iterator<Item = String> {
+    std::iter::from_fn(|| {
+        let mut line = Vec::new();
+        let mut byte = [0u8; 1];
+        loop {
+            match nix::unistd::read(0, &mut byte) {
+                Ok(1) => {
+                    line.push(byte[0]);
+                    if byte[0] == b'\n' {
+                        break;
+                    }
+                }
+                Err(nix::errno::Errno::EINTR) => continue,
+                _ => break,
+            }
+        }
+        if line.is_empty() {
+            None
+        } else {
+            Some(String::from_utf8_lossy(&line).into_owned())
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Cli {
+        Cli::try_parse_from(std::iter::once("pmsh").chain(args.iter().copied())).unwrap()
+    }
+
+    #[test]
+    fn test_command_string_with_name_and_args() {
+        let cli = parse(&["-c", "echo $0 $1", "name", "a", "b"]);
+        let (input, arg0, args) = cli.input("pmsh", true);
+        assert_eq!(input, Input::Command("echo $0 $1".to_string()));
+        assert_eq!(arg0, "name");
+        assert_eq!(args, vec!["a", "b"]);
+
+        let (_, arg0, args) = parse(&["-c", "true"]).input("pmsh", true);
+        assert_eq!(arg0, "pmsh");
+        assert!(args.is_empty());
+    }
+
+    #[test]
+    fn test_script_arguments_keep_their_dashes() {
+        let cli = parse(&["-e", "script.sh", "-x", "--flag"]);
+        assert!(cli.errexit);
+        assert!(!cli.xtrace);
+        let (input, arg0, args) = cli.input("pmsh", true);
+        assert_eq!(input, Input::Script(PathBuf::from("script.sh")));
+        assert_eq!(arg0, "script.sh");
+        assert_eq!(args, vec!["-x", "--flag"]);
+    }
+
+    #[test]
+    fn test_stdin_and_interactive_modes() {
+        let (input, _, args) = parse(&["-s", "a", "b"]).input("pmsh", false);
+        assert_eq!(input, Input::Stdin);
+        assert_eq!(args, vec!["a", "b"]);
+
+        assert_eq!(parse(&[]).input("pmsh", true).0, Input::Interactive);
+        assert_eq!(parse(&[]).input("pmsh", false).0, Input::Stdin);
+        assert_eq!(parse(&["-i"]).input("pmsh", false).0, Input::Interactive);
+    }
+
+    #[test]
+    fn test_login_shell() {
+        let cli = parse(&["--norc", "--rcfile", "/tmp/rc"]);
+        assert!(cli.norc);
+        assert!(!cli.is_login("pmsh"));
+        assert!(cli.is_login("-pmsh"));
+        assert!(parse(&["-l"]).is_login("pmsh"));
+    }
+}
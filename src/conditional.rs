@@ -0,0 +1,404 @@
+use crate::arith;
+use crate::pattern;
+use crate::variables::Variables;
+use std::fs;
+use std::os::unix::fs::{FileTypeExt, MetadataExt, PermissionsExt};
+
+/// The array `=~` stores the match and its groups in.
+pub const REMATCH: &str = "PMSH_REMATCH";
+
+/// Mark a double-quoted part of the right side of `=~`. The parser keeps
+/// them where the quotes were, since conch-parser drops quotes, so that
+/// what it expands to can be matched literally.
+pub const QUOTED_START: char = '\u{2}';
+pub const QUOTED_END: char = '\u{3}';
+
+/// Which syntax a conditional expression is written in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Syntax {
+    /// Arguments to `test` and `[`: already expanded, joined with `-a` and
+    /// `-o`, and compared as plain strings and decimal integers.
+    Test,
+    /// The words of `[[ ... ]]`: expanded as they are evaluated, joined
+    /// with `&&` and `||`, with patterns on `==` and `!=`, regexes on `=~`
+    /// and arithmetic on the integer comparisons.
+    Extended,
+}
+
+/// A parsed conditional expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Unary(String, String),
+    Binary(String, String, String),
+    /// A lone string, true if it is not empty.
+    Word(String),
+}
+
+const UNARY: &[&str] = &[
+    "-a", "-b", "-c", "-d", "-e", "-f", "-g", "-h", "-k", "-n", "-o", "-p", "-r", "-s", "-t", "-u",
+    "-v", "-w", "-x", "-z", "-G", "-L", "-N", "-O", "-S",
+];
+
+const BINARY: &[&str] = &[
+    "=", "==", "!=", "<", ">", "-eq", "-ne", "-lt", "-le", "-gt", "-ge", "-nt", "-ot", "-ef",
+];
+
+fn is_unary(op: &str, syntax: Syntax) -> bool {
+    // `test` takes `-a` as "and" only
+    UNARY.contains(&op) && !(syntax == Syntax::Test && op == "-a")
+}
+
+fn is_binary(op: &str, syntax: Syntax) -> bool {
+    BINARY.contains(&op) || (syntax == Syntax::Extended && op == "=~")
+}
+
+/// Parse a whole conditional expression.
+pub fn parse(words: &[String], syntax: Syntax) -> Result<Expr, String> {
+    let mut parser = Parser {
+        words,
+        pos: 0,
+        syntax,
+    };
+    let expr = parser.or()?;
+    match words.get(parser.pos) {
+        Some(word) => Err(format!("{}: unexpected argument", word)),
+        None => Ok(expr),
+    }
+}
+
+struct Parser<'a> {
+    words: &'a [String],
+    pos: usize,
+    syntax: Syntax,
+}
+
+impl Parser<'_> {
+    fn peek(&self, offset: usize) -> Option<&str> {
+        self.words.get(self.pos + offset).map(|w| w.as_str())
+    }
+
+    fn next(&mut self) -> Result<&str, String> {
+        let word = self
+            .words
+            .get(self.pos)
+            .ok_or_else(|| "argument expected".to_string())?;
+        self.pos += 1;
+        Ok(word)
+    }
+
+    fn operators(&self) -> (&'static str, &'static str) {
+        match self.syntax {
+            Syntax::Test => ("-a", "-o"),
+            Syntax::Extended => ("&&", "||"),
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut left = self.and()?;
+        while self.peek(0) == Some(self.operators().1) {
+            self.pos += 1;
+            left = Expr::Or(Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut left = self.not()?;
+        while self.peek(0) == Some(self.operators().0) {
+            self.pos += 1;
+            left = Expr::And(Box::new(left), Box::new(self.not()?));
+        }
+        Ok(left)
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        // `! = x` and `( = x` compare the strings `!` and `(`
+        let compares =
+            self.peek(2).is_some() && self.peek(1).is_some_and(|op| is_binary(op, self.syntax));
+        match self.peek(0) {
+            Some("!") if !compares && self.peek(1).is_some() => {
+                self.pos += 1;
+                Ok(Expr::Not(Box::new(self.not()?)))
+            }
+            Some("(") if !compares && self.peek(1).is_some() => {
+                self.pos += 1;
+                let expr = self.or()?;
+                if self.next().ok() != Some(")") {
+                    return Err("expected `)'".to_string());
+                }
+                Ok(expr)
+            }
+            _ => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        let syntax = self.syntax;
+        let word = self.next()?.to_string();
+        if let (Some(op), Some(right)) = (self.peek(0), self.peek(1)) {
+            if is_binary(op, syntax) {
+                let (op, right) = (op.to_string(), right.to_string());
+                self.pos += 2;
+                return Ok(Expr::Binary(word, op, right));
+            }
+        }
+        if is_unary(&word, syntax) {
+            if let Some(operand) = self.peek(0) {
+                let operand = operand.to_string();
+                self.pos += 1;
+                return Ok(Expr::Unary(word, operand));
+            }
+        }
+        Ok(Expr::Word(word))
+    }
+}
+
+/// Evaluate `expr`. Errors are those of an invalid operand, such as a
+/// non-integer in an integer comparison.
+pub fn eval(expr: &Expr, vars: &mut Variables, syntax: Syntax) -> Result<bool, String> {
+    let operand = |word: &str, vars: &Variables| match syntax {
+        Syntax::Test => Ok(word.to_string()),
+        Syntax::Extended => vars.expand(word),
+    };
+    Ok(match expr {
+        Expr::Not(inner) => !eval(inner, vars, syntax)?,
+        Expr::And(left, right) => eval(left, vars, syntax)? && eval(right, vars, syntax)?,
+        Expr::Or(left, right) => eval(left, vars, syntax)? || eval(right, vars, syntax)?,
+        Expr::Word(word) => !operand(word, vars)?.is_empty(),
+        Expr::Unary(op, word) => unary(op, &operand(word, vars)?, vars),
+        Expr::Binary(left, op, right) => {
+            let (left, right) = (operand(left, vars)?, operand(right, vars)?);
+            match (op.as_str(), syntax) {
+                ("=" | "==", Syntax::Extended) => pattern::matches(&right, &left),
+                ("!=", Syntax::Extended) => !pattern::matches(&right, &left),
+                ("=~", _) => regex_match(&left, &right, vars)?,
+                ("-eq" | "-ne" | "-lt" | "-le" | "-gt" | "-ge", Syntax::Extended) => {
+                    let left = arith::eval(&left, vars)?;
+                    compare_integers(op, left, arith::eval(&right, vars)?)
+                }
+                _ => binary(op, &left, &right)?,
+            }
+        }
+    })
+}
+
+/// Evaluate a unary test on an expanded operand.
+fn unary(op: &str, operand: &str, vars: &Variables) -> bool {
+    use nix::unistd::{access, getegid, geteuid, AccessFlags};
+
+    let metadata = fs::metadata(operand);
+    let mode = |bit: u32| {
+        metadata
+            .as_ref()
+            .is_ok_and(|m| m.permissions().mode() & bit != 0)
+    };
+    match op {
+        "-z" => operand.is_empty(),
+        "-n" => !operand.is_empty(),
+        "-v" => vars.get(operand).is_some() || vars.array(operand).is_some(),
+        "-o" => vars.options().get(operand) == Some(true),
+        "-t" => operand
+            .trim()
+            .parse()
+            .is_ok_and(|fd| nix::unistd::isatty(fd).unwrap_or(false)),
+        "-h" | "-L" => fs::symlink_metadata(operand).is_ok_and(|m| m.file_type().is_symlink()),
+        "-r" => access(operand, AccessFlags::R_OK).is_ok(),
+        "-w" => access(operand, AccessFlags::W_OK).is_ok(),
+        "-x" => access(operand, AccessFlags::X_OK).is_ok(),
+        "-u" => mode(0o4000),
+        "-g" => mode(0o2000),
+        "-k" => mode(0o1000),
+        _ => {
+            let Ok(metadata) = metadata else {
+                return false;
+            };
+            let file_type = metadata.file_type();
+            match op {
+                "-a" | "-e" => true,
+                "-f" => file_type.is_file(),
+                "-d" => file_type.is_dir(),
+                "-b" => file_type.is_block_device(),
+                "-c" => file_type.is_char_device(),
+                "-p" => file_type.is_fifo(),
+                "-S" => file_type.is_socket(),
+                "-s" => metadata.len() > 0,
+                "-O" => metadata.uid() == geteuid().as_raw(),
+                "-G" => metadata.gid() == getegid().as_raw(),
+                "-N" => metadata.mtime() > metadata.atime(),
+                _ => false,
+            }
+        }
+    }
+}
+
+/// Evaluate a binary test on expanded operands, comparing strings
+/// exactly and integers as decimal numbers.
+fn binary(op: &str, left: &str, right: &str) -> Result<bool, String> {
+    let integer = |s: &str| {
+        s.trim()
+            .parse::<i64>()
+            .map_err(|_| format!("{}: integer expression expected", s))
+    };
+    Ok(match op {
+        "=" | "==" => left == right,
+        "!=" => left != right,
+        "<" => left < right,
+        ">" => left > right,
+        "-eq" | "-ne" | "-lt" | "-le" | "-gt" | "-ge" => {
+            compare_integers(op, integer(left)?, integer(right)?)
+        }
+        "-nt" | "-ot" => {
+            let modified = |path: &str| fs::metadata(path).and_then(|m| m.modified()).ok();
+            let (newer, older) = match op {
+                "-nt" => (modified(left), modified(right)),
+                _ => (modified(right), modified(left)),
+            };
+            match (newer, older) {
+                (Some(newer), Some(older)) => newer > older,
+                (newer, older) => newer.is_some() && older.is_none(),
+            }
+        }
+        "-ef" => match (fs::metadata(left), fs::metadata(right)) {
+            (Ok(l), Ok(r)) => l.dev() == r.dev() && l.ino() == r.ino(),
+            _ => false,
+        },
+        _ => return Err(format!("{}: binary operator expected", op)),
+    })
+}
+
+fn compare_integers(op: &str, left: i64, right: i64) -> bool {
+    match op {
+        "-eq" => left == right,
+        "-ne" => left != right,
+        "-lt" => left < right,
+        "-le" => left <= right,
+        "-gt" => left > right,
+        _ => left >= right,
+    }
+}
+
+/// Match `text` against the extended regular expression `pattern`,
+/// storing the match and its groups in `PMSH_REMATCH`.
+fn regex_match(text: &str, pattern: &str, vars: &mut Variables) -> Result<bool, String> {
+    let pattern = unquote_regex(pattern);
+    let regex = regex::Regex::new(&pattern)
+        .map_err(|_| format!("{}: invalid regular expression", pattern))?;
+    let groups: Vec<String> = match regex.captures(text) {
+        Some(captures) => captures
+            .iter()
+            .map(|group| group.map_or(String::new(), |m| m.as_str().to_string()))
+            .collect(),
+        None => Vec::new(),
+    };
+    let matched = !groups.is_empty();
+    vars.set_array(REMATCH, groups);
+    Ok(matched)
+}
+
+/// `pattern` with the parts between `QUOTED_START` and `QUOTED_END`
+/// escaped, so that they match only themselves.
+fn unquote_regex(pattern: &str) -> String {
+    let mut out = String::with_capacity(pattern.len());
+    let mut rest = pattern;
+    while let Some(start) = rest.find(QUOTED_START) {
+        out.push_str(&rest[..start]);
+        let quoted = &rest[start + QUOTED_START.len_utf8()..];
+        let (literal, after) = quoted.split_once(QUOTED_END).unwrap_or((quoted, ""));
+        out.push_str(&regex::escape(literal));
+        rest = after;
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(s: &str) -> Vec<String> {
+        s.split_whitespace().map(String::from).collect()
+    }
+
+    fn test(s: &str) -> Result<bool, String> {
+        let mut vars = Variables::new();
+        let expr = parse(&words(s), Syntax::Test)?;
+        eval(&expr, &mut vars, Syntax::Test)
+    }
+
+    #[test]
+    fn test_parse_precedence() {
+        let expr = parse(&words("a -o ! b -a c"), Syntax::Test).unwrap();
+        assert_eq!(
+            expr,
+            Expr::Or(
+                Box::new(Expr::Word("a".into())),
+                Box::new(Expr::And(
+                    Box::new(Expr::Not(Box::new(Expr::Word("b".into())))),
+                    Box::new(Expr::Word("c".into())),
+                )),
+            )
+        );
+        assert!(parse(&words("( a"), Syntax::Test).is_err());
+        assert!(parse(&words("a b"), Syntax::Test).is_err());
+    }
+
+    #[test]
+    fn test_strings_and_integers() {
+        assert_eq!(test("abc = abc"), Ok(true));
+        assert_eq!(test("-n x -a -z y"), Ok(false));
+        assert_eq!(test("10 -gt 9"), Ok(true));
+        assert_eq!(test("a < b"), Ok(true));
+        assert!(test("x -eq 1").is_err());
+        // Operators on their own are plain strings
+        assert_eq!(test("-n"), Ok(true));
+        assert_eq!(test("! = !"), Ok(true));
+    }
+
+    #[test]
+    fn test_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("f");
+        fs::write(&file, "data").unwrap();
+        let (dir, file) = (dir.path().display(), file.display());
+        assert_eq!(test(&format!("-d {} -a -f {}", dir, file)), Ok(true));
+        assert_eq!(test(&format!("-s {} -a -r {}", file, file)), Ok(true));
+        assert_eq!(test(&format!("-e {}/missing", dir)), Ok(false));
+        assert_eq!(test(&format!("{} -ef {}", file, file)), Ok(true));
+    }
+
+    #[test]
+    fn test_extended() {
+        let mut vars = Variables::new();
+        vars.set("name".into(), "report.txt".into());
+        let mut check = |s: &str| {
+            let expr = parse(&words(s), Syntax::Extended).unwrap();
+            eval(&expr, &mut vars, Syntax::Extended).unwrap()
+        };
+        assert!(check("$name == *.txt && ! -z $name"));
+        assert!(!check("$name == \\*.txt"));
+        assert!(check("1+1 -eq 2"));
+        assert!(check("$name =~ ^(.*)\\.(txt)$"));
+        assert_eq!(
+            vars.array_values(REMATCH),
+            vec!["report.txt", "report", "txt"]
+        );
+    }
+
+    #[test]
+    fn test_quoted_regex_is_literal() {
+        let mut vars = Variables::new();
+        vars.set("re".into(), "t.t".into());
+        let quoted = format!("$name =~ {}$re{}$", QUOTED_START, QUOTED_END);
+        let mut check = |name: &str, s: &str| {
+            vars.set("name".into(), name.into());
+            let expr = parse(&words(s), Syntax::Extended).unwrap();
+            eval(&expr, &mut vars, Syntax::Extended).unwrap()
+        };
+        assert!(check("at.t", &quoted));
+        assert!(!check("atnt", &quoted));
+        assert!(check("atnt", "$name =~ $re$"));
+    }
+}
@@ -0,0 +1,114 @@
+/// Directories saved by `pushd` (and by `cd` with `set -o autopushd`),
+/// most recent first. The current directory is not stored: it is entry 0
+/// of the stack as `dirs` shows it, so the saved ones start at 1.
+#[derive(Debug, Clone, Default)]
+pub struct DirStack {
+    saved: Vec<String>,
+}
+
+impl DirStack {
+    /// The whole stack as `dirs` lists it, starting with `cwd`.
+    pub fn entries(&self, cwd: &str) -> Vec<String> {
+        std::iter::once(cwd.to_string())
+            .chain(self.saved.iter().cloned())
+            .collect()
+    }
+
+    /// Number of entries, counting the current directory.
+    pub fn len(&self) -> usize {
+        self.saved.len() + 1
+    }
+
+    /// Entry `index` of the stack, where 0 is `cwd`.
+    pub fn get(&self, index: usize, cwd: &str) -> Option<String> {
+        match index {
+            0 => Some(cwd.to_string()),
+            n => self.saved.get(n - 1).cloned(),
+        }
+    }
+
+    /// Save `dir` on top of the stack, below the current directory.
+    pub fn push(&mut self, dir: String) {
+        self.saved.insert(0, dir);
+    }
+
+    /// Remove entry `index`, which must not be the current directory (0).
+    pub fn remove(&mut self, index: usize) -> Option<String> {
+        (1..self.len())
+            .contains(&index)
+            .then(|| self.saved.remove(index - 1))
+    }
+
+    /// Rotate the stack so that entry `index` comes first, as `pushd +N`
+    /// does. `cwd` is stored with the rest; the new entry 0 is returned and
+    /// is no longer stored, since it becomes the current directory.
+    pub fn rotate(&mut self, index: usize, cwd: &str) -> Option<String> {
+        if index >= self.len() {
+            return None;
+        }
+        let mut entries = self.entries(cwd);
+        entries.rotate_left(index);
+        let top = entries.remove(0);
+        self.saved = entries;
+        Some(top)
+    }
+
+    pub fn clear(&mut self) {
+        self.saved.clear();
+    }
+
+    /// The index `+N` or `-N` stands for: `+N` counts from the current
+    /// directory at 0, `-N` from the bottom of the stack at 0.
+    pub fn index(&self, spec: &str) -> Option<usize> {
+        let (from_bottom, digits) = match spec.split_at_checked(1)? {
+            ("+", digits) => (false, digits),
+            ("-", digits) => (true, digits),
+            _ => return None,
+        };
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let n: usize = digits.parse().ok()?;
+        match from_bottom {
+            false => (n < self.len()).then_some(n),
+            true => self.len().checked_sub(n + 1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stack(saved: &[&str]) -> DirStack {
+        DirStack {
+            saved: saved.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_index() {
+        let stack = stack(&["/a", "/b", "/c"]);
+        assert_eq!(stack.index("+0"), Some(0));
+        assert_eq!(stack.index("+3"), Some(3));
+        assert_eq!(stack.index("-0"), Some(3));
+        assert_eq!(stack.index("-3"), Some(0));
+        assert_eq!(stack.index("+4"), None);
+        assert_eq!(stack.index("-4"), None);
+        assert_eq!(stack.index("2"), None);
+        assert_eq!(stack.index("+x"), None);
+    }
+
+    #[test]
+    fn test_rotate_and_remove() {
+        let mut dirs = stack(&["/a", "/b", "/c"]);
+        assert_eq!(dirs.rotate(2, "/cwd"), Some("/b".to_string()));
+        assert_eq!(dirs.entries("/b"), ["/b", "/c", "/cwd", "/a"]);
+        assert_eq!(dirs.rotate(4, "/b"), None);
+
+        assert_eq!(dirs.remove(2), Some("/cwd".to_string()));
+        assert_eq!(dirs.remove(0), None);
+        assert_eq!(dirs.entries("/b"), ["/b", "/c", "/a"]);
+        assert_eq!(dirs.get(2, "/b"), Some("/a".to_string()));
+    }
+}
@@ -1,9 +1,20 @@
-use crate::builtins::{handle_builtin, BuiltinResult};
+use crate::aliases::Aliases;
+use crate::builtins::{handle_builtin, is_builtin, BuiltinResult};
+use crate::conditional::{self, Syntax};
 use crate::functions::Functions;
 use crate::history::HistoryManager;
-use crate::parser::{Command, SimpleCommand};
-use crate::variables::Variables;
-use std::process::{Command as StdCommand, Stdio};
+use crate::parser::{AndOrOp, Command, SimpleCommand};
+use crate::redirect;
+use crate::repl::{self, ExecutorTrait, RealExecutor};
+use crate::resolve;
+use crate::startup;
+use crate::trace;
+use crate::traps::{self, TrapCondition};
+use crate::variables::{Attributes, Variables};
+use std::os::fd::OwnedFd;
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command as StdCommand, Stdio};
+use std::sync::Arc;
 
 pub struct Executor;
 
@@ -12,91 +23,30 @@ impl Executor {
         cmd: &Command,
         vars: &mut Variables,
         functions: &mut Functions,
+        aliases: &mut Aliases,
         history_mgr: &HistoryManager,
         command_history: &mut Vec<String>,
-        oldpwd: &mut Option<String>,
-    ) -> Result<(), String> {
+    ) -> Result<i32, String> {
         match cmd {
             Command::Simple(simple_cmd) => {
-                // Handle variable assignments without command (e.g. VAR=val)
-                if simple_cmd.name.is_empty() {
-                    for (key, value) in &simple_cmd.assignments {
-                        let expanded = vars.expand(value);
-                        vars.set(key.clone(), expanded);
-                    }
-                    return Ok(());
-                }
-
-                // Check if it's a function call first
-                if let Some(body) = functions.get(&simple_cmd.name) {
-                    // Execute function body
-                    let body_clone = body.clone();
-
-                    // Shadow positional args
-                    let saved_args = vars.get_positional_args();
-                    vars.set_positional_args(simple_cmd.args.clone());
-
-                    // Handle temporary variable assignments (VAR=val func)
-                    let mut saved_vars = Vec::new();
-                    for (key, value) in &simple_cmd.assignments {
-                        let expanded_val = vars.expand(value);
-                        // Save old value if exists, or mark for removal
-                        let old_val = vars.get(key).cloned();
-                        saved_vars.push((key.clone(), old_val));
-                        vars.set(key.clone(), expanded_val);
-                    }
-
-                    for pipeline in body_clone {
-                        let result = Self::execute_pipeline(
-                            &pipeline,
-                            vars,
-                            functions,
-                            history_mgr,
-                            command_history,
-                            oldpwd,
-                        );
-
-                        if let Err(e) = result {
-                            // Restore variables
-                            for (key, old_val) in saved_vars {
-                                if let Some(val) = old_val {
-                                    vars.set(key, val);
-                                } else {
-                                    vars.remove(&key); // We need a remove method in Variables
-                                }
-                            }
-                            vars.set_positional_args(saved_args);
-                            return Err(e);
-                        }
-                    }
-
-                    // Restore variables
-                    for (key, old_val) in saved_vars {
-                        if let Some(val) = old_val {
-                            vars.set(key, val);
-                        } else {
-                            vars.remove(&key);
-                        }
-                    }
-                    vars.set_positional_args(saved_args);
-
-                    return Ok(());
-                }
-
-                // Check for builtins
-                match handle_builtin(simple_cmd, history_mgr, command_history, oldpwd, vars) {
-                    Ok(BuiltinResult::HandledExit(code)) => std::process::exit(code),
-                    Ok(BuiltinResult::HandledContinue) => Ok(()),
-                    Ok(BuiltinResult::SourceFile(_)) => {
-                        // Source is handled in repl.rs, but if we get here it means it wasn't caught.
-                        Ok(())
-                    }
-                    Ok(BuiltinResult::NotHandled) => {
-                        // Execute external command
-                        Self::execute_external(simple_cmd, vars)
-                    }
-                    Err(e) => Err(e),
-                }
+                vars.set_lineno(simple_cmd.line);
+                Self::run_trap(
+                    TrapCondition::Debug,
+                    vars,
+                    functions,
+                    aliases,
+                    history_mgr,
+                    command_history,
+                );
+                Self::trace_simple(simple_cmd, vars);
+                Self::execute_simple(
+                    simple_cmd,
+                    vars,
+                    functions,
+                    aliases,
+                    history_mgr,
+                    command_history,
+                )
             }
             Command::Subshell(pipelines) => {
                 // Execute subshell using fork
@@ -108,46 +58,923 @@ impl Executor {
                     Ok(ForkResult::Parent { child, .. }) => {
                         // Wait for child
                         match waitpid(child, None) {
-                            Ok(WaitStatus::Exited(_, code)) => {
-                                if code == 0 {
-                                    Ok(())
-                                } else {
-                                    // We could return an error here, but for now we just return Ok
-                                    // as the command "executed" (even if it failed).
-                                    // TODO: Propagate exit status
-                                    Ok(())
-                                }
-                            }
-                            Ok(WaitStatus::Signaled(_, signal, _)) => {
-                                Err(format!("Subshell killed by signal: {}", signal))
-                            }
+                            Ok(WaitStatus::Exited(_, code)) => Ok(code),
+                            Ok(WaitStatus::Signaled(_, signal, _)) => Ok(128 + signal as i32),
                             Err(e) => Err(format!("Failed to wait for subshell: {}", e)),
-                            _ => Ok(()),
+                            _ => Ok(0),
                         }
                     }
                     Ok(ForkResult::Child) => {
+                        Self::enter_subshell_child(vars);
                         // Execute pipelines
-                        for pipeline in pipelines {
-                            if let Err(e) = Self::execute_pipeline(
-                                pipeline,
-                                vars,
-                                functions,
-                                history_mgr,
-                                command_history,
-                                oldpwd,
-                            ) {
+                        let status = match Self::execute_list(
+                            pipelines,
+                            vars,
+                            functions,
+                            aliases,
+                            history_mgr,
+                            command_history,
+                        ) {
+                            Ok(status) => status,
+                            Err(e) => {
                                 eprintln!("pmsh: {}", e);
-                                std::process::exit(1);
+                                1
+                            }
+                        };
+                        Self::exit_shell(
+                            status,
+                            vars,
+                            functions,
+                            aliases,
+                            history_mgr,
+                            command_history,
+                        )
+                    }
+                    Err(e) => Err(format!("Fork failed: {}", e)),
+                }
+            }
+            Command::Background(pipeline) => {
+                use nix::sys::signal::{signal, SigHandler, Signal};
+                use nix::unistd::{fork, ForkResult};
+
+                match unsafe { fork() } {
+                    Ok(ForkResult::Parent { child, .. }) => {
+                        vars.set_last_background_pid(child.as_raw());
+                        Ok(0)
+                    }
+                    Ok(ForkResult::Child) => {
+                        Self::enter_subshell_child(vars);
+                        // Without job control, asynchronous commands must not
+                        // read the terminal or be interrupted from it
+                        if let Ok(null) = std::fs::File::open("/dev/null") {
+                            let _ = nix::unistd::dup2(std::os::fd::AsRawFd::as_raw_fd(&null), 0);
+                        }
+                        for sig in [Signal::SIGINT, Signal::SIGQUIT] {
+                            if vars.traps().get(TrapCondition::Signal(sig)).is_none() {
+                                let _ = unsafe { signal(sig, SigHandler::SigIgn) };
                             }
                         }
-                        std::process::exit(0);
+                        let status = match Self::execute_pipeline(
+                            pipeline,
+                            vars,
+                            functions,
+                            aliases,
+                            history_mgr,
+                            command_history,
+                        ) {
+                            Ok(status) => status,
+                            Err(e) => {
+                                eprintln!("pmsh: {}", e);
+                                1
+                            }
+                        };
+                        Self::exit_shell(
+                            status,
+                            vars,
+                            functions,
+                            aliases,
+                            history_mgr,
+                            command_history,
+                        )
                     }
                     Err(e) => Err(format!("Fork failed: {}", e)),
                 }
             }
+            Command::Conditional(words, line) => {
+                vars.set_lineno(*line);
+                Self::run_trap(
+                    TrapCondition::Debug,
+                    vars,
+                    functions,
+                    aliases,
+                    history_mgr,
+                    command_history,
+                );
+                if vars.options().xtrace {
+                    let expanded: Vec<String> = words
+                        .iter()
+                        .map(|w| vars.expand(w).unwrap_or_else(|_| w.clone()))
+                        .collect();
+                    trace::trace_conditional(vars, &expanded);
+                }
+                let result = conditional::parse(words, Syntax::Extended)
+                    .and_then(|expr| conditional::eval(&expr, vars, Syntax::Extended));
+                match result {
+                    Ok(matched) => Ok(if matched { 0 } else { 1 }),
+                    Err(e) => {
+                        eprintln!("pmsh: [[: {}", e);
+                        Self::exit_if_unbound(
+                            vars,
+                            functions,
+                            aliases,
+                            history_mgr,
+                            command_history,
+                        );
+                        Ok(2)
+                    }
+                }
+            }
             Command::FunctionDef(name, body) => {
                 functions.set(name.clone(), body.clone());
-                Ok(())
+                Ok(0)
+            }
+            Command::Group(pipelines) => Self::execute_list(
+                pipelines,
+                vars,
+                functions,
+                aliases,
+                history_mgr,
+                command_history,
+            ),
+            Command::AndOr(first, rest) => {
+                let mut status = Self::execute_condition(
+                    std::slice::from_ref(first),
+                    vars,
+                    functions,
+                    aliases,
+                    history_mgr,
+                    command_history,
+                    true,
+                );
+                for (i, (op, pipeline)) in rest.iter().enumerate() {
+                    if vars.is_returning() {
+                        break;
+                    }
+                    let run = match op {
+                        AndOrOp::And => status == 0,
+                        AndOrOp::Or => status != 0,
+                    };
+                    if run {
+                        // Only the last pipeline of the list is subject to `set -e`
+                        status = Self::execute_condition(
+                            std::slice::from_ref(pipeline),
+                            vars,
+                            functions,
+                            aliases,
+                            history_mgr,
+                            command_history,
+                            i + 1 < rest.len(),
+                        );
+                    }
+                }
+                Ok(status)
+            }
+            Command::Not(pipeline) => {
+                let status = Self::execute_condition(
+                    std::slice::from_ref(pipeline),
+                    vars,
+                    functions,
+                    aliases,
+                    history_mgr,
+                    command_history,
+                    true,
+                );
+                Ok(if status == 0 { 1 } else { 0 })
+            }
+            Command::If {
+                conditionals,
+                else_branch,
+            } => {
+                for (guard, body) in conditionals {
+                    let status = Self::execute_condition(
+                        guard,
+                        vars,
+                        functions,
+                        aliases,
+                        history_mgr,
+                        command_history,
+                        true,
+                    );
+                    if vars.is_returning() {
+                        return Ok(status);
+                    }
+                    if status == 0 {
+                        return Self::execute_list(
+                            body,
+                            vars,
+                            functions,
+                            aliases,
+                            history_mgr,
+                            command_history,
+                        );
+                    }
+                }
+                match else_branch {
+                    Some(body) => Self::execute_list(
+                        body,
+                        vars,
+                        functions,
+                        aliases,
+                        history_mgr,
+                        command_history,
+                    ),
+                    None => Ok(0),
+                }
+            }
+            Command::While(guard, body) | Command::Until(guard, body) => {
+                let until = matches!(cmd, Command::Until(..));
+                let mut status = 0;
+                loop {
+                    let guard_status = Self::execute_condition(
+                        guard,
+                        vars,
+                        functions,
+                        aliases,
+                        history_mgr,
+                        command_history,
+                        true,
+                    );
+                    if vars.is_returning() {
+                        return Ok(guard_status);
+                    }
+                    if (guard_status == 0) == until {
+                        break;
+                    }
+                    status = Self::execute_list(
+                        body,
+                        vars,
+                        functions,
+                        aliases,
+                        history_mgr,
+                        command_history,
+                    )?;
+                    if vars.is_returning() {
+                        break;
+                    }
+                }
+                Ok(status)
+            }
+            Command::For(var, words, body) => {
+                let items: Vec<String> = match words {
+                    Some(words) => vars.expand_words(words)?,
+                    None => vars.get_positional_args(),
+                };
+                let mut status = 0;
+                for item in items {
+                    vars.check_writable(var)?;
+                    vars.set(var.clone(), item);
+                    status = Self::execute_list(
+                        body,
+                        vars,
+                        functions,
+                        aliases,
+                        history_mgr,
+                        command_history,
+                    )?;
+                    if vars.is_returning() {
+                        break;
+                    }
+                }
+                Ok(status)
+            }
+            Command::Redirected(inner, redirects) => {
+                // The trace goes where stderr pointed before the redirections
+                if let Command::Simple(simple_cmd) = inner.as_ref() {
+                    vars.set_lineno(simple_cmd.line);
+                    Self::run_trap(
+                        TrapCondition::Debug,
+                        vars,
+                        functions,
+                        aliases,
+                        history_mgr,
+                        command_history,
+                    );
+                    Self::trace_simple(simple_cmd, vars);
+                    let guard = redirect::apply(redirects, vars)?;
+                    // `exec` without a command redirects the shell for good
+                    if simple_cmd.name == "exec"
+                        && simple_cmd.args.is_empty()
+                        && functions.get("exec").is_none()
+                    {
+                        guard.keep();
+                        return Ok(0);
+                    }
+                    let _guard = guard;
+                    return Self::execute_simple(
+                        simple_cmd,
+                        vars,
+                        functions,
+                        aliases,
+                        history_mgr,
+                        command_history,
+                    );
+                }
+                let _guard = redirect::apply(redirects, vars)?;
+                Self::execute(
+                    inner,
+                    vars,
+                    functions,
+                    aliases,
+                    history_mgr,
+                    command_history,
+                )
+            }
+        }
+    }
+
+    fn execute_simple(
+        simple_cmd: &SimpleCommand,
+        vars: &mut Variables,
+        functions: &mut Functions,
+        aliases: &mut Aliases,
+        history_mgr: &HistoryManager,
+        command_history: &mut Vec<String>,
+    ) -> Result<i32, String> {
+        // The words see the previous command's `$_`
+        let last_word = Self::last_word(simple_cmd, vars);
+        let status = Self::run_simple(
+            simple_cmd,
+            vars,
+            functions,
+            aliases,
+            history_mgr,
+            command_history,
+        );
+        if let Some(word) = last_word {
+            vars.set_last_arg(word);
+        }
+        status
+    }
+
+    /// The expanded last word of `cmd`, which becomes `$_` once it has run.
+    /// Assignments on their own leave `$_` alone.
+    pub fn last_word(cmd: &SimpleCommand, vars: &Variables) -> Option<String> {
+        if cmd.name.is_empty() {
+            return None;
+        }
+        match cmd.args.last() {
+            Some(arg) => vars.expand_fields(arg).ok()?.pop(),
+            None => Some(cmd.name.clone()),
+        }
+    }
+
+    fn run_simple(
+        simple_cmd: &SimpleCommand,
+        vars: &mut Variables,
+        functions: &mut Functions,
+        aliases: &mut Aliases,
+        history_mgr: &HistoryManager,
+        command_history: &mut Vec<String>,
+    ) -> Result<i32, String> {
+        // Handle variable assignments without command (e.g. VAR=val)
+        if simple_cmd.name.is_empty() {
+            for (key, value) in &simple_cmd.assignments {
+                let assigned = vars.assign(key, value)?;
+                trace::trace_command(vars, &[(key.clone(), assigned)], &[]);
+            }
+            return Ok(0);
+        }
+
+        // Check if it's a function call first
+        if let Some(body) = functions.get(&simple_cmd.name) {
+            // Execute function body
+            let body_clone = body.clone();
+
+            // Expand arguments and prefix assignments (VAR=val func) in the caller's scope
+            let args = vars.expand_words(&simple_cmd.args)?;
+            let temp_vars = simple_cmd
+                .assignments
+                .iter()
+                .map(|(key, value)| {
+                    vars.check_writable(key)?;
+                    Ok((key.clone(), vars.expand(value)?))
+                })
+                .collect::<Result<Vec<(String, String)>, String>>()?;
+
+            // The function gets its own scope; prefix assignments become
+            // exported locals of it, so popping the scope restores them.
+            vars.push_scope(args);
+            let mut result = Ok(0);
+            for (key, value) in temp_vars {
+                if let Err(e) = vars.declare_local(
+                    &key,
+                    Some(value),
+                    Attributes {
+                        exported: true,
+                        ..Default::default()
+                    },
+                ) {
+                    result = Err(e);
+                    break;
+                }
+            }
+
+            if result.is_ok() {
+                result = Self::execute_list(
+                    &body_clone,
+                    vars,
+                    functions,
+                    aliases,
+                    history_mgr,
+                    command_history,
+                );
+            }
+
+            // `return` decides the status; otherwise it is the last command's
+            let returned = vars.take_return();
+            vars.pop_scope();
+            vars.set_lineno(simple_cmd.line);
+            return result.map(|status| returned.unwrap_or(status));
+        }
+
+        // Check for builtins
+        let handled = Self::run_builtin(
+            simple_cmd,
+            vars,
+            functions,
+            aliases,
+            history_mgr,
+            command_history,
+        )?;
+        match Self::dispatch_builtin(
+            handled,
+            &RealExecutor,
+            vars,
+            functions,
+            aliases,
+            history_mgr,
+            command_history,
+        )? {
+            Some(status) => Ok(status),
+            None => Self::execute_external(simple_cmd, None, vars),
+        }
+    }
+
+    /// Carry out what a builtin returned: its status, or the exit, sourced
+    /// file, eval'd script or external program it stands for. Files and
+    /// scripts run through `executor`. Returns `None` if the command was
+    /// not a builtin, for the caller to run.
+    pub fn dispatch_builtin<E: ExecutorTrait>(
+        result: BuiltinResult,
+        executor: &E,
+        vars: &mut Variables,
+        functions: &mut Functions,
+        aliases: &mut Aliases,
+        history_mgr: &HistoryManager,
+        command_history: &mut Vec<String>,
+    ) -> Result<Option<i32>, String> {
+        let status = match result {
+            BuiltinResult::HandledExit(code) => {
+                Self::exit_builtin(code, vars, functions, aliases, history_mgr, command_history)
+            }
+            BuiltinResult::HandledContinue => 0,
+            BuiltinResult::HandledStatus(status) => status,
+            BuiltinResult::SourceFile(path) => {
+                // The file is run as a script is, with its own error locations
+                match repl::source_file(
+                    &path,
+                    history_mgr,
+                    command_history,
+                    executor,
+                    vars,
+                    functions,
+                    aliases,
+                ) {
+                    Ok(_) => vars.last_status(),
+                    Err(e) => {
+                        eprintln!("pmsh: {}", e);
+                        1
+                    }
+                }
+            }
+            BuiltinResult::Eval(script) => {
+                repl::eval_string(
+                    &script,
+                    history_mgr,
+                    command_history,
+                    executor,
+                    vars,
+                    functions,
+                    aliases,
+                );
+                vars.last_status()
+            }
+            BuiltinResult::External(cmd, program) => Self::execute_external(&cmd, program, vars)?,
+            BuiltinResult::NotHandled => return Ok(None),
+        };
+        Ok(Some(status))
+    }
+
+    /// Run `cmd` if it is a builtin. Its prefix assignments (`IFS=: read`)
+    /// hold only while it runs.
+    pub fn run_builtin(
+        cmd: &SimpleCommand,
+        vars: &mut Variables,
+        functions: &mut Functions,
+        aliases: &mut Aliases,
+        history_mgr: &HistoryManager,
+        command_history: &mut Vec<String>,
+    ) -> Result<BuiltinResult, String> {
+        let mut saved = Vec::new();
+        if is_builtin(&cmd.name) {
+            for (key, value) in &cmd.assignments {
+                let old = vars.get(key).cloned();
+                if let Err(e) = vars.assign(key, value) {
+                    Self::restore_vars(saved, vars);
+                    return Err(e);
+                }
+                saved.push((key.clone(), old));
+            }
+        }
+        let result = handle_builtin(cmd, history_mgr, command_history, vars, functions, aliases);
+        Self::restore_vars(saved, vars);
+        result
+    }
+
+    /// Put back the values variables had before a builtin's prefix
+    /// assignments, newest first.
+    fn restore_vars(saved: Vec<(String, Option<String>)>, vars: &mut Variables) {
+        for (key, old) in saved.into_iter().rev() {
+            match old {
+                Some(value) => vars.set(key, value),
+                None => vars.unset(&key),
+            }
+        }
+    }
+
+    /// With `set -x`, show a simple command as it is about to run. Nothing is
+    /// shown if its words cannot be expanded, since it will fail anyway.
+    pub fn trace_simple(cmd: &SimpleCommand, vars: &Variables) {
+        // Assignments on their own are traced one at a time as they are made
+        if !vars.options().xtrace || cmd.name.is_empty() {
+            return;
+        }
+        let assignments = cmd
+            .assignments
+            .iter()
+            .map(|(key, value)| Ok((key.clone(), vars.expand(value)?)))
+            .collect::<Result<Vec<(String, String)>, String>>();
+        let words = vars.expand_words(&cmd.args).map(|args| {
+            std::iter::once(cmd.name.clone())
+                .chain(args)
+                .collect::<Vec<String>>()
+        });
+        if let (Ok(assignments), Ok(words)) = (assignments, words) {
+            trace::trace_command(vars, &assignments, &words);
+        }
+    }
+
+    /// Returns true if `pipeline` failing with `status` triggers the ERR trap
+    /// and `set -e`. `errored` means the command could not run at all.
+    fn is_checked_failure(
+        pipeline: &[Command],
+        status: i32,
+        errored: bool,
+        vars: &Variables,
+    ) -> bool {
+        if status == 0 || vars.in_condition() {
+            return false;
+        }
+        let mut cmd = match pipeline {
+            _ if errored => return true,
+            [cmd] => cmd,
+            _ => return true,
+        };
+        while let Command::Redirected(inner, _) = cmd {
+            cmd = inner;
+        }
+        // Compound commands and lists already checked the commands inside
+        // them, and a negated pipeline is never checked.
+        matches!(
+            cmd,
+            Command::Simple(_) | Command::Subshell(_) | Command::Conditional(..)
+        )
+    }
+
+    /// Reset the state a forked subshell must not share with its parent.
+    fn enter_subshell_child(vars: &mut Variables) {
+        vars.enter_subshell();
+        Self::enter_forked_child(vars);
+    }
+
+    /// Reset the state any forked copy of the shell must not share with its
+    /// parent. Unlike a subshell, a pipeline command forked this way is
+    /// traced at the depth of the pipeline.
+    fn enter_forked_child(vars: &mut Variables) {
+        vars.set_login_shell(false);
+        vars.set_interactive(false);
+        vars.traps_mut().reset_for_subshell();
+    }
+
+    /// Collect background commands that have finished, so they do not
+    /// linger as zombies. Only the shell's background commands are waited
+    /// for here; other children belong to whoever started them.
+    fn reap_background(vars: &mut Variables) {
+        use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
+        use nix::unistd::Pid;
+
+        vars.background_pids_mut().retain(|&pid| {
+            matches!(
+                waitpid(Pid::from_raw(pid), Some(WaitPidFlag::WNOHANG)),
+                Ok(WaitStatus::StillAlive)
+            )
+        });
+    }
+
+    /// Bookkeeping after `pipeline` finished with `status`: update `$?`; on
+    /// a checked failure run the ERR trap and apply `set -e`; then run the
+    /// traps of any signals that arrived meanwhile. Functions do not inherit
+    /// the ERR trap (there is no `set -E`), so a failure inside one runs it
+    /// once, for the function call that returns the failing status.
+    #[allow(clippy::too_many_arguments)]
+    pub fn finish_pipeline(
+        pipeline: &[Command],
+        status: i32,
+        errored: bool,
+        vars: &mut Variables,
+        functions: &mut Functions,
+        aliases: &mut Aliases,
+        history_mgr: &HistoryManager,
+        command_history: &mut Vec<String>,
+    ) {
+        Self::reap_background(vars);
+        vars.set_last_status(status);
+        // An unset variable under `set -u` ends a non-interactive shell
+        if vars.take_unbound() && errored && !vars.is_interactive() {
+            Self::exit_shell(1, vars, functions, aliases, history_mgr, command_history);
+        }
+        if Self::is_checked_failure(pipeline, status, errored, vars) {
+            if !vars.in_function() {
+                Self::run_trap(
+                    TrapCondition::Err,
+                    vars,
+                    functions,
+                    aliases,
+                    history_mgr,
+                    command_history,
+                );
+            }
+            if vars.options().errexit {
+                Self::exit_shell(
+                    status,
+                    vars,
+                    functions,
+                    aliases,
+                    history_mgr,
+                    command_history,
+                );
+            }
+        }
+        Self::run_pending_traps(vars, functions, aliases, history_mgr, command_history);
+    }
+
+    /// Run the action set with `trap` for `condition`, if any. Actions do
+    /// not trigger traps themselves, and leave `$?` unchanged.
+    pub fn run_trap(
+        condition: TrapCondition,
+        vars: &mut Variables,
+        functions: &mut Functions,
+        aliases: &mut Aliases,
+        history_mgr: &HistoryManager,
+        command_history: &mut Vec<String>,
+    ) {
+        if vars.traps().is_running() {
+            return;
+        }
+        let action = match vars.traps().get(condition) {
+            Some(action) if !action.is_empty() => action.to_string(),
+            _ => return,
+        };
+        vars.traps_mut().set_running(true);
+        Self::run_trap_action(
+            &action,
+            vars,
+            functions,
+            aliases,
+            history_mgr,
+            command_history,
+        );
+        vars.traps_mut().set_running(false);
+    }
+
+    fn run_trap_action(
+        action: &str,
+        vars: &mut Variables,
+        functions: &mut Functions,
+        aliases: &mut Aliases,
+        history_mgr: &HistoryManager,
+        command_history: &mut Vec<String>,
+    ) {
+        let status = vars.last_status();
+        match Command::parse_at(action, vars.lineno().max(1)) {
+            Ok(pipelines) => {
+                if let Err(e) = Self::execute_list(
+                    &pipelines,
+                    vars,
+                    functions,
+                    aliases,
+                    history_mgr,
+                    command_history,
+                ) {
+                    eprintln!("pmsh: {}", e);
+                    Self::exit_if_unbound(vars, functions, aliases, history_mgr, command_history);
+                }
+            }
+            Err(e) => eprintln!("pmsh: trap: {}", e),
+        }
+        vars.set_last_status(status);
+    }
+
+    /// Run the traps of signals received since the last check. A fatal
+    /// signal caught only for the EXIT trap runs it and kills the shell.
+    pub fn run_pending_traps(
+        vars: &mut Variables,
+        functions: &mut Functions,
+        aliases: &mut Aliases,
+        history_mgr: &HistoryManager,
+        command_history: &mut Vec<String>,
+    ) {
+        if vars.traps().is_running() {
+            return;
+        }
+        for sig in traps::take_pending_signals() {
+            let condition = TrapCondition::Signal(sig);
+            if vars.traps().get(condition).is_some() {
+                Self::run_trap(
+                    condition,
+                    vars,
+                    functions,
+                    aliases,
+                    history_mgr,
+                    command_history,
+                );
+            } else if vars.traps().get(TrapCondition::Exit).is_some() {
+                Self::run_exit_trap(
+                    128 + sig as i32,
+                    vars,
+                    functions,
+                    aliases,
+                    history_mgr,
+                    command_history,
+                );
+                traps::die_from(sig);
+            }
+        }
+    }
+
+    /// Run the EXIT trap with `$?` set to `status`. It runs at most once.
+    pub fn run_exit_trap(
+        status: i32,
+        vars: &mut Variables,
+        functions: &mut Functions,
+        aliases: &mut Aliases,
+        history_mgr: &HistoryManager,
+        command_history: &mut Vec<String>,
+    ) {
+        if let Some(action) = vars.traps_mut().take_exit() {
+            vars.set_last_status(status);
+            vars.traps_mut().set_running(true);
+            Self::run_trap_action(
+                &action,
+                vars,
+                functions,
+                aliases,
+                history_mgr,
+                command_history,
+            );
+            vars.traps_mut().set_running(false);
+        }
+    }
+
+    /// Exit for the `exit` builtin; a login shell reads its logout file first.
+    pub fn exit_builtin(
+        status: i32,
+        vars: &mut Variables,
+        functions: &mut Functions,
+        aliases: &mut Aliases,
+        history_mgr: &HistoryManager,
+        command_history: &mut Vec<String>,
+    ) -> ! {
+        startup::run_logout_file(history_mgr, command_history, vars, functions, aliases);
+        Self::exit_shell(
+            status,
+            vars,
+            functions,
+            aliases,
+            history_mgr,
+            command_history,
+        )
+    }
+
+    /// After reporting an error that does not reach `finish_pipeline`:
+    /// exit if it was an unset variable under `set -u` and the shell is
+    /// not interactive.
+    fn exit_if_unbound(
+        vars: &mut Variables,
+        functions: &mut Functions,
+        aliases: &mut Aliases,
+        history_mgr: &HistoryManager,
+        command_history: &mut Vec<String>,
+    ) {
+        if vars.take_unbound() && !vars.is_interactive() {
+            Self::exit_shell(1, vars, functions, aliases, history_mgr, command_history);
+        }
+    }
+
+    /// Exit the shell with `status`, running the EXIT trap first.
+    pub fn exit_shell(
+        status: i32,
+        vars: &mut Variables,
+        functions: &mut Functions,
+        aliases: &mut Aliases,
+        history_mgr: &HistoryManager,
+        command_history: &mut Vec<String>,
+    ) -> ! {
+        Self::run_exit_trap(
+            status,
+            vars,
+            functions,
+            aliases,
+            history_mgr,
+            command_history,
+        );
+        let _ = std::io::Write::flush(&mut std::io::stdout());
+        std::process::exit(status)
+    }
+
+    /// Run pipelines one after another, keeping `$?` up to date.
+    /// Stops early while a `return` is unwinding. Returns the last status.
+    pub fn execute_list(
+        pipelines: &[Vec<Command>],
+        vars: &mut Variables,
+        functions: &mut Functions,
+        aliases: &mut Aliases,
+        history_mgr: &HistoryManager,
+        command_history: &mut Vec<String>,
+    ) -> Result<i32, String> {
+        let mut status = 0;
+        for pipeline in pipelines {
+            status = Self::execute_pipeline(
+                pipeline,
+                vars,
+                functions,
+                aliases,
+                history_mgr,
+                command_history,
+            )?;
+            Self::finish_pipeline(
+                pipeline,
+                status,
+                false,
+                vars,
+                functions,
+                aliases,
+                history_mgr,
+                command_history,
+            );
+            if vars.is_returning() {
+                break;
+            }
+        }
+        Ok(status)
+    }
+
+    /// Run a guard or an `&&`/`||` operand. A command that could not run is
+    /// reported and counts as a failure instead of aborting the enclosing
+    /// command. While `tested`, `set -e` is suspended for everything inside.
+    fn execute_condition(
+        pipelines: &[Vec<Command>],
+        vars: &mut Variables,
+        functions: &mut Functions,
+        aliases: &mut Aliases,
+        history_mgr: &HistoryManager,
+        command_history: &mut Vec<String>,
+        tested: bool,
+    ) -> i32 {
+        if tested {
+            vars.enter_condition();
+        }
+        let result = Self::execute_list(
+            pipelines,
+            vars,
+            functions,
+            aliases,
+            history_mgr,
+            command_history,
+        );
+        if tested {
+            vars.leave_condition();
+        }
+        match result {
+            Ok(status) => status,
+            Err(e) => {
+                eprintln!("pmsh: {}{}", vars.error_location(), e);
+                if tested {
+                    Self::exit_if_unbound(vars, functions, aliases, history_mgr, command_history);
+                    vars.set_last_status(1);
+                } else {
+                    Self::finish_pipeline(
+                        &[],
+                        1,
+                        true,
+                        vars,
+                        functions,
+                        aliases,
+                        history_mgr,
+                        command_history,
+                    );
+                }
+                1
             }
         }
     }
@@ -156,12 +983,12 @@ impl Executor {
         pipeline: &[Command],
         vars: &mut Variables,
         functions: &mut Functions,
+        aliases: &mut Aliases,
         history_mgr: &HistoryManager,
         command_history: &mut Vec<String>,
-        oldpwd: &mut Option<String>,
-    ) -> Result<(), String> {
+    ) -> Result<i32, String> {
         if pipeline.is_empty() {
-            return Ok(());
+            return Ok(0);
         }
 
         // If single command, just execute it
@@ -170,123 +997,368 @@ impl Executor {
                 &pipeline[0],
                 vars,
                 functions,
+                aliases,
                 history_mgr,
                 command_history,
-                oldpwd,
             );
         }
 
         // For pipeline, we need to chain commands
-        let mut children = Vec::new();
+        let mut stages = Vec::new();
         let mut prev_stdout = None;
 
         for (i, cmd) in pipeline.iter().enumerate() {
-            match cmd {
-                Command::Simple(simple_cmd) => {
-                    // Expand variables in args
-                    let expanded_args: Vec<String> =
-                        simple_cmd.args.iter().map(|arg| vars.expand(arg)).collect();
-
-                    let mut command = StdCommand::new(&simple_cmd.name);
-                    command.args(&expanded_args);
-
-                    // Add environment variables
-                    let env_vars = vars.to_env_vars();
-                    command.envs(&env_vars);
-
-                    // Setup stdin
-                    if let Some(stdin) = prev_stdout.take() {
-                        command.stdin(stdin);
-                    } else {
-                        // First command inherits stdin
-                        command.stdin(Stdio::inherit());
+            let is_last = i == pipeline.len() - 1;
+            let stage = match cmd {
+                Command::Redirected(inner, _) => inner.as_ref(),
+                cmd => cmd,
+            };
+            if let Command::Simple(simple_cmd) = stage {
+                vars.set_lineno(simple_cmd.line);
+            }
+            // As in bash, the trap runs before each command is started. Its
+            // commands leave the stages already running to be waited for below.
+            Self::run_trap(
+                TrapCondition::Debug,
+                vars,
+                functions,
+                aliases,
+                history_mgr,
+                command_history,
+            );
+            let stdin = prev_stdout.take();
+            let spawned = match Self::piped_program(stage, vars, functions) {
+                Some((simple_cmd, program)) => {
+                    Self::spawn_piped(cmd, simple_cmd, &program, vars, stdin.as_ref(), is_last).ok()
+                }
+                None => None,
+            };
+            // Anything else, including a program that cannot be started,
+            // runs in a forked shell, which reports its errors itself
+            let started = match spawned {
+                Some(spawned) => Ok(spawned),
+                None => Self::fork_piped(
+                    cmd,
+                    vars,
+                    functions,
+                    aliases,
+                    history_mgr,
+                    command_history,
+                    stdin.as_ref(),
+                    is_last,
+                ),
+            };
+            match started {
+                Ok((stage, stdout)) => {
+                    prev_stdout = stdout;
+                    stages.push(stage);
+                }
+                Err(e) => {
+                    // Kill already started commands
+                    for stage in stages {
+                        stage.kill();
                     }
+                    return Err(e);
+                }
+            }
+        }
 
-                    // Setup stdout
-                    if i < pipeline.len() - 1 {
-                        command.stdout(Stdio::piped());
-                    } else {
-                        // Last command inherits stdout
-                        command.stdout(Stdio::inherit());
-                    }
+        // Wait for all commands. The pipeline's status is that of the last
+        // one, or with pipefail that of the last one to fail.
+        let pipefail = vars.options().pipefail;
+        let mut last_status = 0;
+        for stage in stages {
+            let code = stage.wait()?;
+            if code != 0 || !pipefail {
+                last_status = code;
+            }
+        }
 
-                    command.stderr(Stdio::inherit());
+        Ok(last_status)
+    }
 
-                    match command.spawn() {
-                        Ok(mut child) => {
-                            if i < pipeline.len() - 1 {
-                                prev_stdout = child.stdout.take();
-                            }
-                            children.push(child);
-                        }
-                        Err(e) => {
-                            // Kill already spawned children
-                            for mut child in children {
-                                let _ = child.kill();
-                            }
-                            return Err(format!("Failed to start {}: {}", simple_cmd.name, e));
-                        }
-                    }
-                }
-                _ => {
-                    return Err("Only simple commands supported in pipelines for now".to_string());
-                }
+    /// The external program a pipeline command runs, if it is a simple
+    /// command that is not a function or builtin and names an executable.
+    fn piped_program<'a>(
+        cmd: &'a Command,
+        vars: &Variables,
+        functions: &Functions,
+    ) -> Option<(&'a SimpleCommand, std::path::PathBuf)> {
+        match cmd {
+            Command::Simple(simple_cmd)
+                if !simple_cmd.name.is_empty()
+                    && functions.get(&simple_cmd.name).is_none()
+                    && !is_builtin(&simple_cmd.name) =>
+            {
+                let program = resolve::find_command(&simple_cmd.name, vars)
+                    .filter(|program| resolve::executable(program))?;
+                Some((simple_cmd, program))
             }
+            _ => None,
         }
+    }
 
-        // Wait for all children
-        let mut last_status = Ok(());
-        for mut child in children {
-            match child.wait() {
-                Ok(status) => {
-                    if !status.success() {
-                        let code_str = match status.code() {
-                            Some(code) => code.to_string(),
-                            None => "unknown".to_string(),
-                        };
-                        last_status = Err(format!("Command failed with exit status: {}", code_str));
-                    } else {
-                        last_status = Ok(());
+    /// Start `program` for one command of a multi-command pipeline,
+    /// reading from `stdin` (the previous command's output) and writing to
+    /// a new pipe unless it is the last command. Returns the pipe's end.
+    fn spawn_piped(
+        cmd: &Command,
+        simple_cmd: &SimpleCommand,
+        program: &std::path::Path,
+        vars: &Variables,
+        stdin: Option<&OwnedFd>,
+        is_last: bool,
+    ) -> Result<(PipelineStage, Option<OwnedFd>), String> {
+        let redirects = match cmd {
+            Command::Redirected(_, redirects) => redirects.as_slice(),
+            _ => &[][..],
+        };
+        // Expand variables in args
+        let expanded_args = vars.expand_words(&simple_cmd.args)?;
+
+        // Children see the exported variables and the command's own prefixes
+        let mut env = vars.to_env_vars();
+        for (key, value) in &simple_cmd.assignments {
+            vars.check_writable(key)?;
+            env.insert(key.clone(), vars.expand(value)?);
+        }
+
+        // Redirections are applied in the child after the pipe has been set
+        // up, so they take precedence over it
+        let prepared = match redirects.is_empty() {
+            true => None,
+            false => Some(Arc::new(redirect::prepare(redirects, vars)?)),
+        };
+        Self::trace_simple(simple_cmd, vars);
+
+        let mut child =
+            Self::spawn_external(&simple_cmd.name, program, &expanded_args, |command| {
+                command.env_clear().envs(&env);
+
+                // Setup stdin; the first command inherits it
+                match &stdin {
+                    Some(stdin) => command.stdin(stdin.try_clone()?),
+                    None => command.stdin(Stdio::inherit()),
+                };
+
+                // Setup stdout; the last command inherits it
+                if is_last {
+                    command.stdout(Stdio::inherit());
+                } else {
+                    command.stdout(Stdio::piped());
+                }
+
+                command.stderr(Stdio::inherit());
+
+                if let Some(prepared) = &prepared {
+                    let prepared = Arc::clone(prepared);
+                    unsafe {
+                        command.pre_exec(move || prepared.apply_in_child());
                     }
                 }
-                Err(e) => last_status = Err(e.to_string()),
+                Ok(())
+            })
+            .map_err(|e| format!("Failed to start {}: {}", simple_cmd.name, e))?;
+        let stdout = child.stdout.take().map(OwnedFd::from);
+        Ok((PipelineStage::Program(child), stdout))
+    }
+
+    /// Run one command of a multi-command pipeline in a forked copy of the
+    /// shell, with the pipes as its standard input and output. Builtins,
+    /// functions, compound commands and missing commands run this way.
+    #[allow(clippy::too_many_arguments)]
+    fn fork_piped(
+        cmd: &Command,
+        vars: &mut Variables,
+        functions: &mut Functions,
+        aliases: &mut Aliases,
+        history_mgr: &HistoryManager,
+        command_history: &mut Vec<String>,
+        stdin: Option<&OwnedFd>,
+        is_last: bool,
+    ) -> Result<(PipelineStage, Option<OwnedFd>), String> {
+        use nix::sys::signal::{signal, SigHandler, Signal};
+        use nix::unistd::{dup2, fork, pipe, ForkResult};
+        use std::os::fd::AsRawFd;
+
+        let pipe = match is_last {
+            true => None,
+            false => Some(pipe().map_err(|e| format!("Failed to create pipe: {}", e))?),
+        };
+        // Output still buffered would otherwise be written by both processes
+        let _ = std::io::Write::flush(&mut std::io::stdout());
+        match unsafe { fork() } {
+            Ok(ForkResult::Parent { child, .. }) => {
+                Ok((PipelineStage::Shell(child), pipe.map(|(read, _write)| read)))
+            }
+            Ok(ForkResult::Child) => {
+                Self::enter_forked_child(vars);
+                // A reader that goes away ends the command, as it would a program
+                let _ = unsafe { signal(Signal::SIGPIPE, SigHandler::SigDfl) };
+                if let Some(stdin) = stdin {
+                    let _ = dup2(stdin.as_raw_fd(), 0);
+                }
+                if let Some((read, write)) = pipe {
+                    drop(read);
+                    let _ = dup2(write.as_raw_fd(), 1);
+                }
+                let status = match Self::execute(
+                    cmd,
+                    vars,
+                    functions,
+                    aliases,
+                    history_mgr,
+                    command_history,
+                ) {
+                    Ok(status) => status,
+                    Err(e) => {
+                        eprintln!("pmsh: {}{}", vars.error_location(), e);
+                        1
+                    }
+                };
+                Self::exit_shell(
+                    status,
+                    vars,
+                    functions,
+                    aliases,
+                    history_mgr,
+                    command_history,
+                )
             }
+            Err(e) => Err(format!("Fork failed: {}", e)),
         }
-
-        last_status
     }
 
-    fn execute_external(cmd: &SimpleCommand, vars: &Variables) -> Result<(), String> {
+    /// Run the program `cmd` names, from `program` if the caller has found
+    /// the file already, and wait for it.
+    pub fn execute_external(
+        cmd: &SimpleCommand,
+        program: Option<std::path::PathBuf>,
+        vars: &Variables,
+    ) -> Result<i32, String> {
         // Handle variable assignments (temporary for this command)
         let mut temp_vars = vars.to_env_vars();
         for (key, value) in &cmd.assignments {
-            let expanded_value = vars.expand(value);
+            vars.check_writable(key)?;
+            let expanded_value = vars.expand(value)?;
             temp_vars.insert(key.clone(), expanded_value);
         }
 
-        let expanded_args: Vec<String> = cmd.args.iter().map(|arg| vars.expand(arg)).collect();
+        let expanded_args = vars.expand_words(&cmd.args)?;
 
-        let mut command = StdCommand::new(&cmd.name);
-        command.args(&expanded_args);
+        // A missing command is an ordinary failure, not an error that stops
+        // the function or loop it is in
+        let Some(program) = program.or_else(|| resolve::find_command(&cmd.name, vars)) else {
+            eprintln!(
+                "pmsh: {}{}: command not found",
+                vars.error_location(),
+                cmd.name
+            );
+            return Ok(127);
+        };
+        let spawned = Self::spawn_external(&cmd.name, &program, &expanded_args, |command| {
+            // Children see the exported variables only
+            command.env_clear().envs(&temp_vars);
+
+            // Inherit stdio
+            command.stdin(Stdio::inherit());
+            command.stdout(Stdio::inherit());
+            command.stderr(Stdio::inherit());
+            Ok(())
+        });
+        match spawned {
+            Ok(mut child) => match child.wait() {
+                Ok(status) => Ok(exit_code(status)),
+                Err(e) => Err(format!("Failed to wait on child: {}", e)),
+            },
+            Err(e) => {
+                let errno = nix::errno::Errno::from_raw(e.raw_os_error().unwrap_or(0));
+                eprintln!(
+                    "pmsh: {}{}: {}",
+                    vars.error_location(),
+                    cmd.name,
+                    errno.desc()
+                );
+                Ok(match errno {
+                    nix::errno::Errno::ENOENT => 127,
+                    _ => 126,
+                })
+            }
+        }
+    }
 
-        // Add environment variables
-        command.envs(&temp_vars);
+    /// Start `program`, which the command `name` was resolved to, after
+    /// `setup` has configured its environment and descriptors. An
+    /// executable file the kernel cannot run, such as a script without a
+    /// `#!` line, is run by a new pmsh instead, as POSIX shells do.
+    fn spawn_external(
+        name: &str,
+        program: &std::path::Path,
+        args: &[String],
+        setup: impl Fn(&mut StdCommand) -> std::io::Result<()>,
+    ) -> std::io::Result<Child> {
+        let mut command = StdCommand::new(program);
+        command.arg0(name).args(args);
+        setup(&mut command)?;
+        match command.spawn() {
+            Err(e) if e.raw_os_error() == Some(nix::errno::Errno::ENOEXEC as i32) => {
+                let mut command = StdCommand::new(std::env::current_exe()?);
+                command.arg(program).args(args);
+                setup(&mut command)?;
+                command.spawn()
+            }
+            other => other,
+        }
+    }
+}
 
-        // Inherit stdio
-        command.stdin(Stdio::inherit());
-        command.stdout(Stdio::inherit());
-        command.stderr(Stdio::inherit());
+/// A running command of a multi-command pipeline.
+enum PipelineStage {
+    /// An external program
+    Program(Child),
+    /// A forked copy of the shell
+    Shell(nix::unistd::Pid),
+}
 
-        match command.spawn() {
-            Ok(mut child) => match child.wait() {
-                Ok(_status) => Ok(()),
-                Err(e) => Err(format!("Failed to wait on child: {}", e)),
+impl PipelineStage {
+    fn kill(self) {
+        match self {
+            PipelineStage::Program(mut child) => {
+                let _ = child.kill();
+            }
+            PipelineStage::Shell(pid) => {
+                let _ = nix::sys::signal::kill(pid, nix::sys::signal::Signal::SIGKILL);
+            }
+        }
+    }
+
+    /// Wait for the command to finish and return its status.
+    fn wait(self) -> Result<i32, String> {
+        use nix::sys::wait::{waitpid, WaitStatus};
+
+        match self {
+            PipelineStage::Program(mut child) => match child.wait() {
+                Ok(status) => Ok(exit_code(status)),
+                Err(e) => Err(e.to_string()),
+            },
+            PipelineStage::Shell(pid) => match waitpid(pid, None) {
+                Ok(WaitStatus::Exited(_, code)) => Ok(code),
+                Ok(WaitStatus::Signaled(_, signal, _)) => Ok(128 + signal as i32),
+                Ok(_) => Ok(0),
+                Err(e) => Err(e.to_string()),
             },
-            Err(e) => Err(format!("Failed to execute {}: {}", cmd.name, e)),
         }
     }
 }
 
+/// Convert a child's exit status to a shell status (128+N when killed by signal N).
+fn exit_code(status: std::process::ExitStatus) -> i32 {
+    use std::os::unix::process::ExitStatusExt;
+    status
+        .code()
+        .unwrap_or_else(|| 128 + status.signal().unwrap_or(0))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -295,21 +1367,23 @@ mod tests {
     fn test_execute_echo() {
         let mut vars = Variables::new();
         let mut functions = Functions::new();
+        let mut aliases = Aliases::new();
         let cmd = Command::Simple(SimpleCommand {
             name: "echo".into(),
             args: vec!["hello".into()],
             assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
         });
         let history_mgr = crate::history::HistoryManager::default();
         let mut command_history = vec![];
-        let mut oldpwd = None;
         let res = Executor::execute(
             &cmd,
             &mut vars,
             &mut functions,
+            &mut aliases,
             &history_mgr,
             &mut command_history,
-            &mut oldpwd,
         );
         assert!(res.is_ok());
     }
@@ -318,21 +1392,23 @@ mod tests {
     fn test_execute_pipeline_single_command() {
         let mut vars = Variables::new();
         let mut functions = Functions::new();
+        let mut aliases = Aliases::new();
         let pipeline = vec![Command::Simple(SimpleCommand {
             name: "echo".into(),
             args: vec!["hello".into()],
             assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
         })];
         let history_mgr = crate::history::HistoryManager::default();
         let mut command_history = vec![];
-        let mut oldpwd = None;
         let res = Executor::execute_pipeline(
             &pipeline,
             &mut vars,
             &mut functions,
+            &mut aliases,
             &history_mgr,
             &mut command_history,
-            &mut oldpwd,
         );
         assert!(res.is_ok());
     }
@@ -341,28 +1417,32 @@ mod tests {
     fn test_execute_pipeline_echo_to_wc() {
         let mut vars = Variables::new();
         let mut functions = Functions::new();
+        let mut aliases = Aliases::new();
         let pipeline = vec![
             Command::Simple(SimpleCommand {
                 name: "echo".into(),
                 args: vec!["hello".into(), "world".into()],
                 assignments: vec![],
+                line: 0,
+                quoted: Vec::new(),
             }),
             Command::Simple(SimpleCommand {
                 name: "wc".into(),
                 args: vec!["-w".into()],
                 assignments: vec![],
+                line: 0,
+                quoted: Vec::new(),
             }),
         ];
         let history_mgr = crate::history::HistoryManager::default();
         let mut command_history = vec![];
-        let mut oldpwd = None;
         let res = Executor::execute_pipeline(
             &pipeline,
             &mut vars,
             &mut functions,
+            &mut aliases,
             &history_mgr,
             &mut command_history,
-            &mut oldpwd,
         );
         assert!(res.is_ok());
     }
@@ -371,17 +1451,17 @@ mod tests {
     fn test_execute_pipeline_empty() {
         let mut vars = Variables::new();
         let mut functions = Functions::new();
+        let mut aliases = Aliases::new();
         let pipeline: Vec<Command> = vec![];
         let history_mgr = crate::history::HistoryManager::default();
         let mut command_history = vec![];
-        let mut oldpwd = None;
         let res = Executor::execute_pipeline(
             &pipeline,
             &mut vars,
             &mut functions,
+            &mut aliases,
             &history_mgr,
             &mut command_history,
-            &mut oldpwd,
         );
         // execute_pipeline now returns Ok(()) for empty pipeline in my implementation above
         // but let's check if I should return Err.
@@ -398,76 +1478,87 @@ mod tests {
     fn test_execute_pipeline_exit_status() {
         let mut vars = Variables::new();
         let mut functions = Functions::new();
+        let mut aliases = Aliases::new();
         let history_mgr = crate::history::HistoryManager::default();
         let mut command_history = vec![];
-        let mut oldpwd = None;
 
         let pipeline_success = vec![
             Command::Simple(SimpleCommand {
                 name: "false".into(),
                 args: vec![],
                 assignments: vec![],
+                line: 0,
+                quoted: Vec::new(),
             }),
             Command::Simple(SimpleCommand {
                 name: "true".into(),
                 args: vec![],
                 assignments: vec![],
+                line: 0,
+                quoted: Vec::new(),
             }),
         ];
         let res = Executor::execute_pipeline(
             &pipeline_success,
             &mut vars,
             &mut functions,
+            &mut aliases,
             &history_mgr,
             &mut command_history,
-            &mut oldpwd,
         );
-        assert!(res.is_ok());
+        assert_eq!(res, Ok(0));
 
         let pipeline_fail = vec![
             Command::Simple(SimpleCommand {
                 name: "true".into(),
                 args: vec![],
                 assignments: vec![],
+                line: 0,
+                quoted: Vec::new(),
             }),
             Command::Simple(SimpleCommand {
                 name: "false".into(),
                 args: vec![],
                 assignments: vec![],
+                line: 0,
+                quoted: Vec::new(),
             }),
         ];
         let res = Executor::execute_pipeline(
             &pipeline_fail,
             &mut vars,
             &mut functions,
+            &mut aliases,
             &history_mgr,
             &mut command_history,
-            &mut oldpwd,
         );
-        assert!(res.is_err());
+        // The pipeline's status is the last command's
+        assert_eq!(res, Ok(1));
     }
 
     #[test]
     fn test_execute_variable_assignment() {
         let mut vars = Variables::new();
         let mut functions = Functions::new();
+        let mut aliases = Aliases::new();
         let history_mgr = crate::history::HistoryManager::default();
         let mut command_history = vec![];
-        let mut oldpwd = None;
 
         let cmd = Command::Simple(SimpleCommand {
             name: "".into(),
             args: vec![],
             assignments: vec![("MY_VAR".to_string(), "my_val".to_string())],
+            line: 0,
+            quoted: Vec::new(),
         });
 
         let res = Executor::execute(
             &cmd,
             &mut vars,
             &mut functions,
+            &mut aliases,
             &history_mgr,
             &mut command_history,
-            &mut oldpwd,
         );
         assert!(res.is_ok());
         assert_eq!(vars.get("MY_VAR").unwrap(), "my_val");
@@ -477,15 +1568,17 @@ mod tests {
     fn test_execute_function_def_and_call() {
         let mut vars = Variables::new();
         let mut functions = Functions::new();
+        let mut aliases = Aliases::new();
         let history_mgr = crate::history::HistoryManager::default();
         let mut command_history = vec![];
-        let mut oldpwd = None;
 
         // 1. Define function
         let body = vec![vec![Command::Simple(SimpleCommand {
             name: "echo".into(),
             args: vec!["called_func".into()],
             assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
         })]];
         let def_cmd = Command::FunctionDef("my_func".into(), body);
 
@@ -493,9 +1586,9 @@ mod tests {
             &def_cmd,
             &mut vars,
             &mut functions,
+            &mut aliases,
             &history_mgr,
-            &mut command_history,
-            &mut oldpwd,
+            &mut command_history
         )
         .is_ok());
         assert!(functions.get("my_func").is_some());
@@ -505,6 +1598,8 @@ mod tests {
             name: "my_func".into(),
             args: vec![],
             assignments: vec![("TEMP_VAR".to_string(), "temp_val".to_string())],
+            line: 0,
+            quoted: Vec::new(),
         });
 
         // Set an existing var to ensure it's restored
@@ -514,9 +1609,9 @@ mod tests {
             &call_cmd,
             &mut vars,
             &mut functions,
+            &mut aliases,
             &history_mgr,
-            &mut command_history,
-            &mut oldpwd,
+            &mut command_history
         )
         .is_ok());
 
@@ -528,24 +1623,26 @@ mod tests {
     fn test_execute_subshell() {
         let mut vars = Variables::new();
         let mut functions = Functions::new();
+        let mut aliases = Aliases::new();
         let history_mgr = crate::history::HistoryManager::default();
         let mut command_history = vec![];
-        let mut oldpwd = None;
 
         // Just test that the fork doesn't explode
         let subshell_cmd = Command::Subshell(vec![vec![Command::Simple(SimpleCommand {
             name: "echo".into(),
             args: vec!["in subshell".into()],
             assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
         })]]);
 
         let res = Executor::execute(
             &subshell_cmd,
             &mut vars,
             &mut functions,
+            &mut aliases,
             &history_mgr,
             &mut command_history,
-            &mut oldpwd,
         );
 
         // This fork execution handles exiting in child, so we only see the parent's Ok return
@@ -556,24 +1653,26 @@ mod tests {
     fn test_execute_function_error_restores_vars() {
         let mut vars = Variables::new();
         let mut functions = Functions::new();
+        let mut aliases = Aliases::new();
         let history_mgr = crate::history::HistoryManager::default();
         let mut command_history = vec![];
-        let mut oldpwd = None;
 
-        // Define a function whose body fails (non-existent command)
+        // Define a function whose body fails (bad substitution)
         let body = vec![vec![Command::Simple(SimpleCommand {
-            name: "definitely_not_a_real_command_xyz".into(),
-            args: vec![],
+            name: "echo".into(),
+            args: vec!["${!x}".into()],
             assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
         })]];
         let def_cmd = Command::FunctionDef("failing_func".into(), body);
         Executor::execute(
             &def_cmd,
             &mut vars,
             &mut functions,
+            &mut aliases,
             &history_mgr,
             &mut command_history,
-            &mut oldpwd,
         )
         .unwrap();
 
@@ -585,15 +1684,17 @@ mod tests {
             name: "failing_func".into(),
             args: vec![],
             assignments: vec![("REC_VAR".to_string(), "modified".to_string())],
+            line: 0,
+            quoted: Vec::new(),
         });
 
         let res = Executor::execute(
             &call_cmd,
             &mut vars,
             &mut functions,
+            &mut aliases,
             &history_mgr,
             &mut command_history,
-            &mut oldpwd,
         );
 
         // Function body failed, so error is propagated
@@ -603,16 +1704,282 @@ mod tests {
         assert_eq!(vars.get("REC_VAR").map(|s| s.as_str()), Some("original"));
     }
 
+    #[test]
+    fn test_execute_function_locals_are_scoped() {
+        let mut vars = Variables::new();
+        let mut functions = Functions::new();
+        let mut aliases = Aliases::new();
+        let history_mgr = crate::history::HistoryManager::default();
+        let mut command_history = vec![];
+
+        // inner() { SEEN=$LOC_VAR; LOC_VAR=changed; }
+        // outer() { local LOC_VAR=$1; inner; COPY=$LOC_VAR; }
+        let pipelines = Command::parse(
+            "inner() { SEEN=$LOC_VAR; LOC_VAR=changed; }\n\
+             outer() { local LOC_VAR=$1; inner; COPY=$LOC_VAR; }\n\
+             LOC_VAR=global\n\
+             outer from_arg",
+        )
+        .unwrap();
+        for pipeline in &pipelines {
+            Executor::execute_pipeline(
+                pipeline,
+                &mut vars,
+                &mut functions,
+                &mut aliases,
+                &history_mgr,
+                &mut command_history,
+            )
+            .unwrap();
+        }
+
+        // inner saw outer's local and assigned to it, not to the global
+        assert_eq!(vars.get("SEEN").unwrap(), "from_arg");
+        assert_eq!(vars.get("COPY").unwrap(), "changed");
+        assert_eq!(vars.get("LOC_VAR").unwrap(), "global");
+        assert!(!vars.in_function());
+    }
+
+    #[test]
+    fn test_execute_function_error_pops_scope() {
+        let mut vars = Variables::new();
+        let mut functions = Functions::new();
+        let mut aliases = Aliases::new();
+        let history_mgr = crate::history::HistoryManager::default();
+        let mut command_history = vec![];
+
+        let pipelines = Command::parse("failing() { local ERR_LOCAL=1; echo ${!x}; }").unwrap();
+        Executor::execute_pipeline(
+            &pipelines[0],
+            &mut vars,
+            &mut functions,
+            &mut aliases,
+            &history_mgr,
+            &mut command_history,
+        )
+        .unwrap();
+
+        let call = Command::Simple(SimpleCommand {
+            name: "failing".into(),
+            args: vec![],
+            assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
+        });
+        let res = Executor::execute(
+            &call,
+            &mut vars,
+            &mut functions,
+            &mut aliases,
+            &history_mgr,
+            &mut command_history,
+        );
+        assert!(res.is_err());
+        assert!(!vars.in_function());
+        assert!(vars.get("ERR_LOCAL").is_none());
+    }
+
+    #[test]
+    fn test_execute_assignment_to_readonly_local_fails() {
+        let mut vars = Variables::new();
+        let mut functions = Functions::new();
+        let mut aliases = Aliases::new();
+        let history_mgr = crate::history::HistoryManager::default();
+        let mut command_history = vec![];
+
+        vars.push_scope(vec![]);
+        let readonly = Attributes {
+            readonly: true,
+            ..Default::default()
+        };
+        vars.declare_local("RO_VAR", Some("1".into()), readonly)
+            .unwrap();
+        let cmd = Command::Simple(SimpleCommand {
+            name: "".into(),
+            args: vec![],
+            assignments: vec![("RO_VAR".to_string(), "2".to_string())],
+            line: 0,
+            quoted: Vec::new(),
+        });
+        let res = Executor::execute(
+            &cmd,
+            &mut vars,
+            &mut functions,
+            &mut aliases,
+            &history_mgr,
+            &mut command_history,
+        );
+        assert_eq!(res.unwrap_err(), "RO_VAR: readonly variable");
+        assert_eq!(vars.get("RO_VAR").unwrap(), "1");
+    }
+
+    /// Run `script` line by line and return the status of the last pipeline.
+    fn run_script(
+        script: &str,
+        vars: &mut Variables,
+        functions: &mut Functions,
+        aliases: &mut Aliases,
+    ) -> i32 {
+        let history_mgr = crate::history::HistoryManager::default();
+        let mut command_history = vec![];
+        let pipelines = Command::parse(script).unwrap();
+        Executor::execute_list(
+            &pipelines,
+            vars,
+            functions,
+            aliases,
+            &history_mgr,
+            &mut command_history,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_execute_function_status_is_last_command() {
+        let mut vars = Variables::new();
+        let mut functions = Functions::new();
+        let mut aliases = Aliases::new();
+        let status = run_script(
+            "fails() { true; false; }\nfails",
+            &mut vars,
+            &mut functions,
+            &mut aliases,
+        );
+        assert_eq!(status, 1);
+        assert_eq!(vars.last_status(), 1);
+
+        let status = run_script(
+            "passes() { false; true; }\npasses",
+            &mut vars,
+            &mut functions,
+            &mut aliases,
+        );
+        assert_eq!(status, 0);
+    }
+
+    #[test]
+    fn test_execute_return_unwinds_nested_commands() {
+        let mut vars = Variables::new();
+        let mut functions = Functions::new();
+        let mut aliases = Aliases::new();
+        let status = run_script(
+            "find_it() {\n\
+               for item in a b c; do\n\
+                 while true; do\n\
+                   { if true; then FOUND=$item; return 3; fi; }\n\
+                 done\n\
+               done\n\
+               FOUND=never\n\
+             }\n\
+             find_it",
+            &mut vars,
+            &mut functions,
+            &mut aliases,
+        );
+        assert_eq!(status, 3);
+        assert_eq!(vars.get("FOUND").unwrap(), "a");
+        assert!(!vars.is_returning());
+        assert!(!vars.in_function());
+    }
+
+    #[test]
+    fn test_execute_return_defaults_to_last_status() {
+        let mut vars = Variables::new();
+        let mut functions = Functions::new();
+        let mut aliases = Aliases::new();
+        let status = run_script(
+            "check() { false; return; }\ncheck && RESULT=yes || RESULT=no",
+            &mut vars,
+            &mut functions,
+            &mut aliases,
+        );
+        assert_eq!(status, 0);
+        assert_eq!(vars.get("RESULT").unwrap(), "no");
+    }
+
+    #[test]
+    fn test_execute_and_or_not_and_if() {
+        let mut vars = Variables::new();
+        let mut functions = Functions::new();
+        let mut aliases = Aliases::new();
+        run_script(
+            "true && A=1 || A=2\n\
+             false && B=1 || B=2\n\
+             ! false && C=1\n\
+             if false; then D=1; elif true; then D=2; else D=3; fi\n\
+             false; E=$?",
+            &mut vars,
+            &mut functions,
+            &mut aliases,
+        );
+        assert_eq!(vars.get("A").unwrap(), "1");
+        assert_eq!(vars.get("B").unwrap(), "2");
+        assert_eq!(vars.get("C").unwrap(), "1");
+        assert_eq!(vars.get("D").unwrap(), "2");
+        assert_eq!(vars.get("E").unwrap(), "1");
+    }
+
+    #[test]
+    fn test_execute_loops() {
+        let mut vars = Variables::new();
+        let mut functions = Functions::new();
+        let mut aliases = Aliases::new();
+        run_script(
+            "LIST=\n\
+             for word in x y z; do LIST=$LIST$word; done\n\
+             args() { for arg; do ARGS=$ARGS$arg; done; }\n\
+             args 1 2\n\
+             N=\n\
+             until [ \"$N\" = ...  ]; do N=$N.; done",
+            &mut vars,
+            &mut functions,
+            &mut aliases,
+        );
+        assert_eq!(vars.get("LIST").unwrap(), "xyz");
+        assert_eq!(vars.get("ARGS").unwrap(), "12");
+        assert_eq!(vars.get("N").unwrap(), "...");
+    }
+
+    #[test]
+    fn test_execute_eval() {
+        let mut vars = Variables::new();
+        let mut functions = Functions::new();
+        let mut aliases = Aliases::new();
+        run_script(
+            "for i in 1 2; do eval VAR_$i=$i; done\n\
+             CMD=\"if true; then EVALED=yes; fi\"\n\
+             if true; then eval $CMD; fi\n\
+             early() { eval return 3; NOT_REACHED=1; }\n\
+             early; EARLY=$?",
+            &mut vars,
+            &mut functions,
+            &mut aliases,
+        );
+        assert_eq!(vars.get("VAR_1").unwrap(), "1");
+        assert_eq!(vars.get("VAR_2").unwrap(), "2");
+        assert_eq!(vars.get("EVALED").unwrap(), "yes");
+        assert_eq!(vars.get("EARLY").unwrap(), "3");
+        assert!(vars.get("NOT_REACHED").is_none());
+
+        let status = run_script(
+            "if true; then eval 'echo ('; fi",
+            &mut vars,
+            &mut functions,
+            &mut aliases,
+        );
+        assert_eq!(status, 2);
+    }
+
     #[test]
     #[serial_test::serial]
     fn test_execute_builtin_cd() {
         let mut vars = Variables::new();
         let mut functions = Functions::new();
+        let mut aliases = Aliases::new();
         let history_mgr = crate::history::HistoryManager::default();
         let mut command_history = vec![];
         let tmp = tempfile::TempDir::new().unwrap();
         let tmp_path = tmp.path().to_string_lossy().to_string();
-        let mut oldpwd = None;
 
         let orig = std::env::current_dir().unwrap();
 
@@ -620,15 +1987,17 @@ mod tests {
             name: "cd".into(),
             args: vec![tmp_path.clone()],
             assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
         });
 
         let res = Executor::execute(
             &cmd,
             &mut vars,
             &mut functions,
+            &mut aliases,
             &history_mgr,
             &mut command_history,
-            &mut oldpwd,
         );
 
         assert!(res.is_ok());
@@ -640,28 +2009,41 @@ mod tests {
     }
 
     #[test]
-    fn test_execute_external_nonexistent_fails() {
+    fn test_execute_external_nonexistent_returns_127() {
         let mut vars = Variables::new();
         let mut functions = Functions::new();
+        let mut aliases = Aliases::new();
         let history_mgr = crate::history::HistoryManager::default();
         let mut command_history = vec![];
-        let mut oldpwd = None;
 
         let cmd = Command::Simple(SimpleCommand {
             name: "definitely_not_a_real_command_xyz_12345".into(),
             args: vec![],
             assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
         });
 
         let res = Executor::execute(
             &cmd,
             &mut vars,
             &mut functions,
+            &mut aliases,
             &history_mgr,
             &mut command_history,
-            &mut oldpwd,
         );
 
-        assert!(res.is_err());
+        assert_eq!(res, Ok(127));
+    }
+
+    #[test]
+    fn test_reap_background_leaves_other_children() {
+        let mut vars = Variables::new();
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(100));
+
+        Executor::reap_background(&mut vars);
+
+        assert!(child.wait().unwrap().success());
     }
 }
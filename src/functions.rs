@@ -21,7 +21,13 @@ impl Functions {
         self.funcs.get(name)
     }
 
-    #[allow(dead_code)]
+    /// The names of all defined functions, sorted.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.funcs.keys().map(|k| k.as_str()).collect();
+        names.sort();
+        names
+    }
+
     pub fn remove(&mut self, name: &str) {
         self.funcs.remove(name);
     }
@@ -37,6 +43,8 @@ mod tests {
             name: name.to_string(),
             args: vec![],
             assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
         })]]
     }
 
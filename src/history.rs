@@ -45,20 +45,6 @@ impl HistoryManager {
 
         Ok(())
     }
-
-    pub fn add_entry(&self, entry: &str, history: &mut Vec<String>) -> Result<(), String> {
-        history.push(entry.to_string());
-
-        // Save only the last MAX_HISTORY_SIZE entries
-        if history.len() > MAX_HISTORY_SIZE {
-            let start = history.len() - MAX_HISTORY_SIZE;
-            let limited = history[start..].to_vec();
-            *history = limited;
-        }
-
-        self.save(history)?;
-        Ok(())
-    }
 }
 
 impl Default for HistoryManager {
@@ -81,17 +67,18 @@ mod tests {
 
     #[test]
     fn test_history_manager_max_size() {
-        if let Ok(mgr) = HistoryManager::new() {
-            let mut history = Vec::new();
-
-            // Add more than MAX_HISTORY_SIZE entries
-            for i in 0..=MAX_HISTORY_SIZE {
-                let entry = format!("command_{}", i);
-                let _ = mgr.add_entry(&entry, &mut history);
-            }
-
-            // History should not exceed MAX_HISTORY_SIZE
-            assert!(history.len() <= MAX_HISTORY_SIZE);
-        }
+        let dir = tempfile::TempDir::new().unwrap();
+        let mgr = HistoryManager {
+            history_file: dir.path().join(".pmsh_history"),
+        };
+        let history: Vec<String> = (0..=MAX_HISTORY_SIZE)
+            .map(|i| format!("command_{}", i))
+            .collect();
+
+        // Only the last MAX_HISTORY_SIZE entries are saved
+        mgr.save(&history).unwrap();
+        let saved = mgr.load().unwrap();
+        assert_eq!(saved.len(), MAX_HISTORY_SIZE);
+        assert_eq!(saved[0], "command_1");
     }
 }
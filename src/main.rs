@@ -1,11 +1,25 @@
+mod aliases;
+mod arith;
+mod arrays;
 mod builtins;
+mod cli;
 mod colors;
+mod conditional;
+mod dirstack;
 mod executor;
 mod functions;
 mod history;
+mod options;
 mod parser;
 mod path_utils;
+mod pattern;
+mod redirect;
+mod resolve;
+mod startup;
+mod trace;
+mod traps;
 mod ui;
+mod unparse;
 mod variables;
 
 use history::HistoryManager;
@@ -16,10 +30,23 @@ mod autocomplete;
 mod completion_registry;
 mod repl;
 
+use aliases::Aliases;
 use autocomplete::PmshHelper;
+use clap::Parser;
+use cli::{Cli, Input};
+use executor::Executor;
 use functions::Functions;
+use parser::Command;
+use startup::StartupFiles;
 
 fn main() {
+    let cli = Cli::parse();
+    let argv0 = std::env::args()
+        .next()
+        .unwrap_or_else(|| "pmsh".to_string());
+    let (input, arg0, positional) = cli.input(&argv0, cli::stdin_is_tty());
+    let interactive = cli.interactive || input == Input::Interactive;
+
     // Initialize history manager
     let history_mgr = HistoryManager::new().unwrap_or_else(|e| {
         eprintln!("Warning: Could not initialize history: {}", e);
@@ -29,129 +56,156 @@ fn main() {
     // Load existing history
     let mut command_history = history_mgr.load().unwrap_or_default();
 
-    let args: Vec<String> = std::env::args().collect();
-
-    if args.len() > 1 {
-        // Script execution mode
-        let script_path = &args[1];
-        let contents = std::fs::read_to_string(script_path).unwrap_or_else(|e| {
-            eprintln!("Error reading script {}: {}", script_path, e);
-            std::process::exit(1);
-        });
-
-        // Dummy editor for script execution (no history needed)
-        struct ScriptEditor;
-        impl LineEditor for ScriptEditor {
-            fn readline(&mut self, _prompt: &str) -> ReadlineEvent {
-                ReadlineEvent::Eof
-            }
-            fn add_history_entry(&mut self, _entry: &str) {}
-        }
-
-        let _editor = ScriptEditor;
-        let mut oldpwd: Option<String> = None;
-        let executor = RealExecutor {};
-        let mut vars = variables::Variables::new();
-        let mut functions = Functions::new();
-
-        use crate::parser::Command;
-        match Command::parse_script(&contents) {
-            Ok(pipelines) => {
-                for pipeline in pipelines {
-                    if !repl::execute_pipeline_struct(
-                        &pipeline,
-                        &history_mgr,
-                        &mut command_history,
-                        &executor,
-                        &mut oldpwd,
-                        &mut vars,
-                        &mut functions,
-                    ) {
-                        break;
-                    }
-                }
-            }
-            Err(e) => {
-                eprintln!("Error parsing script: {}", e);
-                std::process::exit(1);
-            }
+    let executor = RealExecutor {};
+    let mut vars = variables::Variables::new();
+    let mut functions = Functions::new();
+    let mut aliases = Aliases::new();
+
+    vars.set_arg0(arg0);
+    vars.set_positional_args(positional);
+    vars.increment_shell_level();
+    vars.init_pwd();
+    let options = vars.options_mut();
+    options.errexit = cli.errexit;
+    options.xtrace = cli.xtrace;
+    options.nounset = cli.nounset;
+
+    StartupFiles {
+        login: cli.is_login(&argv0),
+        interactive,
+        norc: cli.norc,
+        rcfile: cli.rcfile.clone(),
+    }
+    .run(
+        &history_mgr,
+        &mut command_history,
+        &mut vars,
+        &mut functions,
+        &mut aliases,
+    );
+
+    let result = match input {
+        Input::Command(command) => repl::execute_script(
+            &command,
+            None,
+            &history_mgr,
+            &mut command_history,
+            &executor,
+            &mut vars,
+            &mut functions,
+            &mut aliases,
+        ),
+        Input::Script(script_path) => {
+            let contents = std::fs::read_to_string(&script_path).unwrap_or_else(|e| {
+                eprintln!("pmsh: {}: {}", script_path.display(), e);
+                // Like a command that is missing or cannot be run
+                std::process::exit(match e.kind() {
+                    std::io::ErrorKind::NotFound => 127,
+                    _ => 126,
+                });
+            });
+            repl::execute_script(
+                &contents,
+                Some(&script_path.to_string_lossy()),
+                &history_mgr,
+                &mut command_history,
+                &executor,
+                &mut vars,
+                &mut functions,
+                &mut aliases,
+            )
         }
-    } else {
-        // Interactive REPL mode
-        let mut oldpwd: Option<String> = None;
-        let executor = RealExecutor {};
-        let mut vars = variables::Variables::new();
-        let mut functions = Functions::new();
-
-        // Try to source ~/.pmshrc if it exists
-        if let Ok(home) = std::env::var("HOME") {
-            let mut home_dir = std::path::PathBuf::from(home);
-            home_dir.push(".pmshrc");
-            if home_dir.exists() {
-                if let Ok(contents) = std::fs::read_to_string(&home_dir) {
-                    use crate::parser::Command;
-                    if let Ok(pipelines) = Command::parse_script(&contents) {
-                        for pipeline in pipelines {
-                            repl::execute_pipeline_struct(
-                                &pipeline,
-                                &history_mgr,
-                                &mut command_history,
-                                &executor,
-                                &mut oldpwd,
-                                &mut vars,
-                                &mut functions,
-                            );
-                        }
-                    } else {
-                        eprintln!("pmsh: error parsing ~/.pmshrc");
-                    }
-                }
-            }
+        Input::Stdin => repl::execute_chunks(
+            Command::chunks_from_lines(cli::stdin_lines()),
+            None,
+            &history_mgr,
+            &mut command_history,
+            &executor,
+            &mut vars,
+            &mut functions,
+            &mut aliases,
+        ),
+        Input::Interactive => {
+            run_interactive(
+                history_mgr,
+                command_history,
+                executor,
+                vars,
+                functions,
+                aliases,
+            );
+            return;
         }
-
-        // This gets us the line editor with history
-        let config = rustyline::Config::builder()
-            .completion_type(rustyline::CompletionType::List)
-            .build();
-        let mut rl: Editor<PmshHelper, DefaultHistory> =
-            Editor::with_config(config).expect("Failed to create editor");
-        rl.set_helper(Some(PmshHelper::new()));
-
-        // Load history into rustyline
-        for entry in &command_history {
-            let _ = rl.add_history_entry(entry.as_str());
+    };
+
+    // A script's status is that of its last command
+    let status = match result {
+        Ok(_) => vars.last_status(),
+        Err(e) => {
+            eprintln!("pmsh: {}", e);
+            1
         }
+    };
+    Executor::exit_shell(
+        status,
+        &mut vars,
+        &mut functions,
+        &mut aliases,
+        &history_mgr,
+        &mut command_history,
+    );
+}
 
-        // Wrap the rustyline editor as a LineEditor implementation
-        struct RustyEditor {
-            inner: Editor<PmshHelper, DefaultHistory>,
-        }
-        impl LineEditor for RustyEditor {
-            fn readline(&mut self, prompt: &str) -> ReadlineEvent {
-                match self.inner.readline(prompt) {
-                    Ok(line) => ReadlineEvent::Line(line),
-                    Err(ReadlineError::Interrupted) => ReadlineEvent::Interrupted,
-                    Err(ReadlineError::Eof) => ReadlineEvent::Eof,
-                    Err(_e) => ReadlineEvent::Other,
-                }
-            }
+fn run_interactive(
+    history_mgr: HistoryManager,
+    mut command_history: Vec<String>,
+    executor: RealExecutor,
+    vars: variables::Variables,
+    functions: Functions,
+    aliases: Aliases,
+) {
+    // This gets us the line editor with history
+    let config = rustyline::Config::builder()
+        .completion_type(rustyline::CompletionType::List)
+        .build();
+    let mut rl: Editor<PmshHelper, DefaultHistory> =
+        Editor::with_config(config).expect("Failed to create editor");
+    rl.set_helper(Some(PmshHelper::new()));
+
+    // Load history into rustyline
+    for entry in &command_history {
+        let _ = rl.add_history_entry(entry.as_str());
+    }
 
-            fn add_history_entry(&mut self, entry: &str) {
-                let _ = self.inner.add_history_entry(entry);
+    // Wrap the rustyline editor as a LineEditor implementation
+    struct RustyEditor {
+        inner: Editor<PmshHelper, DefaultHistory>,
+    }
+    impl LineEditor for RustyEditor {
+        fn readline(&mut self, prompt: &str) -> ReadlineEvent {
+            match self.inner.readline(prompt) {
+                Ok(line) => ReadlineEvent::Line(line),
+                Err(ReadlineError::Interrupted) => ReadlineEvent::Interrupted,
+                Err(ReadlineError::Eof) => ReadlineEvent::Eof,
+                Err(_e) => ReadlineEvent::Other,
             }
         }
 
-        let mut editor = RustyEditor { inner: rl };
-
-        // Run the refactored REPL loop
-        repl::run_repl_with_state(
-            &mut editor,
-            &history_mgr,
-            &mut command_history,
-            &executor,
-            oldpwd,
-            vars,
-            functions,
-        );
+        fn add_history_entry(&mut self, entry: &str) {
+            let _ = self.inner.add_history_entry(entry);
+        }
     }
+
+    let mut editor = RustyEditor { inner: rl };
+
+    // Run the refactored REPL loop
+    repl::run_repl_with_state(
+        &mut editor,
+        &history_mgr,
+        &mut command_history,
+        &executor,
+        vars,
+        functions,
+        aliases,
+    );
 }
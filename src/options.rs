@@ -0,0 +1,111 @@
+/// Shell options toggled with `set -o name` or their single-letter flags.
+#[derive(Debug, Clone, Default)]
+pub struct ShellOptions {
+    /// `cd` pushes the directory it leaves onto the directory stack.
+    pub autopushd: bool,
+    pub errexit: bool,
+    pub noclobber: bool,
+    pub nounset: bool,
+    pub pipefail: bool,
+    pub verbose: bool,
+    pub xtrace: bool,
+}
+
+/// Option names and their `set` flag letters, in listing order.
+const OPTIONS: &[(&str, Option<char>)] = &[
+    ("autopushd", None),
+    ("errexit", Some('e')),
+    ("noclobber", Some('C')),
+    ("nounset", Some('u')),
+    ("pipefail", None),
+    ("verbose", Some('v')),
+    ("xtrace", Some('x')),
+];
+
+impl ShellOptions {
+    fn flag_mut(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "autopushd" => Some(&mut self.autopushd),
+            "errexit" => Some(&mut self.errexit),
+            "noclobber" => Some(&mut self.noclobber),
+            "nounset" => Some(&mut self.nounset),
+            "pipefail" => Some(&mut self.pipefail),
+            "verbose" => Some(&mut self.verbose),
+            "xtrace" => Some(&mut self.xtrace),
+            _ => None,
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<bool> {
+        match name {
+            "autopushd" => Some(self.autopushd),
+            "errexit" => Some(self.errexit),
+            "noclobber" => Some(self.noclobber),
+            "nounset" => Some(self.nounset),
+            "pipefail" => Some(self.pipefail),
+            "verbose" => Some(self.verbose),
+            "xtrace" => Some(self.xtrace),
+            _ => None,
+        }
+    }
+
+    pub fn set(&mut self, name: &str, value: bool) -> Result<(), String> {
+        match self.flag_mut(name) {
+            Some(flag) => {
+                *flag = value;
+                Ok(())
+            }
+            None => Err(format!("set: {}: invalid option name", name)),
+        }
+    }
+
+    /// Returns the long name of the option with flag letter `letter`.
+    pub fn name_for_letter(letter: char) -> Option<&'static str> {
+        OPTIONS
+            .iter()
+            .find(|(_, l)| *l == Some(letter))
+            .map(|(name, _)| *name)
+    }
+
+    /// All option names, sorted.
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        OPTIONS.iter().map(|(name, _)| *name)
+    }
+
+    /// The letters of the enabled options, as expanded by `$-`.
+    pub fn flags(&self) -> String {
+        OPTIONS
+            .iter()
+            .filter_map(|(name, letter)| letter.filter(|_| self.get(name) == Some(true)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_and_get_by_name() {
+        let mut opts = ShellOptions::default();
+        opts.set("pipefail", true).unwrap();
+        assert_eq!(opts.get("pipefail"), Some(true));
+        assert_eq!(opts.get("errexit"), Some(false));
+        assert!(opts.set("bogus", true).is_err());
+        assert_eq!(opts.get("bogus"), None);
+    }
+
+    #[test]
+    fn test_letters_and_flags() {
+        let mut opts = ShellOptions::default();
+        assert_eq!(ShellOptions::name_for_letter('e'), Some("errexit"));
+        assert_eq!(ShellOptions::name_for_letter('C'), Some("noclobber"));
+        assert_eq!(ShellOptions::name_for_letter('z'), None);
+
+        assert_eq!(opts.flags(), "");
+        opts.errexit = true;
+        opts.nounset = true;
+        opts.pipefail = true;
+        assert_eq!(opts.flags(), "eu");
+    }
+}
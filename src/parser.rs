@@ -1,22 +1,93 @@
+use crate::arrays::{self, ELEMENT_SEP, LITERAL_START, SPLIT_MARK};
+use crate::conditional::{QUOTED_END, QUOTED_START};
+use conch_parser::ast::builder::{self as build, Builder, StringBuilder};
 use conch_parser::ast::{
-    self, Command as ConchCommand, CompoundCommand, CompoundCommandKind, DefaultPipeableCommand,
-    ListableCommand, PipeableCommand, Redirect, TopLevelCommand, TopLevelWord,
+    self, AndOrList, Command as ConchCommand, CompoundCommand, CompoundCommandKind,
+    DefaultPipeableCommand, ListableCommand, PipeableCommand, Redirect as ConchRedirect,
+    TopLevelCommand, TopLevelWord,
 };
 use conch_parser::lexer::Lexer;
-use conch_parser::parse::DefaultParser;
+use conch_parser::parse::{ParseError, Parser, SourcePos};
+use conch_parser::token::Token;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct SimpleCommand {
     pub name: String,
     pub args: Vec<String>,
     pub assignments: Vec<(String, String)>,
+    /// Line (1-based) the command starts on, or 0 if it was not parsed
+    pub line: usize,
+    /// The assignments, name and arguments as written, quotes included,
+    /// to print the command back out; empty if it was not parsed
+    pub quoted: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RedirectKind {
+    /// `[n]< file`
+    Read,
+    /// `[n]> file`
+    Write,
+    /// `[n]>> file`
+    Append,
+    /// `[n]>| file`, which ignores noclobber
+    Clobber,
+    /// `[n]<> file`
+    ReadWrite,
+    /// `[n]<<EOF`; the target holds the document's body
+    Heredoc,
+    /// `[n]<& m` or `[n]<&-`
+    DupRead,
+    /// `[n]>& m` or `[n]>&-`
+    DupWrite,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct Redirect {
+    pub fd: i32,
+    pub kind: RedirectKind,
+    pub target: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AndOrOp {
+    And,
+    Or,
+}
+
+#[allow(clippy::type_complexity)]
 #[derive(Debug, Clone, PartialEq)]
 pub enum Command {
     Simple(SimpleCommand),
     Subshell(Vec<Vec<Command>>),
     FunctionDef(String, Vec<Vec<Command>>),
+    /// `{ list; }`, run in the current shell.
+    Group(Vec<Vec<Command>>),
+    /// A pipeline followed by `&&`/`||` pipelines, evaluated left to right.
+    AndOr(Vec<Command>, Vec<(AndOrOp, Vec<Command>)>),
+    /// `! pipeline`, which inverts the pipeline's status.
+    Not(Vec<Command>),
+    If {
+        /// (guard, body) pairs for the `if` and each `elif`.
+        conditionals: Vec<(Vec<Vec<Command>>, Vec<Vec<Command>>)>,
+        else_branch: Option<Vec<Vec<Command>>>,
+    },
+    /// Guard and body of a `while` loop.
+    While(Vec<Vec<Command>>, Vec<Vec<Command>>),
+    /// Guard and body of an `until` loop.
+    Until(Vec<Vec<Command>>, Vec<Vec<Command>>),
+    /// Loop variable, word list (`None` means the positional parameters) and body.
+    For(String, Option<Vec<String>>, Vec<Vec<Command>>),
+    /// A command with redirections, applied in order around it.
+    Redirected(Box<Command>, Vec<Redirect>),
+    /// `pipeline &`, run asynchronously in a subshell.
+    Background(Vec<Command>),
+    /// `[[ expression ]]`, holding the words between the brackets and the
+    /// line it starts on.
+    Conditional(Vec<String>, usize),
 }
 
 impl SimpleCommand {
@@ -40,6 +111,34 @@ impl SimpleCommand {
         }
     }
 
+    /// A word as it was written, with its quotes.
+    fn word_source(word: &TopLevelWord<String>) -> String {
+        let parts = match &word.0 {
+            ast::ComplexWord::Single(w) => std::slice::from_ref(w),
+            ast::ComplexWord::Concat(ws) => ws.as_slice(),
+        };
+        let written: String = parts
+            .iter()
+            .map(|part| match part {
+                ast::Word::Simple(s) => Self::simple_word_source(s),
+                ast::Word::DoubleQuoted(ws) => {
+                    let inner: String = ws.iter().map(Self::simple_word_source).collect();
+                    format!("\"{}\"", inner)
+                }
+                ast::Word::SingleQuoted(s) => format!("'{}'", s),
+            })
+            .collect();
+        unescape_array_references(&written)
+    }
+
+    fn simple_word_source(word: &ast::DefaultSimpleWord) -> String {
+        match word {
+            ast::SimpleWord::Literal(s) => s.replace(SPLIT_MARK, ""),
+            ast::SimpleWord::Escaped(s) => format!("\\{}", s),
+            _ => Self::simple_word_to_string(word),
+        }
+    }
+
     fn simple_word_to_string(word: &ast::DefaultSimpleWord) -> String {
         match word {
             ast::SimpleWord::Literal(s) => s.clone(),
@@ -68,24 +167,102 @@ impl SimpleCommand {
         }
     }
 
+    fn redirect_from_conch(redirect: &ConchRedirect<TopLevelWord<String>>) -> Redirect {
+        let (fd, kind, word) = match redirect {
+            ConchRedirect::Read(fd, w) => (fd.map_or(0, i32::from), RedirectKind::Read, w),
+            ConchRedirect::Write(fd, w) => (fd.map_or(1, i32::from), RedirectKind::Write, w),
+            ConchRedirect::ReadWrite(fd, w) => {
+                (fd.map_or(0, i32::from), RedirectKind::ReadWrite, w)
+            }
+            ConchRedirect::Append(fd, w) => (fd.map_or(1, i32::from), RedirectKind::Append, w),
+            ConchRedirect::Clobber(fd, w) => (fd.map_or(1, i32::from), RedirectKind::Clobber, w),
+            ConchRedirect::Heredoc(fd, w) => (fd.map_or(0, i32::from), RedirectKind::Heredoc, w),
+            ConchRedirect::DupRead(fd, w) => (fd.map_or(0, i32::from), RedirectKind::DupRead, w),
+            ConchRedirect::DupWrite(fd, w) => (fd.map_or(1, i32::from), RedirectKind::DupWrite, w),
+        };
+        Redirect {
+            fd,
+            kind,
+            target: Self::word_to_string(word),
+        }
+    }
+
     fn simple_command_to_command(
-        simple: &ast::SimpleCommand<String, TopLevelWord<String>, Redirect<TopLevelWord<String>>>,
+        simple: &ast::SimpleCommand<
+            String,
+            TopLevelWord<String>,
+            ConchRedirect<TopLevelWord<String>>,
+        >,
+        line: usize,
+    ) -> Option<Command> {
+        let mut redirects = Vec::new();
+        for item in &simple.redirects_or_env_vars {
+            if let ast::RedirectOrEnvVar::Redirect(r) = item {
+                redirects.push(Self::redirect_from_conch(r));
+            }
+        }
+        for item in &simple.redirects_or_cmd_words {
+            if let ast::RedirectOrCmdWord::Redirect(r) = item {
+                redirects.push(Self::redirect_from_conch(r));
+            }
+        }
+
+        // A redirection-only command (e.g. `> file`) still has to run
+        let mut cmd = match Self::simple_command_to_simple(simple) {
+            Some(cmd) => cmd,
+            None if !redirects.is_empty() => SimpleCommand {
+                name: String::new(),
+                args: vec![],
+                assignments: vec![],
+                line: 0,
+                quoted: Vec::new(),
+            },
+            None => return None,
+        };
+        cmd.line = line;
+        let closed = cmd.args.last().is_some_and(|word| word == "]]");
+        if cmd.name == "[[" && closed && cmd.assignments.is_empty() {
+            let mut words = cmd.args;
+            words.pop();
+            return Some(Command::Conditional(words, line).with_redirects(redirects));
+        }
+        Some(Command::Simple(cmd).with_redirects(redirects))
+    }
+
+    fn simple_command_to_simple(
+        simple: &ast::SimpleCommand<
+            String,
+            TopLevelWord<String>,
+            ConchRedirect<TopLevelWord<String>>,
+        >,
     ) -> Option<SimpleCommand> {
         let mut args = Vec::new();
         let mut assignments = Vec::new();
+        let mut quoted = Vec::new();
 
         // Process redirects_or_env_vars for assignments
         for redirect_or_env in &simple.redirects_or_env_vars {
             if let ast::RedirectOrEnvVar::EnvVar(name, value) = redirect_or_env {
                 let val = value.as_ref().map(Self::word_to_string).unwrap_or_default();
+                let written = value.as_ref().map(Self::word_source).unwrap_or_default();
+                quoted.push(format!("{}={}", name, written));
                 assignments.push((name.to_string(), val));
             }
         }
 
-        // Process redirects_or_cmd_words for command name and args
+        // Process redirects_or_cmd_words for command name and args.
+        // conch-parser only knows `name=value`, so assignments such as
+        // `a[1]=x` and `a+=x` arrive as words and are picked out here.
         for redirect_or_word in &simple.redirects_or_cmd_words {
             if let ast::RedirectOrCmdWord::CmdWord(word) = redirect_or_word {
-                args.push(Self::word_to_string(word));
+                quoted.push(Self::word_source(word));
+                let word = Self::word_to_string(word);
+                match arrays::split_assignment(&word) {
+                    Some((lhs, value)) if args.is_empty() => {
+                        assignments.push((lhs.to_string(), value.to_string()))
+                    }
+                    _ => args.push(word),
+                }
             }
         }
 
@@ -103,32 +280,123 @@ impl SimpleCommand {
             name,
             args,
             assignments,
+            line: 0,
+            quoted,
         })
     }
 }
 
 impl Command {
+    /// Wrap the command so that `redirects` apply to it, if there are any.
+    fn with_redirects(self, redirects: Vec<Redirect>) -> Command {
+        if redirects.is_empty() {
+            self
+        } else {
+            Command::Redirected(Box::new(self), redirects)
+        }
+    }
+
     pub fn parse(input: &str) -> Result<Vec<Vec<Command>>, String> {
-        let lexer = Lexer::new(input.chars());
-        let mut parser = DefaultParser::new(lexer);
+        Self::parse_at(input, 1)
+    }
+
+    /// Like `parse`, for text that starts on line `first_line` of a script,
+    /// so that its commands and errors are numbered as in the script.
+    pub fn parse_at(input: &str, first_line: usize) -> Result<Vec<Vec<Command>>, String> {
+        Self::parse_lines(input, first_line).map_err(|e| e.message)
+    }
+
+    /// Like `parse_at`, keeping the line a syntax error was found on.
+    pub fn parse_lines(input: &str, first_line: usize) -> Result<Vec<Vec<Command>>, SyntaxError> {
+        Self::parse_inner(input, false, first_line).map(|pipelines| pipelines.unwrap_or_default())
+    }
+
+    /// Like `parse_at`, but returns `Ok(None)` when the input stops in the
+    /// middle of a command, i.e. more lines are needed to complete it.
+    fn parse_partial(
+        input: &str,
+        first_line: usize,
+    ) -> Result<Option<Vec<Vec<Command>>>, SyntaxError> {
+        if ends_mid_command(input) {
+            return Ok(None);
+        }
+        Self::parse_inner(input, true, first_line)
+    }
+
+    fn parse_inner(
+        input: &str,
+        partial: bool,
+        first_line: usize,
+    ) -> Result<Option<Vec<Vec<Command>>>, SyntaxError> {
+        let input = rewrite_syntax(input);
+        let start = TokenPlace {
+            line: first_line,
+            bodies: 0,
+        };
+        let place = Rc::new(Cell::new(start));
+        let lines = Rc::new(RefCell::new(Lines::new()));
+        let tokens = LineTokens {
+            tokens: Lexer::new(input.chars()),
+            place: place.clone(),
+            next: start,
+        };
+        let builder = LineBuilder {
+            inner: StringBuilder::default(),
+            place,
+            lines: lines.clone(),
+            first_word: None,
+            previous: None,
+        };
+        let mut parser = Parser::with_builder(tokens, builder);
         let mut pipelines = Vec::new();
 
         loop {
             match parser.complete_command() {
                 Ok(Some(cmd)) => {
-                    let pipeline = Self::process_top_level_command(&cmd);
+                    let pipeline = Self::process_top_level_command(&cmd, &lines.borrow());
+                    lines.borrow_mut().clear();
                     if !pipeline.is_empty() {
                         pipelines.push(pipeline);
                     }
                 }
                 Ok(None) => break,
-                Err(e) => return Err(format!("Parse error: {}", e)),
+                Err(
+                    ParseError::UnexpectedEOF
+                    | ParseError::Unmatched(..)
+                    | ParseError::IncompleteCmd(..),
+                ) if partial => return Ok(None),
+                Err(e) => {
+                    let last_line = first_line + input.trim_end_matches('\n').matches('\n').count();
+                    return Err(SyntaxError::new(e, first_line, last_line));
+                }
             }
         }
 
-        Ok(pipelines)
+        Ok(Some(pipelines))
+    }
+
+    /// Split a script into chunks of whole lines that each hold complete
+    /// commands, so they can be run as they are read, like a shell reading
+    /// its input line by line.
+    pub fn script_chunks(input: &str) -> ScriptChunks<std::str::SplitInclusive<'_, char>> {
+        Self::chunks_from_lines(input.split_inclusive('\n'))
     }
 
+    /// Like `script_chunks`, for input that arrives a line at a time. Each
+    /// line keeps its trailing newline. Lines are only pulled as needed to
+    /// complete the next chunk.
+    pub fn chunks_from_lines<I, S>(lines: I) -> ScriptChunks<I>
+    where
+        I: Iterator<Item = S>,
+        S: AsRef<str>,
+    {
+        ScriptChunks {
+            lines,
+            next_line: 1,
+        }
+    }
+
+    #[allow(dead_code)]
     pub fn parse_pipeline(input: &str) -> Option<Vec<Command>> {
         match Self::parse(input) {
             Ok(mut pipelines) => {
@@ -142,56 +410,73 @@ impl Command {
         }
     }
 
+    #[allow(dead_code)]
     pub fn parse_script(input: &str) -> Result<Vec<Vec<Command>>, String> {
         Self::parse(input)
     }
 
-    fn process_top_level_command(cmd_top_level: &TopLevelCommand<String>) -> Vec<Command> {
-        let mut commands = Vec::new();
+    fn process_top_level_command(
+        cmd_top_level: &TopLevelCommand<String>,
+        lines: &Lines,
+    ) -> Vec<Command> {
         // cmd_top_level.0 is Command<CommandList<String, TopLevelWord<String>, TopLevelCommand<String>>>
         // CommandList is AndOrList<ListableCommand<DefaultPipeableCommand>>
+        match &cmd_top_level.0 {
+            ConchCommand::List(list) => Self::process_and_or(list, lines),
+            ConchCommand::Job(list) => vec![Command::Background(Self::process_and_or(list, lines))],
+        }
+    }
 
-        let command = &cmd_top_level.0;
-        match command {
-            ConchCommand::List(list) => {
-                Self::process_listable(&list.first, &mut commands);
-                for and_or in &list.rest {
-                    match and_or {
-                        ast::AndOr::And(cmd) => Self::process_listable(cmd, &mut commands),
-                        ast::AndOr::Or(cmd) => Self::process_listable(cmd, &mut commands),
-                    }
-                }
-            }
-            ConchCommand::Job(job) => {
-                Self::process_listable(&job.first, &mut commands);
-                for and_or in &job.rest {
-                    match and_or {
-                        ast::AndOr::And(cmd) => Self::process_listable(cmd, &mut commands),
-                        ast::AndOr::Or(cmd) => Self::process_listable(cmd, &mut commands),
-                    }
-                }
+    fn process_command_list(cmds: &[TopLevelCommand<String>], lines: &Lines) -> Vec<Vec<Command>> {
+        let mut pipelines = Vec::new();
+        for top_cmd in cmds {
+            let pipeline = Self::process_top_level_command(top_cmd, lines);
+            if !pipeline.is_empty() {
+                pipelines.push(pipeline);
             }
         }
-        commands
+        pipelines
+    }
+
+    fn process_and_or(
+        list: &AndOrList<ListableCommand<DefaultPipeableCommand>>,
+        lines: &Lines,
+    ) -> Vec<Command> {
+        let first = Self::process_listable(&list.first, lines);
+        if list.rest.is_empty() {
+            return first;
+        }
+
+        let rest = list
+            .rest
+            .iter()
+            .map(|and_or| match and_or {
+                ast::AndOr::And(cmd) => (AndOrOp::And, Self::process_listable(cmd, lines)),
+                ast::AndOr::Or(cmd) => (AndOrOp::Or, Self::process_listable(cmd, lines)),
+            })
+            .collect();
+        vec![Command::AndOr(first, rest)]
     }
 
     fn process_listable(
         listable: &ListableCommand<DefaultPipeableCommand>,
-        commands: &mut Vec<Command>,
-    ) {
+        lines: &Lines,
+    ) -> Vec<Command> {
         match listable {
-            ListableCommand::Pipe(_, cmds) => {
-                for cmd in cmds {
-                    if let Some(c) = Self::extract_from_pipeable(cmd) {
-                        commands.push(c);
-                    }
-                }
-            }
-            ListableCommand::Single(cmd) => {
-                if let Some(c) = Self::extract_from_pipeable(cmd) {
-                    commands.push(c);
+            ListableCommand::Pipe(negated, cmds) => {
+                let commands: Vec<Command> = cmds
+                    .iter()
+                    .filter_map(|cmd| Self::extract_from_pipeable(cmd, lines))
+                    .collect();
+                if *negated {
+                    vec![Command::Not(commands)]
+                } else {
+                    commands
                 }
             }
+            ListableCommand::Single(cmd) => Self::extract_from_pipeable(cmd, lines)
+                .into_iter()
+                .collect(),
         }
     }
 
@@ -199,40 +484,818 @@ impl Command {
     fn process_compound_command(
         compound: &CompoundCommand<
             CompoundCommandKind<String, TopLevelWord<String>, TopLevelCommand<String>>,
-            Redirect<TopLevelWord<String>>,
+            ConchRedirect<TopLevelWord<String>>,
         >,
-    ) -> Option<Vec<Vec<Command>>> {
-        match &compound.kind {
-            CompoundCommandKind::Subshell(cmds) | CompoundCommandKind::Brace(cmds) => {
-                let mut pipelines = Vec::new();
-                for top_cmd in cmds {
-                    let pipeline = Self::process_top_level_command(top_cmd);
-                    if !pipeline.is_empty() {
-                        pipelines.push(pipeline);
-                    }
-                }
-                Some(pipelines)
+        lines: &Lines,
+    ) -> Option<Command> {
+        let redirects = compound
+            .io
+            .iter()
+            .map(SimpleCommand::redirect_from_conch)
+            .collect();
+        let cmd = match &compound.kind {
+            CompoundCommandKind::Subshell(cmds) => {
+                Some(Command::Subshell(Self::process_command_list(cmds, lines)))
             }
-            _ => None,
-        }
+            CompoundCommandKind::Brace(cmds) => {
+                Some(Command::Group(Self::process_command_list(cmds, lines)))
+            }
+            CompoundCommandKind::If {
+                conditionals,
+                else_branch,
+            } => Some(Command::If {
+                conditionals: conditionals
+                    .iter()
+                    .map(|pair| {
+                        (
+                            Self::process_command_list(&pair.guard, lines),
+                            Self::process_command_list(&pair.body, lines),
+                        )
+                    })
+                    .collect(),
+                else_branch: else_branch
+                    .as_ref()
+                    .map(|cmds| Self::process_command_list(cmds, lines)),
+            }),
+            CompoundCommandKind::While(pair) => Some(Command::While(
+                Self::process_command_list(&pair.guard, lines),
+                Self::process_command_list(&pair.body, lines),
+            )),
+            CompoundCommandKind::Until(pair) => Some(Command::Until(
+                Self::process_command_list(&pair.guard, lines),
+                Self::process_command_list(&pair.body, lines),
+            )),
+            CompoundCommandKind::For { var, words, body } => Some(Command::For(
+                var.clone(),
+                words
+                    .as_ref()
+                    .map(|ws| ws.iter().map(SimpleCommand::word_to_string).collect()),
+                Self::process_command_list(body, lines),
+            )),
+            CompoundCommandKind::Case { .. } => None,
+        };
+        cmd.map(|cmd| cmd.with_redirects(redirects))
     }
 
-    fn extract_from_pipeable(cmd: &DefaultPipeableCommand) -> Option<Command> {
+    fn extract_from_pipeable(cmd: &DefaultPipeableCommand, lines: &Lines) -> Option<Command> {
         match cmd {
             PipeableCommand::Simple(simple_cmd) => {
-                SimpleCommand::simple_command_to_command(simple_cmd.as_ref()).map(Command::Simple)
+                let line = lines.get(&(simple_cmd.as_ref() as *const _)).copied();
+                SimpleCommand::simple_command_to_command(simple_cmd.as_ref(), line.unwrap_or(0))
             }
-            PipeableCommand::Compound(compound) => match &compound.kind {
-                CompoundCommandKind::Subshell(_) => {
-                    Self::process_compound_command(compound.as_ref()).map(Command::Subshell)
-                }
-                _ => None,
-            },
+            PipeableCommand::Compound(compound) => Self::process_compound_command(compound, lines),
             PipeableCommand::FunctionDef(name, body) => {
-                Self::process_compound_command(body.as_ref())
-                    .map(|cmds| Command::FunctionDef(name.clone(), cmds))
+                // A brace body runs in the caller's shell, so keep just its list;
+                // any other compound body (e.g. a subshell) is kept whole.
+                match Self::process_compound_command(body.as_ref(), lines)? {
+                    Command::Group(cmds) => Some(Command::FunctionDef(name.clone(), cmds)),
+                    other => Some(Command::FunctionDef(name.clone(), vec![vec![other]])),
+                }
+            }
+        }
+    }
+}
+
+/// A piece of a script made of whole lines.
+pub struct ScriptChunk {
+    /// The source text, as echoed by `set -v`
+    pub text: String,
+    /// Line number (1-based) the chunk starts on
+    pub line: usize,
+    pub commands: Result<Vec<Vec<Command>>, SyntaxError>,
+}
+
+/// Input that does not parse.
+#[derive(Debug)]
+pub struct SyntaxError {
+    /// Line the problem was found on
+    pub line: usize,
+    pub message: String,
+}
+
+impl SyntaxError {
+    /// Describe `error`, found in text that starts on `first_line` and ends
+    /// on `last_line`, with positions counted from the start of the script.
+    fn new<T: std::fmt::Display>(
+        error: ParseError<T>,
+        first_line: usize,
+        last_line: usize,
+    ) -> Self {
+        let shift = |pos: SourcePos| SourcePos {
+            line: pos.line + first_line - 1,
+            ..pos
+        };
+        let error = match error {
+            ParseError::BadFd(start, end) => ParseError::BadFd(shift(start), shift(end)),
+            ParseError::BadIdent(name, pos) => ParseError::BadIdent(name, shift(pos)),
+            ParseError::BadSubst(token, pos) => ParseError::BadSubst(token, shift(pos)),
+            ParseError::Unmatched(token, pos) => ParseError::Unmatched(token, shift(pos)),
+            ParseError::IncompleteCmd(cmd, start, keyword, pos) => {
+                ParseError::IncompleteCmd(cmd, shift(start), keyword, shift(pos))
+            }
+            ParseError::Unexpected(token, pos) => ParseError::Unexpected(token, shift(pos)),
+            other => other,
+        };
+        let line = match &error {
+            ParseError::BadFd(pos, _)
+            | ParseError::BadIdent(_, pos)
+            | ParseError::BadSubst(_, pos)
+            | ParseError::Unmatched(_, pos)
+            | ParseError::IncompleteCmd(_, _, _, pos)
+            | ParseError::Unexpected(_, pos) => pos.line,
+            ParseError::UnexpectedEOF | ParseError::Custom(_) => last_line,
+        };
+        SyntaxError {
+            line,
+            message: format!("Parse error: {}", error),
+        }
+    }
+}
+
+pub struct ScriptChunks<I> {
+    lines: I,
+    next_line: usize,
+}
+
+impl<I, S> Iterator for ScriptChunks<I>
+where
+    I: Iterator<Item = S>,
+    S: AsRef<str>,
+{
+    type Item = ScriptChunk;
+
+    fn next(&mut self) -> Option<ScriptChunk> {
+        let line = self.next_line;
+        let mut text = String::new();
+        for source_line in self.lines.by_ref() {
+            self.next_line += 1;
+            text.push_str(source_line.as_ref());
+            match Command::parse_partial(&text, line) {
+                Ok(None) => continue,
+                commands => {
+                    return Some(ScriptChunk {
+                        commands: commands.map(Option::unwrap_or_default),
+                        text,
+                        line,
+                    })
+                }
+            }
+        }
+
+        // The script ended mid-command; a full parse reports why
+        if text.is_empty() {
+            return None;
+        }
+        Some(ScriptChunk {
+            commands: Command::parse_lines(&text, line),
+            text,
+            line,
+        })
+    }
+}
+
+/// Split `input` into runs of whole lines, telling the bodies of
+/// here-documents, delimiter lines included, apart from the lines that hold
+/// commands. Returns each run and whether it is a body.
+pub fn split_heredoc_bodies(input: &str) -> Vec<(&str, bool)> {
+    let mut runs = Vec::new();
+    let mut pending: std::collections::VecDeque<Heredoc> = Default::default();
+    let mut start = 0;
+    let mut offset = 0;
+    let mut in_body = false;
+    for line in input.split_inclusive('\n') {
+        if pending.is_empty() == in_body {
+            if offset > start {
+                runs.push((&input[start..offset], in_body));
+            }
+            start = offset;
+            in_body = !in_body;
+        }
+        match pending.front() {
+            Some(heredoc) => {
+                if heredoc.ends_at(line.trim_end_matches('\n')) {
+                    pending.pop_front();
+                }
+            }
+            None => pending.extend(heredoc_delimiters(line)),
+        }
+        offset += line.len();
+    }
+    if offset > start {
+        runs.push((&input[start..], in_body));
+    }
+    runs
+}
+
+/// Returns true if `input` ends in a way the parser would accept but that
+/// still needs more lines: after a backslash-newline, or inside the body of
+/// a here-document.
+fn ends_mid_command(input: &str) -> bool {
+    let trailing_backslashes = input
+        .trim_end_matches('\n')
+        .chars()
+        .rev()
+        .take_while(|&c| c == '\\')
+        .count();
+    if input.ends_with('\n') && trailing_backslashes % 2 == 1 {
+        return true;
+    }
+
+    // Here-documents whose bodies have not ended yet
+    let mut pending: std::collections::VecDeque<Heredoc> = Default::default();
+    for line in input.lines() {
+        if let Some(heredoc) = pending.front() {
+            if heredoc.ends_at(line) {
+                pending.pop_front();
+            }
+            continue;
+        }
+        pending.extend(heredoc_delimiters(line));
+    }
+    !pending.is_empty()
+}
+
+/// Rewrite the syntax conch-parser does not know into text it accepts:
+///
+/// - `name=(a "b c")` and `name+=(...)` become `name=` followed by
+///   `LITERAL_START` and the elements separated by `ELEMENT_SEP`, one
+///   word that `Variables::assign` takes apart again
+/// - `${name[...]}`, `${!name...}` and `${#name...}` get their `$`
+///   escaped, so they reach `Variables::expand` as literal text; unquoted
+///   ones that expand to a whole array are preceded by `SPLIT_MARK`
+/// - inside `[[ ... ]]`, the operators `&&`, `||`, `(`, `)`, `<` and `>`
+///   are escaped so the whole conditional is one simple command, and
+///   quoted characters in patterns and regexes are escaped to keep them
+///   literal once the quotes are gone; double-quoted parts of a regex,
+///   which may expand to anything, are marked with `QUOTED_START` and
+///   `QUOTED_END` instead
+///
+/// Quoting is respected. In here-document bodies only the `${...}` forms
+/// are escaped, and only if the delimiter is unquoted. An array literal or
+/// conditional that is not closed is left as it is.
+fn rewrite_syntax(input: &str) -> std::borrow::Cow<'_, str> {
+    if !input.contains("=(") && !input.contains("${") && !input.contains("[[") {
+        return input.into();
+    }
+    let mut out = String::with_capacity(input.len());
+    let mut pending_heredocs: std::collections::VecDeque<Heredoc> = Default::default();
+    let mut quote: Option<char> = None;
+    let mut word_start = true;
+    let mut literal: Option<ArrayLiteral> = None;
+    let mut conditional: Option<Conditional> = None;
+    let mut offset = 0;
+
+    for line in input.split_inclusive('\n') {
+        let line_offset = offset;
+        offset += line.len();
+        if let Some(heredoc) = pending_heredocs.front() {
+            if heredoc.ends_at(line.trim_end_matches('\n')) {
+                pending_heredocs.pop_front();
+                out.push_str(line);
+            } else if heredoc.quoted {
+                out.push_str(line);
+            } else {
+                out.push_str(&escape_heredoc_line(line));
+            }
+            continue;
+        }
+
+        let mut chars = line.char_indices();
+        while let Some((i, c)) = chars.next() {
+            let mut piece = String::new();
+            match c {
+                '\\' if quote != Some('\'') => {
+                    piece.push(c);
+                    piece.extend(chars.next().map(|(_, next)| next));
+                }
+                '\'' | '"' if quote.is_none() => {
+                    quote = Some(c);
+                    piece.push(c);
+                    if c == '"' && conditional.as_ref().is_some_and(|c| c.is_regex()) {
+                        piece.push(QUOTED_START);
+                    }
+                }
+                _ if quote == Some(c) => {
+                    quote = None;
+                    if c == '"' && conditional.as_ref().is_some_and(|c| c.is_regex()) {
+                        piece.push(QUOTED_END);
+                    }
+                    piece.push(c);
+                }
+                '$' if quote != Some('\'') && is_array_reference(&line[i + 1..]) => {
+                    if quote.is_none() && is_list_reference(&line[i + 1..]) {
+                        piece.push(SPLIT_MARK);
+                    }
+                    piece.push_str("\\$");
+                }
+                _ if quote.is_some() => {
+                    // Double-quoted regex text is escaped once expanded
+                    let marked =
+                        quote == Some('"') && conditional.as_ref().is_some_and(|c| c.is_regex());
+                    if !marked && conditional.as_ref().is_some_and(|c| c.is_pattern()) {
+                        // Within double quotes, only these can be escaped
+                        let escapable = quote == Some('\'') || !matches!(c, '$' | '`' | '"' | '\\');
+                        let special = match conditional.as_ref().unwrap().previous.as_str() {
+                            "=~" => ".^$*+?()[]{}|\\".contains(c),
+                            _ => "*?[]\\".contains(c),
+                        };
+                        if escapable && special {
+                            piece.push('\\');
+                        }
+                    }
+                    piece.push(c);
+                }
+                '[' if word_start
+                    && literal.is_none()
+                    && conditional.is_none()
+                    && is_conditional_start(&line[i..]) =>
+                {
+                    conditional = Some(Conditional {
+                        out_len: out.len(),
+                        input_start: line_offset + i,
+                        previous: String::new(),
+                        current: String::new(),
+                    });
+                    out.push_str("[[");
+                    chars.next();
+                    word_start = false;
+                    continue;
+                }
+                ']' if word_start && conditional.is_some() && is_conditional_end(&line[i..]) => {
+                    conditional = None;
+                    out.push_str("]]");
+                    chars.next();
+                    word_start = false;
+                    continue;
+                }
+                '(' | ')' | '<' | '>' | '&' | '|' if conditional.is_some() => {
+                    piece.push('\\');
+                    piece.push(c);
+                }
+                '#' if word_start => {
+                    // A comment runs to the end of the line; inside an
+                    // array literal it is dropped along with the newline
+                    if literal.is_none() {
+                        out.push_str(&line[i..]);
+                    }
+                    break;
+                }
+                ')' if literal.is_some() => {
+                    let mut finished = literal.take().unwrap();
+                    finished.end_element();
+                    out.push(LITERAL_START);
+                    out.push_str(&finished.elements.join(&ELEMENT_SEP.to_string()));
+                    word_start = false;
+                    continue;
+                }
+                _ if c.is_whitespace() && literal.is_some() => {
+                    literal.as_mut().unwrap().end_element();
+                    word_start = true;
+                    continue;
+                }
+                _ if word_start && literal.is_none() => match literal_start(&line[i..]) {
+                    Some(len) => {
+                        literal = Some(ArrayLiteral {
+                            out_len: out.len(),
+                            input_start: line_offset + i,
+                            elements: Vec::new(),
+                            current: String::new(),
+                        });
+                        // Keep `name=` or `name+=`, drop the `(`
+                        out.push_str(&line[i..i + len - 1]);
+                        chars.nth(len - 2);
+                        continue;
+                    }
+                    None => piece.push(c),
+                },
+                _ => piece.push(c),
+            }
+            if let Some(conditional) = conditional.as_mut() {
+                if quote.is_none() && c.is_whitespace() {
+                    conditional.end_word();
+                } else if !matches!(c, '\'' | '"') {
+                    conditional.current.push(c);
+                }
+            }
+            word_start =
+                quote.is_none() && (c.is_whitespace() || matches!(c, ';' | '&' | '|' | '(' | ')'));
+            match literal.as_mut() {
+                Some(literal) => literal.current.push_str(&piece),
+                None => out.push_str(&piece),
             }
         }
+        if quote.is_none() && literal.is_none() {
+            pending_heredocs.extend(heredoc_delimiters(line));
+        }
+    }
+    if let Some(unclosed) = literal {
+        out.truncate(unclosed.out_len);
+        out.push_str(&input[unclosed.input_start..]);
+    } else if let Some(unclosed) = conditional {
+        out.truncate(unclosed.out_len);
+        out.push_str(&input[unclosed.input_start..]);
+    }
+    out.into()
+}
+
+/// A `[[ ... ]]` being read by `rewrite_syntax`.
+struct Conditional {
+    /// Length of the output before the conditional, and where it starts
+    /// in the input, to put it back if it is not closed
+    out_len: usize,
+    input_start: usize,
+    /// The previous word and the one being read, without quotes
+    previous: String,
+    current: String,
+}
+
+impl Conditional {
+    fn end_word(&mut self) {
+        if !self.current.is_empty() {
+            self.previous = std::mem::take(&mut self.current);
+        }
+    }
+
+    /// Returns true if the word being read is a pattern or a regex.
+    fn is_pattern(&self) -> bool {
+        matches!(self.previous.as_str(), "=" | "==" | "!=" | "=~")
+    }
+
+    /// Returns true if the word being read is the regex of `=~`.
+    fn is_regex(&self) -> bool {
+        self.previous == "=~"
+    }
+}
+
+/// Returns true if `text` starts with the `[[` of a conditional.
+fn is_conditional_start(text: &str) -> bool {
+    text.strip_prefix("[[")
+        .is_some_and(|rest| rest.starts_with(char::is_whitespace))
+}
+
+/// Returns true if `text` starts with the `]]` that ends a conditional.
+fn is_conditional_end(text: &str) -> bool {
+    text.strip_prefix("]]").is_some_and(|rest| {
+        rest.is_empty() || rest.starts_with(|c: char| c.is_whitespace() || ";&|)".contains(c))
+    })
+}
+
+/// An array literal being read by `rewrite_syntax`.
+struct ArrayLiteral {
+    /// Length of the output before the literal, and where it starts in
+    /// the input, to put it back if it is not closed
+    out_len: usize,
+    input_start: usize,
+    elements: Vec<String>,
+    current: String,
+}
+
+impl ArrayLiteral {
+    fn end_element(&mut self) {
+        if !self.current.is_empty() {
+            self.elements.push(std::mem::take(&mut self.current));
+        }
+    }
+}
+
+/// If `text` starts with `name=(` or `name+=(`, returns the length of that.
+fn literal_start(text: &str) -> Option<usize> {
+    let (lhs, _) = text.split_once("=(")?;
+    let name = lhs.strip_suffix('+').unwrap_or(lhs);
+    arrays::is_name(name).then_some(lhs.len() + 2)
+}
+
+/// Returns true if the text after a `$` is a `${...}` form that needs
+/// arrays: an element or all elements, the indices, or a length.
+fn is_array_reference(after_dollar: &str) -> bool {
+    let Some(inner) = after_dollar.strip_prefix('{') else {
+        return false;
+    };
+    inner.starts_with(['!', '#']) || inner.split('}').next().is_some_and(|c| c.contains('['))
+}
+
+/// Drop the `\\` that `rewrite_syntax` puts before the `$` of
+/// `${name[...]}` and the like, from a word as it was written.
+fn unescape_array_references(word: &str) -> String {
+    let mut out = String::with_capacity(word.len());
+    let mut chars = word.char_indices();
+    while let Some((i, c)) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some((_, '$')) if is_array_reference(&word[i + 2..]) => out.push('$'),
+            Some((_, next)) => {
+                out.push(c);
+                out.push(next);
+            }
+            None => out.push(c),
+        }
+    }
+    out
+}
+
+/// Returns true if the `${...}` after a `$` expands to all the elements
+/// or keys of an array, `${name[@]}`, `${!name[*]}` and the like.
+fn is_list_reference(after_dollar: &str) -> bool {
+    let content = after_dollar[1..].split('}').next().unwrap_or_default();
+    !content.starts_with('#') && (content.ends_with("[@]") || content.ends_with("[*]"))
+}
+
+/// Escape the `$` of `${name[...]}` and the like in a line of an unquoted
+/// here-document body, as `rewrite_syntax` does outside it. Bodies are not
+/// split into fields, so no `SPLIT_MARK` is added.
+fn escape_heredoc_line(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                out.push(c);
+                out.extend(chars.next().map(|(_, next)| next));
+            }
+            '$' if is_array_reference(&line[i + 1..]) => out.push_str("\\$"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// A here-document whose body has not been read yet.
+struct Heredoc {
+    delimiter: String,
+    /// `<<-`: leading tabs are stripped from the body
+    strip_tabs: bool,
+    /// Part of the delimiter was quoted, so the body is not expanded
+    quoted: bool,
+}
+
+impl Heredoc {
+    /// Returns true if `line` (without its newline) ends the body.
+    fn ends_at(&self, line: &str) -> bool {
+        let candidate = if self.strip_tabs {
+            line.trim_start_matches('\t')
+        } else {
+            line
+        };
+        candidate == self.delimiter
+    }
+}
+
+/// Find the here-documents started on `line`.
+fn heredoc_delimiters(line: &str) -> Vec<Heredoc> {
+    let mut delimiters = Vec::new();
+    let mut rest = line;
+    while let Some(pos) = rest.find("<<") {
+        rest = &rest[pos + 2..];
+        // `<<<` is a here-string, not a here-document
+        if let Some(after) = rest.strip_prefix('<') {
+            rest = after;
+            continue;
+        }
+        let strip_tabs = rest.starts_with('-');
+        if strip_tabs {
+            rest = &rest[1..];
+        }
+        rest = rest.trim_start_matches([' ', '\t']);
+        let end = rest
+            .find(|c: char| c.is_whitespace() || matches!(c, ';' | '&' | '|' | '<' | '>' | ')'))
+            .unwrap_or(rest.len());
+        let word = &rest[..end];
+        let delimiter: String = word
+            .chars()
+            .filter(|c| !matches!(c, '\'' | '"' | '\\'))
+            .collect();
+        rest = &rest[end..];
+        if !delimiter.is_empty() {
+            delimiters.push(Heredoc {
+                quoted: delimiter.len() != word.len(),
+                delimiter,
+                strip_tabs,
+            });
+        }
+    }
+    delimiters
+}
+
+/// Simple commands of a parse, by address, and the lines they start on.
+type Lines = HashMap<*const ast::DefaultSimpleCommand, usize>;
+
+/// How far `LineTokens` has got: the line of the last token it passed on,
+/// and how many tokens that start a body came before that token.
+#[derive(Debug, Clone, Copy)]
+struct TokenPlace {
+    line: usize,
+    bodies: usize,
+}
+
+/// Passes the lexer's tokens on to the parser, keeping track of where they
+/// are for `LineBuilder`.
+struct LineTokens<I> {
+    tokens: I,
+    place: Rc<Cell<TokenPlace>>,
+    next: TokenPlace,
+}
+
+impl<I: Iterator<Item = Token>> Iterator for LineTokens<I> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.next()?;
+        self.place.set(self.next);
+        self.next.line += token.as_str().matches('\n').count();
+        // The words of a `for` loop or `case` are read right before the
+        // first command of the body, which starts after `do` or `)`
+        if token == Token::ParenClose || token == Token::Name("do".to_string()) {
+            self.next.bodies += 1;
+        }
+        Some(token)
+    }
+}
+
+/// Builds the same AST as conch-parser's `StringBuilder`, noting the line
+/// each simple command starts on in `lines`. That is the line of its first
+/// word: the parser has only looked one token past a word when it builds
+/// it, and builds the words of a command before anything else.
+struct LineBuilder {
+    inner: StringBuilder,
+    place: Rc<Cell<TokenPlace>>,
+    lines: Rc<RefCell<Lines>>,
+    /// Where the first word of the command being read was
+    first_word: Option<TokenPlace>,
+    /// `first_word` before the last word was built
+    previous: Option<TokenPlace>,
+}
+
+impl LineBuilder {
+    /// Forget the words read so far, which were part of something else.
+    fn built(&mut self) {
+        self.first_word = None;
+    }
+}
+
+impl Builder for LineBuilder {
+    type Command = <StringBuilder as Builder>::Command;
+    type CommandList = <StringBuilder as Builder>::CommandList;
+    type ListableCommand = <StringBuilder as Builder>::ListableCommand;
+    type PipeableCommand = <StringBuilder as Builder>::PipeableCommand;
+    type CompoundCommand = <StringBuilder as Builder>::CompoundCommand;
+    type Word = <StringBuilder as Builder>::Word;
+    type Redirect = <StringBuilder as Builder>::Redirect;
+    type Error = <StringBuilder as Builder>::Error;
+
+    fn complete_command(
+        &mut self,
+        pre_cmd_comments: Vec<build::Newline>,
+        list: Self::CommandList,
+        separator: build::SeparatorKind,
+        cmd_comment: Option<build::Newline>,
+    ) -> Result<Self::Command, Self::Error> {
+        self.built();
+        self.inner
+            .complete_command(pre_cmd_comments, list, separator, cmd_comment)
+    }
+
+    fn and_or_list(
+        &mut self,
+        first: Self::ListableCommand,
+        rest: Vec<(Vec<build::Newline>, ast::AndOr<Self::ListableCommand>)>,
+    ) -> Result<Self::CommandList, Self::Error> {
+        self.built();
+        self.inner.and_or_list(first, rest)
+    }
+
+    fn pipeline(
+        &mut self,
+        bang: bool,
+        cmds: Vec<(Vec<build::Newline>, Self::PipeableCommand)>,
+    ) -> Result<Self::ListableCommand, Self::Error> {
+        self.built();
+        self.inner.pipeline(bang, cmds)
+    }
+
+    fn simple_command(
+        &mut self,
+        redirects_or_env_vars: Vec<ast::RedirectOrEnvVar<Self::Redirect, String, Self::Word>>,
+        redirects_or_cmd_words: Vec<ast::RedirectOrCmdWord<Self::Redirect, Self::Word>>,
+    ) -> Result<Self::PipeableCommand, Self::Error> {
+        // A lone `name=` has no word
+        let line = self.first_word.take().unwrap_or(self.place.get()).line;
+        let cmd = self
+            .inner
+            .simple_command(redirects_or_env_vars, redirects_or_cmd_words)?;
+        if let PipeableCommand::Simple(simple) = &cmd {
+            self.lines
+                .borrow_mut()
+                .insert(simple.as_ref() as *const _, line);
+        }
+        Ok(cmd)
+    }
+
+    fn brace_group(
+        &mut self,
+        cmds: build::CommandGroup<Self::Command>,
+        redirects: Vec<Self::Redirect>,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.built();
+        self.inner.brace_group(cmds, redirects)
+    }
+
+    fn subshell(
+        &mut self,
+        cmds: build::CommandGroup<Self::Command>,
+        redirects: Vec<Self::Redirect>,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.built();
+        self.inner.subshell(cmds, redirects)
+    }
+
+    fn loop_command(
+        &mut self,
+        kind: build::LoopKind,
+        guard_body_pair: build::GuardBodyPairGroup<Self::Command>,
+        redirects: Vec<Self::Redirect>,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.built();
+        self.inner.loop_command(kind, guard_body_pair, redirects)
+    }
+
+    fn if_command(
+        &mut self,
+        fragments: build::IfFragments<Self::Command>,
+        redirects: Vec<Self::Redirect>,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.built();
+        self.inner.if_command(fragments, redirects)
+    }
+
+    fn for_command(
+        &mut self,
+        fragments: build::ForFragments<Self::Word, Self::Command>,
+        redirects: Vec<Self::Redirect>,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.built();
+        self.inner.for_command(fragments, redirects)
+    }
+
+    fn case_command(
+        &mut self,
+        fragments: build::CaseFragments<Self::Word, Self::Command>,
+        redirects: Vec<Self::Redirect>,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        self.built();
+        self.inner.case_command(fragments, redirects)
+    }
+
+    fn compound_command_into_pipeable(
+        &mut self,
+        cmd: Self::CompoundCommand,
+    ) -> Result<Self::PipeableCommand, Self::Error> {
+        self.built();
+        self.inner.compound_command_into_pipeable(cmd)
+    }
+
+    fn function_declaration(
+        &mut self,
+        name: String,
+        post_name_comments: Vec<build::Newline>,
+        body: Self::CompoundCommand,
+    ) -> Result<Self::PipeableCommand, Self::Error> {
+        self.built();
+        self.inner
+            .function_declaration(name, post_name_comments, body)
+    }
+
+    fn comments(&mut self, comments: Vec<build::Newline>) -> Result<(), Self::Error> {
+        self.inner.comments(comments)
+    }
+
+    fn word(
+        &mut self,
+        kind: build::ComplexWordKind<Self::Command>,
+    ) -> Result<Self::Word, Self::Error> {
+        // Words read before a body started belong to the loop or `case`
+        let place = self.place.get();
+        self.previous = self.first_word;
+        if self
+            .first_word
+            .is_none_or(|first| first.bodies != place.bodies)
+        {
+            self.first_word = Some(place);
+        }
+        self.inner.word(kind)
+    }
+
+    fn redirect(
+        &mut self,
+        kind: build::RedirectKind<Self::Word>,
+    ) -> Result<Self::Redirect, Self::Error> {
+        // A here-document's body is built once the parser has read past it
+        if let build::RedirectKind::Heredoc(..) = kind {
+            self.first_word = self.previous;
+        }
+        self.inner.redirect(kind)
     }
 }
 
@@ -374,6 +1437,262 @@ mod tests {
         assert_eq!(cmd.args, vec!["*", "?", "[", "]", "~", ":"]);
     }
 
+    #[test]
+    fn test_parse_and_or_list() {
+        let result = Command::parse("true && echo yes || ! false").unwrap();
+        assert_eq!(result.len(), 1);
+        match &result[0][0] {
+            Command::AndOr(first, rest) => {
+                assert_eq!(first.len(), 1);
+                assert_eq!(rest.len(), 2);
+                assert_eq!(rest[0].0, AndOrOp::And);
+                assert_eq!(rest[1].0, AndOrOp::Or);
+                assert!(matches!(rest[1].1[0], Command::Not(_)));
+            }
+            other => panic!("Expected AndOr command, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_compound_commands() {
+        let result = Command::parse(
+            "if a; then b; elif c; then d; else e; fi\n\
+             while a; do b; done\n\
+             until a; do b; done\n\
+             for x in 1 2; do echo $x; done\n\
+             for y; do :; done\n\
+             { a; b; }",
+        )
+        .unwrap();
+        assert_eq!(result.len(), 6);
+        match &result[0][0] {
+            Command::If {
+                conditionals,
+                else_branch,
+            } => {
+                assert_eq!(conditionals.len(), 2);
+                assert!(else_branch.is_some());
+            }
+            other => panic!("Expected If command, got {:?}", other),
+        }
+        assert!(matches!(result[1][0], Command::While(..)));
+        assert!(matches!(result[2][0], Command::Until(..)));
+        match &result[3][0] {
+            Command::For(var, words, body) => {
+                assert_eq!(var, "x");
+                assert_eq!(
+                    words.as_deref(),
+                    Some(&["1".to_string(), "2".to_string()][..])
+                );
+                assert_eq!(body.len(), 1);
+            }
+            other => panic!("Expected For command, got {:?}", other),
+        }
+        assert!(matches!(&result[4][0], Command::For(_, None, _)));
+        assert!(matches!(&result[5][0], Command::Group(cmds) if cmds.len() == 2));
+    }
+
+    #[test]
+    fn test_parse_redirects() {
+        let result = Command::parse("cat < in 2>&1 >| out\n> empty\n{ echo; } >> log").unwrap();
+        match &result[0][0] {
+            Command::Redirected(inner, redirects) => {
+                assert!(matches!(inner.as_ref(), Command::Simple(c) if c.name == "cat"));
+                let kinds: Vec<(i32, RedirectKind, &str)> = redirects
+                    .iter()
+                    .map(|r| (r.fd, r.kind, r.target.as_str()))
+                    .collect();
+                assert_eq!(
+                    kinds,
+                    vec![
+                        (0, RedirectKind::Read, "in"),
+                        (2, RedirectKind::DupWrite, "1"),
+                        (1, RedirectKind::Clobber, "out"),
+                    ]
+                );
+            }
+            other => panic!("Expected Redirected command, got {:?}", other),
+        }
+        // A redirection alone still produces a command
+        assert!(
+            matches!(&result[1][0], Command::Redirected(inner, _) if matches!(inner.as_ref(), Command::Simple(c) if c.name.is_empty()))
+        );
+        assert!(
+            matches!(&result[2][0], Command::Redirected(inner, r) if matches!(inner.as_ref(), Command::Group(_)) && r[0].kind == RedirectKind::Append)
+        );
+    }
+
+    #[test]
+    fn test_parse_partial_detects_incomplete_input() {
+        assert!(Command::parse_partial("if true; then\n", 1)
+            .unwrap()
+            .is_none());
+        assert!(Command::parse_partial("echo a &&\n", 1).unwrap().is_none());
+        assert!(Command::parse_partial("echo \"open\n", 1)
+            .unwrap()
+            .is_none());
+        assert!(Command::parse_partial("echo a \\\n", 1).unwrap().is_none());
+        assert!(Command::parse_partial("cat <<EOF\nbody\n", 1)
+            .unwrap()
+            .is_none());
+        assert!(Command::parse_partial("cat <<-'EOF'\nbody\n\tEOF\n", 1)
+            .unwrap()
+            .is_some());
+        assert!(Command::parse_partial("echo done\n", 1).unwrap().is_some());
+        assert!(Command::parse_partial("echo )\n", 1).is_err());
+    }
+
+    #[test]
+    fn test_split_heredoc_bodies() {
+        let input = "cat <<EOF\nll\nEOF\nll\ncat <<-'A' <<B\n\tA\nx\nB\n";
+        assert_eq!(
+            split_heredoc_bodies(input),
+            vec![
+                ("cat <<EOF\n", false),
+                ("ll\nEOF\n", true),
+                ("ll\ncat <<-'A' <<B\n", false),
+                ("\tA\nx\nB\n", true),
+            ]
+        );
+        assert_eq!(split_heredoc_bodies("ll"), vec![("ll", false)]);
+    }
+
+    #[test]
+    fn test_script_chunks() {
+        let script = "# comment\necho one\nf() {\n  echo two\n}\ncat <<EOF\nx\nEOF\nif true\n";
+        let chunks: Vec<ScriptChunk> = Command::script_chunks(script).collect();
+        let starts: Vec<usize> = chunks.iter().map(|c| c.line).collect();
+        assert_eq!(starts, vec![1, 2, 3, 6, 9]);
+        assert_eq!(chunks[2].text, "f() {\n  echo two\n}\n");
+        assert!(chunks[0].commands.as_ref().unwrap().is_empty());
+        assert!(matches!(
+            &chunks[2].commands.as_ref().unwrap()[0][0],
+            Command::FunctionDef(..)
+        ));
+        // The unfinished `if` at the end is reported as a parse error
+        assert!(chunks[4].commands.is_err());
+    }
+
+    #[test]
+    fn test_commands_know_their_lines() {
+        let script = "echo one\nif true; then\n  echo two \\\n    more\n  [[ x ]]\nfi\n";
+        let pipelines = Command::parse_at(script, 10).unwrap();
+        assert!(matches!(&pipelines[0][0], Command::Simple(c) if c.line == 10));
+        let Command::If { conditionals, .. } = &pipelines[1][0] else {
+            panic!("expected an if, got {:?}", pipelines[1][0]);
+        };
+        let (guard, body) = &conditionals[0];
+        assert!(matches!(&guard[0][0], Command::Simple(c) if c.line == 11));
+        assert!(matches!(&body[0][0], Command::Simple(c) if c.line == 12));
+        assert!(matches!(&body[1][0], Command::Conditional(_, 14)));
+
+        // Errors are located in the script, not the chunk
+        let chunks: Vec<ScriptChunk> =
+            Command::script_chunks("echo\nif true; then\n  )\nfi\n").collect();
+        let error = chunks[1].commands.as_ref().unwrap_err();
+        assert_eq!(error.line, 3);
+        assert!(error.message.contains("line 3:3"), "{}", error.message);
+    }
+
+    #[test]
+    fn test_parse_background() {
+        let result = Command::parse("sleep 1 | cat & echo next").unwrap();
+        assert_eq!(result.len(), 2);
+        match &result[0][0] {
+            Command::Background(pipeline) => assert_eq!(pipeline.len(), 2),
+            other => panic!("Expected background pipeline, got {:?}", other),
+        }
+        assert!(matches!(&result[1][0], Command::Simple(_)));
+    }
+
+    #[test]
+    fn test_rewrite_arrays() {
+        assert_eq!(
+            rewrite_syntax("a=(x \"y z\" # c\n w) b+=()"),
+            "a=\u{1e}x\u{1f}\"y z\"\u{1f}w b+=\u{1e}"
+        );
+        assert_eq!(
+            rewrite_syntax("echo \"${a[@]}\" '${a[0]}' ${#a} ${x}"),
+            "echo \"\\${a[@]}\" '${a[0]}' \\${#a} ${x}"
+        );
+        assert_eq!(
+            rewrite_syntax("echo ${a[*]} ${#a[@]} ${!a[@]}"),
+            format!(
+                "echo {m}\\${{a[*]}} \\${{#a[@]}} {m}\\${{!a[@]}}",
+                m = SPLIT_MARK
+            )
+        );
+        // Unquoted here-document bodies only get their references escaped;
+        // quoted ones and unclosed literals are left alone
+        assert_eq!(
+            rewrite_syntax("cat <<EOF\na=(x) ${a[1]} \\${a[0]}\nEOF\n"),
+            "cat <<EOF\na=(x) \\${a[1]} \\${a[0]}\nEOF\n"
+        );
+        let quoted = "cat <<'EOF'\n${a[1]}\nEOF\n";
+        assert_eq!(rewrite_syntax(quoted), quoted);
+        assert_eq!(rewrite_syntax("a=(x\n"), "a=(x\n");
+    }
+
+    #[test]
+    fn test_rewrite_conditionals() {
+        assert_eq!(
+            rewrite_syntax("[[ ( a || b ) && $x < y ]]; echo [[x]]"),
+            "[[ \\( a \\|\\| b \\) \\&\\& $x \\< y ]]; echo [[x]]"
+        );
+        // Quoted pattern and regex characters stay literal
+        assert_eq!(
+            rewrite_syntax("[[ \"*\" == \"*\"* && $f =~ '.'$ ]]"),
+            "[[ \"*\" == \"\\*\"* \\&\\& $f =~ '\\.'$ ]]"
+        );
+        assert_eq!(
+            rewrite_syntax("[[ $x =~ ^\"$re.\"$ ]]"),
+            format!("[[ $x =~ ^\"{}$re.{}\"$ ]]", QUOTED_START, QUOTED_END)
+        );
+        assert_eq!(rewrite_syntax("[[ a &&\n"), "[[ a &&\n");
+    }
+
+    #[test]
+    fn test_parse_conditional() {
+        let pipelines = Command::parse("[[ $a == *.txt || -d $a ]] && echo yes").unwrap();
+        match &pipelines[0][0] {
+            Command::AndOr(first, _) => assert_eq!(
+                first[0],
+                Command::Conditional(
+                    vec!["$a", "==", "*.txt", "||", "-d", "$a"]
+                        .into_iter()
+                        .map(String::from)
+                        .collect(),
+                    1
+                )
+            ),
+            other => panic!("expected an and-or list, got {:?}", other),
+        }
+        let pipelines = Command::parse("[[ x =~ ^(a|b)$ ]]").unwrap();
+        assert_eq!(
+            pipelines[0][0],
+            Command::Conditional(vec!["x".into(), "=~".into(), "^(a|b)$".into()], 1)
+        );
+    }
+
+    #[test]
+    fn test_parse_array_assignments() {
+        let result = Command::parse("a=(1 2) a[3]=x b+=y echo a[1]=z").unwrap();
+        let cmd = match &result[0][0] {
+            Command::Simple(c) => c,
+            _ => panic!("Expected simple command"),
+        };
+        assert_eq!(
+            cmd.assignments,
+            vec![
+                ("a".to_string(), "\u{1e}1\u{1f}2".to_string()),
+                ("a[3]".to_string(), "x".to_string()),
+                ("b+".to_string(), "y".to_string()),
+            ]
+        );
+        assert_eq!(cmd.name, "echo");
+        assert_eq!(cmd.args, vec!["a[1]=z"]);
+    }
+
     #[test]
     fn test_parse_error() {
         let input = "if foo"; // Invalid, missing 'then'
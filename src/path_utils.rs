@@ -21,6 +21,23 @@ pub fn collapse_tilde(path: &str) -> PathBuf {
     }
 }
 
+/// Remove `.` and `..` components from an absolute path without looking
+/// at the file system, as `cd -L` does: `..` drops the component before
+/// it even if that is a symlink.
+pub fn normalize_logical(path: &str) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    format!("/{}", parts.join("/"))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -82,4 +99,12 @@ mod tests {
 
         inner();
     }
+
+    #[test]
+    fn test_normalize_logical() {
+        assert_eq!(normalize_logical("/"), "/");
+        assert_eq!(normalize_logical("/a/./b//c/"), "/a/b/c");
+        assert_eq!(normalize_logical("/a/link/../b"), "/a/b");
+        assert_eq!(normalize_logical("/../.."), "/");
+    }
 }
@@ -0,0 +1,132 @@
+/// Returns true if all of `text` matches the glob `pattern`: `*` matches
+/// any string, `?` any character, `[...]` a set of characters, and `\`
+/// makes the next character match only itself.
+pub fn matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    // Where to resume after the most recent `*`: the pattern position
+    // after it, and the text position it has matched up to
+    let mut backtrack: Option<(usize, usize)> = None;
+    let (mut p, mut t) = (0, 0);
+    while t < text.len() {
+        let step = match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p + 1, t));
+                p += 1;
+                continue;
+            }
+            Some('?') => Some(1),
+            Some('[') => match bracket(&pattern[p..], text[t]) {
+                Some((len, true)) => Some(len),
+                Some((_, false)) => None,
+                None => (text[t] == '[').then_some(1),
+            },
+            Some('\\') if p + 1 < pattern.len() => (pattern[p + 1] == text[t]).then_some(2),
+            Some(&c) => (c == text[t]).then_some(1),
+            None => None,
+        };
+        match (step, backtrack) {
+            (Some(len), _) => {
+                p += len;
+                t += 1;
+            }
+            // Let the last `*` take one more character and try again
+            (None, Some((star_p, star_t))) => {
+                backtrack = Some((star_p, star_t + 1));
+                p = star_p;
+                t = star_t + 1;
+            }
+            (None, None) => return false,
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Match `c` against the bracket expression at the start of `pattern`.
+/// Returns the expression's length and whether `c` is in the set, or
+/// `None` if the `[` is not closed and so is an ordinary character.
+fn bracket(pattern: &[char], c: char) -> Option<(usize, bool)> {
+    let mut i = 1;
+    let negated = matches!(pattern.get(i), Some('!' | '^'));
+    if negated {
+        i += 1;
+    }
+    let mut found = false;
+    let mut first = true;
+    loop {
+        let start = *pattern.get(i)?;
+        if start == ']' && !first {
+            return Some((i + 1, found != negated));
+        }
+        first = false;
+        if start == '[' && pattern.get(i + 1) == Some(&':') {
+            let rest: String = pattern[i + 2..].iter().collect();
+            if let Some(end) = rest.find(":]") {
+                found |= in_class(&rest[..end], c);
+                i += 2 + rest[..end].chars().count() + 2;
+                continue;
+            }
+        }
+        let (low, len) = match (start, pattern.get(i + 1)) {
+            ('\\', Some(&escaped)) => (escaped, 2),
+            _ => (start, 1),
+        };
+        i += len;
+        if pattern.get(i) == Some(&'-') && pattern.get(i + 1).is_some_and(|&h| h != ']') {
+            let high = pattern[i + 1];
+            found |= low <= c && c <= high;
+            i += 2;
+        } else {
+            found |= low == c;
+        }
+    }
+}
+
+/// Returns true if `c` is in the character class `[:name:]`.
+fn in_class(name: &str, c: char) -> bool {
+    match name {
+        "alnum" => c.is_alphanumeric(),
+        "alpha" => c.is_alphabetic(),
+        "blank" => c == ' ' || c == '\t',
+        "digit" => c.is_ascii_digit(),
+        "lower" => c.is_lowercase(),
+        "upper" => c.is_uppercase(),
+        "space" => c.is_whitespace(),
+        "punct" => c.is_ascii_punctuation(),
+        "xdigit" => c.is_ascii_hexdigit(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wildcards() {
+        assert!(matches("*.rs", "main.rs"));
+        assert!(!matches("*.rs", "main.rs.bak"));
+        assert!(matches("a*b*c", "aXXbYYbc"));
+        assert!(matches("?", "x"));
+        assert!(!matches("?", ""));
+        assert!(matches("*", ""));
+        assert!(matches("", ""));
+    }
+
+    #[test]
+    fn test_brackets() {
+        assert!(matches("[a-c]x", "bx"));
+        assert!(!matches("[!a-c]x", "bx"));
+        assert!(matches("[]]", "]"));
+        assert!(matches("[[:digit:]][[:upper:]]", "7Q"));
+        // An unclosed bracket is literal
+        assert!(matches("a[b", "a[b"));
+    }
+
+    #[test]
+    fn test_escapes() {
+        assert!(matches("\\*", "*"));
+        assert!(!matches("\\*", "x"));
+        assert!(matches("a\\?*", "a?bc"));
+    }
+}
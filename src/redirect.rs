@@ -0,0 +1,301 @@
+use crate::parser::{Redirect, RedirectKind};
+use crate::variables::Variables;
+use nix::fcntl::{fcntl, FcntlArg};
+use nix::unistd::{close, dup2};
+use std::fs::{File, OpenOptions};
+use std::io::{Seek, Write};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Descriptors saved while redirections are in effect, and others the
+/// shell keeps for itself, are moved at or above this number, out of the
+/// way of the ones scripts commonly use.
+const SAVED_FD_BASE: RawFd = 10;
+
+enum FdAction {
+    /// Make the second descriptor a copy of the first
+    Dup(RawFd, RawFd),
+    Close(RawFd),
+}
+
+impl FdAction {
+    fn target(&self) -> RawFd {
+        match self {
+            FdAction::Dup(_, target) | FdAction::Close(target) => *target,
+        }
+    }
+
+    fn perform(&self) -> nix::Result<()> {
+        match self {
+            FdAction::Dup(source, target) if source == target => Ok(()),
+            FdAction::Dup(source, target) => dup2(*source, *target).map(drop),
+            FdAction::Close(target) => match close(*target) {
+                // Closing an already closed descriptor is not an error
+                Err(nix::errno::Errno::EBADF) => Ok(()),
+                other => other,
+            },
+        }
+    }
+}
+
+/// Redirections with their files opened, ready to be applied in order.
+pub struct PreparedRedirects {
+    actions: Vec<FdAction>,
+    /// Opened with close-on-exec, so only the duplicated copies reach children
+    _files: Vec<OwnedFd>,
+}
+
+impl PreparedRedirects {
+    /// Apply the redirections to the current process without saving anything.
+    /// Meant for a child between fork and exec, e.g. from `pre_exec`.
+    pub fn apply_in_child(&self) -> std::io::Result<()> {
+        for action in &self.actions {
+            action.perform().map_err(std::io::Error::from)?;
+        }
+        Ok(())
+    }
+}
+
+/// Expand targets and open every file named by `redirects`.
+pub fn prepare(redirects: &[Redirect], vars: &Variables) -> Result<PreparedRedirects, String> {
+    let mut actions = Vec::new();
+    let mut files = Vec::new();
+
+    for redirect in redirects {
+        let target = vars.expand(&redirect.target)?;
+        let file = match redirect.kind {
+            RedirectKind::DupRead | RedirectKind::DupWrite => {
+                if target == "-" {
+                    actions.push(FdAction::Close(redirect.fd));
+                } else {
+                    let source: RawFd = target
+                        .parse()
+                        .map_err(|_| format!("{}: ambiguous redirect", target))?;
+                    actions.push(FdAction::Dup(source, redirect.fd));
+                }
+                continue;
+            }
+            RedirectKind::Heredoc => heredoc_file(&target)?,
+            RedirectKind::Write if vars.options().noclobber => {
+                // Only regular files are protected, so `> /dev/null` still works
+                if std::fs::metadata(&target).is_ok_and(|m| m.is_file()) {
+                    return Err(format!("{}: cannot overwrite existing file", target));
+                }
+                open_file(&target, OpenOptions::new().write(true).create(true))?
+            }
+            RedirectKind::Write | RedirectKind::Clobber => open_file(
+                &target,
+                OpenOptions::new().write(true).create(true).truncate(true),
+            )?,
+            RedirectKind::Append => {
+                open_file(&target, OpenOptions::new().append(true).create(true))?
+            }
+            RedirectKind::Read => open_file(&target, OpenOptions::new().read(true))?,
+            RedirectKind::ReadWrite => open_file(
+                &target,
+                OpenOptions::new().read(true).write(true).create(true),
+            )?,
+        };
+        // Opened files must not take a descriptor that is being redirected
+        let fd = move_high(OwnedFd::from(file));
+        actions.push(FdAction::Dup(fd.as_raw_fd(), redirect.fd));
+        files.push(fd);
+    }
+
+    Ok(PreparedRedirects {
+        actions,
+        _files: files,
+    })
+}
+
+/// Move `fd` out of the way of the descriptors scripts use, such as
+/// `exec 3>file`. The copy is closed on exec.
+pub fn move_high(fd: OwnedFd) -> OwnedFd {
+    match fcntl(fd.as_raw_fd(), FcntlArg::F_DUPFD_CLOEXEC(SAVED_FD_BASE)) {
+        Ok(high) => unsafe { OwnedFd::from_raw_fd(high) },
+        Err(_) => fd,
+    }
+}
+
+fn open_file(path: &str, options: &OpenOptions) -> Result<File, String> {
+    options.open(path).map_err(|e| format!("{}: {}", path, e))
+}
+
+/// Store a here-document's body in an unlinked temporary file, positioned
+/// at the start. A file, unlike a pipe, cannot block the shell on large
+/// bodies and needs no writer to outlive a fork.
+fn heredoc_file(body: &str) -> Result<File, String> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let path = std::env::temp_dir().join(format!(
+        "pmsh-heredoc-{}-{}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .map_err(|e| format!("cannot create temp file for here-document: {}", e))?;
+    let _ = std::fs::remove_file(&path);
+    file.write_all(body.as_bytes())
+        .and_then(|_| file.rewind())
+        .map_err(|e| format!("cannot write here-document: {}", e))?;
+    Ok(file)
+}
+
+/// Redirections applied to the shell itself. Dropping the guard restores
+/// every descriptor it changed.
+pub struct RedirectGuard {
+    /// Original descriptors, or `None` if the descriptor was closed before
+    saved: Vec<(RawFd, Option<OwnedFd>)>,
+}
+
+impl RedirectGuard {
+    /// Leave the redirections in place for good, as `exec` does.
+    pub fn keep(mut self) {
+        flush_std_streams();
+        // Dropping the saved copies closes them
+        self.saved.clear();
+    }
+}
+
+impl Drop for RedirectGuard {
+    fn drop(&mut self) {
+        flush_std_streams();
+        for (target, saved) in self.saved.drain(..).rev() {
+            let _ = match saved {
+                Some(fd) => dup2(fd.as_raw_fd(), target).map(drop),
+                None => close(target),
+            };
+        }
+    }
+}
+
+/// Apply `redirects` to the shell's own descriptors until the guard is dropped.
+pub fn apply(redirects: &[Redirect], vars: &Variables) -> Result<RedirectGuard, String> {
+    let prepared = prepare(redirects, vars)?;
+    flush_std_streams();
+
+    let mut guard = RedirectGuard { saved: Vec::new() };
+    for action in &prepared.actions {
+        let target = action.target();
+        if !guard.saved.iter().any(|(fd, _)| *fd == target) {
+            let saved = fcntl(target, FcntlArg::F_DUPFD_CLOEXEC(SAVED_FD_BASE))
+                .ok()
+                .map(|fd| unsafe { OwnedFd::from_raw_fd(fd) });
+            guard.saved.push((target, saved));
+        }
+        action
+            .perform()
+            .map_err(|e| format!("{}: {}", target, e.desc()))?;
+    }
+    Ok(guard)
+}
+
+/// Output buffered by Rust must reach the descriptor it was written for.
+fn flush_std_streams() {
+    let _ = std::io::stdout().flush();
+    let _ = std::io::stderr().flush();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn redirect(fd: i32, kind: RedirectKind, target: &str) -> Redirect {
+        Redirect {
+            fd,
+            kind,
+            target: target.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_prepare_opens_files() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("out.txt");
+        let vars = Variables::new();
+        let prepared = prepare(
+            &[redirect(1, RedirectKind::Write, path.to_str().unwrap())],
+            &vars,
+        )
+        .unwrap();
+        assert_eq!(prepared.actions.len(), 1);
+        assert!(path.exists());
+    }
+
+    #[test]
+    fn test_noclobber_refuses_existing_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("keep.txt");
+        std::fs::write(&path, "keep").unwrap();
+        let target = path.to_str().unwrap();
+
+        let mut vars = Variables::new();
+        vars.options_mut().noclobber = true;
+        let err = prepare(&[redirect(1, RedirectKind::Write, target)], &vars)
+            .err()
+            .unwrap();
+        assert!(err.contains("cannot overwrite existing file"));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "keep");
+
+        // `>|` overrides noclobber, and appending is always allowed
+        assert!(prepare(&[redirect(1, RedirectKind::Append, target)], &vars).is_ok());
+        prepare(&[redirect(1, RedirectKind::Clobber, target)], &vars).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "");
+    }
+
+    #[test]
+    fn test_dup_targets() {
+        let vars = Variables::new();
+        assert!(prepare(&[redirect(2, RedirectKind::DupWrite, "1")], &vars).is_ok());
+        assert!(prepare(&[redirect(3, RedirectKind::DupWrite, "-")], &vars).is_ok());
+        assert!(prepare(&[redirect(2, RedirectKind::DupWrite, "file")], &vars).is_err());
+    }
+
+    #[test]
+    fn test_heredoc_file_is_readable() {
+        let mut file = heredoc_file("line one\nline two\n").unwrap();
+        let mut body = String::new();
+        file.read_to_string(&mut body).unwrap();
+        assert_eq!(body, "line one\nline two\n");
+    }
+
+    #[test]
+    fn test_apply_restores_descriptor() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("fd.txt");
+        let vars = Variables::new();
+
+        // Use a descriptor nothing else in the test process opens
+        {
+            let _guard = apply(
+                &[redirect(63, RedirectKind::Write, path.to_str().unwrap())],
+                &vars,
+            )
+            .unwrap();
+            nix::unistd::write(unsafe { std::os::fd::BorrowedFd::borrow_raw(63) }, b"hi").unwrap();
+        }
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "hi");
+        assert!(fcntl(63, FcntlArg::F_GETFD).is_err());
+    }
+
+    #[test]
+    fn test_keep_leaves_descriptor_open() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("kept.txt");
+        let vars = Variables::new();
+
+        apply(
+            &[redirect(62, RedirectKind::Write, path.to_str().unwrap())],
+            &vars,
+        )
+        .unwrap()
+        .keep();
+        nix::unistd::write(unsafe { std::os::fd::BorrowedFd::borrow_raw(62) }, b"kept").unwrap();
+        close(62).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "kept");
+    }
+}
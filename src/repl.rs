@@ -1,8 +1,12 @@
-use crate::builtins::{handle_builtin, BuiltinResult};
+use crate::aliases::Aliases;
+use crate::builtins::{is_builtin, BuiltinResult};
 use crate::colors::red;
+use crate::executor::Executor;
 use crate::functions::Functions;
 use crate::history::HistoryManager;
-use crate::parser::Command;
+use crate::parser::{Command, ScriptChunk};
+use crate::startup;
+use crate::traps::TrapCondition;
 
 use crate::ui;
 use crate::variables::Variables;
@@ -25,19 +29,19 @@ pub trait ExecutorTrait {
         cmd: &Command,
         vars: &mut Variables,
         functions: &mut Functions,
+        aliases: &mut Aliases,
         history_mgr: &HistoryManager,
         command_history: &mut Vec<String>,
-        oldpwd: &mut Option<String>,
-    ) -> Result<(), String>;
+    ) -> Result<i32, String>;
     fn execute_pipeline(
         &self,
         pipeline: &[Command],
         vars: &mut Variables,
         functions: &mut Functions,
+        aliases: &mut Aliases,
         history_mgr: &HistoryManager,
         command_history: &mut Vec<String>,
-        oldpwd: &mut Option<String>,
-    ) -> Result<(), String>;
+    ) -> Result<i32, String>;
 }
 
 pub struct RealExecutor;
@@ -48,17 +52,17 @@ impl ExecutorTrait for RealExecutor {
         cmd: &Command,
         vars: &mut Variables,
         functions: &mut Functions,
+        aliases: &mut Aliases,
         history_mgr: &HistoryManager,
         command_history: &mut Vec<String>,
-        oldpwd: &mut Option<String>,
-    ) -> Result<(), String> {
+    ) -> Result<i32, String> {
         crate::executor::Executor::execute(
             cmd,
             vars,
             functions,
+            aliases,
             history_mgr,
             command_history,
-            oldpwd,
         )
     }
 
@@ -67,17 +71,17 @@ impl ExecutorTrait for RealExecutor {
         pipeline: &[Command],
         vars: &mut Variables,
         functions: &mut Functions,
+        aliases: &mut Aliases,
         history_mgr: &HistoryManager,
         command_history: &mut Vec<String>,
-        oldpwd: &mut Option<String>,
-    ) -> Result<(), String> {
+    ) -> Result<i32, String> {
         crate::executor::Executor::execute_pipeline(
             pipeline,
             vars,
             functions,
+            aliases,
             history_mgr,
             command_history,
-            oldpwd,
         )
     }
 }
@@ -98,22 +102,34 @@ pub fn execute_line<E: ExecutorTrait, L: LineEditor>(
     history_mgr: &HistoryManager,
     command_history: &mut Vec<String>,
     executor: &E,
-    oldpwd: &mut Option<String>,
     vars: &mut Variables,
     functions: &mut Functions,
+    aliases: &mut Aliases,
 ) -> bool {
     editor.add_history_entry(line);
+    if vars.options().verbose {
+        eprintln!("{}", line);
+    }
 
-    if let Some(pipeline) = Command::parse_pipeline(line) {
-        return execute_pipeline_struct(
-            &pipeline,
-            history_mgr,
-            command_history,
-            executor,
-            oldpwd,
-            vars,
-            functions,
-        );
+    // Aliases are textual, so they must be expanded before parsing
+    let line = aliases.expand(line);
+    match Command::parse(&line) {
+        Ok(pipelines) => {
+            for pipeline in pipelines {
+                if !execute_pipeline_struct(
+                    &pipeline,
+                    history_mgr,
+                    command_history,
+                    executor,
+                    vars,
+                    functions,
+                    aliases,
+                ) {
+                    return false;
+                }
+            }
+        }
+        Err(e) => eprintln!("pmsh: {}", red(&e)),
     }
     true
 }
@@ -123,63 +139,85 @@ pub fn execute_pipeline_struct<E: ExecutorTrait>(
     history_mgr: &HistoryManager,
     command_history: &mut Vec<String>,
     executor: &E,
-    oldpwd: &mut Option<String>,
     vars: &mut Variables,
     functions: &mut Functions,
+    aliases: &mut Aliases,
 ) -> bool {
-    if pipeline.len() == 1 {
+    // Set when the command could not run at all, which `set -e` always treats as a failure
+    let mut errored = false;
+    let status = if pipeline.len() == 1 {
         // Single command: check for builtins
         let cmd = &pipeline[0];
-        let builtin_res = if let Command::Simple(simple) = cmd {
-            handle_builtin(simple, history_mgr, command_history, oldpwd, vars)
-        } else {
-            Ok(BuiltinResult::NotHandled)
+        let builtin_res = match cmd {
+            // Functions take precedence over builtins of the same name
+            Command::Simple(simple)
+                if is_builtin(&simple.name) && functions.get(&simple.name).is_none() =>
+            {
+                vars.set_lineno(simple.line);
+                Executor::run_trap(
+                    TrapCondition::Debug,
+                    vars,
+                    functions,
+                    aliases,
+                    history_mgr,
+                    command_history,
+                );
+                Executor::trace_simple(simple, vars);
+                let last_word = Executor::last_word(simple, vars);
+                let result = Executor::run_builtin(
+                    simple,
+                    vars,
+                    functions,
+                    aliases,
+                    history_mgr,
+                    command_history,
+                );
+                if let Some(word) = last_word {
+                    vars.set_last_arg(word);
+                }
+                result
+            }
+            _ => Ok(BuiltinResult::NotHandled),
         };
 
         match builtin_res {
-            Ok(BuiltinResult::HandledExit(code)) => std::process::exit(code),
-            Ok(BuiltinResult::HandledContinue) => return true,
-            Ok(BuiltinResult::SourceFile(path)) => {
-                let contents = match std::fs::read_to_string(&path) {
-                    Ok(c) => c,
-                    Err(e) => {
-                        eprintln!("pmsh: source: {}: {}", path, e);
-                        return true;
-                    }
+            Ok(result) => {
+                let ran = match Executor::dispatch_builtin(
+                    result,
+                    executor,
+                    vars,
+                    functions,
+                    aliases,
+                    history_mgr,
+                    command_history,
+                ) {
+                    Ok(Some(status)) => Ok(status),
+                    Ok(None) => executor.execute(
+                        cmd,
+                        vars,
+                        functions,
+                        aliases,
+                        history_mgr,
+                        command_history,
+                    ),
+                    Err(e) => Err(e),
                 };
-                // Use parse_script to handle multiline commands correctly
-                match Command::parse_script(&contents) {
-                    Ok(pipelines) => {
-                        for pipeline in pipelines {
-                            if !execute_pipeline_struct(
-                                &pipeline,
-                                history_mgr,
-                                command_history,
-                                executor,
-                                oldpwd,
-                                vars,
-                                functions,
-                            ) {
-                                return false;
-                            }
-                        }
-                    }
+                match ran {
+                    // History saving is handled by the caller (execute_line) for the full line.
+                    // We don't save individual commands from scripts/pipelines here.
+                    Ok(status) => status,
                     Err(e) => {
-                        eprintln!("pmsh: source: error parsing script: {}", e);
+                        eprintln!("pmsh: {}{}", vars.error_location(), red(&e));
+                        errored = true;
+                        1
                     }
                 }
-                return true;
             }
-            Ok(BuiltinResult::NotHandled) => {
-                match executor.execute(cmd, vars, functions, history_mgr, command_history, oldpwd) {
-                    Ok(()) => {
-                        // History saving is handled by the caller (execute_line) for the full line.
-                        // We don't save individual commands from scripts/pipelines here.
-                    }
-                    Err(e) => eprintln!("pmsh: {}", red(&e.to_string())),
-                }
+            Err(e) => {
+                eprintln!("Builtin error: {}{}", vars.error_location(), red(&e));
+                errored = true;
+                1
             }
-            Err(e) => eprintln!("Builtin error: {}", red(&e.to_string())),
         }
     } else {
         // Pipeline of multiple commands: execute via pipeline
@@ -187,14 +225,186 @@ pub fn execute_pipeline_struct<E: ExecutorTrait>(
             pipeline,
             vars,
             functions,
+            aliases,
             history_mgr,
             command_history,
-            oldpwd,
         ) {
-            Ok(()) => {
-                // History saving removed
+            Ok(status) => status,
+            Err(e) => {
+                eprintln!("pmsh: {}{}", vars.error_location(), red(&e));
+                errored = true;
+                1
             }
-            Err(e) => eprintln!("pmsh: {}", red(&e.to_string())),
+        }
+    };
+    Executor::finish_pipeline(
+        pipeline,
+        status,
+        errored,
+        vars,
+        functions,
+        aliases,
+        history_mgr,
+        command_history,
+    );
+    true
+}
+
+/// Run a script as it is read, a few lines at a time, so that `set -v`
+/// echoes each part before it runs and a syntax error only stops the
+/// script where it occurs. Returns `Ok(false)` if the shell should exit,
+/// and stops early when a `return` unwinds the script.
+#[allow(clippy::too_many_arguments)]
+pub fn execute_script<E: ExecutorTrait>(
+    contents: &str,
+    name: Option<&str>,
+    history_mgr: &HistoryManager,
+    command_history: &mut Vec<String>,
+    executor: &E,
+    vars: &mut Variables,
+    functions: &mut Functions,
+    aliases: &mut Aliases,
+) -> Result<bool, String> {
+    execute_chunks(
+        Command::script_chunks(contents),
+        name,
+        history_mgr,
+        command_history,
+        executor,
+        vars,
+        functions,
+        aliases,
+    )
+}
+
+/// Like `execute_script`, for chunks read by the caller, e.g. from a pipe.
+/// Errors are located by `name`, the file the chunks come from, and line.
+#[allow(clippy::too_many_arguments)]
+pub fn execute_chunks<E: ExecutorTrait>(
+    chunks: impl Iterator<Item = ScriptChunk>,
+    name: Option<&str>,
+    history_mgr: &HistoryManager,
+    command_history: &mut Vec<String>,
+    executor: &E,
+    vars: &mut Variables,
+    functions: &mut Functions,
+    aliases: &mut Aliases,
+) -> Result<bool, String> {
+    let (saved_name, saved_line) = vars.location();
+    let result = 'run: {
+        for chunk in chunks {
+            vars.set_location(name.map(String::from), chunk.line);
+            if vars.options().verbose {
+                eprint!("{}", chunk.text);
+            }
+            // Aliases are textual, so a chunk that uses one is parsed again
+            // once they are expanded, with the aliases defined by now
+            let expanded = aliases.expand(&chunk.text);
+            let commands = match expanded == chunk.text {
+                true => chunk.commands,
+                false => Command::parse_lines(&expanded, chunk.line),
+            };
+            let pipelines = match commands {
+                Ok(pipelines) => pipelines,
+                Err(e) => {
+                    vars.set_lineno(e.line);
+                    break 'run Err(format!("{}{}", vars.error_location(), e.message));
+                }
+            };
+            for pipeline in pipelines {
+                if !execute_pipeline_struct(
+                    &pipeline,
+                    history_mgr,
+                    command_history,
+                    executor,
+                    vars,
+                    functions,
+                    aliases,
+                ) {
+                    break 'run Ok(false);
+                }
+                if vars.is_returning() {
+                    break 'run Ok(true);
+                }
+            }
+        }
+        Ok(true)
+    };
+    vars.set_location(saved_name, saved_line);
+    result
+}
+
+/// Run the file at `path` in the current shell, as the `source` builtin
+/// does. `$?` is set to the status of the last command run, or to the
+/// value given to a `return` that stopped the file early.
+pub fn source_file<E: ExecutorTrait>(
+    path: &str,
+    history_mgr: &HistoryManager,
+    command_history: &mut Vec<String>,
+    executor: &E,
+    vars: &mut Variables,
+    functions: &mut Functions,
+    aliases: &mut Aliases,
+) -> Result<bool, String> {
+    let contents = std::fs::read_to_string(path).map_err(|e| format!("source: {}: {}", path, e))?;
+    vars.set_last_status(0);
+    vars.enter_source();
+    let result = execute_script(
+        &contents,
+        Some(path),
+        history_mgr,
+        command_history,
+        executor,
+        vars,
+        functions,
+        aliases,
+    );
+    vars.leave_source();
+    if let Some(status) = vars.take_return() {
+        vars.set_last_status(status);
+    }
+    result
+}
+
+/// Parse `script` and run it in the current shell, as the `eval` builtin
+/// does. Returns `false` if the shell should exit. `$?` is set to the
+/// status of the last command run, to 0 if there is none, or to 2 if the
+/// script does not parse. The commands see the `$?` from before `eval`.
+pub fn eval_string<E: ExecutorTrait>(
+    script: &str,
+    history_mgr: &HistoryManager,
+    command_history: &mut Vec<String>,
+    executor: &E,
+    vars: &mut Variables,
+    functions: &mut Functions,
+    aliases: &mut Aliases,
+) -> bool {
+    let script = aliases.expand(script);
+    let pipelines = match Command::parse_at(&script, vars.lineno().max(1)) {
+        Ok(pipelines) => pipelines,
+        Err(e) => {
+            eprintln!("pmsh: {}eval: {}", vars.error_location(), red(&e));
+            vars.set_last_status(2);
+            return true;
+        }
+    };
+    if pipelines.is_empty() {
+        vars.set_last_status(0);
+    }
+    for pipeline in pipelines {
+        if !execute_pipeline_struct(
+            &pipeline,
+            history_mgr,
+            command_history,
+            executor,
+            vars,
+            functions,
+            aliases,
+        ) {
+            return false;
+        }
+        if vars.is_returning() {
+            break;
         }
     }
     true
@@ -205,9 +415,9 @@ pub fn run_repl_with_state<E: ExecutorTrait, L: LineEditor>(
     history_mgr: &HistoryManager,
     command_history: &mut Vec<String>,
     executor: &E,
-    mut oldpwd: Option<String>,
     mut vars: Variables,
     mut functions: Functions,
+    mut aliases: Aliases,
 ) {
     // REPL: Read-Eval-Print Loop
     loop {
@@ -223,9 +433,9 @@ pub fn run_repl_with_state<E: ExecutorTrait, L: LineEditor>(
                     history_mgr,
                     command_history,
                     executor,
-                    &mut oldpwd,
                     &mut vars,
                     &mut functions,
+                    &mut aliases,
                 ) {
                     break;
                 }
@@ -247,6 +457,22 @@ pub fn run_repl_with_state<E: ExecutorTrait, L: LineEditor>(
             }
         }
     }
+
+    startup::run_logout_file(
+        history_mgr,
+        command_history,
+        &mut vars,
+        &mut functions,
+        &mut aliases,
+    );
+    Executor::run_exit_trap(
+        vars.last_status(),
+        &mut vars,
+        &mut functions,
+        &mut aliases,
+        history_mgr,
+        command_history,
+    );
 }
 
 #[cfg(test)]
@@ -295,12 +521,12 @@ mod tests {
             cmd: &Command,
             _vars: &mut Variables,
             _functions: &mut Functions,
+            _aliases: &mut Aliases,
             _history_mgr: &HistoryManager,
             _command_history: &mut Vec<String>,
-            _oldpwd: &mut Option<String>,
-        ) -> Result<(), String> {
+        ) -> Result<i32, String> {
             self.calls.borrow_mut().push(cmd.clone());
-            Ok(())
+            Ok(0)
         }
 
         fn execute_pipeline(
@@ -308,21 +534,21 @@ mod tests {
             pipeline: &[Command],
             _vars: &mut Variables,
             _functions: &mut Functions,
+            _aliases: &mut Aliases,
             _history_mgr: &HistoryManager,
             _command_history: &mut Vec<String>,
-            _oldpwd: &mut Option<String>,
-        ) -> Result<(), String> {
+        ) -> Result<i32, String> {
             for cmd in pipeline {
                 self.calls.borrow_mut().push(cmd.clone());
             }
-            Ok(())
+            Ok(0)
         }
     }
 
     #[test]
     fn test_repl_executes_command_and_exits_on_eof() {
         let events = vec![
-            ReadlineEvent::Line("echo hello".to_string()),
+            ReadlineEvent::Line("ls hello".to_string()),
             ReadlineEvent::Eof,
         ];
         let mut editor = MockEditor::new(events);
@@ -337,17 +563,17 @@ mod tests {
             &mgr,
             &mut history,
             &executor,
-            None,
             Variables::new(),
             Functions::new(),
+            Aliases::new(),
         );
 
-        // executor should have been called once with echo
-        // executor should have been called once with echo
+        // executor should have been called once with ls
+        // executor should have been called once with ls
         let calls = executor.calls.borrow();
         assert_eq!(calls.len(), 1);
         if let Command::Simple(cmd) = &calls[0] {
-            assert_eq!(cmd.name, "echo");
+            assert_eq!(cmd.name, "ls");
             assert_eq!(cmd.args, vec!["hello".to_string()]);
         } else {
             panic!("Expected Simple command");
@@ -372,9 +598,9 @@ mod tests {
             &mgr,
             &mut history,
             &executor,
-            None,
             Variables::new(),
             Functions::new(),
+            Aliases::new(),
         );
 
         // executor's execute_pipeline should have been called with 2 commands
@@ -422,13 +648,15 @@ mod tests {
             &mgr,
             &mut history,
             &executor,
-            None,
             Variables::new(),
             Functions::new(),
+            Aliases::new(),
         );
 
-        // ensure history recorded the cd entry and restore cwd
-        assert!(history.iter().any(|h| h.starts_with("cd ")));
+        // cd changed directory without adding an entry of its own to history
+        let cwd = std::env::current_dir().unwrap();
+        assert_eq!(cwd, std::fs::canonicalize(tmp.path()).unwrap());
+        assert!(history.is_empty());
         let _ = std::env::set_current_dir(orig);
     }
 
@@ -443,10 +671,10 @@ mod tests {
                 _cmd: &Command,
                 _vars: &mut Variables,
                 _functions: &mut Functions,
+                _aliases: &mut Aliases,
                 _history_mgr: &HistoryManager,
                 _command_history: &mut Vec<String>,
-                _oldpwd: &mut Option<String>,
-            ) -> Result<(), String> {
+            ) -> Result<i32, String> {
                 Err("execution failed".to_string())
             }
 
@@ -455,10 +683,10 @@ mod tests {
                 _pipeline: &[Command],
                 _vars: &mut Variables,
                 _functions: &mut Functions,
+                _aliases: &mut Aliases,
                 _history_mgr: &HistoryManager,
                 _command_history: &mut Vec<String>,
-                _oldpwd: &mut Option<String>,
-            ) -> Result<(), String> {
+            ) -> Result<i32, String> {
                 Err("pipeline failed".to_string())
             }
         }
@@ -469,7 +697,7 @@ mod tests {
         ];
         let mut editor = MockEditor::new(events);
 
-        // ensure history is written to a temp HOME so add_entry/save won't interfere with real HOME
+        // ensure history is written to a temp HOME so save won't interfere with real HOME
         let tmp_home = tempfile::TempDir::new().unwrap();
         let original = std::env::var("HOME").ok();
         std::env::set_var("HOME", tmp_home.path().to_string_lossy().as_ref());
@@ -483,9 +711,9 @@ mod tests {
             &mgr,
             &mut history,
             &exec,
-            None,
             Variables::new(),
             Functions::new(),
+            Aliases::new(),
         );
 
         // executor failed so history should not contain the failed command
@@ -501,7 +729,7 @@ mod tests {
     fn test_repl_interrupted_event() {
         let events = vec![
             ReadlineEvent::Interrupted,
-            ReadlineEvent::Line("echo fine".to_string()),
+            ReadlineEvent::Line("ls fine".to_string()),
             ReadlineEvent::Eof,
         ];
         let mut editor = MockEditor::new(events);
@@ -516,15 +744,15 @@ mod tests {
             &mgr,
             &mut history,
             &executor,
-            None,
             Variables::new(),
             Functions::new(),
+            Aliases::new(),
         );
 
         let calls = executor.calls.borrow();
         assert_eq!(calls.len(), 1);
         if let Command::Simple(cmd) = &calls[0] {
-            assert_eq!(cmd.name, "echo");
+            assert_eq!(cmd.name, "ls");
         } else {
             panic!("Expected Simple command");
         }
@@ -548,9 +776,9 @@ mod tests {
             &mgr,
             &mut history,
             &executor,
-            None,
             Variables::new(),
             Functions::new(),
+            Aliases::new(),
         );
 
         let calls = executor.calls.borrow();
@@ -562,9 +790,9 @@ mod tests {
         let mgr = HistoryManager::new().unwrap_or_else(|_| HistoryManager::default());
         let mut history: Vec<String> = Vec::new();
         let executor = MockExecutor::new();
-        let mut oldpwd = None;
         let mut vars = Variables::new();
         let mut functions = Functions::new();
+        let mut aliases = Aliases::new();
         let mut editor = MockEditor::new(vec![]);
 
         // Empty line should not call executor
@@ -574,9 +802,9 @@ mod tests {
             &mgr,
             &mut history,
             &executor,
-            &mut oldpwd,
             &mut vars,
             &mut functions,
+            &mut aliases,
         );
         // Should return true (continue the REPL)
         assert!(result);
@@ -588,14 +816,14 @@ mod tests {
     fn test_execute_line_source_file() {
         let tmp = tempfile::NamedTempFile::new().unwrap();
         let tmp_path = tmp.path().to_string_lossy().to_string();
-        std::fs::write(&tmp_path, "echo from_source\n").unwrap();
+        std::fs::write(&tmp_path, "ls from_source\n").unwrap();
 
         let mgr = HistoryManager::new().unwrap_or_else(|_| HistoryManager::default());
         let mut history: Vec<String> = Vec::new();
         let executor = MockExecutor::new();
-        let mut oldpwd = None;
         let mut vars = Variables::new();
         let mut functions = Functions::new();
+        let mut aliases = Aliases::new();
         let mut editor = MockEditor::new(vec![]);
 
         let line = format!("source {}", tmp_path);
@@ -605,16 +833,16 @@ mod tests {
             &mgr,
             &mut history,
             &executor,
-            &mut oldpwd,
             &mut vars,
             &mut functions,
+            &mut aliases,
         );
         assert!(result);
-        // executor should have been called with the echo command from the source file
+        // executor should have been called with the ls command from the source file
         let calls = executor.calls.borrow();
         assert_eq!(calls.len(), 1);
         if let Command::Simple(c) = &calls[0] {
-            assert_eq!(c.name, "echo");
+            assert_eq!(c.name, "ls");
         } else {
             panic!("Expected simple command");
         }
@@ -625,9 +853,9 @@ mod tests {
         let mgr = HistoryManager::new().unwrap_or_else(|_| HistoryManager::default());
         let mut history: Vec<String> = Vec::new();
         let executor = MockExecutor::new();
-        let mut oldpwd = None;
         let mut vars = Variables::new();
         let mut functions = Functions::new();
+        let mut aliases = Aliases::new();
         let mut editor = MockEditor::new(vec![]);
 
         // Sourcing a non-existent file should handle gracefully
@@ -637,9 +865,9 @@ mod tests {
             &mgr,
             &mut history,
             &executor,
-            &mut oldpwd,
             &mut vars,
             &mut functions,
+            &mut aliases,
         );
         // Should return true (continue REPL) even on error
         assert!(result);
@@ -652,9 +880,9 @@ mod tests {
         let mgr = HistoryManager::new().unwrap_or_else(|_| HistoryManager::default());
         let mut history: Vec<String> = Vec::new();
         let executor = MockExecutor::new();
-        let mut oldpwd = None;
         let mut vars = Variables::new();
         let mut functions = Functions::new();
+        let mut aliases = Aliases::new();
 
         // A Subshell command in the pipeline should go through execute_pipeline
         let pipeline = vec![Command::Subshell(vec![vec![Command::Simple(
@@ -662,6 +890,8 @@ mod tests {
                 name: "echo".into(),
                 args: vec!["subshell_test".into()],
                 assignments: vec![],
+                line: 0,
+                quoted: Vec::new(),
             },
         )]])];
 
@@ -670,9 +900,9 @@ mod tests {
             &mgr,
             &mut history,
             &executor,
-            &mut oldpwd,
             &mut vars,
             &mut functions,
+            &mut aliases,
         );
         assert!(result);
     }
@@ -682,19 +912,21 @@ mod tests {
     fn test_execute_pipeline_struct_source() {
         let tmp = tempfile::NamedTempFile::new().unwrap();
         let tmp_path = tmp.path().to_string_lossy().to_string();
-        std::fs::write(&tmp_path, "echo sourced_cmd\n").unwrap();
+        std::fs::write(&tmp_path, "ls sourced_cmd\n").unwrap();
 
         let mgr = HistoryManager::new().unwrap_or_else(|_| HistoryManager::default());
         let mut history: Vec<String> = Vec::new();
         let executor = MockExecutor::new();
-        let mut oldpwd = None;
         let mut vars = Variables::new();
         let mut functions = Functions::new();
+        let mut aliases = Aliases::new();
 
         let pipeline = vec![Command::Simple(crate::parser::SimpleCommand {
             name: "source".into(),
             args: vec![tmp_path],
             assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
         })];
 
         let result = execute_pipeline_struct(
@@ -702,34 +934,79 @@ mod tests {
             &mgr,
             &mut history,
             &executor,
-            &mut oldpwd,
             &mut vars,
             &mut functions,
+            &mut aliases,
         );
         assert!(result);
 
         let calls = executor.calls.borrow();
         assert_eq!(calls.len(), 1);
         if let Command::Simple(c) = &calls[0] {
-            assert_eq!(c.name, "echo");
+            assert_eq!(c.name, "ls");
         } else {
             panic!("Expected simple command from sourced file");
         }
     }
 
+    #[test]
+    #[serial_test::serial]
+    fn test_execute_line_source_stops_at_return() {
+        let tmp = tempfile::NamedTempFile::new().unwrap();
+        let tmp_path = tmp.path().to_string_lossy().to_string();
+        std::fs::write(&tmp_path, "ls before\nreturn 2\nls after\n").unwrap();
+
+        let mgr = HistoryManager::new().unwrap_or_else(|_| HistoryManager::default());
+        let mut history: Vec<String> = Vec::new();
+        let executor = MockExecutor::new();
+        let mut vars = Variables::new();
+        let mut functions = Functions::new();
+        let mut aliases = Aliases::new();
+        let mut editor = MockEditor::new(vec![]);
+
+        execute_line(
+            &format!("source {}", tmp_path),
+            &mut editor,
+            &mgr,
+            &mut history,
+            &executor,
+            &mut vars,
+            &mut functions,
+            &mut aliases,
+        );
+        assert_eq!(executor.calls.borrow().len(), 1);
+        assert_eq!(vars.last_status(), 2);
+        assert!(!vars.is_returning());
+
+        // Outside a function or sourced file, return is an error
+        execute_line(
+            "return 0",
+            &mut editor,
+            &mgr,
+            &mut history,
+            &executor,
+            &mut vars,
+            &mut functions,
+            &mut aliases,
+        );
+        assert_eq!(vars.last_status(), 1);
+    }
+
     #[test]
     fn test_execute_pipeline_struct_source_not_found() {
         let mgr = HistoryManager::new().unwrap_or_else(|_| HistoryManager::default());
         let mut history: Vec<String> = Vec::new();
         let executor = MockExecutor::new();
-        let mut oldpwd = None;
         let mut vars = Variables::new();
         let mut functions = Functions::new();
+        let mut aliases = Aliases::new();
 
         let pipeline = vec![Command::Simple(crate::parser::SimpleCommand {
             name: "source".into(),
             args: vec!["/nonexistent/path.sh".to_string()],
             assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
         })];
 
         let result = execute_pipeline_struct(
@@ -737,9 +1014,9 @@ mod tests {
             &mgr,
             &mut history,
             &executor,
-            &mut oldpwd,
             &mut vars,
             &mut functions,
+            &mut aliases,
         );
         // Should still return true (continue REPL even on error)
         assert!(result);
@@ -755,10 +1032,10 @@ mod tests {
                 _cmd: &Command,
                 _vars: &mut Variables,
                 _functions: &mut Functions,
+                _aliases: &mut Aliases,
                 _history_mgr: &HistoryManager,
                 _command_history: &mut Vec<String>,
-                _oldpwd: &mut Option<String>,
-            ) -> Result<(), String> {
+            ) -> Result<i32, String> {
                 Err("command failed".to_string())
             }
 
@@ -767,10 +1044,10 @@ mod tests {
                 _pipeline: &[Command],
                 _vars: &mut Variables,
                 _functions: &mut Functions,
+                _aliases: &mut Aliases,
                 _history_mgr: &HistoryManager,
                 _command_history: &mut Vec<String>,
-                _oldpwd: &mut Option<String>,
-            ) -> Result<(), String> {
+            ) -> Result<i32, String> {
                 Err("pipeline failed".to_string())
             }
         }
@@ -778,24 +1055,26 @@ mod tests {
         let mgr = HistoryManager::new().unwrap_or_else(|_| HistoryManager::default());
         let mut history: Vec<String> = Vec::new();
         let executor = FailingExecutor;
-        let mut oldpwd = None;
         let mut vars = Variables::new();
         let mut functions = Functions::new();
+        let mut aliases = Aliases::new();
 
         // Single command - executor error
         let pipeline = vec![Command::Simple(crate::parser::SimpleCommand {
             name: "some_cmd".into(),
             args: vec![],
             assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
         })];
         let result = execute_pipeline_struct(
             &pipeline,
             &mgr,
             &mut history,
             &executor,
-            &mut oldpwd,
             &mut vars,
             &mut functions,
+            &mut aliases,
         );
         assert!(result); // even on error, the REPL continues
 
@@ -805,11 +1084,15 @@ mod tests {
                 name: "cmd1".into(),
                 args: vec![],
                 assignments: vec![],
+                line: 0,
+                quoted: Vec::new(),
             }),
             Command::Simple(crate::parser::SimpleCommand {
                 name: "cmd2".into(),
                 args: vec![],
                 assignments: vec![],
+                line: 0,
+                quoted: Vec::new(),
             }),
         ];
         let result = execute_pipeline_struct(
@@ -817,9 +1100,9 @@ mod tests {
             &mgr,
             &mut history,
             &executor,
-            &mut oldpwd,
             &mut vars,
             &mut functions,
+            &mut aliases,
         );
         assert!(result);
     }
@@ -829,24 +1112,26 @@ mod tests {
         let mgr = HistoryManager::new().unwrap_or_else(|_| HistoryManager::default());
         let mut history: Vec<String> = Vec::new();
         let executor = MockExecutor::new();
-        let mut oldpwd = None;
         let mut vars = Variables::new();
         let mut functions = Functions::new();
+        let mut aliases = Aliases::new();
 
         // complete -W wordlist mycmd
         let pipeline = vec![Command::Simple(crate::parser::SimpleCommand {
             name: "complete".into(),
             args: vec!["-W".into(), "foo bar".into(), "mycmd".into()],
             assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
         })];
         let result = execute_pipeline_struct(
             &pipeline,
             &mgr,
             &mut history,
             &executor,
-            &mut oldpwd,
             &mut vars,
             &mut functions,
+            &mut aliases,
         );
         assert!(result);
 
@@ -855,15 +1140,17 @@ mod tests {
             name: "compgen".into(),
             args: vec!["-W".into(), "hello world".into(), "--".into(), "hel".into()],
             assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
         })];
         let result = execute_pipeline_struct(
             &pipeline,
             &mgr,
             &mut history,
             &executor,
-            &mut oldpwd,
             &mut vars,
             &mut functions,
+            &mut aliases,
         );
         assert!(result);
 
@@ -872,17 +1159,128 @@ mod tests {
             name: "version".into(),
             args: vec![],
             assignments: vec![],
+            line: 0,
+            quoted: Vec::new(),
         })];
         let result = execute_pipeline_struct(
             &pipeline,
             &mgr,
             &mut history,
             &executor,
-            &mut oldpwd,
             &mut vars,
             &mut functions,
+            &mut aliases,
+        );
+        assert!(result);
+    }
+
+    #[test]
+    fn test_execute_line_runs_every_pipeline() {
+        let mgr = HistoryManager::new().unwrap_or_else(|_| HistoryManager::default());
+        let mut history: Vec<String> = Vec::new();
+        let executor = MockExecutor::new();
+        let mut vars = Variables::new();
+        let mut functions = Functions::new();
+        let mut aliases = Aliases::new();
+        let mut editor = MockEditor::new(vec![]);
+
+        let result = execute_line(
+            "ls one; ls two",
+            &mut editor,
+            &mgr,
+            &mut history,
+            &executor,
+            &mut vars,
+            &mut functions,
+            &mut aliases,
+        );
+        assert!(result);
+        assert_eq!(executor.calls.borrow().len(), 2);
+    }
+
+    #[test]
+    fn test_execute_line_eval() {
+        let mgr = HistoryManager::new().unwrap_or_else(|_| HistoryManager::default());
+        let mut history: Vec<String> = Vec::new();
+        let executor = MockExecutor::new();
+        let mut vars = Variables::new();
+        let mut functions = Functions::new();
+        let mut aliases = Aliases::new();
+        let mut editor = MockEditor::new(vec![]);
+
+        let result = execute_line(
+            "eval ls one\\; ls two",
+            &mut editor,
+            &mgr,
+            &mut history,
+            &executor,
+            &mut vars,
+            &mut functions,
+            &mut aliases,
         );
         assert!(result);
+        assert_eq!(executor.calls.borrow().len(), 2);
+
+        // A script that does not parse is an eval failure
+        execute_line(
+            "eval 'ls ('",
+            &mut editor,
+            &mgr,
+            &mut history,
+            &executor,
+            &mut vars,
+            &mut functions,
+            &mut aliases,
+        );
+        assert_eq!(executor.calls.borrow().len(), 2);
+        assert_eq!(vars.last_status(), 2);
+    }
+
+    #[test]
+    fn test_execute_line_expands_aliases() {
+        let mgr = HistoryManager::new().unwrap_or_else(|_| HistoryManager::default());
+        let mut history: Vec<String> = Vec::new();
+        let executor = MockExecutor::new();
+        let mut vars = Variables::new();
+        let mut functions = Functions::new();
+        let mut aliases = Aliases::new();
+        let mut editor = MockEditor::new(vec![]);
+
+        let result = execute_line(
+            "alias repl_test_greet='echo hello | wc -c; ls'",
+            &mut editor,
+            &mgr,
+            &mut history,
+            &executor,
+            &mut vars,
+            &mut functions,
+            &mut aliases,
+        );
+        assert!(result);
+        assert!(executor.calls.borrow().is_empty());
+
+        execute_line(
+            "repl_test_greet again",
+            &mut editor,
+            &mgr,
+            &mut history,
+            &executor,
+            &mut vars,
+            &mut functions,
+            &mut aliases,
+        );
+
+        // The alias value holds a pipeline and a second command
+        let calls = executor.calls.borrow();
+        assert_eq!(calls.len(), 3);
+        if let Command::Simple(c) = &calls[2] {
+            assert_eq!(c.name, "ls");
+            assert_eq!(c.args, vec!["again".to_string()]);
+        } else {
+            panic!("Expected simple command");
+        }
+        // History keeps what the user typed, not the expansion
+        assert_eq!(editor.history[1], "repl_test_greet again");
     }
 
     #[test]
@@ -890,9 +1288,9 @@ mod tests {
         let mgr = HistoryManager::new().unwrap_or_else(|_| HistoryManager::default());
         let mut history: Vec<String> = Vec::new();
         let executor = MockExecutor::new();
-        let mut oldpwd = None;
         let mut vars = Variables::new();
         let mut functions = Functions::new();
+        let mut aliases = Aliases::new();
         let mut editor = MockEditor::new(vec![]);
 
         // Calling complete through execute_line
@@ -902,9 +1300,9 @@ mod tests {
             &mgr,
             &mut history,
             &executor,
-            &mut oldpwd,
             &mut vars,
             &mut functions,
+            &mut aliases,
         );
         assert!(result);
         // executor should not have been called since it's a builtin
@@ -0,0 +1,288 @@
+use crate::aliases::Aliases;
+use crate::builtins::is_builtin;
+use crate::functions::Functions;
+use crate::variables::Variables;
+use std::collections::BTreeMap;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+/// A `PATH` that finds the standard utilities, for `command -p`.
+pub const DEFAULT_PATH: &str = "/usr/bin:/bin";
+
+/// Reserved words, which are recognized before aliases and commands.
+const KEYWORDS: &[&str] = &[
+    "!", "[[", "]]", "case", "coproc", "do", "done", "elif", "else", "esac", "fi", "for",
+    "function", "if", "in", "select", "then", "time", "until", "while", "{", "}",
+];
+
+pub fn is_keyword(name: &str) -> bool {
+    KEYWORDS.contains(&name)
+}
+
+/// One thing a command name can refer to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Resolution {
+    /// An alias, with its value
+    Alias(String),
+    Keyword,
+    Function,
+    Builtin,
+    /// An executable file, and whether it was found in the hash table
+    File(PathBuf, bool),
+}
+
+impl Resolution {
+    /// The kind of command, as `type -t` prints it.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Resolution::Alias(_) => "alias",
+            Resolution::Keyword => "keyword",
+            Resolution::Function => "function",
+            Resolution::Builtin => "builtin",
+            Resolution::File(..) => "file",
+        }
+    }
+
+    /// A sentence describing what `name` is, as `type` prints it.
+    pub fn describe(&self, name: &str) -> String {
+        match self {
+            Resolution::Alias(value) => format!("{} is aliased to `{}'", name, value),
+            Resolution::Keyword => format!("{} is a shell keyword", name),
+            Resolution::Function => format!("{} is a function", name),
+            Resolution::Builtin => format!("{} is a shell builtin", name),
+            Resolution::File(path, true) => format!("{} is hashed ({})", name, path.display()),
+            Resolution::File(path, false) => format!("{} is {}", name, path.display()),
+        }
+    }
+}
+
+/// Everything `name` refers to, in the order the shell looks: aliases and
+/// keywords, which take effect before a command is parsed, then functions,
+/// builtins and files. Unless `all` is set, only the first is returned, and
+/// the file is looked up through the hash table.
+pub fn resolve(
+    name: &str,
+    vars: &Variables,
+    functions: &Functions,
+    aliases: &Aliases,
+    all: bool,
+) -> Vec<Resolution> {
+    let mut found = Vec::new();
+    if let Some(value) = aliases.get(name) {
+        found.push(Resolution::Alias(value.clone()));
+    }
+    if is_keyword(name) {
+        found.push(Resolution::Keyword);
+    }
+    if functions.get(name).is_some() {
+        found.push(Resolution::Function);
+    }
+    if is_builtin(name) {
+        found.push(Resolution::Builtin);
+    }
+    let search_path = vars.get("PATH").map(String::as_str).unwrap_or("");
+    if !all {
+        if found.is_empty() {
+            if let Some(hashed) = vars.command_hash().get(name, search_path) {
+                found.push(Resolution::File(hashed, true));
+            } else if let Some(path) = locate(name, search_path).next() {
+                found.push(Resolution::File(path, false));
+            }
+        }
+        found.truncate(1);
+        return found;
+    }
+    found.extend(locate(name, search_path).map(|path| Resolution::File(path, false)));
+    found
+}
+
+/// The file to run for `name`, found through the hash table, which
+/// remembers it for next time. Names with a slash are used as they are.
+pub fn find_command(name: &str, vars: &Variables) -> Option<PathBuf> {
+    if name.contains('/') {
+        return Some(PathBuf::from(name));
+    }
+    let search_path = vars.get("PATH").map(String::as_str).unwrap_or("");
+    vars.command_hash().find(name, search_path)
+}
+
+/// Every executable file `name` stands for: itself if it has a slash,
+/// otherwise the ones in the directories of `search_path`.
+fn locate<'a>(name: &'a str, search_path: &'a str) -> Box<dyn Iterator<Item = PathBuf> + 'a> {
+    match name.contains('/') {
+        true => Box::new(
+            Some(PathBuf::from(name))
+                .into_iter()
+                .filter(|p| executable(p)),
+        ),
+        false => Box::new(search(name, search_path)),
+    }
+}
+
+/// Every executable file called `name` in the directories of `search_path`.
+pub fn search<'a>(name: &'a str, search_path: &'a str) -> impl Iterator<Item = PathBuf> + 'a {
+    search_path
+        .split(':')
+        .map(move |dir| match dir {
+            // An empty entry stands for the current directory
+            "" => Path::new(".").join(name),
+            dir => Path::new(dir).join(name),
+        })
+        .filter(|path| executable(path))
+}
+
+/// Returns true if `path` is a file anyone may execute.
+pub fn executable(path: &Path) -> bool {
+    std::fs::metadata(path).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[derive(Debug, Clone)]
+struct HashEntry {
+    path: PathBuf,
+    /// Number of times the command was run through this entry
+    hits: usize,
+}
+
+/// Where commands were found in `PATH`, so it need not be searched again.
+#[derive(Debug, Clone, Default)]
+pub struct CommandHash {
+    /// The `PATH` the entries belong to; they are forgotten when it changes
+    search_path: Option<String>,
+    entries: BTreeMap<String, HashEntry>,
+}
+
+impl CommandHash {
+    /// The remembered location of `name` in `search_path`, without
+    /// searching for it.
+    pub fn get(&self, name: &str, search_path: &str) -> Option<PathBuf> {
+        if self.search_path.as_deref() != Some(search_path) {
+            return None;
+        }
+        self.entries.get(name).map(|e| e.path.clone())
+    }
+
+    /// Look `name` up in `search_path`, remembering where it was found.
+    /// Each lookup counts as a hit.
+    pub fn find(&mut self, name: &str, search_path: &str) -> Option<PathBuf> {
+        let entry = self.entry(name, search_path)?;
+        entry.hits += 1;
+        Some(entry.path.clone())
+    }
+
+    /// Search for `name` and remember it without counting a hit, as
+    /// `hash name` does. Returns false if it was not found.
+    pub fn remember(&mut self, name: &str, search_path: &str) -> bool {
+        self.entry(name, search_path).is_some()
+    }
+
+    fn entry(&mut self, name: &str, search_path: &str) -> Option<&mut HashEntry> {
+        self.sync(search_path);
+        if !self.entries.contains_key(name) {
+            let path = search(name, search_path).next()?;
+            self.entries
+                .insert(name.to_string(), HashEntry { path, hits: 0 });
+        }
+        self.entries.get_mut(name)
+    }
+
+    /// Remember `path` as the location of `name`, as `hash -p` does.
+    pub fn insert(&mut self, name: String, path: PathBuf, search_path: &str) {
+        self.sync(search_path);
+        self.entries.insert(name, HashEntry { path, hits: 0 });
+    }
+
+    /// Forget the entries if they were found in a different `PATH`.
+    fn sync(&mut self, search_path: &str) {
+        if self.search_path.as_deref() != Some(search_path) {
+            self.entries.clear();
+            self.search_path = Some(search_path.to_string());
+        }
+    }
+
+    /// Forget every location. A new `PATH` does the same.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Every command remembered for `search_path` with its hits and
+    /// location, sorted by name.
+    pub fn entries(&mut self, search_path: &str) -> impl Iterator<Item = (&str, usize, &Path)> {
+        self.sync(search_path);
+        self.entries
+            .iter()
+            .map(|(name, e)| (name.as_str(), e.hits, e.path.as_path()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn make_executable(dir: &Path, name: &str) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_search_skips_non_executables() {
+        let first = tempfile::TempDir::new().unwrap();
+        let second = tempfile::TempDir::new().unwrap();
+        fs::write(first.path().join("tool"), "not executable").unwrap();
+        let tool = make_executable(second.path(), "tool");
+        let search_path = format!("{}:{}", first.path().display(), second.path().display());
+        assert_eq!(search("tool", &search_path).collect::<Vec<_>>(), [tool]);
+        assert_eq!(search("missing", &search_path).count(), 0);
+    }
+
+    #[test]
+    fn test_hash_is_forgotten_when_path_changes() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let tool = make_executable(dir.path(), "tool");
+        let search_path = dir.path().to_str().unwrap();
+
+        let mut hash = CommandHash::default();
+        assert!(hash.remember("tool", search_path));
+        assert_eq!(hash.find("tool", search_path), Some(tool.clone()));
+        assert_eq!(
+            hash.entries(search_path).collect::<Vec<_>>(),
+            [("tool", 1, tool.as_path())]
+        );
+        // The entry is used even after the file goes away
+        fs::remove_file(&tool).unwrap();
+        assert_eq!(hash.find("tool", search_path), Some(tool));
+        assert_eq!(hash.find("tool", "/nonexistent"), None);
+        assert_eq!(hash.entries(search_path).count(), 0);
+    }
+
+    #[test]
+    fn test_resolve_order() {
+        let mut vars = Variables::new();
+        let mut functions = Functions::new();
+        let aliases = Aliases::new();
+        let dir = tempfile::TempDir::new().unwrap();
+        let tool = make_executable(dir.path(), "cd");
+        vars.set("PATH".into(), dir.path().display().to_string());
+
+        assert_eq!(
+            resolve("cd", &vars, &functions, &aliases, false),
+            [Resolution::Builtin]
+        );
+        functions.set("cd".into(), vec![]);
+        assert_eq!(
+            resolve("cd", &vars, &functions, &aliases, true),
+            [
+                Resolution::Function,
+                Resolution::Builtin,
+                Resolution::File(tool, false)
+            ]
+        );
+        assert_eq!(
+            resolve("if", &vars, &functions, &aliases, false),
+            [Resolution::Keyword]
+        );
+        assert!(resolve("missing", &vars, &functions, &aliases, true).is_empty());
+    }
+}
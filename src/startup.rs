@@ -0,0 +1,175 @@
+use crate::aliases::Aliases;
+use crate::functions::Functions;
+use crate::history::HistoryManager;
+use crate::repl::{self, RealExecutor};
+use crate::variables::Variables;
+use std::path::{Path, PathBuf};
+
+/// Read by every interactive shell, before the user's rc file.
+const SYSTEM_RC: &str = "/etc/pmsh/pmshrc";
+
+/// The files a shell reads when it starts, in this order:
+///
+/// - login shells: `~/.pmsh_profile`
+/// - interactive shells: `/etc/pmsh/pmshrc`, then `~/.pmshrc`; `--rcfile`
+///   names a file to read instead of both, and `--norc` skips them
+/// - non-interactive shells: the file named by `$PMSH_ENV`, if set
+///
+/// Login shells also read `~/.pmsh_logout` when they exit, see
+/// `run_logout_file`.
+pub struct StartupFiles {
+    pub login: bool,
+    pub interactive: bool,
+    pub norc: bool,
+    pub rcfile: Option<PathBuf>,
+}
+
+impl StartupFiles {
+    fn paths(&self, vars: &Variables) -> Vec<PathBuf> {
+        let home = vars.get("HOME").map(PathBuf::from);
+        let mut paths = Vec::new();
+        if self.login {
+            paths.extend(home.as_ref().map(|home| home.join(".pmsh_profile")));
+        }
+        if self.interactive {
+            match &self.rcfile {
+                _ if self.norc => {}
+                Some(rcfile) => paths.push(rcfile.clone()),
+                None => {
+                    paths.push(PathBuf::from(SYSTEM_RC));
+                    paths.extend(home.as_ref().map(|home| home.join(".pmshrc")));
+                }
+            }
+        } else if let Some(env) = vars.get("PMSH_ENV") {
+            // Like POSIX `ENV`, the value undergoes parameter expansion
+            match vars.expand(env) {
+                Ok(path) if !path.is_empty() => paths.push(PathBuf::from(path)),
+                Ok(_) => {}
+                Err(e) => eprintln!("pmsh: PMSH_ENV: {}", e),
+            }
+        }
+        paths
+    }
+
+    pub fn run(
+        &self,
+        history_mgr: &HistoryManager,
+        command_history: &mut Vec<String>,
+        vars: &mut Variables,
+        functions: &mut Functions,
+        aliases: &mut Aliases,
+    ) {
+        vars.set_login_shell(self.login);
+        vars.set_interactive(self.interactive);
+        for path in self.paths(vars) {
+            source_if_exists(
+                &path,
+                history_mgr,
+                command_history,
+                vars,
+                functions,
+                aliases,
+            );
+        }
+    }
+}
+
+/// Read `~/.pmsh_logout` if this is a login shell. It is read only once.
+pub fn run_logout_file(
+    history_mgr: &HistoryManager,
+    command_history: &mut Vec<String>,
+    vars: &mut Variables,
+    functions: &mut Functions,
+    aliases: &mut Aliases,
+) {
+    if !vars.is_login_shell() {
+        return;
+    }
+    vars.set_login_shell(false);
+    if let Some(home) = vars.get("HOME").map(PathBuf::from) {
+        source_if_exists(
+            &home.join(".pmsh_logout"),
+            history_mgr,
+            command_history,
+            vars,
+            functions,
+            aliases,
+        );
+    }
+}
+
+/// Run a startup file the way the `source` builtin would. A missing file is
+/// skipped; errors are reported with the file name and line.
+fn source_if_exists(
+    path: &Path,
+    history_mgr: &HistoryManager,
+    command_history: &mut Vec<String>,
+    vars: &mut Variables,
+    functions: &mut Functions,
+    aliases: &mut Aliases,
+) {
+    if !path.exists() {
+        return;
+    }
+    if let Err(e) = repl::source_file(
+        &path.to_string_lossy(),
+        history_mgr,
+        command_history,
+        &RealExecutor,
+        vars,
+        functions,
+        aliases,
+    ) {
+        eprintln!("pmsh: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(login: bool, interactive: bool) -> StartupFiles {
+        StartupFiles {
+            login,
+            interactive,
+            norc: false,
+            rcfile: None,
+        }
+    }
+
+    #[test]
+    fn test_startup_paths() {
+        let mut vars = Variables::new();
+        vars.set("HOME".to_string(), "/home/u".to_string());
+        vars.unset("PMSH_ENV");
+
+        assert_eq!(
+            files(true, true).paths(&vars),
+            vec![
+                PathBuf::from("/home/u/.pmsh_profile"),
+                PathBuf::from(SYSTEM_RC),
+                PathBuf::from("/home/u/.pmshrc"),
+            ]
+        );
+        assert!(files(false, false).paths(&vars).is_empty());
+
+        vars.set("PMSH_ENV".to_string(), "$HOME/.env".to_string());
+        assert_eq!(
+            files(false, false).paths(&vars),
+            vec![PathBuf::from("/home/u/.env")]
+        );
+    }
+
+    #[test]
+    fn test_rcfile_and_norc() {
+        let mut vars = Variables::new();
+        vars.set("HOME".to_string(), "/home/u".to_string());
+
+        let mut startup = files(false, true);
+        startup.rcfile = Some(PathBuf::from("/tmp/rc"));
+        assert_eq!(startup.paths(&vars), vec![PathBuf::from("/tmp/rc")]);
+
+        startup.norc = true;
+        assert!(startup.paths(&vars).is_empty());
+    }
+}
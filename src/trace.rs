@@ -0,0 +1,143 @@
+use crate::builtins::common::shell_quote;
+use crate::variables::Variables;
+use nix::fcntl::{fcntl, FcntlArg};
+use std::os::fd::{BorrowedFd, RawFd};
+
+/// Prefix used when `PS4` is unset.
+const DEFAULT_PS4: &str = "+ ";
+
+/// With `set -x`, print a command about to run, with its assignments and
+/// words already expanded, to the trace output.
+pub fn trace_command(vars: &Variables, assignments: &[(String, String)], words: &[String]) {
+    if !vars.options().xtrace {
+        return;
+    }
+    let line = format!(
+        "{}{}\n",
+        prefix(vars),
+        assignments
+            .iter()
+            .map(|(key, value)| match value.as_str() {
+                "" => format!("{}=", key),
+                value => format!("{}={}", key, quote_word(value)),
+            })
+            .chain(words.iter().map(|word| quote_word(word)))
+            .collect::<Vec<_>>()
+            .join(" ")
+    );
+    write_trace(vars, &line);
+}
+
+/// With `set -x`, print a `[[ ... ]]` about to run, with its words
+/// already expanded.
+pub fn trace_conditional(vars: &Variables, words: &[String]) {
+    if !vars.options().xtrace {
+        return;
+    }
+    let words: Vec<String> = words.iter().map(|word| quote_word(word)).collect();
+    let line = format!("{}[[ {} ]]\n", prefix(vars), words.join(" "));
+    write_trace(vars, &line);
+}
+
+/// The expanded `PS4`, with its first character repeated once for every
+/// level of nesting beyond the top.
+fn prefix(vars: &Variables) -> String {
+    let ps4 = match vars.get("PS4") {
+        Some(ps4) => vars.expand(ps4).unwrap_or_else(|_| ps4.clone()),
+        None => DEFAULT_PS4.to_string(),
+    };
+    match ps4.chars().next() {
+        Some(first) => {
+            let extra = first.to_string().repeat(vars.nesting_depth() - 1);
+            format!("{}{}", extra, ps4)
+        }
+        None => ps4,
+    }
+}
+
+/// Quote `word` only if reading it back would not yield the same word.
+fn quote_word(word: &str) -> String {
+    let plain = !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-+=./:,@%^".contains(c));
+    if plain {
+        word.to_string()
+    } else {
+        shell_quote(word)
+    }
+}
+
+/// Write to the descriptor named by `PMSH_XTRACEFD` if it is open, or to
+/// standard error otherwise.
+fn write_trace(vars: &Variables, line: &str) {
+    let fd = vars
+        .get("PMSH_XTRACEFD")
+        .and_then(|fd| fd.parse::<RawFd>().ok())
+        .filter(|fd| *fd >= 0 && fcntl(*fd, FcntlArg::F_GETFD).is_ok());
+    match fd {
+        Some(fd) => {
+            let fd = unsafe { BorrowedFd::borrow_raw(fd) };
+            let mut bytes = line.as_bytes();
+            while !bytes.is_empty() {
+                match nix::unistd::write(fd, bytes) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => bytes = &bytes[n..],
+                }
+            }
+        }
+        None => eprint!("{}", line),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote_word() {
+        assert_eq!(quote_word("echo"), "echo");
+        assert_eq!(quote_word("--name=a/b.txt"), "--name=a/b.txt");
+        assert_eq!(quote_word("two words"), "'two words'");
+        assert_eq!(quote_word(""), "''");
+        assert_eq!(quote_word("it's"), "'it'\\''s'");
+    }
+
+    #[test]
+    fn test_prefix_repeats_first_char_with_depth() {
+        let mut vars = Variables::new();
+        vars.set("PS4".to_string(), "+ ".to_string());
+        assert_eq!(prefix(&vars), "+ ");
+        vars.push_scope(vec![]);
+        vars.push_scope(vec![]);
+        assert_eq!(prefix(&vars), "+++ ");
+        vars.pop_scope();
+        vars.pop_scope();
+
+        vars.set("TRACE_TAG".to_string(), "x".to_string());
+        vars.set("PS4".to_string(), "$TRACE_TAG> ".to_string());
+        vars.enter_subshell();
+        assert_eq!(prefix(&vars), "xx> ");
+    }
+
+    #[test]
+    fn test_trace_to_xtracefd() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("trace.txt");
+        let file = std::fs::File::create(&path).unwrap();
+
+        let mut vars = Variables::new();
+        vars.options_mut().xtrace = true;
+        vars.set("PS4".to_string(), "+ ".to_string());
+        vars.set(
+            "PMSH_XTRACEFD".to_string(),
+            std::os::fd::AsRawFd::as_raw_fd(&file).to_string(),
+        );
+        trace_command(&vars, &[], &["echo".to_string(), "a b".to_string()]);
+        trace_command(&vars, &[("A".to_string(), "1 2".to_string())], &[]);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "+ echo 'a b'\n+ A='1 2'\n"
+        );
+    }
+}
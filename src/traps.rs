@@ -0,0 +1,302 @@
+use crate::redirect::move_high;
+use nix::errno::Errno;
+use nix::fcntl::OFlag;
+use nix::sys::signal::{self, SaFlags, SigAction, SigHandler, SigSet, Signal};
+use std::collections::BTreeMap;
+use std::os::fd::{AsFd, OwnedFd};
+use std::sync::OnceLock;
+
+/// What a trap can be set on. Ordered as `trap -p` lists them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TrapCondition {
+    /// The shell is exiting
+    Exit,
+    Signal(Signal),
+    /// Before each simple command
+    Debug,
+    /// After a command fails where `set -e` would apply
+    Err,
+}
+
+impl TrapCondition {
+    /// Parse a condition as given to `trap`: `EXIT`, `ERR`, `DEBUG`, a signal
+    /// name with or without the `SIG` prefix, or a signal number.
+    pub fn parse(name: &str) -> Option<TrapCondition> {
+        let upper = name.to_ascii_uppercase();
+        match upper.as_str() {
+            "0" | "EXIT" => return Some(TrapCondition::Exit),
+            "DEBUG" => return Some(TrapCondition::Debug),
+            "ERR" => return Some(TrapCondition::Err),
+            _ => {}
+        }
+        if let Ok(number) = name.parse::<i32>() {
+            return Signal::try_from(number).ok().map(TrapCondition::Signal);
+        }
+        let full = if upper.starts_with("SIG") {
+            upper
+        } else {
+            format!("SIG{}", upper)
+        };
+        full.parse().ok().map(TrapCondition::Signal)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TrapCondition::Exit => "EXIT",
+            TrapCondition::Signal(sig) => sig.as_str(),
+            TrapCondition::Debug => "DEBUG",
+            TrapCondition::Err => "ERR",
+        }
+    }
+}
+
+/// Signals whose default action kills the shell. While an EXIT trap is
+/// set, they are caught so the trap still runs before the shell dies.
+const FATAL_SIGNALS: &[Signal] = &[Signal::SIGHUP, Signal::SIGINT, Signal::SIGTERM];
+
+/// The actions set with `trap`.
+#[derive(Debug, Clone, Default)]
+pub struct Traps {
+    actions: BTreeMap<TrapCondition, String>,
+    /// Set while an action runs, so DEBUG and ERR do not trigger themselves
+    running: bool,
+    /// In a subshell that has not set or reset a trap yet, the parent's
+    /// traps, which `trap` still lists
+    inherited: Option<BTreeMap<TrapCondition, String>>,
+}
+
+impl Traps {
+    /// The action for `condition`. An empty action means it is ignored.
+    pub fn get(&self, condition: TrapCondition) -> Option<&str> {
+        self.actions.get(&condition).map(String::as_str)
+    }
+
+    /// All set traps in listing order, or in a subshell that has not changed
+    /// any yet, those of its parent, as bash lists them.
+    pub fn listed(&self) -> impl Iterator<Item = (TrapCondition, &str)> {
+        let actions = self.inherited.as_ref().unwrap_or(&self.actions);
+        actions.iter().map(|(c, a)| (*c, a.as_str()))
+    }
+
+    /// Run `action` on `condition`, or ignore it if `action` is empty.
+    pub fn set(&mut self, condition: TrapCondition, action: String) -> Result<(), String> {
+        self.inherited = None;
+        if let TrapCondition::Signal(sig) = condition {
+            let handler = if action.is_empty() {
+                SigHandler::SigIgn
+            } else {
+                SigHandler::Handler(on_signal)
+            };
+            install(sig, handler)?;
+        }
+        self.actions.insert(condition, action);
+        if condition == TrapCondition::Exit {
+            self.update_fatal_handlers();
+        }
+        Ok(())
+    }
+
+    /// Restore the default behavior for `condition`.
+    pub fn reset(&mut self, condition: TrapCondition) -> Result<(), String> {
+        self.inherited = None;
+        self.actions.remove(&condition);
+        match condition {
+            TrapCondition::Signal(sig) if !self.catches_for_exit(sig) => {
+                install(sig, SigHandler::SigDfl)
+            }
+            TrapCondition::Signal(_) => install_for_exit(condition),
+            TrapCondition::Exit => {
+                self.update_fatal_handlers();
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Remove the EXIT trap and return its action, so it runs only once.
+    pub fn take_exit(&mut self) -> Option<String> {
+        self.actions.remove(&TrapCondition::Exit)
+    }
+
+    /// A subshell starts with no traps, except that ignored signals stay
+    /// ignored.
+    pub fn reset_for_subshell(&mut self) {
+        let inherited = self
+            .inherited
+            .take()
+            .unwrap_or_else(|| self.actions.clone());
+        let caught: Vec<TrapCondition> = self
+            .actions
+            .iter()
+            .filter(|(_, action)| !action.is_empty())
+            .map(|(condition, _)| *condition)
+            .collect();
+        for condition in caught {
+            let _ = self.reset(condition);
+        }
+        self.inherited = Some(inherited);
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    pub fn set_running(&mut self, running: bool) {
+        self.running = running;
+    }
+
+    /// Returns true if `sig` is only caught so that the EXIT trap runs.
+    fn catches_for_exit(&self, sig: Signal) -> bool {
+        FATAL_SIGNALS.contains(&sig)
+            && self.actions.contains_key(&TrapCondition::Exit)
+            && !self.actions.contains_key(&TrapCondition::Signal(sig))
+    }
+
+    fn update_fatal_handlers(&self) {
+        for &sig in FATAL_SIGNALS {
+            if self.actions.contains_key(&TrapCondition::Signal(sig)) {
+                continue;
+            }
+            let _ = if self.catches_for_exit(sig) {
+                install_for_exit(TrapCondition::Signal(sig))
+            } else {
+                restore_default(sig)
+            };
+        }
+    }
+}
+
+/// Catch a fatal signal for the EXIT trap, unless the shell was started
+/// with it ignored.
+fn install_for_exit(condition: TrapCondition) -> Result<(), String> {
+    if let TrapCondition::Signal(sig) = condition {
+        let old = install_raw(sig, SigHandler::Handler(on_signal))?;
+        if old == SigHandler::SigIgn {
+            install_raw(sig, SigHandler::SigIgn)?;
+        }
+    }
+    Ok(())
+}
+
+/// Undo `install_for_exit`, leaving a signal ignored at startup ignored.
+fn restore_default(sig: Signal) -> Result<(), String> {
+    let old = install_raw(sig, SigHandler::SigDfl)?;
+    if old == SigHandler::SigIgn {
+        install_raw(sig, SigHandler::SigIgn)?;
+    }
+    Ok(())
+}
+
+fn install(sig: Signal, handler: SigHandler) -> Result<(), String> {
+    install_raw(sig, handler).map(drop)
+}
+
+fn install_raw(sig: Signal, handler: SigHandler) -> Result<SigHandler, String> {
+    // Make sure the pipe exists before the handler can run
+    signal_pipe();
+    let action = SigAction::new(handler, SaFlags::SA_RESTART, SigSet::empty());
+    unsafe { signal::sigaction(sig, &action) }
+        .map(|old| old.handler())
+        .map_err(|e| format!("trap: {}: {}", sig, e.desc()))
+}
+
+/// Self-pipe the signal handler writes signal numbers into, read back at
+/// safe points between commands.
+fn signal_pipe() -> &'static (OwnedFd, OwnedFd) {
+    static PIPE: OnceLock<(OwnedFd, OwnedFd)> = OnceLock::new();
+    PIPE.get_or_init(|| {
+        let (read_end, write_end) = nix::unistd::pipe2(OFlag::O_CLOEXEC | OFlag::O_NONBLOCK)
+            .expect("failed to create signal pipe");
+        (move_high(read_end), move_high(write_end))
+    })
+}
+
+extern "C" fn on_signal(sig: nix::libc::c_int) {
+    // Only async-signal-safe calls here: a write to a non-blocking pipe
+    let saved = Errno::last_raw();
+    let (_, write_end) = signal_pipe();
+    let _ = nix::unistd::write(write_end.as_fd(), &[sig as u8]);
+    Errno::set_raw(saved);
+}
+
+/// Signals received since the last call, in order.
+pub fn take_pending_signals() -> Vec<Signal> {
+    let (read_end, _) = signal_pipe();
+    let mut signals = Vec::new();
+    let mut buf = [0u8; 64];
+    while let Ok(n @ 1..) = nix::unistd::read(std::os::fd::AsRawFd::as_raw_fd(read_end), &mut buf) {
+        signals.extend(
+            buf[..n]
+                .iter()
+                .filter_map(|&b| Signal::try_from(b as i32).ok()),
+        );
+    }
+    signals
+}
+
+/// Terminate the shell the way `sig` would have without a handler.
+pub fn die_from(sig: Signal) -> ! {
+    let _ = install(sig, SigHandler::SigDfl);
+    let _ = signal::raise(sig);
+    std::process::exit(128 + sig as i32);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_conditions() {
+        assert_eq!(TrapCondition::parse("EXIT"), Some(TrapCondition::Exit));
+        assert_eq!(TrapCondition::parse("0"), Some(TrapCondition::Exit));
+        assert_eq!(TrapCondition::parse("err"), Some(TrapCondition::Err));
+        assert_eq!(
+            TrapCondition::parse("INT"),
+            Some(TrapCondition::Signal(Signal::SIGINT))
+        );
+        assert_eq!(
+            TrapCondition::parse("SIGTERM"),
+            Some(TrapCondition::Signal(Signal::SIGTERM))
+        );
+        assert_eq!(
+            TrapCondition::parse("1"),
+            Some(TrapCondition::Signal(Signal::SIGHUP))
+        );
+        assert_eq!(TrapCondition::parse("NOPE"), None);
+        assert_eq!(TrapCondition::parse("999"), None);
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_caught_signal_is_queued() {
+        let mut traps = Traps::default();
+        let cond = TrapCondition::Signal(Signal::SIGUSR1);
+        traps.set(cond, "echo caught".to_string()).unwrap();
+        take_pending_signals();
+
+        signal::raise(Signal::SIGUSR1).unwrap();
+        assert_eq!(take_pending_signals(), vec![Signal::SIGUSR1]);
+        assert!(take_pending_signals().is_empty());
+
+        traps.reset(cond).unwrap();
+        assert_eq!(traps.get(cond), None);
+    }
+
+    #[test]
+    fn test_subshell_keeps_only_ignored_traps() {
+        let mut traps = Traps::default();
+        traps
+            .set(TrapCondition::Exit, "echo bye".to_string())
+            .unwrap();
+        traps.set(TrapCondition::Debug, String::new()).unwrap();
+        traps.reset_for_subshell();
+        assert_eq!(traps.get(TrapCondition::Exit), None);
+        assert_eq!(traps.get(TrapCondition::Debug), Some(""));
+
+        // The parent's traps are listed until the subshell changes one
+        let listed: Vec<_> = traps.listed().map(|(c, _)| c).collect();
+        assert_eq!(listed, vec![TrapCondition::Exit, TrapCondition::Debug]);
+        traps.reset(TrapCondition::Debug).unwrap();
+        assert_eq!(traps.listed().count(), 0);
+    }
+}
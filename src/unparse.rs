@@ -0,0 +1,359 @@
+use crate::arrays;
+use crate::conditional::{QUOTED_END, QUOTED_START};
+use crate::parser::{AndOrOp, Command, Redirect, RedirectKind};
+
+/// Spaces per level of nesting.
+const INDENT: usize = 4;
+
+/// The definition of function `name` with `body`, as `declare -f` shows it.
+/// Simple commands keep their words as written; other words, such as those
+/// of `for`, have lost their quotes and are quoted again where needed.
+pub fn function(name: &str, body: &[Vec<Command>]) -> String {
+    let mut printer = Printer::default();
+    printer.function(name, body);
+    printer.out
+}
+
+#[derive(Default)]
+struct Printer {
+    out: String,
+    depth: usize,
+    /// Bodies of here-documents on the current line, written after it.
+    heredocs: Vec<String>,
+}
+
+impl Printer {
+    fn function(&mut self, name: &str, body: &[Vec<Command>]) {
+        self.out.push_str(&format!("{} ()\n", name));
+        self.start_line();
+        self.out.push('{');
+        self.block(body);
+        self.out.push('}');
+        self.end_line();
+    }
+
+    fn start_line(&mut self) {
+        self.out.push_str(&" ".repeat(self.depth * INDENT));
+    }
+
+    /// Finish a line, followed by the here-documents it started.
+    fn end_line(&mut self) {
+        self.out.push('\n');
+        for body in std::mem::take(&mut self.heredocs) {
+            let delimiter = delimiter_for(&body);
+            self.out.push_str(&body);
+            if !body.is_empty() && !body.ends_with('\n') {
+                self.out.push('\n');
+            }
+            self.out.push_str(&delimiter);
+            self.out.push('\n');
+        }
+    }
+
+    /// A list indented one level deeper on lines of its own, leaving the
+    /// output at the start of the line that closes it.
+    fn block(&mut self, list: &[Vec<Command>]) {
+        self.end_line();
+        self.depth += 1;
+        for pipeline in list {
+            self.start_line();
+            self.pipeline(pipeline);
+            self.end_line();
+        }
+        self.depth -= 1;
+        self.start_line();
+    }
+
+    /// A list on one line, as in the guard of `if` or `while`.
+    fn inline(&mut self, list: &[Vec<Command>]) {
+        for (i, pipeline) in list.iter().enumerate() {
+            if i > 0 {
+                self.out.push_str("; ");
+            }
+            self.pipeline(pipeline);
+        }
+    }
+
+    fn pipeline(&mut self, pipeline: &[Command]) {
+        for (i, cmd) in pipeline.iter().enumerate() {
+            if i > 0 {
+                self.out.push_str(" | ");
+            }
+            self.command(cmd);
+        }
+    }
+
+    fn command(&mut self, cmd: &Command) {
+        match cmd {
+            Command::Simple(simple) => {
+                let mut words: Vec<String> = simple
+                    .assignments
+                    .iter()
+                    .map(|(name, value)| format!("{}={}", name, assigned_value(value)))
+                    .chain(
+                        std::iter::once(&simple.name)
+                            .filter(|n| !n.is_empty())
+                            .map(|n| word(n)),
+                    )
+                    .chain(simple.args.iter().map(|arg| word(arg)))
+                    .collect();
+                // Words are written as they were, except array literals,
+                // which the parser has already taken apart
+                if simple.quoted.len() == words.len() {
+                    for (w, quoted) in words.iter_mut().zip(&simple.quoted) {
+                        if !quoted.contains(arrays::LITERAL_START) {
+                            w.clone_from(quoted);
+                        }
+                    }
+                }
+                self.out.push_str(&words.join(" "));
+            }
+            Command::Subshell(body) => {
+                self.out.push('(');
+                self.block(body);
+                self.out.push(')');
+            }
+            Command::Group(body) => {
+                self.out.push('{');
+                self.block(body);
+                self.out.push('}');
+            }
+            Command::FunctionDef(name, body) => {
+                self.out.push_str(&format!("{} () {{", name));
+                self.block(body);
+                self.out.push('}');
+            }
+            Command::AndOr(first, rest) => {
+                self.pipeline(first);
+                for (op, pipeline) in rest {
+                    self.out.push_str(match op {
+                        AndOrOp::And => " && ",
+                        AndOrOp::Or => " || ",
+                    });
+                    self.pipeline(pipeline);
+                }
+            }
+            Command::Not(pipeline) => {
+                self.out.push_str("! ");
+                self.pipeline(pipeline);
+            }
+            Command::If {
+                conditionals,
+                else_branch,
+            } => {
+                for (i, (guard, body)) in conditionals.iter().enumerate() {
+                    self.out.push_str(if i == 0 { "if " } else { "elif " });
+                    self.inline(guard);
+                    self.out.push_str("; then");
+                    self.block(body);
+                }
+                if let Some(body) = else_branch {
+                    self.out.push_str("else");
+                    self.block(body);
+                }
+                self.out.push_str("fi");
+            }
+            Command::While(guard, body) | Command::Until(guard, body) => {
+                let keyword = match cmd {
+                    Command::While(..) => "while ",
+                    _ => "until ",
+                };
+                self.out.push_str(keyword);
+                self.inline(guard);
+                self.out.push_str("; do");
+                self.block(body);
+                self.out.push_str("done");
+            }
+            Command::For(var, words, body) => {
+                self.out.push_str(&format!("for {}", var));
+                if let Some(words) = words {
+                    self.out.push_str(" in");
+                    for w in words {
+                        self.out.push(' ');
+                        self.out.push_str(&word(w));
+                    }
+                }
+                self.out.push_str("; do");
+                self.block(body);
+                self.out.push_str("done");
+            }
+            Command::Redirected(inner, redirects) => {
+                self.command(inner);
+                for redirect in redirects {
+                    self.out.push(' ');
+                    self.redirect(redirect);
+                }
+            }
+            Command::Background(pipeline) => {
+                self.pipeline(pipeline);
+                self.out.push_str(" &");
+            }
+            // The words are kept as written: quoting them would turn
+            // operators such as `<` and `&&` into strings
+            Command::Conditional(words, _) => {
+                let words = words.join(" ").replace([QUOTED_START, QUOTED_END], "\"");
+                self.out.push_str(&format!("[[ {} ]]", words));
+            }
+        }
+    }
+
+    fn redirect(&mut self, redirect: &Redirect) {
+        let (default_fd, op) = match redirect.kind {
+            RedirectKind::Read => (0, "<"),
+            RedirectKind::Write => (1, ">"),
+            RedirectKind::Append => (1, ">>"),
+            RedirectKind::Clobber => (1, ">|"),
+            RedirectKind::ReadWrite => (0, "<>"),
+            RedirectKind::Heredoc => (0, "<<"),
+            RedirectKind::DupRead => (0, "<&"),
+            RedirectKind::DupWrite => (1, ">&"),
+        };
+        if redirect.fd != default_fd {
+            self.out.push_str(&redirect.fd.to_string());
+        }
+        self.out.push_str(op);
+        match redirect.kind {
+            RedirectKind::Heredoc => {
+                self.out.push_str(&delimiter_for(&redirect.target));
+                self.heredocs.push(redirect.target.clone());
+            }
+            RedirectKind::DupRead | RedirectKind::DupWrite => {
+                self.out.push_str(&word(&redirect.target));
+            }
+            _ => {
+                self.out.push(' ');
+                self.out.push_str(&word(&redirect.target));
+            }
+        }
+    }
+}
+
+/// Write a word back out. A word that would now be split or read as an
+/// operator goes in double quotes, which keep its expansions, and so does
+/// one with a whole array in it that was not split.
+fn word(w: &str) -> String {
+    let list = (w.contains("[@]}") || w.contains("[*]}")) && !w.contains(arrays::SPLIT_MARK);
+    let w = &w.replace(arrays::SPLIT_MARK, "");
+    let special = |c: char| c.is_whitespace() || ";&|<>()'\"\\`".contains(c);
+    if !list && !w.is_empty() && !w.contains(special) {
+        return w.to_string();
+    }
+    let mut quoted = String::from('"');
+    for c in w.chars() {
+        if matches!(c, '"' | '\\' | '`') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// The value of an assignment, which may be an array literal.
+fn assigned_value(value: &str) -> String {
+    match arrays::literal_elements(value) {
+        Some(elements) => {
+            let elements: Vec<String> = elements.iter().map(|e| word(e)).collect();
+            format!("({})", elements.join(" "))
+        }
+        None if value.is_empty() => String::new(),
+        None => word(value),
+    }
+}
+
+/// A here-document delimiter that does not occur as a line of `body`.
+fn delimiter_for(body: &str) -> String {
+    let mut delimiter = "EOF".to_string();
+    let mut n = 0;
+    while body.lines().any(|line| line == delimiter) {
+        n += 1;
+        delimiter = format!("EOF{}", n);
+    }
+    delimiter
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn show(source: &str) -> String {
+        let parsed = Command::parse(source).unwrap();
+        match &parsed[0][0] {
+            Command::FunctionDef(name, body) => function(name, body),
+            other => panic!("expected a function, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_simple_function() {
+        assert_eq!(
+            show("greet() { echo hello $1; x=1 y= env | grep x > /dev/null; }"),
+            "greet ()\n{\n    echo hello $1\n    x=1 y= env | grep x > /dev/null\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_compound_commands() {
+        let source = "f() {
+  if [ $# -gt 0 ]; then for a in $@; do echo $a; done
+  elif true; then (cd /tmp && pwd) || echo no
+  else while ! false; do break; done
+  fi
+}";
+        assert_eq!(
+            show(source),
+            "f ()
+{
+    if [ $# -gt 0 ]; then
+        for a in $@; do
+            echo $a
+        done
+    elif true; then
+        (
+            cd /tmp && pwd
+        ) || echo no
+    else
+        while ! false; do
+            break
+        done
+    fi
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_heredoc_and_redirects() {
+        let source = "f() {\n  cat <<END 2>&1\nEOF\nline $x\nEND\n  echo 2>> log\n}";
+        assert_eq!(
+            show(source),
+            "f ()\n{\n    cat <<EOF1 2>&1\nEOF\nline $x\nEOF1\n    echo 2>> log\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_words_keep_their_quotes() {
+        assert_eq!(
+            show("f() { [ -n \"$1\" ] && x='a $b' echo \\$y \"${a[@]}\" ${a[1]} > \"$o\"; }"),
+            "f ()\n{\n    [ -n \"$1\" ] && x='a $b' echo \\$y \"${a[@]}\" ${a[1]} > $o\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_word_quoting() {
+        assert_eq!(word("plain$x"), "plain$x");
+        assert_eq!(word("two words"), "\"two words\"");
+        assert_eq!(word(""), "\"\"");
+        assert_eq!(word("a\"b"), "\"a\\\"b\"");
+        assert_eq!(word("${a[@]}"), "\"${a[@]}\"");
+        assert_eq!(word(&format!("{}${{a[@]}}", arrays::SPLIT_MARK)), "${a[@]}");
+        assert_eq!(
+            assigned_value(&format!(
+                "{}a b{}c",
+                arrays::LITERAL_START,
+                arrays::ELEMENT_SEP
+            )),
+            "(\"a b\" c)"
+        );
+    }
+}
@@ -1,110 +1,1077 @@
+use crate::arith;
+use crate::arrays::{self, Array, Key, Target, SPLIT_MARK};
+use crate::dirstack::DirStack;
+use crate::options::ShellOptions;
+use crate::resolve::CommandHash;
+use crate::traps::Traps;
+use std::cell::{Cell, RefCell, RefMut};
 use std::collections::{HashMap, HashSet};
 use std::env;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How many namerefs may be followed before giving up on a loop.
+const MAX_NAMEREFS: usize = 8;
+
+/// Attributes of a variable, set with `declare`, `local`, `export` and
+/// `readonly`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Attributes {
+    pub exported: bool,
+    pub readonly: bool,
+    /// `-i`: assigned values are evaluated as arithmetic expressions.
+    pub integer: bool,
+    /// `-l` and `-u`: assigned values are converted to lower or upper case.
+    pub lowercase: bool,
+    pub uppercase: bool,
+    /// `-n`: the value names another variable, which is used instead.
+    pub nameref: bool,
+}
+
+impl Attributes {
+    /// The attribute letters as `declare` takes them, in the order
+    /// `declare -p` prints them.
+    pub fn letters(&self) -> String {
+        [
+            (self.integer, 'i'),
+            (self.lowercase, 'l'),
+            (self.nameref, 'n'),
+            (self.readonly, 'r'),
+            (self.uppercase, 'u'),
+            (self.exported, 'x'),
+        ]
+        .iter()
+        .filter(|(set, _)| *set)
+        .map(|(_, letter)| *letter)
+        .collect()
+    }
+
+    /// Returns true if every attribute set in `other` is also set here.
+    pub fn contains(&self, other: Attributes) -> bool {
+        (!other.exported || self.exported)
+            && (!other.readonly || self.readonly)
+            && (!other.integer || self.integer)
+            && (!other.lowercase || self.lowercase)
+            && (!other.uppercase || self.uppercase)
+            && (!other.nameref || self.nameref)
+    }
+}
+
+/// A variable declared with `local` inside a function frame.
+#[derive(Debug, Clone, Default)]
+struct LocalVar {
+    /// `None` means declared but unset; it still shadows outer values.
+    value: Option<String>,
+    /// Set instead of `value` when the local is an array.
+    array: Option<Array>,
+    attrs: Attributes,
+}
+
+/// The variables and positional arguments owned by one function call.
+#[derive(Debug, Clone, Default)]
+struct Scope {
+    locals: HashMap<String, LocalVar>,
+    saved_positional_args: Vec<String>,
+}
 
 #[derive(Debug, Clone, Default)]
 pub struct Variables {
     vars: HashMap<String, String>,
-    exported: HashSet<String>,
+    /// Array variables; a name is either here or in `vars`, not both.
+    arrays: HashMap<String, Array>,
+    /// Attributes of global variables. A name may have attributes before
+    /// it has a value, as after `declare -i n`.
+    attrs: HashMap<String, Attributes>,
     positional_args: Vec<String>,
+    /// `$0`: the shell's name, or the script being run.
+    arg0: String,
+    /// Function scopes, innermost last. Lookups walk them before the globals,
+    /// which gives locals dynamic scope: callees see their callers' locals.
+    scopes: Vec<Scope>,
+    /// Exit status of the most recent pipeline, i.e. `$?`.
+    last_status: i32,
+    /// Set by `return` while a function body or sourced file is unwinding.
+    pending_return: Option<i32>,
+    /// Number of files currently being executed by `source`.
+    source_depth: usize,
+    options: ShellOptions,
+    /// Nesting depth of commands whose status is being tested (`if`/`while`
+    /// guards, `&&`/`||` operands, `!`), where `set -e` does not apply.
+    condition_depth: usize,
+    /// Number of subshells this process is nested in.
+    subshell_depth: usize,
+    traps: Traps,
+    /// Script or sourced file being run, for error messages.
+    source_name: Option<String>,
+    /// Line the running command starts on, or 0 for interactive input.
+    lineno: usize,
+    login_shell: bool,
+    /// Reading commands from a terminal, or started with `-i`.
+    interactive: bool,
+    /// `$$`: the main shell's process ID, which subshells keep.
+    shell_pid: u32,
+    /// `$!`: process ID of the most recent background command.
+    last_background_pid: Option<i32>,
+    /// Background commands that have not been collected yet.
+    background_pids: Vec<i32>,
+    /// `$_`: the last argument of the previous simple command.
+    last_arg: String,
+    /// State of the `RANDOM` generator; reading `$RANDOM` advances it.
+    random_state: Cell<u32>,
+    /// Set when an expansion fails on an unset variable under `set -u`.
+    /// The error itself is a plain message; this tells whoever reports it
+    /// that a non-interactive shell must exit.
+    unbound: Cell<bool>,
+    /// Epoch time at which `SECONDS` was zero.
+    seconds_origin: i64,
+    /// Where `getopts` is in a word of clustered options: the `OPTIND` it
+    /// belongs to and the offset of the next option letter.
+    getopts_cursor: Option<(usize, usize)>,
+    /// Locations of commands found in `PATH`. Looking a command up
+    /// remembers it, so the table changes behind shared references.
+    command_hash: RefCell<CommandHash>,
+    /// Directories saved by `pushd`.
+    dir_stack: DirStack,
+}
+
+/// Current time in whole seconds since the epoch.
+fn epoch_seconds() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
 }
 
 impl Variables {
     pub fn new() -> Self {
         let mut vars = HashMap::new();
-        let mut exported = HashSet::new();
+        let mut attrs = HashMap::new();
         // Initialize with environment variables — all are considered exported
+        let exported = Attributes {
+            exported: true,
+            ..Default::default()
+        };
         for (key, value) in env::vars() {
-            exported.insert(key.clone());
+            attrs.insert(key.clone(), exported);
             vars.insert(key, value);
         }
+        vars.insert("PPID".to_string(), nix::unistd::getppid().to_string());
+        vars.insert("OPTIND".to_string(), "1".to_string());
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let seed = now.subsec_nanos() ^ (now.as_secs() as u32) ^ std::process::id();
         Variables {
             vars,
-            exported,
+            arrays: HashMap::new(),
+            attrs,
             positional_args: Vec::new(),
+            arg0: "pmsh".to_string(),
+            scopes: Vec::new(),
+            last_status: 0,
+            pending_return: None,
+            source_depth: 0,
+            options: ShellOptions::default(),
+            condition_depth: 0,
+            subshell_depth: 0,
+            traps: Traps::default(),
+            source_name: None,
+            lineno: 0,
+            login_shell: false,
+            interactive: false,
+            shell_pid: std::process::id(),
+            last_background_pid: None,
+            background_pids: Vec::new(),
+            last_arg: String::new(),
+            random_state: Cell::new(seed),
+            unbound: Cell::new(false),
+            seconds_origin: now.as_secs() as i64,
+            getopts_cursor: None,
+            command_hash: RefCell::default(),
+            dir_stack: DirStack::default(),
         }
     }
 
-    #[allow(dead_code)]
+    /// Record the process ID of a command started in the background.
+    pub fn set_last_background_pid(&mut self, pid: i32) {
+        self.last_background_pid = Some(pid);
+        self.background_pids.push(pid);
+    }
+
+    /// Process IDs of the background commands not collected yet.
+    pub fn background_pids_mut(&mut self) -> &mut Vec<i32> {
+        &mut self.background_pids
+    }
+
+    /// Remember the last argument of a simple command for `$_`.
+    pub fn set_last_arg(&mut self, arg: String) {
+        self.last_arg = arg;
+    }
+
+    /// Increment and export `SHLVL`, as every new shell does at startup.
+    pub fn increment_shell_level(&mut self) {
+        let level = self
+            .get("SHLVL")
+            .and_then(|v| v.trim().parse::<i64>().ok())
+            .unwrap_or(0);
+        self.set("SHLVL".to_string(), (level.max(0) + 1).to_string());
+        self.export("SHLVL");
+    }
+
+    /// Set and export `PWD` at startup. An inherited value is kept if it
+    /// names the current directory, which preserves the symlinks in it.
+    pub fn init_pwd(&mut self) {
+        let Ok(cwd) = env::current_dir() else {
+            return;
+        };
+        let inherited = self.get("PWD").filter(|pwd| {
+            pwd.starts_with('/')
+                && std::fs::canonicalize(pwd).ok() == std::fs::canonicalize(&cwd).ok()
+        });
+        if inherited.is_none() {
+            self.set("PWD".to_string(), cwd.to_string_lossy().into_owned());
+        }
+        self.export("PWD");
+    }
+
+    /// Values computed on every expansion rather than stored.
+    fn dynamic_value(&self, key: &str) -> Option<String> {
+        match key {
+            "_" => Some(self.last_arg.clone()),
+            "RANDOM" => {
+                // The same linear congruential generator as bash, so a
+                // seeded sequence is reproducible
+                let state = self
+                    .random_state
+                    .get()
+                    .wrapping_mul(1103515245)
+                    .wrapping_add(12345);
+                self.random_state.set(state);
+                Some(((state >> 16) & 0x7fff).to_string())
+            }
+            "SECONDS" => Some((epoch_seconds() - self.seconds_origin).to_string()),
+            "EPOCHSECONDS" => Some(epoch_seconds().to_string()),
+            "LINENO" => Some(self.lineno.to_string()),
+            "PMSH_PID" => Some(std::process::id().to_string()),
+            _ => None,
+        }
+    }
+
+    /// Find the innermost local binding for `key`, if any.
+    fn local(&self, key: &str) -> Option<&LocalVar> {
+        self.scopes.iter().rev().find_map(|s| s.locals.get(key))
+    }
+
+    fn local_mut(&mut self, key: &str) -> Option<&mut LocalVar> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .find_map(|s| s.locals.get_mut(key))
+    }
+
+    /// Follow namerefs from `name` to the variable it refers to.
+    fn resolve(&self, name: &str) -> String {
+        let mut name = name.to_string();
+        for _ in 0..MAX_NAMEREFS {
+            if !self.attributes(&name).nameref {
+                break;
+            }
+            match self.raw_value(&name) {
+                Some(target) if arrays::is_name(target) => name = target.clone(),
+                _ => break,
+            }
+        }
+        name
+    }
+
+    /// The value stored under `name` itself, without following namerefs.
+    fn raw_value(&self, name: &str) -> Option<&String> {
+        match self.local(name) {
+            Some(local) => local.value.as_ref(),
+            None => self.vars.get(name),
+        }
+    }
+
+    /// Assign a value. If a function scope has a local of that name, the
+    /// innermost one is updated; otherwise the global is set.
+    /// Readonly variables are left untouched; call `check_writable` first.
+    /// Attributes that convert values are applied by `assign`.
     pub fn set(&mut self, key: String, value: String) {
+        let key = self.resolve(&key);
+        if self.is_readonly(&key) {
+            return;
+        }
+        self.set_value(key, value);
+    }
+
+    /// Store `value` under `key` itself, following no namerefs.
+    fn set_value(&mut self, key: String, value: String) {
+        if key == "?" {
+            // `$?` is shell state, not a variable; never export it
+            self.last_status = value.parse().unwrap_or(0);
+            return;
+        }
+        match key.as_str() {
+            // Assigning to RANDOM seeds it, and to SECONDS sets its count
+            "RANDOM" => {
+                self.random_state.set(value.trim().parse().unwrap_or(0));
+                return;
+            }
+            "SECONDS" => {
+                self.seconds_origin = epoch_seconds() - value.trim().parse::<i64>().unwrap_or(0);
+                return;
+            }
+            _ => {}
+        }
+        // Assigning to an array's plain name sets its first element
+        if let Some(array) = self.array_mut(&key) {
+            array.set(Key::Index(0), value);
+            return;
+        }
+        if let Some(local) = self.local_mut(&key) {
+            local.value = Some(value);
+            return;
+        }
         self.vars.insert(key, value);
     }
 
-    /// Remove a variable from the shell's internal map only.
-    /// Used for scoped variable restoration (e.g. temporary VAR=val assignments).
-    /// Does NOT touch the process environment.
-    pub fn remove(&mut self, key: &str) {
-        self.vars.remove(key);
-        self.exported.remove(key);
+    /// Apply the attributes of `name` to a value being assigned to it.
+    /// `old` is the value being appended to with `+=`.
+    fn convert(&self, name: &str, old: Option<&str>, value: String) -> Result<String, String> {
+        let attrs = self.attributes(name);
+        let value = if attrs.integer {
+            let old = match old {
+                Some(old) => arith::eval(old, self)?,
+                None => 0,
+            };
+            old.wrapping_add(arith::eval(&value, self)?).to_string()
+        } else {
+            format!("{}{}", old.unwrap_or_default(), value)
+        };
+        Ok(if attrs.lowercase {
+            value.to_lowercase()
+        } else if attrs.uppercase {
+            value.to_uppercase()
+        } else {
+            value
+        })
+    }
+
+    /// Perform the assignment `lhs=value`, where `lhs` is a name, possibly
+    /// with a subscript and a trailing `+` for `+=`, and `value` is not yet
+    /// expanded. Returns the value assigned, for tracing.
+    pub fn assign(&mut self, lhs: &str, value: &str) -> Result<String, String> {
+        let target =
+            Target::parse(lhs).ok_or_else(|| format!("{}: not a valid identifier", lhs))?;
+        let name = &self.resolve(target.name);
+        self.check_writable(name)?;
+        if let Some(elements) = arrays::literal_elements(value) {
+            if target.subscript.is_some() {
+                return Err(format!("{}: cannot assign list to array member", lhs));
+            }
+            return self.assign_list(name, &elements, target.append);
+        }
+
+        let value = self.expand(value)?;
+        let subscript = match target.subscript {
+            Some(subscript) => self.expand(subscript)?,
+            None if self.array_ref(name).is_some() => "0".to_string(),
+            None => {
+                let old = self.get(name).filter(|_| target.append);
+                let value = self.convert(name, old.map(|s| s.as_str()), value)?;
+                self.set_value(name.to_string(), value.clone());
+                return Ok(value);
+            }
+        };
+        // Assigning an element makes a scalar into an indexed array
+        self.declare_array(name, false)?;
+        let Some(array) = self.array_ref(name) else {
+            return Ok(value);
+        };
+        let key = array.key(&subscript, |s| arith::eval(s, self))?;
+        let old = array.get(&key).filter(|_| target.append);
+        let value = self.convert(name, old.map(|s| s.as_str()), value)?;
+        if let Some(array) = self.array_mut(name) {
+            array.set(key, value.clone());
+        }
+        Ok(value)
+    }
+
+    /// Assign the elements of an array literal, `(a b)` or `([k]=v ...)`.
+    /// An associative array stays associative; anything else becomes an
+    /// indexed array.
+    fn assign_list(
+        &mut self,
+        name: &str,
+        elements: &[&str],
+        append: bool,
+    ) -> Result<String, String> {
+        let mut array = match self.array_ref(name) {
+            Some(array) if append => array.clone(),
+            Some(array) if array.is_assoc() => Array::assoc(),
+            _ => Array::indexed(),
+        };
+        if append && self.array_ref(name).is_none() {
+            if let Some(old) = self.get(name) {
+                array.set(Key::Index(0), old.clone());
+            }
+        }
+        let mut next = array.next_index();
+        for element in elements {
+            let keyed = element
+                .strip_prefix('[')
+                .and_then(|rest| rest.split_once("]="));
+            if let Some((subscript, value)) = keyed {
+                let subscript = self.expand(subscript)?;
+                let key = array.key(&subscript, |s| arith::eval(s, self))?;
+                if let Key::Index(i) = key {
+                    next = i + 1;
+                }
+                let value = self.expand(value)?;
+                array.set(key, self.convert(name, None, value)?);
+                continue;
+            }
+            for value in self.expand_fields(element)? {
+                let value = self.convert(name, None, value)?;
+                if array.is_assoc() {
+                    return Err(format!(
+                        "{}: {}: must use subscript when assigning associative array",
+                        name, value
+                    ));
+                }
+                array.set(Key::Index(next), value);
+                next += 1;
+            }
+        }
+        let shown = format!("({})", array.values().join(" "));
+        self.store_array(name, array);
+        Ok(shown)
+    }
+
+    /// The array bound to `name`: the innermost local's if `name` is
+    /// local, otherwise the global one.
+    fn array_ref(&self, name: &str) -> Option<&Array> {
+        match self.local(name) {
+            Some(local) => local.array.as_ref(),
+            None => self.arrays.get(name),
+        }
+    }
+
+    fn array_mut(&mut self, name: &str) -> Option<&mut Array> {
+        if self.local(name).is_some() {
+            return self.local_mut(name).and_then(|local| local.array.as_mut());
+        }
+        self.arrays.get_mut(name)
+    }
+
+    /// Set `name` to an indexed array of `values`, taken as they are.
+    pub fn set_array(&mut self, name: &str, values: Vec<String>) {
+        let name = &self.resolve(name);
+        if self.is_readonly(name) {
+            return;
+        }
+        let mut array = Array::indexed();
+        for (i, value) in values.into_iter().enumerate() {
+            array.set(Key::Index(i), value);
+        }
+        self.store_array(name, array);
+    }
+
+    /// Bind `array` to `name`, replacing any scalar value.
+    fn store_array(&mut self, name: &str, array: Array) {
+        if let Some(local) = self.local_mut(name) {
+            local.value = None;
+            local.array = Some(array);
+            return;
+        }
+        self.vars.remove(name);
+        self.arrays.insert(name.to_string(), array);
+    }
+
+    /// Make `name` an array, as `declare -a` or `declare -A` do. A scalar
+    /// value becomes the first element of an indexed array.
+    pub fn declare_array(&mut self, name: &str, assoc: bool) -> Result<(), String> {
+        let name = &self.resolve(name);
+        match self.array_ref(name) {
+            Some(array) if assoc && !array.is_assoc() => Err(format!(
+                "{}: cannot convert indexed to associative array",
+                name
+            )),
+            Some(_) => Ok(()),
+            None => {
+                let mut array = if assoc {
+                    Array::assoc()
+                } else {
+                    Array::indexed()
+                };
+                if let Some(value) = self.raw_value(name) {
+                    array.set(Key::Index(0), value.clone());
+                }
+                self.store_array(name, array);
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns the array `name`, if it is one.
+    pub fn array(&self, name: &str) -> Option<&Array> {
+        self.array_ref(&self.resolve(name))
+    }
+
+    /// Names of all variables visible here, including those declared
+    /// without a value, sorted.
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .vars
+            .keys()
+            .chain(self.arrays.keys())
+            .chain(self.attrs.keys())
+            .chain(self.scopes.iter().flat_map(|s| s.locals.keys()))
+            .cloned()
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Describe `name` as a `declare` command that recreates it, as
+    /// `declare -p` prints it. Returns `None` for unknown names.
+    pub fn declaration(&self, name: &str) -> Option<String> {
+        let attrs = self.attributes(name);
+        let array = self.array_ref(name);
+        let value = self.raw_value(name);
+        if array.is_none() && value.is_none() && attrs == Attributes::default() {
+            return None;
+        }
+        let kind = match array {
+            Some(array) if array.is_assoc() => "A",
+            Some(_) => "a",
+            None => "",
+        };
+        let mut flags = format!("{}{}", kind, attrs.letters());
+        if flags.is_empty() {
+            flags.push('-');
+        }
+        Some(match (array, value) {
+            (Some(array), _) => format!("declare -{} {}={}", flags, name, array.to_literal()),
+            (None, Some(value)) => format!(
+                "declare -{} {}={}",
+                flags,
+                name,
+                arrays::double_quote(value)
+            ),
+            (None, None) => format!("declare -{} {}", flags, name),
+        })
+    }
+
+    /// The attributes of `name` itself, without following namerefs.
+    pub fn attributes(&self, name: &str) -> Attributes {
+        match self.local(name) {
+            Some(local) => local.attrs,
+            None => self.attrs.get(name).copied().unwrap_or_default(),
+        }
+    }
+
+    /// Change the attributes of `name` itself: those in `add` are set and
+    /// those in `remove` cleared. The readonly attribute cannot be cleared.
+    pub fn set_attributes(
+        &mut self,
+        name: &str,
+        add: Attributes,
+        remove: Attributes,
+    ) -> Result<(), String> {
+        if remove.readonly && self.is_readonly(name) {
+            return Err(format!("{}: readonly variable", name));
+        }
+        let attrs = match self.local_mut(name) {
+            Some(local) => &mut local.attrs,
+            None => self.attrs.entry(name.to_string()).or_default(),
+        };
+        let update = |flag: &mut bool, add: bool, remove: bool| *flag = (*flag || add) && !remove;
+        update(&mut attrs.exported, add.exported, remove.exported);
+        update(&mut attrs.readonly, add.readonly, false);
+        update(&mut attrs.integer, add.integer, remove.integer);
+        update(&mut attrs.nameref, add.nameref, remove.nameref);
+        // Lower and upper case exclude each other; the newest wins
+        update(
+            &mut attrs.lowercase,
+            add.lowercase,
+            remove.lowercase || add.uppercase,
+        );
+        update(
+            &mut attrs.uppercase,
+            add.uppercase,
+            remove.uppercase || add.lowercase,
+        );
+        Ok(())
+    }
+
+    /// Make `name` refer to `target`, as `declare -n name=target` does.
+    pub fn set_nameref(&mut self, name: &str, target: &str) -> Result<(), String> {
+        if !arrays::is_name(target) || target == name {
+            return Err(format!(
+                "{}: invalid variable name for name reference",
+                target
+            ));
+        }
+        self.check_writable(name)?;
+        self.set_value(name.to_string(), target.to_string());
+        self.set_attributes(
+            name,
+            Attributes {
+                nameref: true,
+                ..Default::default()
+            },
+            Attributes::default(),
+        )
     }
 
     /// Mark a variable as exported to child processes.
-    /// If the variable exists in the shell, it is also propagated to the process environment.
     pub fn export(&mut self, key: &str) {
-        self.exported.insert(key.to_string());
-        if let Some(val) = self.vars.get(key) {
-            env::set_var(key, val);
+        if let Some(local) = self.local_mut(key) {
+            local.attrs.exported = true;
+            return;
         }
+        self.attrs.entry(key.to_string()).or_default().exported = true;
     }
 
-    /// Unset a variable: removes it from the shell and the process environment.
+    /// Unset a variable, which also stops exporting it.
+    /// A local is unset in its own scope and keeps shadowing outer values.
+    /// Readonly variables are kept; call `check_writable` first.
     pub fn unset(&mut self, key: &str) {
+        // `unset 'a[i]'` removes one element; `a[@]` the whole array
+        if let Some(Target {
+            name,
+            subscript: Some(subscript),
+            ..
+        }) = Target::parse(key)
+        {
+            let name = &self.resolve(name);
+            if self.is_readonly(name) {
+                return;
+            }
+            if subscript == "@" || subscript == "*" {
+                if self.array_ref(name).is_some() {
+                    self.unset(name);
+                }
+            } else if let Some(array) = self.array_ref(name) {
+                if let Ok(key) = array.key(subscript, |s| arith::eval(s, self)) {
+                    if let Some(array) = self.array_mut(name) {
+                        array.remove(&key);
+                    }
+                }
+            }
+            return;
+        }
+        let key = &self.resolve(key);
+        if self.is_readonly(key) {
+            return;
+        }
+        if let Some(local) = self.local_mut(key) {
+            local.value = None;
+            local.array = None;
+            return;
+        }
         self.vars.remove(key);
-        self.exported.remove(key);
-        env::remove_var(key);
+        self.arrays.remove(key);
+        self.attrs.remove(key);
     }
 
     /// Returns true if the given variable is currently marked as exported.
     #[allow(dead_code)]
     pub fn is_exported(&self, key: &str) -> bool {
-        self.exported.contains(key)
+        self.attributes(key).exported
+    }
+
+    /// Returns true if assignments to the variable must be rejected.
+    pub fn is_readonly(&self, key: &str) -> bool {
+        self.attributes(&self.resolve(key)).readonly
+    }
+
+    /// The error for writing to `key` if it is readonly. Everything that
+    /// assigns to variables checks this first.
+    pub fn check_writable(&self, key: &str) -> Result<(), String> {
+        match self.is_readonly(key) {
+            true => Err(format!("{}: readonly variable", key)),
+            false => Ok(()),
+        }
     }
 
     /// Returns an iterator over exported variable names and their values.
+    /// Arrays cannot be exported.
     pub fn exported_vars(&self) -> impl Iterator<Item = (&str, &str)> {
-        self.exported
-            .iter()
-            .filter_map(move |k| self.vars.get(k).map(|v| (k.as_str(), v.as_str())))
+        let mut seen = HashSet::new();
+        let names: Vec<&String> = self
+            .attrs
+            .keys()
+            .chain(self.scopes.iter().flat_map(|s| s.locals.keys()))
+            .filter(|k| seen.insert(k.as_str()))
+            .collect();
+        names.into_iter().filter_map(move |k| {
+            if !self.is_exported(k) || self.array_ref(k).is_some() {
+                return None;
+            }
+            self.get(k).map(|v| (k.as_str(), v.as_str()))
+        })
+    }
+
+    /// Enter a function call: push a new local scope and install its arguments
+    /// as the positional parameters.
+    pub fn push_scope(&mut self, args: Vec<String>) {
+        let saved = std::mem::replace(&mut self.positional_args, args);
+        self.scopes.push(Scope {
+            locals: HashMap::new(),
+            saved_positional_args: saved,
+        });
+    }
+
+    /// Leave a function call, dropping its locals and restoring the caller's
+    /// positional parameters.
+    pub fn pop_scope(&mut self) {
+        if let Some(scope) = self.scopes.pop() {
+            self.positional_args = scope.saved_positional_args;
+        }
+    }
+
+    /// Returns true while a function body is executing.
+    pub fn in_function(&self) -> bool {
+        !self.scopes.is_empty()
+    }
+
+    /// Declare `key` local to the current function scope, adding `attrs`.
+    ///
+    /// Without a value, an existing local keeps its value and a new one starts
+    /// out unset. A local inherits the export flag of the variable it shadows.
+    pub fn declare_local(
+        &mut self,
+        key: &str,
+        value: Option<String>,
+        attrs: Attributes,
+    ) -> Result<(), String> {
+        let inherited_export = self.is_exported(key);
+        if self.scopes.is_empty() {
+            return Err("local: can only be used in a function".to_string());
+        }
+        // Neither a readonly local nor a readonly variable it would shadow
+        // can be redeclared
+        self.check_writable(key)?;
+        let scope = self.scopes.last_mut().unwrap();
+        let local = scope.locals.entry(key.to_string()).or_default();
+        if value.is_some() {
+            local.value = value;
+        }
+        local.attrs.exported |= attrs.exported || inherited_export;
+        let readonly = attrs.readonly;
+        self.set_attributes(
+            key,
+            Attributes {
+                readonly: false,
+                ..attrs
+            },
+            Attributes::default(),
+        )?;
+        if readonly {
+            self.set_attributes(
+                key,
+                Attributes {
+                    readonly: true,
+                    ..Default::default()
+                },
+                Attributes::default(),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Returns true if the innermost function scope has a local `key`.
+    pub fn is_local(&self, key: &str) -> bool {
+        self.scopes
+            .last()
+            .is_some_and(|scope| scope.locals.contains_key(key))
+    }
+
+    /// Returns the locals of the innermost function scope, sorted by name.
+    pub fn current_locals(&self) -> Vec<(String, Option<String>)> {
+        let mut locals: Vec<(String, Option<String>)> = self
+            .scopes
+            .last()
+            .map(|s| {
+                s.locals
+                    .iter()
+                    .map(|(k, l)| (k.clone(), l.value.clone()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        locals.sort();
+        locals
+    }
+
+    pub fn last_status(&self) -> i32 {
+        self.last_status
+    }
+
+    pub fn set_last_status(&mut self, status: i32) {
+        self.last_status = status;
+    }
+
+    /// Returns true where `return` is allowed: inside a function body or a
+    /// file run by `source`.
+    pub fn can_return(&self) -> bool {
+        self.in_function() || self.source_depth > 0
+    }
+
+    /// Start unwinding the current function or sourced file with `status`.
+    pub fn request_return(&mut self, status: i32) {
+        self.pending_return = Some(status);
+    }
+
+    /// Returns true while a `return` is unwinding; command lists stop early.
+    pub fn is_returning(&self) -> bool {
+        self.pending_return.is_some()
+    }
+
+    /// Stop unwinding, yielding the status passed to `return`, if any.
+    pub fn take_return(&mut self) -> Option<i32> {
+        self.pending_return.take()
+    }
+
+    pub fn enter_source(&mut self) {
+        self.source_depth += 1;
+    }
+
+    pub fn leave_source(&mut self) {
+        self.source_depth = self.source_depth.saturating_sub(1);
+    }
+
+    pub fn options(&self) -> &ShellOptions {
+        &self.options
+    }
+
+    pub fn options_mut(&mut self) -> &mut ShellOptions {
+        &mut self.options
+    }
+
+    pub fn enter_condition(&mut self) {
+        self.condition_depth += 1;
+    }
+
+    pub fn leave_condition(&mut self) {
+        self.condition_depth = self.condition_depth.saturating_sub(1);
+    }
+
+    /// Returns true while an enclosing command is testing the status of the
+    /// running one, so neither `set -e` nor the ERR trap apply.
+    pub fn in_condition(&self) -> bool {
+        self.condition_depth > 0
+    }
+
+    pub fn traps(&self) -> &Traps {
+        &self.traps
+    }
+
+    pub fn traps_mut(&mut self) -> &mut Traps {
+        &mut self.traps
+    }
+
+    /// Record that this process is a forked subshell of its parent.
+    pub fn enter_subshell(&mut self) {
+        self.subshell_depth += 1;
+    }
+
+    /// How deeply the running command is nested in function calls and
+    /// subshells, starting at 1 at the top level. Shown by `set -x`.
+    pub fn nesting_depth(&self) -> usize {
+        1 + self.scopes.len() + self.subshell_depth
+    }
+
+    /// Where commands are currently read from: a file name, if any, and
+    /// the line number.
+    pub fn location(&self) -> (Option<String>, usize) {
+        (self.source_name.clone(), self.lineno)
+    }
+
+    pub fn set_location(&mut self, source_name: Option<String>, lineno: usize) {
+        self.source_name = source_name;
+        self.lineno = lineno;
+    }
+
+    pub fn lineno(&self) -> usize {
+        self.lineno
+    }
+
+    /// Note that the command on `line` is about to run. Interactive input
+    /// is not numbered, and neither are commands that were not parsed
+    /// (line 0), so they leave the line alone.
+    pub fn set_lineno(&mut self, line: usize) {
+        if line != 0 && self.lineno != 0 {
+            self.lineno = line;
+        }
+    }
+
+    /// The `file: line N: ` prefix for errors in commands read from a
+    /// script, or nothing for interactive input.
+    pub fn error_location(&self) -> String {
+        match (&self.source_name, self.lineno) {
+            (_, 0) => String::new(),
+            (Some(name), line) => format!("{}: line {}: ", name, line),
+            (None, line) => format!("line {}: ", line),
+        }
+    }
+
+    pub fn is_login_shell(&self) -> bool {
+        self.login_shell
+    }
+
+    pub fn set_login_shell(&mut self, login_shell: bool) {
+        self.login_shell = login_shell;
+    }
+
+    pub fn is_interactive(&self) -> bool {
+        self.interactive
+    }
+
+    pub fn set_interactive(&mut self, interactive: bool) {
+        self.interactive = interactive;
+    }
+
+    /// The error for expanding `name` while it is unset under `set -u`.
+    fn unbound_error(&self, name: &str) -> String {
+        self.unbound.set(true);
+        format!("{}: unbound variable", name)
+    }
+
+    /// Returns true if an expansion failed on an unset variable since the
+    /// last call, see `unbound_error`.
+    pub fn take_unbound(&self) -> bool {
+        self.unbound.replace(false)
+    }
+
+    /// `$-`: the letters of the options that are on, and `i` in an
+    /// interactive shell.
+    pub fn flags(&self) -> String {
+        let mut flags = self.options.flags();
+        if self.interactive {
+            flags.push('i');
+        }
+        flags
+    }
+
+    /// Returns every visible variable and its value, sorted by name.
+    pub fn all_vars(&self) -> Vec<(String, String)> {
+        let mut vars: Vec<(String, String)> = self.visible_vars().into_iter().collect();
+        vars.sort();
+        vars
     }
 
     pub fn set_positional_args(&mut self, args: Vec<String>) {
         self.positional_args = args;
     }
 
+    pub fn set_arg0(&mut self, arg0: String) {
+        self.arg0 = arg0;
+    }
+
+    pub fn getopts_cursor(&self) -> Option<(usize, usize)> {
+        self.getopts_cursor
+    }
+
+    pub fn set_getopts_cursor(&mut self, cursor: Option<(usize, usize)>) {
+        self.getopts_cursor = cursor;
+    }
+
+    pub fn command_hash(&self) -> RefMut<'_, CommandHash> {
+        self.command_hash.borrow_mut()
+    }
+
+    pub fn dir_stack(&self) -> &DirStack {
+        &self.dir_stack
+    }
+
+    pub fn dir_stack_mut(&mut self) -> &mut DirStack {
+        &mut self.dir_stack
+    }
+
+    /// The current directory as `PWD` names it, or as the OS reports it if
+    /// `PWD` is unset.
+    pub fn pwd(&self) -> String {
+        match self.get("PWD") {
+            Some(pwd) if !pwd.is_empty() => pwd.clone(),
+            _ => env::current_dir()
+                .map(|d| d.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        }
+    }
+
     pub fn get_positional_args(&self) -> Vec<String> {
         self.positional_args.clone()
     }
 
     pub fn get(&self, key: &str) -> Option<&String> {
         if let Ok(idx) = key.parse::<usize>() {
-            if idx > 0 && idx <= self.positional_args.len() {
-                return Some(&self.positional_args[idx - 1]);
+            if idx == 0 {
+                return Some(&self.arg0);
             }
-            // $0 is usually the shell name or script name, not handled in positional_args yet
-            // but we can return None or handle it if we store it.
-            return None;
+            return self.positional_args.get(idx - 1);
+        }
+        let resolved = self.resolve(key);
+        // An array's plain name refers to its first element
+        match self.array_ref(&resolved) {
+            Some(Array::Indexed(map)) => map.get(&0),
+            Some(Array::Assoc(map)) => map.get("0"),
+            None => self.raw_value(&resolved),
         }
-        self.vars.get(key)
     }
 
+    /// The environment for child processes: the exported variables only.
     pub fn to_env_vars(&self) -> HashMap<String, String> {
-        self.vars.clone()
+        self.exported_vars()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn visible_vars(&self) -> HashMap<String, String> {
+        let mut env_vars = self.vars.clone();
+        // Apply scopes outermost first so the innermost local wins
+        for scope in &self.scopes {
+            for (key, local) in &scope.locals {
+                match &local.value {
+                    Some(value) => env_vars.insert(key.clone(), value.clone()),
+                    None => env_vars.remove(key),
+                };
+            }
+        }
+        env_vars
     }
 
     /// Expand variables in a string.
-    /// Replaces $VAR with its value.
-    pub fn expand(&self, input: &str) -> String {
+    /// Replaces $VAR with its value. With `set -u`, expanding an unset
+    /// variable is an error.
+    pub fn expand(&self, input: &str) -> Result<String, String> {
         if !input.contains('$') {
-            return input.to_string();
+            return Ok(input.to_string());
         }
 
         let mut result = String::new();
         let mut chars = input.chars().peekable();
 
         while let Some(c) = chars.next() {
+            // Splitting is up to `expand_fields`
+            if c == SPLIT_MARK {
+                continue;
+            }
             if c == '$' {
                 let mut var_name = String::new();
 
-                // Handle braced variables like ${VAR}
+                if chars.peek() == Some(&'{') {
+                    let mut braced = chars.clone();
+                    braced.next();
+                    // Without a closing brace, the text is left as it is
+                    if let Some(content) = take_braced(&mut braced) {
+                        chars = braced;
+                        result.push_str(&self.expand_braced(&content)?);
+                        continue;
+                    }
+                }
+
                 // Check for special single-character vars first
                 if let Some(&next_char) = chars.peek() {
                     if matches!(next_char, '@' | '*' | '#' | '?' | '-' | '$' | '!') {
@@ -132,18 +1099,291 @@ impl Variables {
                     result.push_str(&self.positional_args.join(" "));
                 } else if var_name == "#" {
                     result.push_str(&self.positional_args.len().to_string());
+                } else if var_name == "?" {
+                    result.push_str(&self.last_status.to_string());
                 } else if var_name == "$" {
-                    result.push_str(&std::process::id().to_string());
+                    result.push_str(&self.shell_pid.to_string());
+                } else if var_name == "-" {
+                    result.push_str(&self.flags());
+                } else if var_name == "!" {
+                    if let Some(pid) = self.last_background_pid {
+                        result.push_str(&pid.to_string());
+                    }
+                } else if let Some(val) = self.dynamic_value(&var_name) {
+                    result.push_str(&val);
                 } else if let Some(val) = self.get(&var_name) {
                     result.push_str(val);
+                } else if self.options.nounset {
+                    return Err(self.unbound_error(&var_name));
                 }
                 // If var not found, it expands to empty string
             } else {
                 result.push(c);
             }
         }
-        result
+        Ok(result)
     }
+
+    /// Expand the inside of `${...}`: a name, an array element
+    /// `name[sub]`, all elements `name[@]` (joined with spaces here, see
+    /// `expand_fields`), their indices `!name[@]`, or a length `#...`.
+    fn expand_braced(&self, content: &str) -> Result<String, String> {
+        let bad_substitution = || format!("${{{}}}: bad substitution", content);
+        if let Some(rest) = content.strip_prefix('#').filter(|r| !r.is_empty()) {
+            let len = match split_subscript(rest) {
+                // `${#@}` and `${#*}` count the positional parameters, as `$#`
+                None if rest == "@" || rest == "*" => self.positional_args.len(),
+                Some((name, "@" | "*")) => self.array_values(name).len(),
+                Some((name, subscript)) => self
+                    .element(name, subscript)?
+                    .map_or(0, |v| v.chars().count()),
+                None => self.expand_braced(rest)?.chars().count(),
+            };
+            return Ok(len.to_string());
+        }
+        if let Some(rest) = content.strip_prefix('!') {
+            return match split_subscript(rest) {
+                Some((name, "@" | "*")) => Ok(self.array_keys(name).join(" ")),
+                _ => Err(bad_substitution()),
+            };
+        }
+        match split_subscript(content) {
+            Some((name, "@" | "*")) => Ok(self.array_values(name).join(" ")),
+            Some((name, subscript)) => match self.element(name, subscript)? {
+                Some(value) => Ok(value),
+                None if self.options.nounset => Err(self.unbound_error(content)),
+                None => Ok(String::new()),
+            },
+            None if arrays::is_name(content) || content.chars().all(|c| c.is_ascii_digit()) => {
+                match self.dynamic_value(content) {
+                    Some(value) => Ok(value),
+                    None => match self.get(content) {
+                        Some(value) => Ok(value.clone()),
+                        None if self.options.nounset => Err(self.unbound_error(content)),
+                        None => Ok(String::new()),
+                    },
+                }
+            }
+            None if content.len() == 1 => self.expand(&format!("${}", content)),
+            None => Err(bad_substitution()),
+        }
+    }
+
+    /// The element `name[subscript]`, with the subscript not yet expanded.
+    /// A scalar acts as an array with one element at index 0.
+    fn element(&self, name: &str, subscript: &str) -> Result<Option<String>, String> {
+        let subscript = self.expand(subscript)?;
+        match self.array(name) {
+            Some(array) => {
+                let key = array.key(&subscript, |s| arith::eval(s, self))?;
+                Ok(array.get(&key).cloned())
+            }
+            None => match Array::indexed().key(&subscript, |s| arith::eval(s, self))? {
+                Key::Index(0) => Ok(self.get(name).cloned()),
+                _ => Ok(None),
+            },
+        }
+    }
+
+    /// All values of `name`: an array's elements, or a scalar by itself.
+    pub fn array_values(&self, name: &str) -> Vec<String> {
+        match self.array(name) {
+            Some(array) => array.values(),
+            None => self.get(name).cloned().into_iter().collect(),
+        }
+    }
+
+    fn array_keys(&self, name: &str) -> Vec<String> {
+        match self.array(name) {
+            Some(array) => array.keys(),
+            None if self.get(name).is_some() => vec!["0".to_string()],
+            None => Vec::new(),
+        }
+    }
+
+    /// Expand a word into fields. Like `expand`, except that `$@`,
+    /// `${name[@]}` and `${!name[@]}` produce one field per element; text
+    /// around them joins the first and last fields. An empty array by
+    /// itself produces no fields at all.
+    /// A leading tilde prefix is expanded first, see `expand_tilde`.
+    pub fn expand_fields(&self, word: &str) -> Result<Vec<String>, String> {
+        let Some((dir, rest)) = self.expand_tilde(word) else {
+            return self.expand_list_fields(word);
+        };
+        let mut fields = self.expand_list_fields(rest)?;
+        match fields.first_mut() {
+            Some(first) => first.insert_str(0, &dir),
+            None => fields.push(dir),
+        }
+        Ok(fields)
+    }
+
+    /// Expand the tilde prefix of `word`: the text from a leading `~` up to
+    /// the first `/`. `~` is `HOME`, `~+` is `PWD`, `~-` is `OLDPWD`, `~N`,
+    /// `~+N` and `~-N` are entries of the directory stack as `dirs` numbers
+    /// them, and `~user` is that user's home directory. Returns the
+    /// directory and the rest of the word, or `None` if the prefix does not
+    /// name a directory and the word is left as it is.
+    fn expand_tilde<'w>(&self, word: &'w str) -> Option<(String, &'w str)> {
+        let rest = word.strip_prefix('~')?;
+        let end = rest.find('/').unwrap_or(rest.len());
+        let (prefix, rest) = rest.split_at(end);
+        let dir = match prefix {
+            "" => self.get("HOME").cloned(),
+            "+" => self.get("PWD").cloned(),
+            "-" => self.get("OLDPWD").cloned(),
+            _ if prefix
+                .trim_start_matches(['+', '-'])
+                .starts_with(|c: char| c.is_ascii_digit()) =>
+            {
+                let spec = match prefix.starts_with(['+', '-']) {
+                    true => prefix.to_string(),
+                    false => format!("+{}", prefix),
+                };
+                let index = self.dir_stack.index(&spec)?;
+                self.dir_stack.get(index, &self.pwd())
+            }
+            user => nix::unistd::User::from_name(user)
+                .ok()
+                .flatten()
+                .map(|u| u.dir.to_string_lossy().into_owned()),
+        }?;
+        Some((dir, rest))
+    }
+
+    /// Expand a word into fields without looking for a tilde prefix.
+    fn expand_list_fields(&self, word: &str) -> Result<Vec<String>, String> {
+        let Some((start, end, list)) = self.find_list_reference(word) else {
+            return Ok(vec![self.expand(word)?]);
+        };
+        let prefix = self.expand(&word[..start])?;
+        let mut rest = self.expand_list_fields(&word[end..])?;
+        let suffix = rest.remove(0);
+
+        let mut fields = list;
+        match fields.len() {
+            0 => {
+                let joined = prefix + &suffix;
+                if !joined.is_empty() || !rest.is_empty() {
+                    fields.push(joined);
+                }
+            }
+            n => {
+                fields[0].insert_str(0, &prefix);
+                fields[n - 1].push_str(&suffix);
+            }
+        }
+        fields.extend(rest);
+        Ok(fields)
+    }
+
+    /// Expand each word into fields, see `expand_fields`.
+    pub fn expand_words(&self, words: &[String]) -> Result<Vec<String>, String> {
+        let mut fields = Vec::new();
+        for word in words {
+            fields.extend(self.expand_fields(word)?);
+        }
+        Ok(fields)
+    }
+
+    /// Split `value` into fields at the characters of `IFS`, as an unquoted
+    /// expansion is. A separator is any IFS whitespace around at most one
+    /// other IFS character; IFS whitespace at either end is dropped.
+    fn split_fields(&self, value: &str) -> Vec<String> {
+        let ifs = self.get("IFS").map_or(" \t\n", |ifs| ifs.as_str());
+        let is_space = |c: char| ifs.contains(c) && matches!(c, ' ' | '\t' | '\n');
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        let mut chars = value.trim_matches(is_space).chars().peekable();
+        while let Some(c) = chars.next() {
+            if !ifs.contains(c) {
+                field.push(c);
+                continue;
+            }
+            while chars.next_if(|&c| is_space(c)).is_some() {}
+            if is_space(c) && chars.next_if(|&c| ifs.contains(c)).is_some() {
+                while chars.next_if(|&c| is_space(c)).is_some() {}
+            }
+            fields.push(std::mem::take(&mut field));
+        }
+        if !field.is_empty() {
+            fields.push(field);
+        }
+        fields
+    }
+
+    /// Find the first `$@`, `${name[@]}` or `${!name[@]}` in `word`; returns its
+    /// byte range and the fields it expands to. After `SPLIT_MARK`, the
+    /// elements are split further, and `${name[*]}` is a list too.
+    fn find_list_reference(&self, word: &str) -> Option<(usize, usize, Vec<String>)> {
+        let mut from = 0;
+        while let Some(pos) = word[from..].find('$') {
+            let start = from + pos;
+            let rest = &word[start + 1..];
+            // `$@` is a field per positional parameter
+            if rest.starts_with('@') {
+                return Some((start, start + 2, self.positional_args.clone()));
+            }
+            if !rest.starts_with('{') {
+                // `$$` is whole, so its second `$` does not start another
+                from = start + if rest.starts_with('$') { 2 } else { 1 };
+                continue;
+            }
+            let mut chars = word[start + 2..].chars().peekable();
+            let content = take_braced(&mut chars)?;
+            let end = start + 2 + content.len() + 1;
+            if content == "@" {
+                return Some((start, end, self.positional_args.clone()));
+            }
+            let (name, keys) = match content.strip_prefix('!') {
+                Some(rest) => (rest, true),
+                None => (content.as_str(), false),
+            };
+            let split = word[..start].ends_with(SPLIT_MARK);
+            // Quoted, `${name[*]}` is a single word, which `expand` makes
+            if let Some((name, "@")) | Some((name, "*")) =
+                split_subscript(name).filter(|(_, sub)| *sub == "@" || split)
+            {
+                let list = if keys {
+                    self.array_keys(name)
+                } else {
+                    self.array_values(name)
+                };
+                if !split {
+                    return Some((start, end, list));
+                }
+                let fields = list.iter().flat_map(|v| self.split_fields(v)).collect();
+                return Some((start - SPLIT_MARK.len_utf8(), end, fields));
+            }
+            from = end;
+        }
+        None
+    }
+}
+
+/// Take the text up to the `}` matching an opening `{` that has already
+/// been consumed, leaving the iterator after it. Returns `None` if there
+/// is no such `}`.
+fn take_braced(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> Option<String> {
+    let mut content = String::new();
+    let mut depth = 0;
+    for c in chars.by_ref() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth == 0 => return Some(content),
+            '}' => depth -= 1,
+            _ => {}
+        }
+        content.push(c);
+    }
+    None
+}
+
+/// Split `name[sub]` into its name and subscript.
+fn split_subscript(s: &str) -> Option<(&str, &str)> {
+    let (name, rest) = s.split_once('[')?;
+    let subscript = rest.strip_suffix(']')?;
+    arrays::is_name(name).then_some((name, subscript))
 }
 
 #[cfg(test)]
@@ -156,22 +1396,22 @@ mod tests {
         vars.set("FOO".to_string(), "bar".to_string());
         vars.set("BAZ".to_string(), "qux".to_string());
 
-        assert_eq!(vars.expand("hello $FOO"), "hello bar");
-        assert_eq!(vars.expand("$FOO world"), "bar world");
-        assert_eq!(vars.expand("$FOO$BAZ"), "barqux");
-        assert_eq!(vars.expand("no vars"), "no vars");
-        assert_eq!(vars.expand("$NONEXISTENT"), "");
-        assert_eq!(vars.expand("$"), "$");
+        assert_eq!(vars.expand("hello $FOO").unwrap(), "hello bar");
+        assert_eq!(vars.expand("$FOO world").unwrap(), "bar world");
+        assert_eq!(vars.expand("$FOO$BAZ").unwrap(), "barqux");
+        assert_eq!(vars.expand("no vars").unwrap(), "no vars");
+        assert_eq!(vars.expand("$NONEXISTENT").unwrap(), "");
+        assert_eq!(vars.expand("$").unwrap(), "$");
 
         vars.set_positional_args(vec!["arg1".to_string(), "arg2".to_string()]);
-        assert_eq!(vars.expand("$1"), "arg1");
-        assert_eq!(vars.expand("$2"), "arg2");
-        assert_eq!(vars.expand("$3"), ""); // Non-existent positional arg
+        assert_eq!(vars.expand("$1").unwrap(), "arg1");
+        assert_eq!(vars.expand("$2").unwrap(), "arg2");
+        assert_eq!(vars.expand("$3").unwrap(), ""); // Non-existent positional arg
 
-        assert_eq!(vars.expand("$*"), "arg1 arg2");
+        assert_eq!(vars.expand("$*").unwrap(), "arg1 arg2");
 
         vars.set_positional_args(vec!["single".to_string()]);
-        assert_eq!(vars.expand("$@"), "single");
+        assert_eq!(vars.expand("$@").unwrap(), "single");
     }
 
     #[test]
@@ -180,30 +1420,32 @@ mod tests {
         // $$ is process ID
         // We can just verify it expands to something non-empty and changes based on std::process::id
         let pid = std::process::id().to_string();
-        assert_eq!(vars.expand("$$"), pid);
+        assert_eq!(vars.expand("$$").unwrap(), pid);
 
-        // $! is not implemented
-        assert_eq!(vars.expand("$!"), "");
+        // $! is empty until a command is run in the background
+        assert_eq!(vars.expand("$!").unwrap(), "");
+        vars.set_last_background_pid(1234);
+        assert_eq!(vars.expand("$!").unwrap(), "1234");
 
         // $? exit status
         vars.set("?".to_string(), "1".to_string());
-        assert_eq!(vars.expand("$?"), "1");
+        assert_eq!(vars.expand("$?").unwrap(), "1");
 
         // $- is not implemented (expands to empty normally without set)
-        assert_eq!(vars.expand("$-"), "");
+        assert_eq!(vars.expand("$-").unwrap(), "");
 
         // $# number of arguments
         vars.set_positional_args(vec!["a".to_string(), "b".to_string()]);
-        assert_eq!(vars.expand("$#"), "2");
+        assert_eq!(vars.expand("$#").unwrap(), "2");
     }
 
     #[test]
-    fn test_variable_remove() {
+    fn test_variable_unset() {
         let mut vars = Variables::new();
         vars.set("TEST_VAR".to_string(), "value".to_string());
         assert_eq!(vars.get("TEST_VAR"), Some(&"value".to_string()));
 
-        vars.remove("TEST_VAR");
+        vars.unset("TEST_VAR");
         assert_eq!(vars.get("TEST_VAR"), None);
     }
 
@@ -212,14 +1454,340 @@ mod tests {
         let mut vars = Variables::new();
         vars.set("A".to_string(), "1".to_string());
         vars.set("B".to_string(), "2".to_string());
+        vars.set("UNEXPORTED".to_string(), "3".to_string());
+        vars.export("A");
+        vars.export("B");
 
         let env_map = vars.to_env_vars();
         assert!(env_map.contains_key("A"));
         assert!(env_map.contains_key("B"));
         assert_eq!(env_map.get("A").unwrap(), "1");
         assert_eq!(env_map.get("B").unwrap(), "2");
+        assert!(!env_map.contains_key("UNEXPORTED"));
+        assert!(std::env::var("A").is_err());
 
         // Internal variables shouldn't leak
         assert!(!env_map.contains_key("?"));
     }
+
+    #[test]
+    fn test_scope_shadowing_and_pop() {
+        let mut vars = Variables::new();
+        vars.set("SCOPED".to_string(), "global".to_string());
+
+        vars.push_scope(vec!["a".to_string()]);
+        vars.declare_local("SCOPED", Some("local".to_string()), Attributes::default())
+            .unwrap();
+        assert_eq!(vars.get("SCOPED").map(|s| s.as_str()), Some("local"));
+        assert_eq!(vars.expand("$SCOPED $1").unwrap(), "local a");
+
+        vars.pop_scope();
+        assert_eq!(vars.get("SCOPED").map(|s| s.as_str()), Some("global"));
+        assert_eq!(vars.expand("$1").unwrap(), "");
+    }
+
+    #[test]
+    fn test_dynamic_scope_visible_to_callees() {
+        let mut vars = Variables::new();
+        vars.set("DYN".to_string(), "global".to_string());
+
+        // caller declares a local, callee assigns to it
+        vars.push_scope(vec![]);
+        vars.declare_local("DYN", Some("caller".to_string()), Attributes::default())
+            .unwrap();
+        vars.push_scope(vec![]);
+        assert_eq!(vars.get("DYN").map(|s| s.as_str()), Some("caller"));
+        vars.set("DYN".to_string(), "from_callee".to_string());
+        vars.pop_scope();
+
+        assert_eq!(vars.get("DYN").map(|s| s.as_str()), Some("from_callee"));
+        vars.pop_scope();
+        assert_eq!(vars.get("DYN").map(|s| s.as_str()), Some("global"));
+    }
+
+    #[test]
+    fn test_local_without_value_shadows_as_unset() {
+        let mut vars = Variables::new();
+        vars.set("UNSET_LOCAL".to_string(), "global".to_string());
+        vars.push_scope(vec![]);
+        vars.declare_local("UNSET_LOCAL", None, Attributes::default())
+            .unwrap();
+        assert_eq!(vars.get("UNSET_LOCAL"), None);
+        assert!(!vars.to_env_vars().contains_key("UNSET_LOCAL"));
+        vars.pop_scope();
+        assert_eq!(vars.get("UNSET_LOCAL").map(|s| s.as_str()), Some("global"));
+    }
+
+    #[test]
+    fn test_local_readonly_and_export() {
+        let mut vars = Variables::new();
+        vars.push_scope(vec![]);
+        vars.declare_local(
+            "RO",
+            Some("1".to_string()),
+            Attributes {
+                exported: true,
+                readonly: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(vars.is_readonly("RO"));
+        vars.set("RO".to_string(), "2".to_string());
+        assert_eq!(vars.get("RO").map(|s| s.as_str()), Some("1"));
+        assert!(vars.exported_vars().any(|(k, v)| k == "RO" && v == "1"));
+
+        vars.pop_scope();
+        assert!(!vars.is_readonly("RO"));
+        assert!(vars.get("RO").is_none());
+    }
+
+    #[test]
+    fn test_last_status_and_return_state() {
+        let mut vars = Variables::new();
+        vars.set_last_status(3);
+        assert_eq!(vars.expand("$?").unwrap(), "3");
+        assert!(!vars.to_env_vars().contains_key("?"));
+
+        assert!(!vars.can_return());
+        vars.enter_source();
+        assert!(vars.can_return());
+        vars.leave_source();
+        vars.push_scope(vec![]);
+        assert!(vars.can_return());
+
+        vars.request_return(5);
+        assert!(vars.is_returning());
+        assert_eq!(vars.take_return(), Some(5));
+        assert!(!vars.is_returning());
+        vars.pop_scope();
+    }
+
+    #[test]
+    fn test_nounset_and_option_flags() {
+        let mut vars = Variables::new();
+        vars.options_mut().nounset = true;
+        vars.options_mut().errexit = true;
+        assert_eq!(vars.expand("$-").unwrap(), "eu");
+        vars.set_interactive(true);
+        assert_eq!(vars.expand("$-").unwrap(), "eui");
+        vars.set_interactive(false);
+        assert!(!vars.take_unbound());
+        assert_eq!(
+            vars.expand("x$NOUNSET_TEST_MISSING").unwrap_err(),
+            "NOUNSET_TEST_MISSING: unbound variable"
+        );
+        assert!(vars.take_unbound());
+        assert!(!vars.take_unbound());
+        assert!(vars.expand("${1}").is_err());
+        assert!(vars.take_unbound());
+        // Special parameters are always set
+        assert_eq!(vars.expand("$#$@").unwrap(), "0");
+        assert!(!vars.take_unbound());
+
+        vars.enter_condition();
+        assert!(vars.in_condition());
+        vars.leave_condition();
+        assert!(!vars.in_condition());
+    }
+
+    #[test]
+    fn test_error_location() {
+        let mut vars = Variables::new();
+        assert_eq!(vars.error_location(), "");
+        vars.set_location(Some("rc".to_string()), 3);
+        assert_eq!(vars.error_location(), "rc: line 3: ");
+        vars.set_location(None, 7);
+        assert_eq!(vars.error_location(), "line 7: ");
+    }
+
+    #[test]
+    fn test_tilde_expansion() {
+        let mut vars = Variables::new();
+        vars.set("HOME".to_string(), "/home/u".to_string());
+        vars.set("OLDPWD".to_string(), "/old".to_string());
+        assert_eq!(vars.expand_fields("~").unwrap(), vec!["/home/u"]);
+        assert_eq!(vars.expand_fields("~/x/~").unwrap(), vec!["/home/u/x/~"]);
+        assert_eq!(vars.expand_fields("~-/x").unwrap(), vec!["/old/x"]);
+        assert_eq!(vars.expand_fields("a~").unwrap(), vec!["a~"]);
+        assert_eq!(
+            vars.expand_fields("~no-such-user-here").unwrap(),
+            vec!["~no-such-user-here"]
+        );
+        // Only words, not arbitrary text, start with a tilde prefix
+        assert_eq!(vars.expand("~").unwrap(), "~");
+    }
+
+    #[test]
+    fn test_dynamic_variables() {
+        let mut vars = Variables::new();
+        vars.set("RANDOM".to_string(), "42".to_string());
+        let first = vars.expand("$RANDOM $RANDOM").unwrap();
+        vars.set("RANDOM".to_string(), "42".to_string());
+        assert_eq!(vars.expand("$RANDOM $RANDOM").unwrap(), first);
+        assert!(first
+            .split(' ')
+            .all(|n| n.parse::<u32>().is_ok_and(|n| n < 32768)));
+
+        vars.set("SECONDS".to_string(), "100".to_string());
+        let seconds: i64 = vars.expand("$SECONDS").unwrap().parse().unwrap();
+        assert!((100..102).contains(&seconds));
+
+        vars.set_location(None, 12);
+        assert_eq!(vars.expand("$LINENO").unwrap(), "12");
+
+        assert_eq!(vars.expand("$_").unwrap(), "");
+        vars.set_last_arg("word".to_string());
+        assert_eq!(vars.expand("$_").unwrap(), "word");
+        assert!(vars.get("PPID").is_some());
+    }
+
+    #[test]
+    fn test_array_assignment_and_expansion() {
+        let mut vars = Variables::new();
+        vars.assign("a", "\u{1e}x\u{1f}y z\u{1f}w").unwrap();
+        assert_eq!(vars.expand("${a[1]} ${#a[@]} $a").unwrap(), "y z 3 x");
+        assert_eq!(
+            vars.expand_fields("${a[@]}").unwrap(),
+            vec!["x", "y z", "w"]
+        );
+        assert_eq!(vars.expand_fields("<${a[*]}>").unwrap(), vec!["<x y z w>"]);
+        assert_eq!(
+            vars.expand_fields("<${a[@]}>").unwrap(),
+            vec!["<x", "y z", "w>"]
+        );
+
+        // Sparse indices, appending and negative subscripts
+        vars.assign("a[7]", "s").unwrap();
+        vars.assign("a+", "\u{1e}t").unwrap();
+        assert_eq!(vars.expand("${!a[@]}").unwrap(), "0 1 2 7 8");
+        assert_eq!(vars.expand("${a[-1]} ${#a[1]}").unwrap(), "t 3");
+        vars.assign("a[0]+", "x").unwrap();
+        assert_eq!(vars.expand("$a").unwrap(), "xx");
+
+        // Elements are expanded, and `${b[@]}` splices in every element
+        vars.assign("b", "\u{1e}${a[@]}\u{1f}[10]=ten").unwrap();
+        assert_eq!(vars.array_values("b").len(), 6);
+        assert_eq!(vars.expand("${b[10]}").unwrap(), "ten");
+
+        vars.assign("e", "\u{1e}").unwrap();
+        assert!(vars.expand_fields("${e[@]}").unwrap().is_empty());
+        assert_eq!(vars.expand_fields("x${e[@]}").unwrap(), vec!["x"]);
+    }
+
+    #[test]
+    fn test_scalars_as_arrays() {
+        let mut vars = Variables::new();
+        vars.set("s".to_string(), "one".to_string());
+        assert_eq!(vars.expand("${s[0]} ${#s[@]} ${#s}").unwrap(), "one 1 3");
+        vars.set_positional_args(vec!["a".into(), "b".into(), "c".into()]);
+        assert_eq!(vars.expand("${#@} ${#*} ${#1}").unwrap(), "3 3 1");
+        vars.assign("s+", "two").unwrap();
+        assert_eq!(vars.get("s").unwrap(), "onetwo");
+
+        // Assigning an element turns a scalar into an array
+        vars.assign("s[1]", "x").unwrap();
+        assert_eq!(vars.expand_fields("${s[@]}").unwrap(), vec!["onetwo", "x"]);
+
+        vars.options_mut().nounset = true;
+        assert!(vars.expand("${s[5]}").is_err());
+        assert_eq!(vars.expand("${s").unwrap(), "${s");
+        assert!(vars.expand("${s[}").is_err());
+        assert!(vars.expand("${!s}").is_err());
+    }
+
+    #[test]
+    fn test_declare_local_outside_function() {
+        let mut vars = Variables::new();
+        assert!(vars
+            .declare_local("X", None, Attributes::default())
+            .is_err());
+        assert!(!vars.in_function());
+    }
+
+    #[test]
+    fn test_attributes_convert_values() {
+        let mut vars = Variables::new();
+        let integer = Attributes {
+            integer: true,
+            ..Default::default()
+        };
+        vars.set_attributes("N", integer, Attributes::default())
+            .unwrap();
+        vars.set("M".to_string(), "4".to_string());
+        assert_eq!(vars.assign("N", "M * 2 + 1").unwrap(), "9");
+        assert_eq!(vars.assign("N+", "1").unwrap(), "10");
+        assert!(vars.assign("N", "1 +").is_err());
+
+        let upper = Attributes {
+            uppercase: true,
+            ..Default::default()
+        };
+        vars.set_attributes("U", upper, Attributes::default())
+            .unwrap();
+        vars.assign("U", "mixed Case").unwrap();
+        assert_eq!(vars.get("U").map(|s| s.as_str()), Some("MIXED CASE"));
+        assert_eq!(
+            vars.declaration("U").unwrap(),
+            r#"declare -u U="MIXED CASE""#
+        );
+    }
+
+    #[test]
+    fn test_global_readonly() {
+        let mut vars = Variables::new();
+        vars.set("RO_G".to_string(), "1".to_string());
+        let readonly = Attributes {
+            readonly: true,
+            ..Default::default()
+        };
+        vars.set_attributes("RO_G", readonly, Attributes::default())
+            .unwrap();
+        vars.set("RO_G".to_string(), "2".to_string());
+        vars.unset("RO_G");
+        assert_eq!(vars.get("RO_G").map(|s| s.as_str()), Some("1"));
+        assert!(vars.assign("RO_G", "3").is_err());
+        assert_eq!(
+            vars.check_writable("RO_G").unwrap_err(),
+            "RO_G: readonly variable"
+        );
+
+        // A local cannot shadow it either
+        vars.push_scope(vec![]);
+        assert_eq!(
+            vars.declare_local("RO_G", Some("4".into()), Attributes::default())
+                .unwrap_err(),
+            "RO_G: readonly variable"
+        );
+        assert!(!vars.is_local("RO_G"));
+        vars.pop_scope();
+        assert!(vars
+            .set_attributes("RO_G", Attributes::default(), readonly)
+            .is_err());
+    }
+
+    #[test]
+    fn test_nameref() {
+        let mut vars = Variables::new();
+        vars.set("TARGET".to_string(), "a".to_string());
+        vars.set_nameref("REF", "TARGET").unwrap();
+        assert_eq!(vars.get("REF").map(|s| s.as_str()), Some("a"));
+        vars.assign("REF", "b").unwrap();
+        assert_eq!(vars.get("TARGET").map(|s| s.as_str()), Some("b"));
+        assert_eq!(vars.expand("$REF").unwrap(), "b");
+        assert_eq!(
+            vars.declaration("REF").unwrap(),
+            r#"declare -n REF="TARGET""#
+        );
+
+        vars.assign("ARR", "\u{1e}x\u{1f}y").unwrap();
+        vars.set_nameref("AREF", "ARR").unwrap();
+        assert_eq!(vars.expand("${AREF[1]}").unwrap(), "y");
+
+        // A reference to itself is refused, and a loop does not hang
+        assert!(vars.set_nameref("SELF", "SELF").is_err());
+        vars.set_nameref("LOOP1", "LOOP2").unwrap();
+        vars.set_nameref("LOOP2", "LOOP1").unwrap();
+        assert!(vars.get("LOOP1").is_some());
+    }
 }
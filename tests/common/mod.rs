@@ -0,0 +1,24 @@
+use std::io::Write;
+use std::process::Command;
+
+/// Run `script` from a file with pmsh and return its (stdout, stderr, exit
+/// status). PS4 is cleared so traces use the shell's default prompt.
+pub fn run_script(script: &str) -> (String, String, i32) {
+    let bin = std::env::var("CARGO_BIN_EXE_pmsh").unwrap_or_else(|_| {
+        let manifest = std::env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR not set");
+        format!("{}/target/debug/pmsh", manifest)
+    });
+    let mut script_file = tempfile::NamedTempFile::new().expect("failed to create temp file");
+    write!(script_file, "{}", script).expect("failed to write script");
+
+    let output = Command::new(bin)
+        .arg(script_file.path())
+        .env_remove("PS4")
+        .output()
+        .expect("failed to run pmsh");
+    (
+        String::from_utf8_lossy(&output.stdout).to_string(),
+        String::from_utf8_lossy(&output.stderr).to_string(),
+        output.status.code().unwrap_or(-1),
+    )
+}
@@ -71,3 +71,21 @@ fn integration_repl_subshell_cd_isolation() {
     p.expect(Regex(regex::escape(current_dir_str).as_str()))
         .expect("CD leaked!");
 }
+
+#[test]
+fn integration_repl_read_prompt() {
+    let bin = std::env::var("CARGO_BIN_EXE_pmsh").unwrap_or_else(|_| {
+        let manifest = std::env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR not set");
+        format!("{}/target/debug/pmsh", manifest)
+    });
+    let mut p = spawn(&bin).expect("failed to spawn pmsh");
+    p.expect(Regex("\\$ ")).expect("did not see prompt");
+
+    // The prompt is shown because input comes from the terminal
+    p.send_line("read -p 'Continue? ' ans; echo \"answer=$ans\"")
+        .expect("failed to send line");
+    p.expect(Regex("\\nContinue\\? "))
+        .expect("did not see read prompt");
+    p.send_line("yes").expect("failed to send answer");
+    p.expect(Regex("answer=yes")).expect("did not see answer");
+}
@@ -0,0 +1,59 @@
+mod common;
+use common::run_script;
+
+#[test]
+fn test_alias_not_found_status() {
+    let (out, err, _) = run_script(
+        "alias greet='echo hello'
+alias greet nosuch
+echo \"status $?\"
+alias greet
+echo \"status $?\"
+",
+    );
+    assert_eq!(
+        out, "alias greet='echo hello'\nstatus 1\nalias greet='echo hello'\nstatus 0\n",
+        "stderr: {}",
+        err
+    );
+    assert!(err.contains("alias: nosuch: not found"));
+}
+
+#[test]
+fn test_unalias_not_found_status() {
+    let (out, err, _) = run_script(
+        "alias greet='echo hello'
+unalias nosuch greet
+echo \"status $?\"
+alias
+echo \"listed\"
+",
+    );
+    assert_eq!(out, "status 1\nlisted\n", "stderr: {}", err);
+    assert!(err.contains("unalias: nosuch: not found"));
+}
+
+#[test]
+fn test_aliases_expand_in_scripts_eval_and_sourced_files() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let sourced = dir.path().join("sourced.sh");
+    std::fs::write(&sourced, "alias inner='echo inner'\ninner sourced\n").unwrap();
+    let script = format!(
+        "alias say='echo said'
+say script
+cat <<EOF
+say body
+EOF
+eval 'say eval'
+. {}
+inner after
+",
+        sourced.display()
+    );
+    let (out, err, _) = run_script(&script);
+    assert_eq!(
+        out, "said script\nsay body\nsaid eval\ninner sourced\ninner after\n",
+        "stderr: {}",
+        err
+    );
+}
@@ -0,0 +1,117 @@
+mod common;
+use common::run_script;
+
+#[test]
+fn test_indexed_array_in_loop() {
+    let script = "hosts=(
+  web1      # front end
+  \"db 1\"
+)
+hosts+=(cache)
+for host in \"${hosts[@]}\"; do
+  echo \"deploy $host\"
+done
+echo ${#hosts[@]} ${hosts[2]}
+";
+    let (out, err, _) = run_script(script);
+    assert_eq!(
+        out, "deploy web1\ndeploy db 1\ndeploy cache\n3 cache\n",
+        "stderr: {}",
+        err
+    );
+}
+
+#[test]
+fn test_sparse_indices_and_unset() {
+    let script = "a=(x y z)
+a[10]=w
+unset 'a[1]'
+echo \"${!a[@]}\"
+echo \"${a[*]}\"
+";
+    let (out, _, _) = run_script(script);
+    assert_eq!(out, "0 2 10\nx z w\n");
+}
+
+#[test]
+fn test_associative_array() {
+    let script = "declare -A port
+port[web]=80
+port[db]=5432
+port+=([cache]=6379)
+for svc in \"${!port[@]}\"; do
+  echo \"$svc=${port[$svc]}\"
+done
+declare -A
+";
+    let (out, _, _) = run_script(script);
+    assert_eq!(
+        out,
+        "cache=6379\ndb=5432\nweb=80\n\
+         declare -A port=([cache]=\"6379\" [db]=\"5432\" [web]=\"80\")\n"
+    );
+}
+
+#[test]
+fn test_array_elements_as_arguments() {
+    let script = "opts=(-n \"two words\")
+printf '<%s>' \"${opts[@]}\" last
+echo
+empty=()
+printf '<%s>' start \"${empty[@]}\" end
+echo
+";
+    let (out, _, _) = run_script(script);
+    assert_eq!(out, "<-n><two words><last>\n<start><end>\n");
+}
+
+#[test]
+fn test_unquoted_elements_are_split() {
+    let script = "a=(\"p q\" r)
+printf '<%s>' ${a[@]}
+echo
+printf '<%s>' ${a[*]}
+echo
+printf '<%s>' \"${a[*]}\" x${a[@]}y
+echo
+IFS=:
+b=(1:2 3)
+printf '<%s>' ${b[@]}
+echo
+";
+    let (out, err, _) = run_script(script);
+    assert_eq!(
+        out, "<p><q><r>\n<p><q><r>\n<p q r><xp><q><ry>\n<1><2><3>\n",
+        "stderr: {}",
+        err
+    );
+}
+
+#[test]
+fn test_subscripts_are_arithmetic() {
+    let script = "a=(x y z)
+i=0
+echo \"${a[i+1]} ${a[$i + 2]} ${a[-1]} ${a[i*5-1]}\"
+n=${#a[@]}
+a[n-1]=last
+a[n]=new
+echo \"${a[@]}\"
+declare -A m
+m[i+1]=key
+echo \"${!m[@]}\"
+";
+    let (out, err, _) = run_script(script);
+    assert_eq!(out, "y z z z\nx y last new\ni+1\n", "stderr: {}", err);
+}
+
+#[test]
+fn test_elements_in_here_documents() {
+    let script = "a=(p q)
+cat <<EOF
+${a[1]} of ${#a[@]}
+EOF
+echo after
+";
+    let (out, err, _) = run_script(script);
+    assert_eq!(out, "q of 2\nafter\n", "stderr: {}", err);
+}
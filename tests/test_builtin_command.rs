@@ -0,0 +1,63 @@
+mod common;
+use common::run_script;
+
+#[test]
+fn test_wrappers_call_the_builtin() {
+    let dir = tempfile::TempDir::new().expect("failed to create temp dir");
+    let script = format!(
+        "cd() {{ builtin cd \"$@\" && echo \"in $PWD\"; }}
+cd {dir}
+echo() {{ builtin echo \"[$*]\"; }}
+echo wrapped words
+command echo plain
+",
+        dir = dir.path().display()
+    );
+    let (out, err, _) = run_script(&script);
+    let dir = dir.path().canonicalize().unwrap();
+    assert_eq!(
+        out,
+        format!("in {}\n[wrapped words]\nplain\n", dir.display()),
+        "stderr: {}",
+        err
+    );
+}
+
+#[test]
+fn test_command_skips_functions() {
+    let script = "ls() { echo function; }
+ls /
+command ls -d /
+command -p ls -d /
+PATH=/nonexistent
+command -p ls -d /
+";
+    let (out, err, _) = run_script(script);
+    assert_eq!(out, "function\n/\n/\n/\n", "stderr: {}", err);
+}
+
+#[test]
+fn test_command_p_runs_program_by_its_name() {
+    let (out, err, _) = run_script("command -p cat /proc/self/cmdline\n");
+    assert_eq!(out, "cat\0/proc/self/cmdline\0", "stderr: {}", err);
+}
+
+#[test]
+fn test_missing_commands_return_127() {
+    let script = "f() { echo function; }
+command f
+echo \"command $?\"
+builtin f
+echo \"builtin $?\"
+builtin
+echo \"empty $?\"
+";
+    let (out, err, _) = run_script(script);
+    assert_eq!(
+        out, "command 127\nbuiltin 127\nempty 0\n",
+        "stderr: {}",
+        err
+    );
+    assert!(err.contains("command: f: not found"));
+    assert!(err.contains("builtin: f: not a shell builtin"));
+}
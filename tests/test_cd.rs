@@ -0,0 +1,119 @@
+mod common;
+use common::run_script;
+
+/// A directory holding `real/sub`, a symlink `link` to `real`, and
+/// `projects/app`, without symlinks in its own path.
+fn tree() -> (tempfile::TempDir, String) {
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::create_dir_all(dir.path().join("real/sub")).unwrap();
+    std::fs::create_dir_all(dir.path().join("projects/app")).unwrap();
+    std::os::unix::fs::symlink(dir.path().join("real"), dir.path().join("link")).unwrap();
+    let root = std::fs::canonicalize(dir.path()).unwrap();
+    (dir, root.to_string_lossy().into_owned())
+}
+
+#[test]
+fn test_cd_logical_and_physical() {
+    let (_dir, root) = tree();
+    let script = format!(
+        "cd {root}/link/sub
+echo \"$PWD\"
+cd ..
+echo \"$PWD\"
+cd -P sub
+echo \"$PWD\"
+cd -L {root}/link/./sub/..
+echo \"$PWD\"
+cd -P ..
+echo \"$PWD\"
+"
+    );
+    let (out, err, _) = run_script(&script);
+    assert_eq!(
+        out,
+        format!("{root}/link/sub\n{root}/link\n{root}/real/sub\n{root}/link\n{root}\n"),
+        "stderr: {}",
+        err
+    );
+}
+
+#[test]
+fn test_cd_updates_and_exports_pwd_and_oldpwd() {
+    let (_dir, root) = tree();
+    let script = format!(
+        "cd {root}/real
+cd {root}/projects
+/bin/sh -c 'echo \"$OLDPWD -> $PWD\"'
+cd -
+echo \"$OLDPWD -> $PWD\"
+"
+    );
+    let (out, err, _) = run_script(&script);
+    assert_eq!(
+        out,
+        format!(
+            "{root}/real -> {root}/projects
+{root}/real
+{root}/projects -> {root}/real
+"
+        ),
+        "stderr: {}",
+        err
+    );
+}
+
+#[test]
+fn test_cd_searches_cdpath() {
+    let (_dir, root) = tree();
+    let script = format!(
+        "cd {root}/real
+CDPATH=:{root}/projects
+cd app
+echo \"$? $PWD\"
+cd {root}/real
+cd sub
+echo \"$? $PWD\"
+cd ./app
+echo \"missing $?\"
+"
+    );
+    let (out, err, _) = run_script(&script);
+    assert_eq!(
+        out,
+        format!(
+            "{root}/projects/app
+0 {root}/projects/app
+0 {root}/real/sub
+missing 1
+"
+        ),
+        "stderr: {}",
+        err
+    );
+    assert!(err.starts_with("cd: ./app: "), "stderr: {}", err);
+}
+
+#[test]
+fn test_cd_errors_fail() {
+    let script = "cd /no/such/dir
+echo \"missing $?\"
+unset OLDPWD
+cd -
+echo \"oldpwd $?\"
+unset HOME
+cd
+echo \"home $?\"
+cd -Q
+echo \"option $?\"
+";
+    let (out, err, _) = run_script(script);
+    assert!(
+        out.starts_with("missing 1\noldpwd 1\nhome 1\n"),
+        "stdout: {}",
+        out
+    );
+    assert!(out.ends_with("option 2\n"), "stdout: {}", out);
+    assert!(err.contains("cd: /no/such/dir: "), "stderr: {}", err);
+    assert!(err.contains("cd: OLDPWD not set\n"), "stderr: {}", err);
+    assert!(err.contains("cd: HOME not set\n"), "stderr: {}", err);
+}
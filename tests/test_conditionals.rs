@@ -0,0 +1,62 @@
+mod common;
+use common::run_script;
+
+#[test]
+fn test_bracket_sees_shell_variables() {
+    let script = "count=3
+if [ \"$count\" -ge 2 ] && test -n \"$count\"; then
+  echo enough
+fi
+[ -f /nonexistent/file ] || echo missing
+";
+    let (out, err, _) = run_script(script);
+    assert_eq!(out, "enough\nmissing\n", "stderr: {}", err);
+}
+
+#[test]
+fn test_double_brackets_with_patterns() {
+    let script = "for f in notes.txt image.png '*.txt'; do
+  if [[ $f == *.txt && ! ( $f == \"*.txt\" ) ]]; then
+    echo \"text $f\"
+  fi
+done
+";
+    let (out, err, _) = run_script(script);
+    assert_eq!(out, "text notes.txt\n", "stderr: {}", err);
+}
+
+#[test]
+fn test_regex_populates_rematch() {
+    let script = "version=v1.22.3
+if [[ $version =~ ^v([0-9]+)\\.([0-9]+) ]]; then
+  echo \"${PMSH_REMATCH[0]} major=${PMSH_REMATCH[1]} minor=${PMSH_REMATCH[2]}\"
+fi
+[[ nope =~ [0-9] ]]
+echo \"$? ${#PMSH_REMATCH[@]}\"
+";
+    let (out, err, _) = run_script(script);
+    assert_eq!(out, "v1.22 major=1 minor=22\n1 0\n", "stderr: {}", err);
+}
+
+#[test]
+fn test_quoted_regex_matches_literally() {
+    let script = "re='a.c'
+[[ abc =~ $re ]] && echo unquoted
+[[ abc =~ \"$re\" ]] || echo quoted
+[[ xa.cy =~ ^x\"$re\"y$ ]] && echo mixed
+[[ a+ =~ \"+\" ]] && echo literal
+";
+    let (out, err, _) = run_script(script);
+    assert_eq!(out, "unquoted\nquoted\nmixed\nliteral\n", "stderr: {}", err);
+}
+
+#[test]
+fn test_syntax_errors_return_two() {
+    let (out, err, _) = run_script("[ 1 -eq x ]\necho $?\n[[ x =~ ( ]]\necho $?\n");
+    assert_eq!(out, "2\n2\n");
+    assert!(
+        err.contains("integer expression expected"),
+        "stderr: {}",
+        err
+    );
+}
@@ -0,0 +1,91 @@
+mod common;
+use common::run_script;
+
+#[test]
+fn test_integer_and_case_attributes() {
+    let script = "declare -i total=0
+for n in 1 2 3; do
+  total+=n*2
+done
+typeset -u shout=quiet
+declare -l whisper=LOUD
+echo $total $shout $whisper
+";
+    let (out, err, _) = run_script(script);
+    assert_eq!(out, "12 QUIET loud\n", "stderr: {}", err);
+}
+
+#[test]
+fn test_readonly_is_enforced() {
+    let script = "readonly LIMIT=10
+LIMIT=20
+echo \"after assign $? $LIMIT\"
+unset LIMIT
+echo \"after unset $? $LIMIT\"
+";
+    let (out, err, _) = run_script(script);
+    assert_eq!(out, "after assign 1 10\nafter unset 1 10\n");
+    assert!(err.contains("LIMIT: readonly variable"), "stderr: {}", err);
+    assert!(err.contains("cannot unset"), "stderr: {}", err);
+}
+
+#[test]
+fn test_readonly_blocks_every_write() {
+    let script = "readonly RO=1
+f() { local RO=2; }
+f
+echo \"local $?\"
+RO=3 env | grep '^RO='
+echo \"prefix $?\"
+g() { echo \"in g $RO\"; }
+RO=4 g
+echo \"function prefix $?\"
+readonly OPTARG
+getopts a: opt -a x
+echo \"getopts $? [$opt]\"
+echo \"final $RO\"
+";
+    let (out, err, _) = run_script(script);
+    assert_eq!(
+        out, "local 1\nprefix 1\nfunction prefix 1\ngetopts 1 []\nfinal 1\n",
+        "stderr: {}",
+        err
+    );
+    assert!(
+        err.contains("local: RO: readonly variable"),
+        "stderr: {}",
+        err
+    );
+    assert!(
+        err.contains("getopts: OPTARG: readonly variable"),
+        "stderr: {}",
+        err
+    );
+}
+
+#[test]
+fn test_nameref_in_function() {
+    let script = "set_result() {
+  local -n out=$1
+  out=\"from function\"
+}
+set_result answer
+echo $answer
+";
+    let (out, err, _) = run_script(script);
+    assert_eq!(out, "from function\n", "stderr: {}", err);
+}
+
+#[test]
+fn test_print_is_resourceable() {
+    let script = "declare -ix n=5
+declare -a list=(a \"b c\")
+declare -p n list
+";
+    let (out, err, _) = run_script(script);
+    assert_eq!(
+        out, "declare -ix n=\"5\"\ndeclare -a list=([0]=\"a\" [1]=\"b c\")\n",
+        "stderr: {}",
+        err
+    );
+}
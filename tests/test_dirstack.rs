@@ -0,0 +1,176 @@
+mod common;
+use common::run_script;
+
+/// A directory with subdirectories `a`, `b` and `c`, without symlinks in
+/// its path so that `PWD` matches it.
+fn tree() -> (tempfile::TempDir, String) {
+    let dir = tempfile::TempDir::new().unwrap();
+    for sub in ["a", "b", "c"] {
+        std::fs::create_dir(dir.path().join(sub)).unwrap();
+    }
+    let root = std::fs::canonicalize(dir.path()).unwrap();
+    (dir, root.to_string_lossy().into_owned())
+}
+
+#[test]
+fn test_pushd_popd_and_dirs() {
+    let (_dir, root) = tree();
+    let script = format!(
+        "HOME={root}
+cd {root}
+pushd a
+pushd ../b
+dirs -v
+dirs -l
+pushd
+pushd +2
+popd +1
+popd
+popd
+echo \"empty $?\"
+pushd
+echo \"none $?\"
+pwd
+"
+    );
+    let (out, err, _) = run_script(&script);
+    assert_eq!(
+        out,
+        format!(
+            "~/a ~
+~/b ~/a ~
+ 0  ~/b
+ 1  ~/a
+ 2  ~
+{root}/b {root}/a {root}
+~/a ~/b ~
+~ ~/a ~/b
+~ ~/b
+~/b
+empty 1
+none 1
+{root}/b
+"
+        ),
+        "stderr: {}",
+        err
+    );
+    assert_eq!(
+        err,
+        "popd: directory stack empty\npushd: no other directory\n"
+    );
+}
+
+#[test]
+fn test_dirs_entry_and_formats() {
+    let (_dir, root) = tree();
+    let script = format!(
+        "HOME={root}
+cd {root}
+pushd a > /dev/null
+pushd ../b > /dev/null
+dirs +0
+dirs -1
+dirs -v +1
+dirs -p -0
+dirs -l +2
+dirs -v +0 +1
+dirs -p
+dirs +5
+echo \"range $?\"
+"
+    );
+    let (out, err, _) = run_script(&script);
+    assert_eq!(
+        out,
+        format!("~/b\n~/a\n 1  ~/a\n~\n{root}\n 1  ~/a\n~/b\n~/a\n~\nrange 1\n"),
+        "stderr: {}",
+        err
+    );
+    assert_eq!(err, "dirs: +5: directory stack index out of range\n");
+}
+
+#[test]
+fn test_stack_errors_leave_it_unchanged() {
+    let (_dir, root) = tree();
+    let script = format!(
+        "cd {root}
+pushd a > /dev/null
+pushd missing
+echo \"missing $?\"
+pushd +5
+echo \"range $?\"
+popd -3
+dirs -c
+dirs -l
+"
+    );
+    let (out, err, _) = run_script(&script);
+    assert_eq!(out, format!("missing 1\nrange 1\n{root}/a\n"));
+    assert!(err.contains("pushd: missing: "), "stderr: {}", err);
+    assert!(
+        err.contains("pushd: +5: directory stack index out of range"),
+        "stderr: {}",
+        err
+    );
+    assert!(
+        err.contains("popd: -3: directory stack index out of range"),
+        "stderr: {}",
+        err
+    );
+}
+
+#[test]
+fn test_tilde_expands_stack_entries() {
+    let (_dir, root) = tree();
+    let script = format!(
+        "cd {root}
+pushd a > /dev/null
+pushd ../b > /dev/null
+echo ~+ ~- ~1 ~+2 ~-0 ~2/c ~9
+"
+    );
+    let (out, err, _) = run_script(&script);
+    assert_eq!(
+        out,
+        format!("{root}/b {root}/a {root}/a {root} {root} {root}/c ~9\n"),
+        "stderr: {}",
+        err
+    );
+}
+
+#[test]
+fn test_autopushd_and_cd_index() {
+    let (_dir, root) = tree();
+    let script = format!(
+        "set -o autopushd
+cd {root}/a
+cd ../b
+cd ../c
+dirs -l
+cd -2
+dirs -l
+cd -7
+echo \"range $?\"
+set +o autopushd
+cd -1
+dirs -l
+"
+    );
+    let (out, err, _) = run_script(&script);
+    let start = std::env::current_dir().unwrap();
+    let start = start.to_string_lossy();
+    assert_eq!(
+        out,
+        format!(
+            "{root}/c {root}/b {root}/a {start}
+{root}/a {root}/c {root}/b {start}
+range 1
+{root}/c {root}/c {root}/b {start}
+"
+        ),
+        "stderr: {}",
+        err
+    );
+    assert_eq!(err, "cd: -7: directory stack index out of range\n");
+}
@@ -0,0 +1,62 @@
+mod common;
+use common::run_script;
+
+#[test]
+fn test_printf_table() {
+    let script = "printf '%-6s|%5.2f|%03d\\n' apple 1.5 7 pear 12.345 42
+printf -v line '%s=%q' key 'a b'
+echo \"$line\"
+";
+    let (out, err, _) = run_script(script);
+    assert_eq!(
+        out, "apple | 1.50|007\npear  |12.35|042\nkey=a\\ b\n",
+        "stderr: {}",
+        err
+    );
+}
+
+#[test]
+fn test_builtin_output_follows_redirections() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("out.txt");
+    let script = format!(
+        "echo -n partial > {f}\necho -e ' line\\tend' >> {f}\nprintf '%s\\n' after >> {f}\necho done\n",
+        f = file.display()
+    );
+    let (out, err, _) = run_script(&script);
+    assert_eq!(out, "done\n", "stderr: {}", err);
+    assert_eq!(
+        std::fs::read_to_string(&file).unwrap(),
+        "partial line\tend\nafter\n"
+    );
+}
+
+#[test]
+fn test_builtins_in_pipelines() {
+    let script = "echo -e 'a\\tb' | cat -A
+printf '%03d|%s\\n' 7 x | tr a-z A-Z
+f() { echo \"in f $1\"; }
+f one | cat
+alias ll='ls -l'
+alias | cat
+{ echo one; echo two; } | wc -l
+x=1 | cat
+echo \"x=$x\"
+trap 'echo t' USR1
+trap -p USR1 | cat
+help | head -1 > /dev/null
+echo \"help $?\"
+definitely_not_a_command_xyz | cat
+echo \"missing $?\"
+echo hi | definitely_not_a_command_xyz
+echo \"missing last $?\"
+";
+    let (out, err, _) = run_script(script);
+    assert_eq!(
+        out,
+        "a^Ib$\n007|X\nin f one\nalias ll='ls -l'\n2\nx=\ntrap -- 'echo t' SIGUSR1\nhelp 0\nmissing 0\nmissing last 127\n",
+        "stderr: {}",
+        err
+    );
+    assert!(err.contains("definitely_not_a_command_xyz: command not found"));
+}
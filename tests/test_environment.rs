@@ -0,0 +1,25 @@
+mod common;
+use common::run_script;
+
+#[test]
+fn test_only_exported_variables_reach_children() {
+    let script = "SECRET=hidden
+export SHARED=visible
+env | grep -c '^SECRET='
+env | grep '^SHARED='
+unset SHARED
+env | grep -c '^SHARED='
+";
+    let (out, err, _) = run_script(script);
+    assert_eq!(out, "0\nSHARED=visible\n0\n", "stderr: {}", err);
+}
+
+#[test]
+fn test_prefix_assignments_reach_pipelines() {
+    let script = "ONCE=1 env | grep '^ONCE='
+ONCE=2 env | cat | grep '^ONCE='
+env | grep -c '^ONCE='
+";
+    let (out, err, _) = run_script(script);
+    assert_eq!(out, "ONCE=1\nONCE=2\n0\n", "stderr: {}", err);
+}
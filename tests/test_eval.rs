@@ -0,0 +1,53 @@
+mod common;
+use common::run_script;
+
+#[test]
+fn test_eval_runs_in_current_shell() {
+    // Setup lines printed by tools are typically a mix of assignments,
+    // exports and function definitions
+    let script = "SETUP=\"export TOOL_HOME=/opt/tool; tool() { echo tool called; }\"
+eval $SETUP
+tool run
+env | grep TOOL_HOME
+for n in 1 2; do
+  eval item_$n=value$n
+done
+echo $item_1 $item_2
+eval 'echo one | tr a-z A-Z; echo two'
+";
+    let (out, err, status) = run_script(script);
+    assert_eq!(
+        out, "tool called\nTOOL_HOME=/opt/tool\nvalue1 value2\nONE\ntwo\n",
+        "stderr: {}",
+        err
+    );
+    assert_eq!(status, 0);
+}
+
+#[test]
+fn test_eval_status() {
+    let script = "eval false
+echo \"false: $?\"
+eval
+echo \"empty: $?\"
+eval 'echo ('
+echo \"parse error: $?\"
+";
+    let (out, err, _) = run_script(script);
+    assert_eq!(out, "false: 1\nempty: 0\nparse error: 2\n");
+    assert!(err.contains("eval:"), "stderr: {}", err);
+}
+
+#[test]
+fn test_eval_sees_previous_status() {
+    let script = "false
+eval 'echo \"top: $?\"'
+f() {
+  false
+  eval 'echo \"in f: $?\"'
+}
+f
+";
+    let (out, err, _) = run_script(script);
+    assert_eq!(out, "top: 1\nin f: 1\n", "stderr: {}", err);
+}
@@ -0,0 +1,65 @@
+mod common;
+use common::run_script;
+
+#[test]
+fn test_exec_redirections_change_shell_descriptors() {
+    let dir = tempfile::TempDir::new().expect("failed to create temp dir");
+    let log = dir.path().join("log.txt");
+    let script = format!(
+        "exec 3>{log}
+echo first >&3
+exec 4>&1 1>&3
+echo second
+exec 1>&4 4>&- 3>&-
+echo back
+echo closed >&3
+echo \"status $?\"
+",
+        log = log.display()
+    );
+    let (out, err, _) = run_script(&script);
+    assert_eq!(out, "back\nstatus 1\n", "stderr: {}", err);
+    assert_eq!(std::fs::read_to_string(&log).unwrap(), "first\nsecond\n");
+}
+
+#[test]
+fn test_exec_replaces_shell() {
+    // The EXIT trap does not run and nothing after exec does either
+    let script = "trap 'echo trap ran' EXIT
+UNEXPORTED=1
+export EXPORTED=1
+PREFIXED=1 exec env
+echo not reached
+";
+    let (out, err, status) = run_script(script);
+    assert_eq!(status, 0, "stderr: {}", err);
+    assert!(out.contains("EXPORTED=1\n"), "stdout: {}", out);
+    assert!(out.contains("PREFIXED=1\n"), "stdout: {}", out);
+    assert!(!out.contains("UNEXPORTED"), "stdout: {}", out);
+    assert!(!out.contains("trap ran") && !out.contains("not reached"));
+
+    let (out, _, _) = run_script("export EXPORTED=1\nexec -c env\n");
+    assert_eq!(out, "");
+}
+
+#[test]
+fn test_exec_argv0() {
+    let (out, err, _) = run_script("exec -l -a custom cat /proc/self/cmdline\n");
+    assert_eq!(out, "-custom\0/proc/self/cmdline\0", "stderr: {}", err);
+}
+
+#[test]
+fn test_exec_failure_exits_script() {
+    let script = "trap 'echo trap ran' EXIT
+exec pmsh-test-no-such-command
+echo not reached
+";
+    let (out, err, status) = run_script(script);
+    assert_eq!(status, 127);
+    assert_eq!(out, "trap ran\n");
+    assert!(
+        err.contains("pmsh-test-no-such-command: not found"),
+        "stderr: {}",
+        err
+    );
+}
@@ -0,0 +1,119 @@
+mod common;
+use common::run_script;
+
+#[test]
+fn test_declare_f_prints_source() {
+    let script = "greet() {
+  for name in $@; do
+    if [ $name = world ]; then echo hello $name; else echo hi $name; fi
+  done
+}
+declare -f greet
+";
+    let (out, err, _) = run_script(script);
+    assert_eq!(
+        out,
+        "greet ()
+{
+    for name in $@; do
+        if [ $name = world ]; then
+            echo hello $name
+        else
+            echo hi $name
+        fi
+    done
+}
+",
+        "stderr: {}",
+        err
+    );
+}
+
+#[test]
+fn test_printed_functions_read_back() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let script = format!(
+        "count() {{
+  for n in $@; do echo $n; done
+  echo $1 | tr 1 x
+  cat <<END
+done counting
+END
+}}
+functions count > {dir}/saved
+unset -f count
+count 1 2> /dev/null || echo gone
+source {dir}/saved
+count 1 2
+",
+        dir = dir.path().display()
+    );
+    let (out, err, _) = run_script(&script);
+    assert_eq!(out, "gone\n1\n2\nx\ndone counting\n", "stderr: {}", err);
+}
+
+#[test]
+fn test_printed_functions_keep_quotes() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let script = format!(
+        "check() {{ [ -n \"$1\" ] && echo \"got  $1\" 'as is' || echo empty; }}
+declare -f check > {dir}/saved
+cat {dir}/saved
+unset -f check
+source {dir}/saved
+check
+check \"a  b\"
+",
+        dir = dir.path().display()
+    );
+    let (out, err, _) = run_script(&script);
+    assert_eq!(
+        out,
+        "check ()
+{
+    [ -n \"$1\" ] && echo \"got  $1\" 'as is' || echo empty
+}
+empty
+got  a  b as is
+",
+        "stderr: {}",
+        err
+    );
+}
+
+#[test]
+fn test_declare_capital_f_and_unset() {
+    let script = "b() { :; }
+a() { :; }
+declare -F
+declare -F a missing
+echo \"missing $?\"
+a=1
+unset a
+declare -F
+unset a
+declare -F
+unset -v b
+declare -F
+unset -f b
+declare -F
+echo done
+";
+    let (out, err, _) = run_script(script);
+    assert_eq!(
+        out,
+        "declare -f a
+declare -f b
+declare -f a
+missing 1
+declare -f a
+declare -f b
+declare -f b
+declare -f b
+done
+",
+        "stderr: {}",
+        err
+    );
+    assert_eq!(err, "declare: missing: not found\n");
+}
@@ -0,0 +1,50 @@
+mod common;
+use common::run_script;
+
+#[test]
+fn test_help_lists_and_describes_builtins() {
+    let script = "help > /dev/null
+echo \"list $?\"
+help -s 'ex*'
+help -d cd
+help echo
+help nothing
+echo \"missing $?\"
+";
+    let (out, err, _) = run_script(script);
+    assert_eq!(
+        out,
+        "list 0
+exec: exec [-cl] [-a name] [command [argument ...]]
+exit: exit [n]
+export: export [name[=value] ...]
+cd - Change the shell working directory.
+Usage: echo [-neE] [arg ...]
+
+Write arguments to standard output.
+missing 1
+",
+        "stderr: {}",
+        err
+    );
+    assert!(err.contains("help: no help topics match `nothing'"));
+}
+
+#[test]
+fn test_enable_disables_builtins() {
+    let script = "enable -n echo
+type -t echo
+enable -n
+enable echo
+type -t echo
+enable -n nothing
+echo \"missing $?\"
+";
+    let (out, err, _) = run_script(script);
+    assert_eq!(
+        out, "file\nenable -n echo\nbuiltin\nmissing 1\n",
+        "stderr: {}",
+        err
+    );
+    assert!(err.contains("enable: nothing: not a shell builtin"));
+}
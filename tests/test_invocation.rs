@@ -0,0 +1,101 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+fn pmsh() -> Command {
+    let bin = std::env::var("CARGO_BIN_EXE_pmsh").unwrap_or_else(|_| {
+        let manifest = std::env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR not set");
+        format!("{}/target/debug/pmsh", manifest)
+    });
+    let mut cmd = Command::new(bin);
+    cmd.env_remove("PS4");
+    cmd
+}
+
+/// Run pmsh with `args`, feeding `stdin`; returns (stdout, stderr, code).
+fn run(args: &[&str], stdin: &str) -> (String, String, i32) {
+    let mut child = pmsh()
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to run pmsh");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    (
+        String::from_utf8_lossy(&output.stdout).to_string(),
+        String::from_utf8_lossy(&output.stderr).to_string(),
+        output.status.code().unwrap_or(-1),
+    )
+}
+
+#[test]
+fn test_command_string_sets_name_and_arguments() {
+    let (out, _, code) = run(&["-c", "echo \"$0 $1 $2 $#\"", "myname", "a", "b c"], "");
+    assert_eq!(out, "myname a b c 2\n");
+    assert_eq!(code, 0);
+
+    let (_, _, code) = run(&["-c", "false"], "");
+    assert_eq!(code, 1);
+}
+
+#[test]
+fn test_script_receives_arguments() {
+    let mut script = tempfile::NamedTempFile::new().unwrap();
+    write!(script, "echo \"$0\"\necho \"$1|$2\"\n").unwrap();
+    let path = script.path().to_str().unwrap();
+
+    let (out, _, _) = run(&[path, "-x", "two"], "");
+    assert_eq!(out, format!("{}\n-x|two\n", path));
+}
+
+#[test]
+fn test_commands_from_stdin() {
+    let (out, _, code) = run(&[], "echo one\nif true; then\n  echo two\nfi\nexit 3\n");
+    assert_eq!(out, "one\ntwo\nExiting.\n");
+    assert_eq!(code, 3);
+
+    let (out, _, _) = run(&["-s", "p", "q"], "echo $1 $2\n");
+    assert_eq!(out, "p q\n");
+}
+
+#[test]
+fn test_stdin_is_shared_with_commands() {
+    // Only the line being run is consumed, so `cat` reads what follows it
+    let (out, _, _) = run(&[], "cat\nnot a command\n");
+    assert_eq!(out, "not a command\n");
+}
+
+#[test]
+fn test_option_letters() {
+    let (out, err, code) = run(&["-e", "-x", "-c", "echo start; false; echo unreached"], "");
+    assert_eq!(out, "start\n");
+    assert_eq!(err, "+ echo start\n+ false\n");
+    assert_eq!(code, 1);
+
+    let (_, err, code) = run(&["-u", "-c", "echo $PMSH_SURELY_UNSET"], "");
+    assert!(err.contains("PMSH_SURELY_UNSET: unbound variable"));
+    assert_eq!(code, 1);
+}
+
+#[test]
+fn test_missing_script_exits_127() {
+    let (out, err, code) = run(&["/nonexistent/script.sh"], "");
+    assert_eq!(out, "");
+    assert!(err.contains("/nonexistent/script.sh"), "stderr: {}", err);
+    assert_eq!(code, 127);
+}
+
+#[test]
+fn test_interactive_flag_in_dollar_dash() {
+    let (out, _, _) = run(&["--norc", "-i", "-e", "-c", "echo $-"], "");
+    assert_eq!(out, "ei\n");
+
+    let (out, _, _) = run(&["-e", "-c", "echo $-"], "");
+    assert_eq!(out, "e\n");
+}
@@ -0,0 +1,112 @@
+mod common;
+use common::run_script;
+
+#[test]
+fn test_umask_octal_and_symbolic() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let script = format!(
+        "umask 022
+umask
+umask -S
+umask u=rwx,g=rx,o=
+umask
+umask g-x,o+r
+umask -S
+/bin/sh -c umask
+umask 077
+touch {dir}/file
+umask 1000
+echo \"range $?\"
+umask u=z
+echo \"symbolic $?\"
+umask
+",
+        dir = dir.path().display()
+    );
+    let (out, err, _) = run_script(&script);
+    assert_eq!(
+        out,
+        "0022
+u=rwx,g=rx,o=rx
+0027
+u=rwx,g=r,o=r
+0033
+range 1
+symbolic 1
+0077
+",
+        "stderr: {}",
+        err
+    );
+    let mode = std::os::unix::fs::PermissionsExt::mode(
+        &std::fs::metadata(dir.path().join("file"))
+            .unwrap()
+            .permissions(),
+    );
+    assert_eq!(mode & 0o777, 0o600);
+    assert!(
+        err.contains("umask: 1000: octal number out of range"),
+        "stderr: {}",
+        err
+    );
+    assert!(
+        err.contains("umask: `z': invalid symbolic mode character"),
+        "stderr: {}",
+        err
+    );
+}
+
+#[test]
+fn test_ulimit_shows_and_sets_limits() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let script = format!(
+        "ulimit -n 64
+ulimit -n
+ulimit -Hn
+ulimit -S -n 32
+ulimit -Sn
+ulimit -Hn
+/bin/sh -c 'ulimit -n'
+ulimit -c 0
+ulimit -c
+ulimit -cn
+ulimit -a > {dir}/all
+grep -c . {dir}/all
+ulimit -S -n 100
+echo \"raise $?\"
+ulimit -n lots
+echo \"number $?\"
+ulimit -n
+",
+        dir = dir.path().display()
+    );
+    let (out, err, _) = run_script(&script);
+    assert_eq!(
+        out,
+        "64
+64
+32
+64
+32
+0
+core file size              (blocks, -c) 0
+open files                          (-n) 32
+8
+raise 1
+number 1
+32
+",
+        "stderr: {}",
+        err
+    );
+    assert!(
+        err.contains("ulimit: open files: cannot modify limit: Invalid argument"),
+        "stderr: {}",
+        err
+    );
+    assert!(
+        err.contains("ulimit: lots: invalid number"),
+        "stderr: {}",
+        err
+    );
+}
@@ -0,0 +1,82 @@
+mod common;
+use common::run_script;
+
+#[test]
+fn test_set_and_shift() {
+    let script = "set -- one \"two words\" three
+echo \"$# $1|$2\"
+shift
+echo \"$# $1\"
+shift 2
+echo \"$# [$*]\"
+set -- a b
+shift 3
+echo \"status $? $#\"
+set --
+echo \"$# [$@]\"
+";
+    let (out, err, _) = run_script(script);
+    assert_eq!(
+        out, "3 one|two words\n2 two words\n0 []\nstatus 1 2\n0 []\n",
+        "stderr: {}",
+        err
+    );
+    assert!(err.contains("shift: 3: shift count out of range"));
+}
+
+#[test]
+fn test_quoted_at_keeps_arguments() {
+    let script = "count() {
+  echo $#
+}
+set -- \"a b\" c
+count \"$@\"
+set -- \"$@\" d
+echo \"$# $1\"
+";
+    let (out, err, _) = run_script(script);
+    assert_eq!(out, "2\n3 a b\n", "stderr: {}", err);
+}
+
+#[test]
+fn test_getopts_in_function() {
+    let script = "parse() {
+  local OPTIND opt
+  while getopts \":ab:c\" opt; do
+    if [ \"$opt\" = \":\" ]; then
+      echo \"missing $OPTARG\"
+    elif [ \"$opt\" = \"?\" ]; then
+      echo \"unknown $OPTARG\"
+    else
+      echo \"$opt[$OPTARG]\"
+    fi
+  done
+  declare -i n=OPTIND-1
+  shift $n
+  echo \"rest: $*\"
+}
+parse -ac -b val -x file1 file2
+parse -bfoo -- -c
+parse -b
+";
+    let (out, err, _) = run_script(script);
+    assert_eq!(
+        out,
+        "a[]\nc[]\nb[val]\nunknown x\nrest: file1 file2\nb[foo]\nrest: -c\nmissing b\nrest: \n",
+        "stderr: {}",
+        err
+    );
+}
+
+#[test]
+fn test_getopts_reports_errors() {
+    let script = "while getopts ab: opt -x -b; do
+  echo \"$opt $OPTIND\"
+done
+echo \"done $OPTIND\"
+";
+    let (out, err, _) = run_script(script);
+    assert_eq!(out, "? 2\n? 3\ndone 3\n", "stderr: {}", err);
+    assert!(err.contains("illegal option -- x"));
+    assert!(err.contains("option requires an argument -- b"));
+}
@@ -0,0 +1,60 @@
+use std::io::Write;
+
+mod common;
+use common::run_script;
+
+#[test]
+fn test_read_loop_over_file() {
+    let mut input = tempfile::NamedTempFile::new().expect("failed to create temp file");
+    write!(input, "alpha beta gamma\nx\\ y z\nno newline").expect("failed to write input");
+    let script = format!(
+        "while read first rest; do
+  echo \"[$first] [$rest]\"
+done < {path}
+while read -r line; do
+  echo \"raw: $line\"
+done < {path}
+",
+        path = input.path().display()
+    );
+    let (out, err, _) = run_script(&script);
+    // The last line has no newline, so read fails and ends the loop
+    assert_eq!(
+        out, "[alpha] [beta gamma]\n[x y] [z]\nraw: alpha beta gamma\nraw: x\\ y z\n",
+        "stderr: {}",
+        err
+    );
+}
+
+#[test]
+fn test_read_splitting_and_options() {
+    let script = "IFS=: read user pass rest <<EOF
+root:x:0:0:/root
+EOF
+echo \"$user|$pass|$rest|$IFS\"
+read <<EOF
+  kept as is
+EOF
+echo \"[$REPLY]\"
+read -a words <<EOF
+ one  two three
+EOF
+echo \"${#words[@]} ${words[1]}\"
+read -n 3 head <<EOF
+abcdef
+EOF
+echo \"$head\"
+read -d , field <<EOF
+a b,c
+EOF
+echo \"[$field]\"
+read missing < /dev/null
+echo \"status $? [$missing]\"
+";
+    let (out, err, _) = run_script(script);
+    assert_eq!(
+        out, "root|x|0:0:/root|\n[  kept as is]\n3 two\nabc\n[a b]\nstatus 1 []\n",
+        "stderr: {}",
+        err
+    );
+}
@@ -0,0 +1,163 @@
+use std::os::unix::fs::PermissionsExt;
+
+mod common;
+use common::run_script;
+
+/// A directory holding an executable `tool` script with no `#!` line.
+fn tool_dir() -> tempfile::TempDir {
+    let dir = tempfile::TempDir::new().expect("failed to create temp dir");
+    let tool = dir.path().join("tool");
+    std::fs::write(&tool, "echo \"tool: $*\"\n").expect("failed to write tool");
+    std::fs::set_permissions(&tool, std::fs::Permissions::from_mode(0o755))
+        .expect("failed to make tool executable");
+    dir
+}
+
+#[test]
+fn test_type_reports_each_kind() {
+    let dir = tool_dir();
+    let script = format!(
+        "PATH={dir}
+alias greet='echo hello'
+f() {{ echo f; }}
+type greet if f cd tool
+type -t greet if f cd tool
+type missing
+echo \"status $?\"
+",
+        dir = dir.path().display()
+    );
+    let (out, err, _) = run_script(&script);
+    assert_eq!(
+        out,
+        format!(
+            "greet is aliased to `echo hello'
+if is a shell keyword
+f is a function
+cd is a shell builtin
+tool is {dir}/tool
+alias
+keyword
+function
+builtin
+file
+status 1
+",
+            dir = dir.path().display()
+        ),
+        "stderr: {}",
+        err
+    );
+    assert!(err.contains("type: missing: not found"));
+}
+
+#[test]
+fn test_command_v_and_which() {
+    let dir = tool_dir();
+    let script = format!(
+        "PATH={dir}
+alias greet='echo hello'
+f() {{ echo f; }}
+command -v greet f cd tool
+command -v missing
+echo \"status $?\"
+command -V cd
+which tool f
+",
+        dir = dir.path().display()
+    );
+    let (out, err, _) = run_script(&script);
+    assert_eq!(
+        out,
+        format!(
+            "alias greet='echo hello'
+f
+cd
+{dir}/tool
+status 1
+cd is a shell builtin
+{dir}/tool
+f: shell function
+",
+            dir = dir.path().display()
+        ),
+        "stderr: {}",
+        err
+    );
+}
+
+#[test]
+fn test_hash_table() {
+    let dir = tool_dir();
+    let script = format!(
+        "PATH={dir}
+hash
+tool > /dev/null
+tool > /dev/null
+hash
+type tool
+hash -r
+hash
+hash -p {dir}/tool renamed
+renamed via hash
+hash missing
+echo \"status $?\"
+PATH={dir}:/bin
+hash
+",
+        dir = dir.path().display()
+    );
+    let (out, err, _) = run_script(&script);
+    assert_eq!(
+        out,
+        format!(
+            "hash: hash table empty
+hits\tcommand
+   2\t{dir}/tool
+tool is hashed ({dir}/tool)
+hash: hash table empty
+tool: via hash
+status 1
+hash: hash table empty
+",
+            dir = dir.path().display()
+        ),
+        "stderr: {}",
+        err
+    );
+    assert!(err.contains("hash: missing: not found"));
+}
+
+#[test]
+fn test_script_without_shebang_runs_in_pmsh() {
+    let dir = tool_dir();
+    let script = format!(
+        "PATH={dir}:/usr/bin:/bin
+tool one
+tool two | tr a-z A-Z
+exec tool three
+echo not reached
+",
+        dir = dir.path().display()
+    );
+    let (out, err, _) = run_script(&script);
+    assert_eq!(
+        out, "tool: one\nTOOL: TWO\ntool: three\n",
+        "stderr: {}",
+        err
+    );
+}
+
+#[test]
+fn test_missing_command_is_not_found() {
+    let (out, err, _) = run_script(
+        "f() { definitely_not_a_command_xyz; echo \"in f $?\"; }
+f
+for i in 1 2; do definitely_not_a_command_xyz; done
+echo \"after $?\"
+",
+    );
+    assert_eq!(out, "in f 127\nafter 127\n", "stderr: {}", err);
+    assert!(err.contains("pmsh: "));
+    assert!(err.contains("definitely_not_a_command_xyz: command not found"));
+}
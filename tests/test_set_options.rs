@@ -0,0 +1,71 @@
+mod common;
+use common::run_script;
+
+#[test]
+fn test_errexit_stops_on_failure() {
+    let (out, _, code) = run_script("set -e\necho before\nfalse\necho after\n");
+    assert_eq!(out, "before\n");
+    assert_eq!(code, 1);
+}
+
+#[test]
+fn test_errexit_exceptions() {
+    let script = "set -e
+if false; then echo no; fi
+while false; do :; done
+false && echo no
+false || echo fallback
+! true
+check() { false; echo inside; }
+check || echo unreached
+echo survived
+";
+    let (out, _, code) = run_script(script);
+    assert_eq!(out, "fallback\ninside\nsurvived\n");
+    assert_eq!(code, 0);
+}
+
+#[test]
+fn test_pipefail() {
+    let (out, _, _) = run_script("false | true; echo $?\nset -o pipefail\nfalse | true; echo $?\n");
+    assert_eq!(out, "0\n1\n");
+}
+
+#[test]
+fn test_noclobber_and_override() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let target = dir.path().join("out.txt");
+    let script = format!(
+        "echo first > {0}\nset -C\necho second > {0}\ncat {0}\necho third >| {0}\ncat {0}\n",
+        target.display()
+    );
+    let (out, _, _) = run_script(&script);
+    assert_eq!(out, "first\nthird\n");
+}
+
+#[test]
+fn test_nounset_reports_unbound_variable() {
+    let (out, _, code) = run_script("set -eu\necho start\necho $PMSH_TEST_UNSET_VAR\necho end\n");
+    assert_eq!(out, "start\n");
+    assert_eq!(code, 1);
+}
+
+#[test]
+fn test_nounset_is_fatal_without_errexit() {
+    // Wherever the expansion happens, nothing after it runs
+    for line in [
+        "echo $PMSH_TEST_UNSET_VAR",
+        "f() { echo $PMSH_TEST_UNSET_VAR; }; f",
+        "if [ -n \"$PMSH_TEST_UNSET_VAR\" ]; then echo then; fi",
+        "[[ $PMSH_TEST_UNSET_VAR == x ]] || echo false",
+    ] {
+        let (out, _, code) = run_script(&format!("set -u\necho start\n{}\necho end\n", line));
+        assert_eq!(out, "start\n", "{}", line);
+        assert_eq!(code, 1, "{}", line);
+    }
+
+    // Commands of a pipeline run in subshells, so only they stop
+    let (out, err, _) = run_script("set -u\necho ${PMSH_TEST_UNSET_VAR} | cat\necho \"end $?\"\n");
+    assert_eq!(out, "end 0\n");
+    assert!(err.contains("PMSH_TEST_UNSET_VAR"), "stderr: {}", err);
+}
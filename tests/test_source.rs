@@ -1,6 +1,16 @@
 use expectrl::{spawn, Expect, Regex};
 use std::io::Write;
 
+mod common;
+use common::run_script;
+
+/// A file to source that defines `lib_var` and reports `$?` on entry.
+fn library() -> tempfile::NamedTempFile {
+    let mut lib = tempfile::NamedTempFile::new().expect("failed to create temp file");
+    write!(lib, "echo \"sourced $?\"\nlib_var=set\n").expect("failed to write library");
+    lib
+}
+
 #[test]
 fn test_source_builtin() {
     let bin = std::env::var("CARGO_BIN_EXE_pmsh").unwrap_or_else(|_| {
@@ -62,3 +72,44 @@ fn test_source_builtin() {
     p.expect(Regex("unique_marker_file"))
         .expect("did not see marker file");
 }
+
+#[test]
+fn test_source_inside_function() {
+    let lib = library();
+    let script = format!(
+        "load() {{
+  source {lib}
+  echo \"in load $lib_var\"
+}}
+load
+echo \"after load $lib_var\"
+",
+        lib = lib.path().display()
+    );
+    let (out, err, _) = run_script(&script);
+    assert_eq!(
+        out, "sourced 0\nin load set\nafter load set\n",
+        "stderr: {}",
+        err
+    );
+}
+
+#[test]
+fn test_source_inside_if_body() {
+    let lib = library();
+    let script = format!(
+        "if true; then
+  . {lib}
+fi
+echo \"lib_var=$lib_var\"
+if true; then
+  source /nonexistent/lib.sh
+  echo \"status $?\"
+fi
+",
+        lib = lib.path().display()
+    );
+    let (out, err, _) = run_script(&script);
+    assert_eq!(out, "sourced 0\nlib_var=set\nstatus 1\n", "stderr: {}", err);
+    assert!(err.contains("/nonexistent/lib.sh"), "stderr: {}", err);
+}
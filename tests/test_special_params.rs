@@ -0,0 +1,69 @@
+use std::process::Command;
+
+/// Run `script` with `pmsh -c`; returns (stdout, code).
+fn run(script: &str, envs: &[(&str, &str)]) -> (String, i32) {
+    let bin = std::env::var("CARGO_BIN_EXE_pmsh").unwrap_or_else(|_| {
+        let manifest = std::env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR not set");
+        format!("{}/target/debug/pmsh", manifest)
+    });
+    let output = Command::new(bin)
+        .args(["-c", script])
+        .envs(envs.iter().copied())
+        .output()
+        .expect("failed to run pmsh");
+    (
+        String::from_utf8_lossy(&output.stdout).to_string(),
+        output.status.code().unwrap_or(-1),
+    )
+}
+
+#[test]
+fn test_pids_in_subshells() {
+    let (out, _) = run("echo $$ $PMSH_PID; (echo $$ $PMSH_PID)", &[]);
+    let lines: Vec<Vec<&str>> = out.lines().map(|l| l.split(' ').collect()).collect();
+    assert_eq!(lines[0][0], lines[0][1]);
+    // A subshell keeps `$$` but has its own PMSH_PID
+    assert_eq!(lines[1][0], lines[0][0]);
+    assert_ne!(lines[1][1], lines[0][1]);
+}
+
+#[test]
+fn test_background_command_sets_last_pid() {
+    let (out, code) = run(
+        "echo \"before [$!]\"; sleep 0.1 & echo started $!; echo after",
+        &[],
+    );
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines[0], "before []");
+    let pid: i32 = lines[1].strip_prefix("started ").unwrap().parse().unwrap();
+    assert!(pid > 0);
+    assert_eq!(lines[2], "after");
+    assert_eq!(code, 0);
+}
+
+#[test]
+fn test_last_argument() {
+    let (out, _) = run("echo a b c >/dev/null; echo $_; true x; echo \"$_\"", &[]);
+    assert_eq!(out, "c\nx\n");
+}
+
+#[test]
+fn test_shlvl_and_pwd() {
+    let (out, _) = run(
+        "echo $SHLVL; cd /; echo $PWD $OLDPWD; sh -c 'echo $PWD'",
+        &[("SHLVL", "4"), ("PWD", "/nonexistent")],
+    );
+    let cwd = std::env::current_dir().unwrap();
+    assert_eq!(out, format!("5\n/ {}\n/\n", cwd.display()));
+}
+
+#[test]
+fn test_lineno_is_the_line_of_each_command() {
+    let script = "echo $LINENO\n\
+                  if true; then\n  echo $LINENO\n  echo $LINENO\nfi\n\
+                  f() {\n  echo $LINENO\n}\n\
+                  for i in 1\ndo echo $LINENO\ndone\n\
+                  f; echo $LINENO";
+    let (out, _) = run(script, &[]);
+    assert_eq!(out, "1\n3\n4\n10\n7\n12\n");
+}
@@ -0,0 +1,95 @@
+use std::process::{Command, Stdio};
+
+/// Run pmsh with HOME set to `home`; returns (stdout, stderr, code).
+fn run(home: &std::path::Path, args: &[&str], envs: &[(&str, &str)]) -> (String, String, i32) {
+    let bin = std::env::var("CARGO_BIN_EXE_pmsh").unwrap_or_else(|_| {
+        let manifest = std::env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR not set");
+        format!("{}/target/debug/pmsh", manifest)
+    });
+    let output = Command::new(bin)
+        .args(args)
+        .env("HOME", home)
+        .env_remove("PMSH_ENV")
+        .envs(envs.iter().copied())
+        .stdin(Stdio::null())
+        .output()
+        .expect("failed to run pmsh");
+    (
+        String::from_utf8_lossy(&output.stdout).to_string(),
+        String::from_utf8_lossy(&output.stderr).to_string(),
+        output.status.code().unwrap_or(-1),
+    )
+}
+
+fn home_with(files: &[(&str, &str)]) -> tempfile::TempDir {
+    let home = tempfile::TempDir::new().unwrap();
+    for (name, contents) in files {
+        std::fs::write(home.path().join(name), contents).unwrap();
+    }
+    home
+}
+
+#[test]
+fn test_login_shell_reads_profile_and_logout() {
+    let home = home_with(&[
+        (".pmsh_profile", "echo profile\n"),
+        (".pmsh_logout", "echo logout\n"),
+        (".pmshrc", "echo rc\n"),
+    ]);
+    let (out, _, code) = run(home.path(), &["-l", "-c", "echo body; exit 3"], &[]);
+    assert_eq!(out, "profile\nbody\nExiting.\nlogout\n");
+    assert_eq!(code, 3);
+
+    // Without --login, neither file is read, and neither is the rc file
+    let (out, _, _) = run(home.path(), &["-c", "echo body"], &[]);
+    assert_eq!(out, "body\n");
+}
+
+#[test]
+fn test_interactive_rc_and_options() {
+    let home = home_with(&[(".pmshrc", "echo rc\n"), ("other_rc", "echo other\n")]);
+    let (out, _, _) = run(home.path(), &["-i"], &[]);
+    assert!(out.starts_with("rc\n"));
+
+    let rcfile = home.path().join("other_rc");
+    let (out, _, _) = run(
+        home.path(),
+        &["-i", "--rcfile", rcfile.to_str().unwrap()],
+        &[],
+    );
+    assert!(out.starts_with("other\n"));
+
+    let (out, _, _) = run(home.path(), &["-i", "--norc"], &[]);
+    assert!(!out.contains("rc\n"));
+}
+
+#[test]
+fn test_rc_file_uses_its_own_aliases() {
+    let home = home_with(&[(".pmshrc", "alias greet='echo hi from'\ngreet rc\n")]);
+    let (out, _, _) = run(home.path(), &["-i"], &[]);
+    assert!(out.starts_with("hi from rc\n"), "{}", out);
+}
+
+#[test]
+fn test_env_file_for_non_interactive_shells() {
+    let home = home_with(&[("env.sh", "GREETING=hello\n")]);
+    let (out, _, _) = run(
+        home.path(),
+        &["-c", "echo $GREETING"],
+        &[("PMSH_ENV", "$HOME/env.sh")],
+    );
+    assert_eq!(out, "hello\n");
+}
+
+#[test]
+fn test_startup_errors_name_file_and_line() {
+    let home = home_with(&[(".pmsh_profile", "echo ok\nif then\n")]);
+    let (out, err, _) = run(home.path(), &["-l", "-c", "echo body"], &[]);
+    assert_eq!(out, "ok\nbody\n");
+    let profile = home.path().join(".pmsh_profile");
+    assert!(
+        err.contains(&format!("pmsh: {}: line 2: Parse error", profile.display())),
+        "unexpected stderr: {}",
+        err
+    );
+}
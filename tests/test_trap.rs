@@ -0,0 +1,106 @@
+mod common;
+use common::run_script;
+
+#[test]
+fn test_exit_trap_cleans_up() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let tmp = dir.path().join("work");
+    let script = format!(
+        "tmp={}
+mkdir \"$tmp\"
+trap 'rm -rf \"$tmp\"; echo cleaned' EXIT
+echo working
+",
+        tmp.display()
+    );
+    let (out, _, code) = run_script(&script);
+    assert_eq!(out, "working\ncleaned\n");
+    assert_eq!(code, 0);
+    assert!(!tmp.exists());
+}
+
+#[test]
+fn test_exit_trap_runs_on_exit_and_errexit() {
+    let (out, _, code) = run_script("trap 'echo status $?' EXIT\nexit 4\n");
+    assert_eq!(out, "Exiting.\nstatus 4\n");
+    assert_eq!(code, 4);
+
+    let (out, _, code) = run_script("set -e\ntrap 'echo bye' EXIT\nfalse\necho unreached\n");
+    assert_eq!(out, "bye\n");
+    assert_eq!(code, 1);
+}
+
+#[test]
+fn test_err_trap_skips_tested_commands() {
+    let script = "trap 'echo failed $?' ERR
+false
+if false; then :; fi
+false || true
+sh -c 'exit 3'
+";
+    let (out, _, _) = run_script(script);
+    assert_eq!(out, "failed 1\nfailed 3\n");
+}
+
+#[test]
+fn test_err_trap_runs_once_for_functions() {
+    let script = "trap 'echo failed $? at $LINENO' ERR
+f() {
+  false
+}
+f
+g() { return 3; }
+g
+h() { false; echo in h; }
+h
+";
+    let (out, _, _) = run_script(script);
+    assert_eq!(out, "failed 1 at 5\nfailed 3 at 7\nin h\n");
+}
+
+#[test]
+fn test_debug_trap_and_reset() {
+    let script = "trap 'echo debug' DEBUG
+echo one
+trap - DEBUG
+echo two
+";
+    let (out, _, _) = run_script(script);
+    assert_eq!(out, "debug\none\ndebug\ntwo\n");
+}
+
+#[test]
+fn test_debug_trap_in_pipeline() {
+    let script = "trap 'sleep 0.2; echo debug >&2' DEBUG
+/bin/true | cat
+echo status $?
+";
+    let (out, err, _) = run_script(script);
+    assert_eq!(out, "status 0\n");
+    assert_eq!(err, "debug\ndebug\ndebug\n");
+}
+
+#[test]
+fn test_signal_traps() {
+    let script = "trap 'echo got usr1' USR1
+kill -USR1 $$
+echo after
+trap '' USR1
+kill -USR1 $$
+echo ignored
+trap -p
+";
+    let (out, _, code) = run_script(script);
+    assert_eq!(out, "got usr1\nafter\nignored\ntrap -- '' SIGUSR1\n");
+    assert_eq!(code, 0);
+}
+
+#[test]
+fn test_subshell_does_not_inherit_exit_trap() {
+    let script = "trap 'echo outer' EXIT
+( echo inner )
+( trap 'echo sub' EXIT; true )
+";
+    let (out, _, _) = run_script(script);
+    assert_eq!(out, "inner\nsub\nouter\n");
+}
@@ -0,0 +1,73 @@
+mod common;
+use common::run_script;
+
+#[test]
+fn test_xtrace_prints_expanded_commands() {
+    // Programs in a pipeline are traced in order; a forked builtin is not
+    let script = "set -x
+NAME='a b'
+echo \"$NAME\" done
+f() { echo in f; }
+f arg
+/bin/echo one | cat
+set +x
+echo untraced
+";
+    let (out, err, _) = run_script(script);
+    assert_eq!(out, "a b done\nin f\none\nuntraced\n");
+    assert_eq!(
+        err,
+        "+ NAME='a b'\n+ echo 'a b' done\n+ f arg\n++ echo in f\n+ /bin/echo one\n+ cat\n+ set +x\n"
+    );
+}
+
+#[test]
+fn test_xtrace_custom_ps4() {
+    let script = "TAG=trace
+PS4='$TAG> '
+set -x
+( true )
+";
+    let (_, err, _) = run_script(script);
+    assert_eq!(err, "ttrace> true\n");
+}
+
+#[test]
+fn test_xtrace_traces_before_redirections() {
+    let (out, err, _) = run_script("set -x\necho hidden 2>/dev/null >/dev/null\n");
+    assert_eq!(out, "");
+    assert_eq!(err, "+ echo hidden\n");
+}
+
+#[test]
+fn test_xtracefd_sends_trace_elsewhere() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let trace = dir.path().join("trace.log");
+    let script = format!(
+        "{{
+PMSH_XTRACEFD=1
+set -x
+echo hi
+}} > {}
+",
+        trace.display()
+    );
+    let (_, err, _) = run_script(&script);
+    assert_eq!(err, "");
+    assert_eq!(std::fs::read_to_string(&trace).unwrap(), "+ echo hi\nhi\n");
+}
+
+#[test]
+fn test_verbose_echoes_input() {
+    let script = "set -v
+# a comment
+if true; then
+  echo yes
+fi
+set +v
+echo quiet
+";
+    let (out, err, _) = run_script(script);
+    assert_eq!(out, "yes\nquiet\n");
+    assert_eq!(err, "# a comment\nif true; then\n  echo yes\nfi\nset +v\n");
+}